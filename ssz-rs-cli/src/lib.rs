@@ -0,0 +1,415 @@
+//! A command-line tool for common tasks on SSZ encodings: decoding to and encoding from JSON,
+//! computing the `hash_tree_root` and generalized indices, and generating and verifying Merkle
+//! proofs.
+//!
+//! The tool works over the types in a `Registry`. The `ssz` binary supports the types of the
+//! default registry; downstream crates can ship a binary supporting their own types by registering
+//! them and calling `run`:
+//!
+//! ```no_run
+//! use ssz_rs::prelude::*;
+//!
+//! #[derive(Debug, Default, SimpleSerialize, ConsensusSerialize, ConsensusDeserialize)]
+//! struct Checkpoint {
+//!     epoch: u64,
+//!     root: ByteVector<32>,
+//! }
+//!
+//! let mut registry = ssz_rs_cli::Registry::default();
+//! registry.register::<Checkpoint>("Checkpoint");
+//! std::process::exit(ssz_rs_cli::run(&registry, std::env::args_os()));
+//! ```
+//!
+//! Encodings are read from and written to files, where files with the `.ssz_snappy` extension hold
+//! encodings compressed with the raw snappy format, as in the consensus spec tests and gossip
+//! messages. Values are represented in JSON as in the consensus APIs (see `ConsensusSerialize`).
+mod path;
+mod registry;
+
+pub use crate::{
+    path::parse_path,
+    registry::{Registry, SszType},
+};
+use clap::{Parser, Subcommand};
+use ssz_rs::{
+    proofs::Proof, snappy::SnappyError, DeserializeError, GeneralizedIndex, MerkleizationError,
+    Node, SerializeError,
+};
+use std::{
+    ffi::OsString,
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Errors of the command-line tool.
+#[derive(Debug)]
+pub enum Error {
+    /// No type was registered with the given name.
+    UnknownType(String),
+    /// The path could not be parsed.
+    InvalidPath(String),
+    /// The proof was not valid for the given root.
+    InvalidProof,
+    Io(io::Error),
+    Json(serde_json::Error),
+    Serialize(SerializeError),
+    Deserialize(DeserializeError),
+    Snappy(SnappyError),
+    Merkleization(MerkleizationError),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl From<SerializeError> for Error {
+    fn from(err: SerializeError) -> Self {
+        Self::Serialize(err)
+    }
+}
+
+impl From<DeserializeError> for Error {
+    fn from(err: DeserializeError) -> Self {
+        Self::Deserialize(err)
+    }
+}
+
+impl From<SnappyError> for Error {
+    fn from(err: SnappyError) -> Self {
+        Self::Snappy(err)
+    }
+}
+
+impl From<MerkleizationError> for Error {
+    fn from(err: MerkleizationError) -> Self {
+        Self::Merkleization(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownType(name) => write!(f, "no type named `{name}` is registered"),
+            Self::InvalidPath(path) => write!(f, "invalid path `{path}`"),
+            Self::InvalidProof => write!(f, "the proof is not valid"),
+            Self::Io(err) => write!(f, "{err}"),
+            Self::Json(err) => write!(f, "invalid JSON: {err}"),
+            Self::Serialize(err) => write!(f, "could not serialize: {err}"),
+            Self::Deserialize(err) => write!(f, "could not deserialize: {err}"),
+            Self::Snappy(err) => write!(f, "{err}"),
+            Self::Merkleization(err) => write!(f, "merkleization error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// The format of an encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A plain SSZ encoding.
+    Ssz,
+    /// An SSZ encoding compressed with the raw snappy format.
+    SszSnappy,
+}
+
+impl Format {
+    /// Returns the format of the encoding in the file at `path`, based on its extension.
+    pub fn of(path: &Path) -> Self {
+        if path.extension().is_some_and(|extension| extension == "ssz_snappy") {
+            Self::SszSnappy
+        } else {
+            Self::Ssz
+        }
+    }
+}
+
+// The JSON representation of a Merkle proof along with the root it was generated against.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct ProofJson {
+    leaf: Node,
+    branch: Vec<Node>,
+    index: GeneralizedIndex,
+    root: Node,
+}
+
+#[derive(Debug, Parser)]
+#[command(name = "ssz", about = "Work with SSZ encodings of the registered types")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// List the registered types.
+    Types,
+    /// Decode an encoding and print the value as JSON.
+    Decode {
+        /// The name of the type of the value.
+        r#type: String,
+        /// A file with the encoding, compressed if it has the `.ssz_snappy` extension.
+        file: PathBuf,
+    },
+    /// Encode a value given as JSON.
+    Encode {
+        /// The name of the type of the value.
+        r#type: String,
+        /// A file with the JSON representation of the value.
+        file: PathBuf,
+        /// The file to write the encoding to, compressed if it has the `.ssz_snappy` extension.
+        /// If not given, the encoding is printed in hex.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Decode an encoding and print the `hash_tree_root` of the value.
+    Root {
+        /// The name of the type of the value.
+        r#type: String,
+        /// A file with the encoding, compressed if it has the `.ssz_snappy` extension.
+        file: PathBuf,
+    },
+    /// Print the generalized index of a path in a type.
+    Gindex {
+        /// The name of the type.
+        r#type: String,
+        /// The path, e.g. `body.attestations[3]` or `validators.<length>`.
+        path: String,
+    },
+    /// Decode an encoding and print a Merkle proof of the node at a path as JSON.
+    Prove {
+        /// The name of the type of the value.
+        r#type: String,
+        /// A file with the encoding, compressed if it has the `.ssz_snappy` extension.
+        file: PathBuf,
+        /// The path, e.g. `body.attestations[3]` or `validators.<length>`.
+        path: String,
+    },
+    /// Verify a Merkle proof printed by `prove`.
+    Verify {
+        /// A file with the proof.
+        proof: PathBuf,
+        /// The root to verify the proof against, instead of the root recorded in the proof.
+        #[arg(long)]
+        root: Option<Node>,
+    },
+}
+
+fn execute(registry: &Registry, command: Command, out: &mut impl Write) -> Result<(), Error> {
+    match command {
+        Command::Types => {
+            for name in registry.names() {
+                writeln!(out, "{name}")?;
+            }
+        }
+        Command::Decode { r#type, file } => {
+            let json = registry.get(&r#type)?.decode_json(&fs::read(&file)?, Format::of(&file))?;
+            serde_json::to_writer_pretty(&mut *out, &json)?;
+            writeln!(out)?;
+        }
+        Command::Encode { r#type, file, output } => {
+            let json = serde_json::from_slice(&fs::read(file)?)?;
+            let ty = registry.get(&r#type)?;
+            match output {
+                Some(output) => fs::write(&output, ty.encode_json(json, Format::of(&output))?)?,
+                None => {
+                    let encoding = ty.encode_json(json, Format::Ssz)?;
+                    writeln!(out, "0x{}", hex::encode(encoding))?
+                }
+            }
+        }
+        Command::Root { r#type, file } => {
+            let root =
+                registry.get(&r#type)?.hash_tree_root(&fs::read(&file)?, Format::of(&file))?;
+            writeln!(out, "{root}")?;
+        }
+        Command::Gindex { r#type, path } => {
+            let index = registry.get(&r#type)?.generalized_index(&parse_path(&path)?)?;
+            writeln!(out, "{index}")?;
+        }
+        Command::Prove { r#type, file, path } => {
+            let (proof, root) = registry.get(&r#type)?.prove(
+                &fs::read(&file)?,
+                Format::of(&file),
+                &parse_path(&path)?,
+            )?;
+            let proof =
+                ProofJson { leaf: proof.leaf, branch: proof.branch, index: proof.index, root };
+            serde_json::to_writer_pretty(&mut *out, &proof)?;
+            writeln!(out)?;
+        }
+        Command::Verify { proof, root } => {
+            let proof: ProofJson = serde_json::from_slice(&fs::read(proof)?)?;
+            let root = root.unwrap_or(proof.root);
+            Proof { leaf: proof.leaf, branch: proof.branch, index: proof.index }
+                .verify(root)
+                .map_err(|_| Error::InvalidProof)?;
+            writeln!(out, "valid proof for {root}")?;
+        }
+    }
+    Ok(())
+}
+
+/// Run the command-line tool over the types in `registry` with the command-line arguments `args`,
+/// including the name of the binary, and return the exit code.
+pub fn run<I, T>(registry: &Registry, args: I) -> i32
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let cli = match Cli::try_parse_from(args) {
+        Ok(cli) => cli,
+        Err(err) => {
+            let _ = err.print();
+            return err.exit_code()
+        }
+    };
+    match execute(registry, cli.command, &mut io::stdout().lock()) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("error: {err}");
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ssz_rs::{prelude::*, snappy};
+
+    #[derive(
+        Debug, Default, PartialEq, Eq, SimpleSerialize, ConsensusSerialize, ConsensusDeserialize,
+    )]
+    struct Checkpoint {
+        epoch: u64,
+        root: ByteVector<32>,
+    }
+
+    #[derive(
+        Debug, Default, PartialEq, Eq, SimpleSerialize, ConsensusSerialize, ConsensusDeserialize,
+    )]
+    struct Checkpoints {
+        flag: bool,
+        checkpoints: List<Checkpoint, 16>,
+    }
+
+    fn registry() -> Registry {
+        let mut registry = Registry::default();
+        registry.register::<Checkpoints>("Checkpoints");
+        registry
+    }
+
+    fn execute_args(registry: &Registry, args: &[&str]) -> Result<String, Error> {
+        let cli = Cli::try_parse_from(["ssz"].iter().chain(args)).unwrap();
+        let mut out = vec![];
+        execute(registry, cli.command, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ssz-rs-cli-{}-{name}", std::process::id()))
+    }
+
+    fn value() -> Checkpoints {
+        let checkpoints = (0..3)
+            .map(|i| Checkpoint { epoch: i, root: ByteVector::from([i as u8; 32]) })
+            .collect::<Vec<_>>();
+        Checkpoints { flag: true, checkpoints: checkpoints.try_into().unwrap() }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let registry = registry();
+        let value = value();
+        let ssz_file = temp_path("roundtrip.ssz");
+        let snappy_file = temp_path("roundtrip.ssz_snappy");
+        let json_file = temp_path("roundtrip.json");
+        fs::write(&ssz_file, serialize(&value).unwrap()).unwrap();
+
+        let json = execute_args(&registry, &["decode", "Checkpoints", ssz_file.to_str().unwrap()])
+            .unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&json).unwrap(),
+            serde_json::to_value(Consensus(&value)).unwrap()
+        );
+        fs::write(&json_file, json).unwrap();
+
+        let args = ["encode", "Checkpoints", json_file.to_str().unwrap()];
+        let mut args = args.to_vec();
+        args.extend(["--output", snappy_file.to_str().unwrap()]);
+        execute_args(&registry, &args).unwrap();
+        assert_eq!(fs::read(&snappy_file).unwrap(), snappy::encode_gossip(&value).unwrap());
+
+        for file in [&ssz_file, &snappy_file] {
+            let root =
+                execute_args(&registry, &["root", "Checkpoints", file.to_str().unwrap()]).unwrap();
+            assert_eq!(root.trim(), value.hash_tree_root().unwrap().to_string());
+        }
+
+        let hex = execute_args(&registry, &["encode", "uint16", json_file.to_str().unwrap()]);
+        assert!(hex.is_err());
+        fs::write(&json_file, "\"258\"").unwrap();
+        let hex = execute_args(&registry, &["encode", "uint16", json_file.to_str().unwrap()]);
+        assert_eq!(hex.unwrap(), "0x0201\n");
+
+        for file in [ssz_file, snappy_file, json_file] {
+            fs::remove_file(file).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_proofs() {
+        let registry = registry();
+        let value = value();
+        let ssz_file = temp_path("proofs.ssz");
+        let proof_file = temp_path("proofs.json");
+        fs::write(&ssz_file, serialize(&value).unwrap()).unwrap();
+
+        let path = "checkpoints[2].epoch";
+        let index = execute_args(&registry, &["gindex", "Checkpoints", path]).unwrap();
+        let expected = Checkpoints::generalized_index(&parse_path(path).unwrap()).unwrap();
+        assert_eq!(index, format!("{expected}\n"));
+
+        let proof =
+            execute_args(&registry, &["prove", "Checkpoints", ssz_file.to_str().unwrap(), path])
+                .unwrap();
+        let proof_json: ProofJson = serde_json::from_str(&proof).unwrap();
+        assert_eq!(proof_json.index, expected);
+        assert_eq!(proof_json.root, value.hash_tree_root().unwrap());
+        fs::write(&proof_file, proof).unwrap();
+
+        let proof_file = proof_file.to_str().unwrap();
+        assert!(execute_args(&registry, &["verify", proof_file]).is_ok());
+        let other_root = Node::default().to_string();
+        assert!(matches!(
+            execute_args(&registry, &["verify", proof_file, "--root", &other_root]),
+            Err(Error::InvalidProof)
+        ));
+
+        assert!(matches!(
+            execute_args(&registry, &["gindex", "Checkpoint", path]),
+            Err(Error::UnknownType(..))
+        ));
+        assert!(execute_args(&registry, &["gindex", "Checkpoints", "checkpoints[16]"]).is_err());
+
+        fs::remove_file(ssz_file).unwrap();
+        fs::remove_file(proof_file).unwrap();
+    }
+
+    #[test]
+    fn test_types() {
+        let types = execute_args(&registry(), &["types"]).unwrap();
+        assert!(types.lines().any(|name| name == "uint64"));
+        assert!(types.lines().any(|name| name == "Checkpoints"));
+    }
+}
@@ -0,0 +1,5 @@
+use ssz_rs_cli::{run, Registry};
+
+fn main() {
+    std::process::exit(run(&Registry::default(), std::env::args_os()));
+}
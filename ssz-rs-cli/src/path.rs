@@ -0,0 +1,54 @@
+use crate::Error;
+use ssz_rs::PathElement;
+
+/// Parse a path written like an expression accessing the target, e.g. `body.attestations[3]`.
+///
+/// Fields are separated by `.`, indices are written in brackets and the length of a list or the
+/// selector of a union are written as `<length>` and `<selector>`, e.g. `validators.<length>`.
+pub fn parse_path(input: &str) -> Result<Vec<PathElement>, Error> {
+    let invalid = || Error::InvalidPath(input.to_string());
+    let mut path = vec![];
+    let mut rest = input;
+    while !rest.is_empty() {
+        if let Some(index) = rest.strip_prefix('[') {
+            let (index, next) = index.split_once(']').ok_or_else(invalid)?;
+            path.push(PathElement::Index(index.parse().map_err(|_| invalid())?));
+            rest = next;
+            continue
+        }
+        if !path.is_empty() {
+            rest = rest.strip_prefix('.').ok_or_else(invalid)?;
+        }
+        let end = rest.find(['.', '[']).unwrap_or(rest.len());
+        let (element, next) = rest.split_at(end);
+        path.push(match element {
+            "<length>" => PathElement::Length,
+            "<selector>" => PathElement::Selector,
+            "" => return Err(invalid()),
+            field => PathElement::from(field),
+        });
+        rest = next;
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_path() {
+        assert_eq!(parse_path("").unwrap(), vec![]);
+        assert_eq!(
+            parse_path("body.attestations[3].bits").unwrap(),
+            vec!["body".into(), "attestations".into(), 3.into(), "bits".into()]
+        );
+        assert_eq!(parse_path("[1][2]").unwrap(), vec![1.into(), 2.into()]);
+        assert_eq!(parse_path("a.<length>").unwrap(), vec!["a".into(), PathElement::Length]);
+        assert_eq!(parse_path("<selector>").unwrap(), vec![PathElement::Selector]);
+
+        for input in ["a..b", ".a", "a.", "a[", "a[x]", "a[1]b"] {
+            assert!(parse_path(input).is_err(), "{input}");
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{Error, Format};
+use ssz_rs::{prelude::*, proofs::ProofAndWitness, snappy};
+use std::{collections::BTreeMap, marker::PhantomData};
+
+/// The operations of the command-line tool on the encodings of some type.
+///
+/// Implemented for each type added to a `Registry` with `Registry::register`.
+pub trait SszType {
+    /// Decode `data` and return the JSON representation of the value.
+    fn decode_json(&self, data: &[u8], format: Format) -> Result<serde_json::Value, Error>;
+
+    /// Encode the value with the JSON representation `json`.
+    fn encode_json(&self, json: serde_json::Value, format: Format) -> Result<Vec<u8>, Error>;
+
+    /// Decode `data` and return the `hash_tree_root` of the value.
+    fn hash_tree_root(&self, data: &[u8], format: Format) -> Result<Node, Error>;
+
+    /// Return the generalized index of `path` in the type.
+    fn generalized_index(&self, path: Path) -> Result<GeneralizedIndex, Error>;
+
+    /// Decode `data` and prove the node at `path` in the Merkle tree of the value.
+    fn prove(&self, data: &[u8], format: Format, path: Path) -> Result<ProofAndWitness, Error>;
+}
+
+struct Registered<T>(PhantomData<fn() -> T>);
+
+impl<T: SimpleSerialize> Registered<T> {
+    fn decode(data: &[u8], format: Format) -> Result<T, Error> {
+        match format {
+            Format::Ssz => Ok(ssz_rs::deserialize(data)?),
+            Format::SszSnappy => Ok(snappy::decode_gossip(data)?),
+        }
+    }
+
+    fn encode(value: &T, format: Format) -> Result<Vec<u8>, Error> {
+        match format {
+            Format::Ssz => Ok(ssz_rs::serialize(value)?),
+            Format::SszSnappy => Ok(snappy::encode_gossip(value)?),
+        }
+    }
+}
+
+impl<T> SszType for Registered<T>
+where
+    T: SimpleSerialize + ConsensusSerialize + ConsensusDeserialize,
+{
+    fn decode_json(&self, data: &[u8], format: Format) -> Result<serde_json::Value, Error> {
+        let value = Self::decode(data, format)?;
+        Ok(serde_json::to_value(Consensus(value))?)
+    }
+
+    fn encode_json(&self, json: serde_json::Value, format: Format) -> Result<Vec<u8>, Error> {
+        let Consensus(value) = serde_json::from_value::<Consensus<T>>(json)?;
+        Self::encode(&value, format)
+    }
+
+    fn hash_tree_root(&self, data: &[u8], format: Format) -> Result<Node, Error> {
+        Ok(Self::decode(data, format)?.hash_tree_root()?)
+    }
+
+    fn generalized_index(&self, path: Path) -> Result<GeneralizedIndex, Error> {
+        Ok(T::generalized_index(path)?)
+    }
+
+    fn prove(&self, data: &[u8], format: Format, path: Path) -> Result<ProofAndWitness, Error> {
+        Ok(Self::decode(data, format)?.prove(path)?)
+    }
+}
+
+/// The types supported by the command-line tool, by name.
+///
+/// The default registry contains the basic types as named in the SSZ spec, e.g. `uint64` and
+/// `boolean`, along with the `BytesN` types of the consensus specs.
+pub struct Registry {
+    types: BTreeMap<String, Box<dyn SszType>>,
+}
+
+impl Registry {
+    /// Returns a registry without any types.
+    pub fn empty() -> Self {
+        Self { types: BTreeMap::new() }
+    }
+
+    /// Add the type `T` under `name`, replacing any type previously registered under `name`.
+    pub fn register<T>(&mut self, name: &str) -> &mut Self
+    where
+        T: SimpleSerialize + ConsensusSerialize + ConsensusDeserialize + 'static,
+    {
+        self.types.insert(name.to_string(), Box::new(Registered::<T>(PhantomData)));
+        self
+    }
+
+    /// Returns the type registered under `name`.
+    pub fn get(&self, name: &str) -> Result<&dyn SszType, Error> {
+        self.types.get(name).map(AsRef::as_ref).ok_or_else(|| Error::UnknownType(name.to_string()))
+    }
+
+    /// Returns the names of the registered types, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.types.keys().map(String::as_str)
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry
+            .register::<u8>("uint8")
+            .register::<u16>("uint16")
+            .register::<u32>("uint32")
+            .register::<u64>("uint64")
+            .register::<u128>("uint128")
+            .register::<U256>("uint256")
+            .register::<bool>("boolean")
+            .register::<ByteVector<4>>("Bytes4")
+            .register::<ByteVector<8>>("Bytes8")
+            .register::<ByteVector<20>>("Bytes20")
+            .register::<ByteVector<32>>("Bytes32")
+            .register::<ByteVector<48>>("Bytes48")
+            .register::<ByteVector<96>>("Bytes96");
+        registry
+    }
+}
@@ -0,0 +1,108 @@
+//! Derivation of the `CompatibleUnion` type, a union with explicit selectors whose variants share a
+//! compatible Merkle tree layout.
+//!
+//! An enum with `#[ssz(compatible_union)]` must mark every variant with `#[ssz(selector = N)]`,
+//! where `N` is unique and in `1..=127`. The variants must all have the same type or otherwise
+//! must all be the stable container or profiles of the same stable container (see `StableLayout`).
+use crate::{filter_ssz_attrs, validate_no_attrs};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DataEnum, Fields, Generics, Lit, Meta, NestedMeta, Type, Variant};
+
+const MAX_SELECTOR: u8 = 127;
+
+// Returns the selector given with `#[ssz(selector = N)]` on `variant`, if any.
+pub(crate) fn extract_selector(variant: &Variant) -> Option<u8> {
+    let mut selectors =
+        filter_ssz_attrs(variant.attrs.iter()).flat_map(|attr| match attr.parse_meta().unwrap() {
+            Meta::List(args) => args
+                .nested
+                .iter()
+                .map(|arg| match arg {
+                    NestedMeta::Meta(Meta::NameValue(arg)) if arg.path.is_ident("selector") => {
+                        match &arg.lit {
+                            Lit::Int(value) => value
+                                .base10_parse::<u8>()
+                                .expect("selector must be an integer in `1..=127`"),
+                            _ => panic!("`selector` requires an integer value"),
+                        }
+                    }
+                    _ => panic!("only the `selector` attribute is supported on variants"),
+                })
+                .collect::<Vec<_>>(),
+            _ => panic!("only list-like attributes are supported"),
+        });
+    let selector = selectors.next();
+    if selectors.next().is_some() {
+        panic!("only one selector is allowed per variant")
+    }
+    selector
+}
+
+fn variant_type(variant: &Variant) -> &Type {
+    match &variant.fields {
+        Fields::Unnamed(inner) => &inner.unnamed[0].ty,
+        _ => unreachable!("validated to only have unnamed variants"),
+    }
+}
+
+// Validates an enum deriving a compatible union.
+//
+// Panics if validation fails which aborts the macro derivation.
+pub(crate) fn validate_derive_input(data: &Data) {
+    let data = match data {
+        Data::Enum(data) => data,
+        _ => panic!("`compatible_union` option is only compatible with enums"),
+    };
+    if data.variants.is_empty() {
+        panic!("SSZ unions must have at least 1 variant; this enum has none");
+    }
+    let mut selectors = vec![];
+    for variant in &data.variants {
+        match &variant.fields {
+            Fields::Unnamed(inner) if inner.unnamed.len() == 1 => {
+                validate_no_attrs(inner.unnamed.iter())
+            }
+            _ => panic!("variants of a compatible union must have exactly 1 unnamed type"),
+        }
+        let selector = extract_selector(variant)
+            .expect("each variant of a compatible union requires `#[ssz(selector = N)]`");
+        if selector == 0 || selector > MAX_SELECTOR {
+            panic!("selectors of a compatible union must be in `1..={MAX_SELECTOR}`")
+        }
+        if selectors.contains(&selector) {
+            panic!("selector {selector} is used by more than one variant")
+        }
+        selectors.push(selector);
+    }
+}
+
+// Returns the explicit selector of each variant of a compatible union.
+pub(crate) fn selectors(data: &DataEnum) -> Vec<u8> {
+    data.variants
+        .iter()
+        .map(|variant| extract_selector(variant).expect("validated to have a selector"))
+        .collect()
+}
+
+// Returns code that only compiles if the variants share a compatible Merkle tree layout.
+pub(crate) fn derive_layout_check(data: &DataEnum, generics: &Generics) -> TokenStream {
+    let types = data.variants.iter().map(variant_type).collect::<Vec<_>>();
+    // SAFETY: index is safe because validated to have at least one variant; qed
+    let first = types[0];
+    let check_by_variant = types.iter().map(|ty| {
+        quote! {
+            (&&ssz_rs::__internal::VariantLayouts::<#first, #ty>::default()).assert_compatible();
+        }
+    });
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    quote! {
+        const _: () = {
+            #[allow(dead_code)]
+            fn assert_compatible_layouts #impl_generics () #where_clause {
+                use ssz_rs::__internal::{IdenticalLayouts as _, StableLayouts as _};
+                #(#check_by_variant)*
+            }
+        };
+    }
+}
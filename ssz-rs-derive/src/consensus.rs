@@ -0,0 +1,219 @@
+//! Derivation of the `ConsensusSerialize` and `ConsensusDeserialize` traits for the JSON
+//! representation of the consensus APIs.
+//!
+//! Containers are objects with a key for each field, and unions are objects with the `selector` of
+//! the variant and its `data`. The optional fields of stable containers and profiles are `null` if
+//! they are not present. Deserialization of a container uses a hidden struct deriving `serde`'s
+//! `Deserialize` with each field wrapped in `Consensus`.
+use crate::{stable_container::option_inner_type, union_selectors, with_bounds, HelperAttr};
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{spanned::Spanned, Data, Field, Fields, Generics, Ident};
+
+// Returns `true` if `field` is an optional field of a stable container or profile, which is `null`
+// when not present rather than a union.
+fn is_optional_field(field: &Field, helper_attr: Option<&HelperAttr>) -> bool {
+    helper_attr.is_some_and(HelperAttr::is_stable) && option_inner_type(&field.ty).is_some()
+}
+
+pub(crate) fn derive_serialize_impl(
+    data: &Data,
+    name: &Ident,
+    generics: &Generics,
+    helper_attr: Option<&HelperAttr>,
+) -> TokenStream {
+    let body = match data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => {
+                let name_str = name.to_string();
+                let field_count = fields.named.len();
+                let serialization_by_field = fields.named.iter().map(|f| {
+                    let field_name = f.ident.as_ref().expect("only named fields");
+                    let field_name_str = field_name.to_string();
+                    let value = if is_optional_field(f, helper_attr) {
+                        quote! { &self.#field_name.as_ref().map(ssz_rs::Consensus) }
+                    } else {
+                        quote! { &ssz_rs::Consensus(&self.#field_name) }
+                    };
+                    quote_spanned! { f.span() =>
+                        state.serialize_field(#field_name_str, #value)?;
+                    }
+                });
+                quote! {
+                    use ssz_rs::__internal::serde::ser::SerializeStruct;
+
+                    let mut state = serializer.serialize_struct(#name_str, #field_count)?;
+                    #(#serialization_by_field)*
+                    state.end()
+                }
+            }
+            Fields::Unnamed(..) => quote! {
+                ssz_rs::ConsensusSerialize::serialize_consensus(&self.0, serializer)
+            },
+            _ => unimplemented!(
+                "this type of struct is currently not supported by this derive macro"
+            ),
+        },
+        Data::Enum(ref data) => {
+            let selectors = union_selectors(data, helper_attr);
+            let serialization_by_variant = data.variants.iter().enumerate().map(|(i, variant)| {
+                let variant_name = &variant.ident;
+                let selector = selectors[i];
+                match &variant.fields {
+                    Fields::Unnamed(..) => {
+                        if matches!(helper_attr, Some(&HelperAttr::Transparent)) {
+                            quote_spanned! { variant.span() =>
+                                Self::#variant_name(value) => ssz_rs::ConsensusSerialize::serialize_consensus(value, serializer),
+                            }
+                        } else {
+                            quote_spanned! { variant.span() =>
+                                Self::#variant_name(value) => ssz_rs::__internal::serialize_union(serializer, #selector, &ssz_rs::Consensus(value)),
+                            }
+                        }
+                    }
+                    Fields::Unit => quote_spanned! { variant.span() =>
+                        Self::None => ssz_rs::__internal::serialize_union(serializer, 0, &()),
+                    },
+                    _ => unreachable!(),
+                }
+            });
+            quote! {
+                match self {
+                    #(#serialization_by_variant)*
+                }
+            }
+        }
+        Data::Union(..) => unreachable!("data was already validated to exclude union types"),
+    };
+
+    let generics = with_bounds(generics, quote! { ssz_rs::ConsensusSerialize });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics ssz_rs::ConsensusSerialize for #name #ty_generics #where_clause {
+            fn serialize_consensus<S: ssz_rs::__internal::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                #body
+            }
+        }
+    }
+}
+
+pub(crate) fn derive_deserialize_impl(
+    data: &Data,
+    name: &Ident,
+    generics: &Generics,
+    helper_attr: Option<&HelperAttr>,
+) -> TokenStream {
+    let generics = with_bounds(generics, quote! { ssz_rs::ConsensusDeserialize });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => {
+                let shadow_by_field = fields.named.iter().map(|f| {
+                    let field_name = &f.ident;
+                    let field_type = &f.ty;
+                    if is_optional_field(f, helper_attr) {
+                        // SAFETY: `is_optional_field` implies an inner type; qed
+                        let inner_type = option_inner_type(field_type).unwrap();
+                        quote_spanned! { f.span() =>
+                            #field_name: Option<ssz_rs::Consensus<#inner_type>>,
+                        }
+                    } else {
+                        quote_spanned! { f.span() =>
+                            #field_name: ssz_rs::Consensus<#field_type>,
+                        }
+                    }
+                });
+                let initialization_by_field = fields.named.iter().map(|f| {
+                    let field_name = &f.ident;
+                    if is_optional_field(f, helper_attr) {
+                        quote_spanned! { f.span() =>
+                            #field_name: shadow.#field_name.map(|value| value.0),
+                        }
+                    } else {
+                        quote_spanned! { f.span() =>
+                            #field_name: shadow.#field_name.0,
+                        }
+                    }
+                });
+                quote! {
+                    #[derive(ssz_rs::__internal::serde::Deserialize)]
+                    #[serde(crate = "ssz_rs::__internal::serde", bound = "")]
+                    struct ConsensusShadow #impl_generics #where_clause {
+                        #(#shadow_by_field)*
+                    }
+
+                    let shadow = <ConsensusShadow #ty_generics as ssz_rs::__internal::serde::Deserialize>::deserialize(deserializer)?;
+                    Ok(Self {
+                        #(#initialization_by_field)*
+                    })
+                }
+            }
+            Fields::Unnamed(ref fields) => {
+                // SAFETY: index is safe because Punctuated always has a first element; qed
+                let field_type = &fields.unnamed[0].ty;
+                quote! {
+                    let result = <#field_type as ssz_rs::ConsensusDeserialize>::deserialize_consensus(deserializer)?;
+                    Ok(Self(result))
+                }
+            }
+            _ => unimplemented!(
+                "this type of struct is currently not supported by this derive macro"
+            ),
+        },
+        Data::Enum(ref data) => {
+            if matches!(helper_attr, Some(&HelperAttr::Transparent)) {
+                panic!("`transparent` option is not supported when deriving `ConsensusDeserialize`")
+            }
+            let selectors = union_selectors(data, helper_attr);
+            let deserialization_by_variant = data.variants.iter().enumerate().map(|(i, variant)| {
+                let variant_name = &variant.ident;
+                let selector = selectors[i];
+                match &variant.fields {
+                    Fields::Unnamed(inner) => {
+                        // SAFETY: index is safe because Punctuated always has a first element; qed
+                        let variant_type = &inner.unnamed[0].ty;
+                        quote_spanned! { variant.span() =>
+                            #selector => {
+                                let value = <#variant_type as ssz_rs::ConsensusDeserialize>::deserialize_consensus(deserializer)?;
+                                Ok(Self::#variant_name(value))
+                            }
+                        }
+                    }
+                    Fields::Unit => quote_spanned! { variant.span() =>
+                        0 => {
+                            <() as ssz_rs::__internal::serde::Deserialize>::deserialize(deserializer)?;
+                            Ok(Self::None)
+                        }
+                    },
+                    _ => unreachable!(),
+                }
+            });
+            return quote! {
+                impl #impl_generics ssz_rs::__internal::ConsensusUnion for #name #ty_generics #where_clause {
+                    fn deserialize_variant<'de, D: ssz_rs::__internal::serde::Deserializer<'de>>(selector: u8, deserializer: D) -> Result<Self, D::Error> {
+                        match selector {
+                            #(#deserialization_by_variant)*
+                            selector => Err(ssz_rs::__internal::invalid_selector(selector)),
+                        }
+                    }
+                }
+
+                impl #impl_generics ssz_rs::ConsensusDeserialize for #name #ty_generics #where_clause {
+                    fn deserialize_consensus<'de, D: ssz_rs::__internal::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                        ssz_rs::__internal::deserialize_union(deserializer)
+                    }
+                }
+            }
+        }
+        Data::Union(..) => unreachable!("data was already validated to exclude union types"),
+    };
+
+    quote! {
+        impl #impl_generics ssz_rs::ConsensusDeserialize for #name #ty_generics #where_clause {
+            fn deserialize_consensus<'de, D: ssz_rs::__internal::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                #body
+            }
+        }
+    }
+}
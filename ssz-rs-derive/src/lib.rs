@@ -4,13 +4,25 @@
 //!
 //! Refer to the `examples` in the `ssz_rs` crate for a better idea on how to use this derive macro.
 //!
-//! This proc macro supports one attribute `ssz(transparent)` to pass through calls on a wrapping
-//! Rust enum to the underlying data. Refers to this crate's tests for example usage.
+//! This proc macro supports the attribute `ssz(transparent)` to pass through calls on a wrapping
+//! Rust enum to the underlying data, the attributes `ssz(stable_container = N)` and
+//! `ssz(profile(B))` to derive the `StableContainer[N]` and `Profile[B]` types of EIP-7495 for a
+//! struct, and the attribute `ssz(compatible_union)` along with `ssz(selector = N)` on each variant
+//! to derive a `CompatibleUnion` for an enum. Refers to this crate's tests for example usage.
+//!
+//! The `SszSchema` macro derives a runtime description of the structure of the same types. With the
+//! `serde` feature of `ssz_rs`, the `ConsensusSerialize` and `ConsensusDeserialize` macros derive
+//! the JSON representation of the consensus APIs for them.
+mod compatible_union;
+mod consensus;
+mod schema;
+mod stable_container;
+
 use proc_macro2::TokenStream;
 use quote::{format_ident, quote, quote_spanned};
 use syn::{
-    parse_macro_input, spanned::Spanned, Attribute, Data, DeriveInput, Field, Fields, Generics,
-    Ident, Meta, NestedMeta, PathArguments,
+    parse_macro_input, parse_quote, spanned::Spanned, Attribute, Data, DataEnum, DeriveInput,
+    Field, Fields, Generics, Ident, Lit, Meta, NestedMeta, PathArguments,
 };
 
 // NOTE: copied here from `ssz_rs` crate as it is unlikely to change
@@ -20,7 +32,7 @@ const BYTES_PER_CHUNK: usize = 32;
 const SSZ_HELPER_ATTRIBUTE: &str = "ssz";
 
 fn derive_serialize_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> TokenStream {
-    match data {
+    let serialize_to_impl = match data {
         Data::Struct(ref data) => {
             let fields = match data.fields {
                 // "regular" struct with 1+ fields
@@ -30,7 +42,11 @@ fn derive_serialize_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> Token
                 Fields::Unnamed(..) => {
                     return quote! {
                         fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, ssz_rs::SerializeError> {
-                                self.0.serialize(buffer)
+                                ssz_rs::Serialize::serialize(&self.0, buffer)
+                        }
+
+                        fn serialize_to<S: ssz_rs::Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, ssz_rs::SerializeError> {
+                                ssz_rs::Serialize::serialize_to(&self.0, sink)
                         }
                     }
                 }
@@ -38,38 +54,67 @@ fn derive_serialize_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> Token
                     "this type of struct is currently not supported by this derive macro"
                 ),
             };
-            let serialization_by_field = fields.iter().map(|f| match &f.ident {
-                Some(field_name) => quote_spanned! { f.span() =>
-                    serializer.with_element(&self.#field_name)?;
-                },
+            let fixed_length_by_field = fields.iter().map(|f| {
+                let field_type = &f.ty;
+                quote_spanned! { f.span() =>
+                    ssz_rs::__internal::fixed_portion_length::<#field_type>()
+                }
+            });
+            let fixed_part_by_field = fields.iter().map(|f| match &f.ident {
+                Some(field_name) => {
+                    let field_name_str = field_name.to_string();
+                    quote_spanned! { f.span() =>
+                        serializer.with_fixed_part(&self.#field_name).map_err(|err| {
+                            err.in_element(ssz_rs::PathElement::from(#field_name_str))
+                        })?;
+                    }
+                }
+                None => panic!("should have already returned an impl"),
+            });
+            let variable_part_by_field = fields.iter().map(|f| match &f.ident {
+                Some(field_name) => {
+                    let field_name_str = field_name.to_string();
+                    quote_spanned! { f.span() =>
+                        serializer.with_variable_part(&self.#field_name).map_err(|err| {
+                            err.in_element(ssz_rs::PathElement::from(#field_name_str))
+                        })?;
+                    }
+                }
                 None => panic!("should have already returned an impl"),
             });
 
             quote! {
-                fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, ssz_rs::SerializeError> {
-                    let mut serializer = ssz_rs::__internal::Serializer::default();
+                fn serialize_to<S: ssz_rs::Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, ssz_rs::SerializeError> {
+                    let fixed_length = 0 #(+ #fixed_length_by_field)*;
+                    let mut serializer = ssz_rs::__internal::Serializer::new(sink, fixed_length);
+
+                    #(#fixed_part_by_field)*
 
-                    #(#serialization_by_field)*
+                    #(#variable_part_by_field)*
 
-                    serializer.serialize(buffer)
+                    serializer.finalize()
                 }
             }
         }
         Data::Enum(ref data) => {
+            let selectors = union_selectors(data, helper_attr);
             let serialization_by_variant = data.variants.iter().enumerate().map(|(i, variant)| {
                 let variant_name = &variant.ident;
+                let selector = selectors[i];
+                let selector_index = selector as usize;
                 match &variant.fields {
                     Fields::Unnamed(..) => {
                         if matches!(helper_attr, Some(&HelperAttr::Transparent)) {
                             quote_spanned! { variant.span() =>
-                                Self::#variant_name(value) => value.serialize(buffer),
+                                Self::#variant_name(value) => ssz_rs::Serialize::serialize_to(value, sink),
                             }
                         } else {
                             quote_spanned! { variant.span() =>
                                 Self::#variant_name(value) => {
-                                    let selector = #i as u8;
-                                    let selector_bytes = selector.serialize(buffer)?;
-                                    let value_bytes  = value.serialize(buffer)?;
+                                    let selector = #selector;
+                                    let selector_bytes = ssz_rs::Serialize::serialize_to(&selector, sink)?;
+                                    let value_bytes = ssz_rs::Serialize::serialize_to(value, sink)
+                                        .map_err(|err| err.in_element(ssz_rs::PathElement::Index(#selector_index)))?;
                                     Ok(selector_bytes + value_bytes)
                                 }
                             }
@@ -78,7 +123,7 @@ fn derive_serialize_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> Token
                     Fields::Unit => {
                         quote_spanned! { variant.span() =>
                             Self::None => {
-                                0u8.serialize(buffer)
+                                ssz_rs::Serialize::serialize_to(&0u8, sink)
                             }
                         }
                     }
@@ -87,7 +132,7 @@ fn derive_serialize_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> Token
             });
 
             quote! {
-                fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, ssz_rs::SerializeError> {
+                fn serialize_to<S: ssz_rs::Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, ssz_rs::SerializeError> {
                     match self {
                         #(#serialization_by_variant)*
                     }
@@ -95,11 +140,19 @@ fn derive_serialize_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> Token
             }
         }
         Data::Union(..) => unreachable!("data was already validated to exclude union types"),
+    };
+
+    quote! {
+        fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, ssz_rs::SerializeError> {
+            ssz_rs::Serialize::serialize_to(self, buffer)
+        }
+
+        #serialize_to_impl
     }
 }
 
 fn derive_deserialize_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> TokenStream {
-    match data {
+    let deserialize_with_impl = match data {
         Data::Struct(ref data) => {
             let fields = match data.fields {
                 // "regular" struct with 1+ fields
@@ -115,6 +168,11 @@ fn derive_deserialize_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> Tok
                             let result = <#field_type>::deserialize(&encoding)?;
                             Ok(Self(result))
                         }
+
+                        fn deserialize_with(encoding: &[u8], context: &mut ssz_rs::DecodeContext) -> Result<Self, ssz_rs::DeserializeError> {
+                            let result = <#field_type as ssz_rs::Deserialize>::deserialize_with(&encoding, context)?;
+                            Ok(Self(result))
+                        }
                     }
                 }
                 _ => unimplemented!(
@@ -134,25 +192,29 @@ fn derive_deserialize_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> Tok
             let initialization_by_field = fields.iter().enumerate().map(|(i, f)| {
                 let field_type = &f.ty;
                 match &f.ident {
-                    Some(field_name) => quote_spanned! { f.span() =>
-                        #field_name: <#field_type>::deserialize(&encoding[spans[2*#i]..spans[2*#i+1]])?,
-                    },
+                    Some(field_name) => {
+                        let field_name_str = field_name.to_string();
+                        quote_spanned! { f.span() =>
+                            #field_name: <#field_type as ssz_rs::Deserialize>::deserialize_with(&encoding[spans[2*#i]..spans[2*#i+1]], context)
+                                .map_err(|err| err.in_element(ssz_rs::PathElement::from(#field_name_str), spans[2*#i]))?,
+                        }
+                    }
                     None => panic!("should have already returned an impl"),
                 }
             });
 
             quote! {
-                fn deserialize(encoding: &[u8]) -> Result<Self, ssz_rs::DeserializeError> {
-                    let mut deserializer = ssz_rs::__internal::ContainerDeserializer::default();
+                let mut deserializer = ssz_rs::__internal::ContainerDeserializer::default();
 
-                    #(#deserialization_by_field)*
+                #(#deserialization_by_field)*
 
-                    let spans = deserializer.finalize(encoding)?;
+                let spans = deserializer.finalize(encoding)?;
 
+                context.nested(|context| {
                     Ok(Self {
                         #(#initialization_by_field)*
                     })
-                }
+                })
             }
         }
         Data::Enum(ref data) => {
@@ -166,7 +228,9 @@ fn derive_deserialize_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> Tok
                             // element; qed
                             let variant_type = &inner.unnamed[0];
                             quote_spanned! { variant.span() =>
-                                if let Ok(value) = <#variant_type>::deserialize(encoding) {
+                                if let Ok(value) = context.attempt(|context| {
+                                    <#variant_type as ssz_rs::Deserialize>::deserialize_with(encoding, context)
+                                }) {
                                     return Ok(Self::#variant_name(value))
                                 }
                             }
@@ -179,10 +243,10 @@ fn derive_deserialize_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> Tok
                     Err(ssz_rs::DeserializeError::NoMatchingVariant)
                 }
             } else {
+                let selectors = union_selectors(data, helper_attr);
                 let deserialization_by_variant =
                     data.variants.iter().enumerate().map(|(i, variant)| {
-                        // NOTE: this is "safe" as the number of legal variants fits into `u8`
-                        let i = i as u8;
+                        let i = selectors[i];
                         let variant_name = &variant.ident;
                         match &variant.fields {
                             Fields::Unnamed(inner) => {
@@ -192,7 +256,9 @@ fn derive_deserialize_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> Tok
                                 quote_spanned! { variant.span() =>
                                     #i => {
                                         // SAFETY: index is safe because encoding isn't empty; qed
-                                        let value = <#variant_type>::deserialize(&encoding[1..])?;
+                                        let value = context.nested(|context| {
+                                            <#variant_type as ssz_rs::Deserialize>::deserialize_with(&encoding[1..], context)
+                                        }).map_err(|err| err.in_element(ssz_rs::PathElement::Index(#i as usize), 1))?;
                                         Ok(Self::#variant_name(value))
                                     }
                                 }
@@ -223,19 +289,27 @@ fn derive_deserialize_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> Tok
             };
 
             quote! {
-                fn deserialize(encoding: &[u8]) -> Result<Self, ssz_rs::DeserializeError> {
-                    if encoding.is_empty() {
-                        return Err(ssz_rs::DeserializeError::ExpectedFurtherInput {
-                            provided: 0,
-                            expected: 1,
-                        });
-                    }
-
-                    #body
+                if encoding.is_empty() {
+                    return Err(ssz_rs::DeserializeError::ExpectedFurtherInput {
+                        provided: 0,
+                        expected: 1,
+                    });
                 }
+
+                #body
             }
         }
         Data::Union(..) => unreachable!("data was already validated to exclude union types"),
+    };
+
+    quote! {
+        fn deserialize(encoding: &[u8]) -> Result<Self, ssz_rs::DeserializeError> {
+            Self::deserialize_with(encoding, &mut ssz_rs::DecodeContext::default())
+        }
+
+        fn deserialize_with(encoding: &[u8], context: &mut ssz_rs::DecodeContext) -> Result<Self, ssz_rs::DeserializeError> {
+            #deserialize_with_impl
+        }
     }
 }
 
@@ -313,6 +387,9 @@ fn derive_merkleization_impl(
     generics: &Generics,
     helper_attr: Option<&HelperAttr>,
 ) -> TokenStream {
+    if let Some(helper_attr) = helper_attr.filter(|attr| attr.is_stable()) {
+        return stable_container::derive_merkleization_impl(data, name, generics, helper_attr)
+    }
     let (hash_tree_root_impl, chunks_impl) = match data {
         Data::Struct(ref data) => {
             let fields = match data.fields {
@@ -325,12 +402,12 @@ fn derive_merkleization_impl(
             let field_count = fields.iter().len();
             let impl_by_field = fields.iter().enumerate().map(|(i, f)| match &f.ident {
                 Some(field_name) => quote_spanned! { f.span() =>
-                    let chunk = self.#field_name.hash_tree_root()?;
+                    let chunk = self.#field_name.hash_tree_root_with::<H>()?;
                     let range = #i*#BYTES_PER_CHUNK..(#i+1)*#BYTES_PER_CHUNK;
                     chunks[range].copy_from_slice(chunk.as_ref());
                 },
                 None => quote_spanned! { f.span() =>
-                    let chunk = self.0.hash_tree_root()?;
+                    let chunk = self.0.hash_tree_root_with::<H>()?;
                     let range = #i*#BYTES_PER_CHUNK..(#i+1)*#BYTES_PER_CHUNK;
                     chunks[range].copy_from_slice(chunk.as_ref());
                 },
@@ -341,38 +418,40 @@ fn derive_merkleization_impl(
                 Ok(chunks)
             };
             let hash_tree_root_impl = quote! {
-                let chunks = self.assemble_chunks()?;
-                ssz_rs::__internal::merkleize(&chunks, None)
+                let chunks = self.assemble_chunks::<H>()?;
+                ssz_rs::__internal::merkleize_with::<H>(&chunks, None)
             };
             (hash_tree_root_impl, chunks_impl)
         }
         Data::Enum(ref data) => {
+            let selectors = union_selectors(data, helper_attr);
             let implementations = data.variants.iter().enumerate().map(|(i, variant)| {
                 let variant_name = &variant.ident;
+                let selector = selectors[i] as usize;
                 match &variant.fields {
                     Fields::Unnamed(..) => {
-                        // NOTE: validated to only be `transparent` operation at this point...
-                        let hash_tree_by_variant = if helper_attr.is_some() {
-                            quote_spanned! { variant.span() =>
-                               Self::#variant_name(value) => value.hash_tree_root(),
-                            }
-                        } else {
-                            quote_spanned! { variant.span() =>
-                               Self::#variant_name(value) => {
-                                   let selector = #i;
-                                   let chunks = value.hash_tree_root()?;
-                                   Ok(ssz_rs::__internal::mix_in_selector(chunks, selector))
-                               }
-                            }
-                        };
+                        let hash_tree_by_variant =
+                            if matches!(helper_attr, Some(&HelperAttr::Transparent)) {
+                                quote_spanned! { variant.span() =>
+                                   Self::#variant_name(value) => value.hash_tree_root_with::<H>(),
+                                }
+                            } else {
+                                quote_spanned! { variant.span() =>
+                                   Self::#variant_name(value) => {
+                                       let selector = #selector;
+                                       let chunks = value.hash_tree_root_with::<H>()?;
+                                       Ok(ssz_rs::__internal::mix_in_selector_with::<H>(chunks, selector))
+                                   }
+                                }
+                            };
                         let chunks_by_variant = quote! {
-                           Self::#variant_name(value) => Ok(value.hash_tree_root()?.to_vec()),
+                           Self::#variant_name(value) => Ok(value.hash_tree_root_with::<H>()?.to_vec()),
                         };
                         (hash_tree_by_variant, chunks_by_variant)
                     }
                     Fields::Unit => (
                         quote_spanned! { variant.span() =>
-                            Self::None => Ok(ssz_rs::__internal::mix_in_selector(
+                            Self::None => Ok(ssz_rs::__internal::mix_in_selector_with::<H>(
                                 Node::default(),
                                 0,
                             )),
@@ -401,18 +480,36 @@ fn derive_merkleization_impl(
         }
         Data::Union(..) => unreachable!("data was already validated to exclude union types"),
     };
+    let layout_check = match data {
+        Data::Enum(ref data) if matches!(helper_attr, Some(&HelperAttr::CompatibleUnion)) => {
+            compatible_union::derive_layout_check(data, generics)
+        }
+        _ => quote! {},
+    };
     let (impl_generics, ty_generics, _) = generics.split_for_impl();
     quote! {
+        #layout_check
+
         impl #impl_generics #name #ty_generics {
-            fn assemble_chunks(&self) -> Result<Vec<u8>, ssz_rs::MerkleizationError> {
+            fn assemble_chunks<H: ssz_rs::MerkleHasher>(&self) -> Result<Vec<u8>, ssz_rs::MerkleizationError> {
                 #chunks_impl
             }
         }
 
         impl #impl_generics ssz_rs::HashTreeRoot for #name #ty_generics {
             fn hash_tree_root(&self) -> Result<ssz_rs::Node, ssz_rs::MerkleizationError> {
+                self.hash_tree_root_with::<ssz_rs::Sha256Hasher>()
+            }
+
+            fn hash_tree_root_with<H: ssz_rs::MerkleHasher>(&self) -> Result<ssz_rs::Node, ssz_rs::MerkleizationError> {
                 #hash_tree_root_impl
             }
+
+            fn elements_to_chunks<H: ssz_rs::MerkleHasher>(elements: &[Self]) -> Result<Vec<u8>, ssz_rs::MerkleizationError> {
+                use ssz_rs::__internal::{ParallelElementsToChunks as _, SequentialElementsToChunks as _};
+                // NOTE: computes the roots in parallel when `Self` is `Sync`
+                (&&ssz_rs::__internal::Elements(elements)).elements_to_chunks::<H>()
+            }
         }
     }
 }
@@ -421,7 +518,16 @@ fn derive_generalized_indexable_impl(
     data: &Data,
     name: &Ident,
     generics: &Generics,
+    helper_attr: Option<&HelperAttr>,
 ) -> TokenStream {
+    if let Some(helper_attr) = helper_attr.filter(|attr| attr.is_stable()) {
+        return stable_container::derive_generalized_indexable_impl(
+            data,
+            name,
+            generics,
+            helper_attr,
+        )
+    }
     let (impl_generics, ty_generics, _) = generics.split_for_impl();
 
     let (compute_generalized_index_impl, helper_impl) = match data {
@@ -479,17 +585,17 @@ fn derive_generalized_indexable_impl(
             Fields::Unit => unreachable!("validated to exclude this type"),
         },
         Data::Enum(ref data) => {
-            let variant_count = data.variants.len();
-
+            let selectors = union_selectors(data, helper_attr);
             let impl_by_variant = data.variants.iter().enumerate().map(|(i, variant)| {
                 let variant_name = &variant.ident;
+                let selector = selectors[i] as usize;
                 match &variant.fields {
                     Fields::Unnamed(ref fields) => {
                         let field =
                             fields.unnamed.first().expect("validated to only have one field");
                         let ty = &field.ty;
                         quote! {
-                            #i => {
+                            #selector => {
                                 <#ty as ssz_rs::GeneralizedIndexable>::compute_generalized_index(child, rest)
                             }
                         }
@@ -518,13 +624,10 @@ fn derive_generalized_indexable_impl(
                 if let Some((next, rest)) = path.split_first() {
                     match next {
                         PathElement::Index(i) => {
-                            if *i >= #variant_count {
-                                return Err(MerkleizationError::InvalidPathElement(next.clone()))
-                            }
                             let child = parent * 2;
                             match *i {
                                 #(#impl_by_variant)*
-                                _ => unreachable!("validated in covered range"),
+                                _ => Err(MerkleizationError::InvalidPathElement(next.clone())),
                             }
                         }
                         PathElement::Selector => {
@@ -588,14 +691,23 @@ fn derive_generalized_indexable_impl(
     }
 }
 
-fn derive_prove_impl(data: &Data, name: &Ident, generics: &Generics) -> TokenStream {
+fn derive_prove_impl(
+    data: &Data,
+    name: &Ident,
+    generics: &Generics,
+    helper_attr: Option<&HelperAttr>,
+) -> TokenStream {
+    if let Some(helper_attr) = helper_attr.filter(|attr| attr.is_stable()) {
+        return stable_container::derive_prove_impl(data, name, generics, helper_attr)
+    }
     let (impl_generics, ty_generics, _) = generics.split_for_impl();
 
-    let (chunks_impl, prove_element_impl, decoration_impl) = match data {
+    let (chunks_impl, prove_element_impl, subtree_impl, decoration_impl) = match data {
         Data::Struct(ref data) => match data.fields {
             Fields::Named(ref fields) => {
                 let fields = &fields.named;
                 let field_count = fields.len();
+                let field_names = fields.iter().map(|field| &field.ident);
                 let impl_by_field = fields.iter().enumerate().map(|(i, field)| {
                     let field_name = field.ident.as_ref().expect("only named fields");
                     quote! {
@@ -606,7 +718,7 @@ fn derive_prove_impl(data: &Data, name: &Ident, generics: &Generics) -> TokenStr
                     }
                 });
                 let chunks_impl = quote! {
-                    self.assemble_chunks()
+                    self.assemble_chunks::<H>()
                 };
 
                 let prove_element_impl = quote! {
@@ -620,41 +732,61 @@ fn derive_prove_impl(data: &Data, name: &Ident, generics: &Generics) -> TokenStr
                     }
                 };
 
-                (chunks_impl, prove_element_impl, None)
+                let subtree_impl = quote! {
+                    let elements = vec![
+                        #(ssz_rs::Prove::subtree::<H>(&self.#field_names)?),*
+                    ];
+                    Ok(ssz_rs::Subtree::from_elements(elements, #field_count, None))
+                };
+
+                (chunks_impl, prove_element_impl, subtree_impl, None)
             }
             Fields::Unnamed(..) => {
                 // NOTE: new type pattern, proxy to wrapped type...
                 let chunks_impl = quote! {
-                    self.0.chunks()
+                    ssz_rs::Prove::chunks::<H>(&self.0)
                 };
 
                 let prove_element_impl = quote! {
-                    self.0.prove_element(index, prover)
+                    ssz_rs::Prove::prove_element(&self.0, index, prover)
+                };
+
+                let subtree_impl = quote! {
+                    ssz_rs::Prove::subtree::<H>(&self.0)
                 };
 
                 let decoration_impl = quote! {
                     fn decoration(&self) -> Option<usize> {
                         self.0.decoration()
                     }
+
+                    fn decoration_root<H: ssz_rs::MerkleHasher>(&self) -> Result<Option<ssz_rs::Node>, ssz_rs::MerkleizationError> {
+                        ssz_rs::Prove::decoration_root::<H>(&self.0)
+                    }
                 };
-                (chunks_impl, prove_element_impl, Some(decoration_impl))
+                (chunks_impl, prove_element_impl, subtree_impl, Some(decoration_impl))
             }
             Fields::Unit => unreachable!("validated to exclude this type"),
         },
         Data::Enum(ref data) => {
             let variant_count = data.variants.len();
+            let selectors = union_selectors(data, helper_attr);
 
             let implementations = data.variants.iter().enumerate().map(|(i, variant)| {
                 let variant_name = &variant.ident;
+                let selector = selectors[i] as usize;
                 match &variant.fields {
                     Fields::Unnamed(..) => {
                         let prove_element_impl = quote! {
                             Self::#variant_name(value) => prover.compute_proof(value),
                         };
+                        let subtree_impl = quote! {
+                            Self::#variant_name(value) => ssz_rs::Prove::subtree::<H>(value)?,
+                        };
                         let decoration_impl = quote! {
-                            Self::#variant_name(_) => Some(#i),
+                            Self::#variant_name(_) => Some(#selector),
                         };
-                        (prove_element_impl, decoration_impl)
+                        (prove_element_impl, (subtree_impl, decoration_impl))
                     }
                     Fields::Unit => {
                         // NOTE: this has already been validated to conform to:
@@ -669,16 +801,23 @@ fn derive_prove_impl(data: &Data, name: &Ident, generics: &Generics) -> TokenStr
                                     prover.compute_proof(&leaf)
                                 }
                             },
-                            quote! {
-                                Self::None => Some(#i),
-                            },
+                            (
+                                quote! {
+                                    Self::None => ssz_rs::Subtree::default(),
+                                },
+                                quote! {
+                                    Self::None => Some(#i),
+                                },
+                            ),
                         )
                     }
                     _ => unreachable!("other variants validated to not exist"),
                 }
             });
-            let (impl_by_variant, decoration_by_variant): (Vec<_>, Vec<_>) =
-                implementations.unzip();
+            let (impl_by_variant, (subtree_by_variant, decoration_by_variant)): (
+                Vec<_>,
+                (Vec<_>, Vec<_>),
+            ) = implementations.unzip();
 
             let prove_element_impl = quote! {
                 if index >= #variant_count {
@@ -690,7 +829,14 @@ fn derive_prove_impl(data: &Data, name: &Ident, generics: &Generics) -> TokenStr
                 }
             };
             let chunks_impl = quote! {
-                self.assemble_chunks()
+                self.assemble_chunks::<H>()
+            };
+            let subtree_impl = quote! {
+                let element = match self {
+                    #(#subtree_by_variant)*
+                };
+                let decoration = ssz_rs::Prove::decoration_root::<H>(self)?;
+                Ok(ssz_rs::Subtree::from_elements(vec![element], 1, decoration))
             };
             let decoration_impl = quote! {
                 fn decoration(&self) -> Option<usize> {
@@ -699,35 +845,325 @@ fn derive_prove_impl(data: &Data, name: &Ident, generics: &Generics) -> TokenStr
                     }
                 }
             };
-            (chunks_impl, prove_element_impl, Some(decoration_impl))
+            (chunks_impl, prove_element_impl, subtree_impl, Some(decoration_impl))
         }
         Data::Union(..) => unreachable!("data was already validated to exclude union types"),
     };
 
     quote! {
         impl #impl_generics ssz_rs::Prove for #name #ty_generics {
-            fn chunks(&self) -> Result<Vec<u8>, ssz_rs::MerkleizationError> {
+            fn chunks<H: ssz_rs::MerkleHasher>(&self) -> Result<Vec<u8>, ssz_rs::MerkleizationError> {
                 #chunks_impl
             }
 
-            fn prove_element(
+            fn prove_element<H: ssz_rs::MerkleHasher>(
                 &self,
                 index: usize,
-                prover: &mut ssz_rs::proofs::Prover,
+                prover: &mut ssz_rs::proofs::Prover<H>,
             ) -> Result<(), ssz_rs::MerkleizationError> {
                 #prove_element_impl
             }
 
+            fn subtree<H: ssz_rs::MerkleHasher>(&self) -> Result<ssz_rs::Subtree<H>, ssz_rs::MerkleizationError> {
+                #subtree_impl
+            }
+
             #decoration_impl
         }
     }
 }
 
+fn derive_diff_impl(
+    data: &Data,
+    name: &Ident,
+    generics: &Generics,
+    helper_attr: Option<&HelperAttr>,
+) -> TokenStream {
+    if let Some(helper_attr) = helper_attr.filter(|attr| attr.is_stable()) {
+        return stable_container::derive_diff_impl(data, name, generics, helper_attr)
+    }
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+
+    let diff_elements_impl = match data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => {
+                let impl_by_field = fields.named.iter().map(|field| {
+                    let field_name = field.ident.as_ref().expect("only named fields");
+                    let selector = format!("{field_name}");
+                    let field_ty = &field.ty;
+                    quote! {
+                        differ.compare_element::<Self, #field_ty>(
+                            ssz_rs::PathElement::Field(#selector.to_string()),
+                            &self.#field_name,
+                            &other.#field_name,
+                        )?;
+                    }
+                });
+                quote! {
+                    #(#impl_by_field)*
+                    Ok(())
+                }
+            }
+            Fields::Unnamed(..) => {
+                // NOTE: new type pattern, proxy to wrapped type...
+                quote! {
+                    ssz_rs::Diff::diff_elements(&self.0, &other.0, differ)
+                }
+            }
+            Fields::Unit => unreachable!("validated to exclude this type"),
+        },
+        Data::Enum(ref data) => {
+            let selectors = union_selectors(data, helper_attr);
+            let impl_by_variant = data.variants.iter().enumerate().filter_map(|(i, variant)| {
+                let variant_name = &variant.ident;
+                let selector = selectors[i] as usize;
+                match &variant.fields {
+                    Fields::Unnamed(ref fields) => {
+                        let field =
+                            fields.unnamed.first().expect("validated to only have one field");
+                        let ty = &field.ty;
+                        let body = if matches!(helper_attr, Some(&HelperAttr::Transparent)) {
+                            quote! {
+                                ssz_rs::Diff::diff_elements(left, right, differ)
+                            }
+                        } else {
+                            quote! {
+                                differ.compare_element::<Self, #ty>(
+                                    ssz_rs::PathElement::Index(#selector),
+                                    left,
+                                    right,
+                                )
+                            }
+                        };
+                        Some(quote! {
+                            (Self::#variant_name(left), Self::#variant_name(right)) => #body,
+                        })
+                    }
+                    // NOTE: two `None` values have the same root
+                    Fields::Unit => None,
+                    _ => unreachable!("other variants validated to not exist"),
+                }
+            });
+            let other_variants_impl = if data.variants.len() > 1 {
+                // NOTE: values of different variants are reported as a whole
+                Some(quote! {
+                    _ => Ok(()),
+                })
+            } else {
+                None
+            };
+            quote! {
+                match (self, other) {
+                    #(#impl_by_variant)*
+                    #other_variants_impl
+                }
+            }
+        }
+        Data::Union(..) => unreachable!("data was already validated to exclude union types"),
+    };
+
+    quote! {
+        impl #impl_generics ssz_rs::Diff for #name #ty_generics {
+            fn diff_elements(
+                &self,
+                other: &Self,
+                differ: &mut ssz_rs::Differ,
+            ) -> Result<(), ssz_rs::MerkleizationError> {
+                #diff_elements_impl
+            }
+        }
+    }
+}
+
+fn derive_cacheable_impl(data: &Data, name: &Ident, generics: &Generics) -> TokenStream {
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+
+    let cacheable_impl = match data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => {
+                let field_count = fields.named.len();
+                let impl_by_field = fields.named.iter().enumerate().map(|(i, field)| {
+                    let field_name = field.ident.as_ref().expect("only named fields");
+                    quote! {
+                        #i => self.#field_name.hash_tree_root_with::<H>(),
+                    }
+                });
+                quote! {
+                    fn chunk_len(&self) -> usize {
+                        #field_count
+                    }
+
+                    fn chunk<H: ssz_rs::MerkleHasher>(&self, index: usize) -> Result<ssz_rs::Node, ssz_rs::MerkleizationError> {
+                        match index {
+                            #(#impl_by_field)*
+                            _ => Err(ssz_rs::MerkleizationError::InvalidInnerIndex),
+                        }
+                    }
+                }
+            }
+            Fields::Unnamed(ref fields) => {
+                // NOTE: new type pattern, proxy to wrapped type...
+                let field = fields.unnamed.first().expect("validated to only have one field");
+                let ty = &field.ty;
+                quote! {
+                    fn chunk_len(&self) -> usize {
+                        self.0.chunk_len()
+                    }
+
+                    fn chunk<H: ssz_rs::MerkleHasher>(&self, index: usize) -> Result<ssz_rs::Node, ssz_rs::MerkleizationError> {
+                        self.0.chunk::<H>(index)
+                    }
+
+                    fn chunk_index(index: usize) -> usize {
+                        <#ty as ssz_rs::Cacheable>::chunk_index(index)
+                    }
+                }
+            }
+            Fields::Unit => unreachable!("validated to exclude this type"),
+        },
+        _ => panic!("`Cacheable` is only supported for structs"),
+    };
+
+    quote! {
+        impl #impl_generics ssz_rs::Cacheable for #name #ty_generics {
+            #cacheable_impl
+        }
+    }
+}
+
+fn derive_viewable_impl(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+    let vis = &input.vis;
+    let generics = &input.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let data = match &input.data {
+        Data::Struct(ref data) => data,
+        _ => panic!("views can only be derived for structs"),
+    };
+    let fields = match data.fields {
+        Fields::Named(ref fields) => &fields.named,
+        // "newtype" pattern, so use the view of the inner type
+        Fields::Unnamed(ref fields) => {
+            let inner_type = &fields.unnamed[0].ty;
+            return quote! {
+                impl #impl_generics ssz_rs::Viewable for #name #ty_generics #where_clause {
+                    type View<'a> = <#inner_type as ssz_rs::Viewable>::View<'a>;
+
+                    fn view(encoding: &[u8]) -> Result<Self::View<'_>, ssz_rs::DeserializeError> {
+                        <#inner_type as ssz_rs::Viewable>::view(encoding)
+                    }
+                }
+            }
+        }
+        _ => unimplemented!("this type of struct is currently not supported by this derive macro"),
+    };
+
+    let view_name = format_ident!("{}View", name);
+    let mut view_generics = generics.clone();
+    view_generics.params.insert(0, syn::parse_quote!('a));
+    let (view_impl_generics, view_ty_generics, _) = view_generics.split_for_impl();
+
+    let span_count = 2 * fields.len();
+    let parse_by_field = fields.iter().map(|f| {
+        let field_type = &f.ty;
+        quote_spanned! { f.span() =>
+            deserializer.parse::<#field_type>(encoding)?;
+        }
+    });
+    let accessor_by_field = fields.iter().enumerate().map(|(i, f)| {
+        let field_name = f.ident.as_ref().expect("only named fields");
+        let field_type = &f.ty;
+        let field_vis = &f.vis;
+        quote_spanned! { f.span() =>
+            #field_vis fn #field_name(&self) -> Result<<#field_type as ssz_rs::Viewable>::View<'a>, ssz_rs::DeserializeError> {
+                let encoding = &self.encoding[self.spans[2*#i]..self.spans[2*#i+1]];
+                <#field_type as ssz_rs::Viewable>::view(encoding)
+            }
+        }
+    });
+    let view_name_str = view_name.to_string();
+    let view_doc = format!("A borrowed view over the encoding of a `{name}`.");
+
+    quote! {
+        #[doc = #view_doc]
+        #vis struct #view_name #view_impl_generics #where_clause {
+            encoding: &'a [u8],
+            spans: [usize; #span_count],
+            _p: core::marker::PhantomData<#name #ty_generics>,
+        }
+
+        impl #view_impl_generics Clone for #view_name #view_ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl #view_impl_generics Copy for #view_name #view_ty_generics #where_clause {}
+
+        impl #view_impl_generics core::fmt::Debug for #view_name #view_ty_generics #where_clause {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.debug_struct(#view_name_str).finish_non_exhaustive()
+            }
+        }
+
+        impl #view_impl_generics #view_name #view_ty_generics #where_clause {
+            #(#accessor_by_field)*
+
+            /// Returns the underlying encoding.
+            #vis fn as_bytes(&self) -> &'a [u8] {
+                self.encoding
+            }
+
+            /// Decode the entire value this view refers to.
+            #vis fn decode(&self) -> Result<#name #ty_generics, ssz_rs::DeserializeError> {
+                <#name #ty_generics as ssz_rs::Deserialize>::deserialize(self.encoding)
+            }
+        }
+
+        impl #impl_generics ssz_rs::Viewable for #name #ty_generics #where_clause {
+            type View<'a> = #view_name #view_ty_generics;
+
+            fn view(encoding: &[u8]) -> Result<Self::View<'_>, ssz_rs::DeserializeError> {
+                let mut deserializer = ssz_rs::__internal::ContainerDeserializer::default();
+
+                #(#parse_by_field)*
+
+                let spans = deserializer.finalize(encoding)?;
+                let spans = spans.try_into().expect("one span per field");
+                Ok(#view_name { encoding, spans, _p: core::marker::PhantomData })
+            }
+        }
+    }
+}
+
+// Returns `generics` where each type parameter is additionally bound by `bound`.
+fn with_bounds(generics: &Generics, bound: TokenStream) -> Generics {
+    let mut generics = generics.clone();
+    let params = generics.type_params().map(|param| param.ident.clone()).collect::<Vec<_>>();
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        where_clause.predicates.push(parse_quote!(#param: #bound));
+    }
+    generics
+}
+
+// Returns the selector of each variant of a union, which is the position of the variant unless the
+// union is a `compatible_union` with explicit selectors.
+pub(crate) fn union_selectors(data: &DataEnum, helper_attr: Option<&HelperAttr>) -> Vec<u8> {
+    if matches!(helper_attr, Some(&HelperAttr::CompatibleUnion)) {
+        compatible_union::selectors(data)
+    } else {
+        // NOTE: this is "safe" as the number of legal variants fits into `u8`
+        (0..data.variants.len()).map(|i| i as u8).collect()
+    }
+}
+
 fn is_valid_none_identifier(ident: &Ident) -> bool {
     *ident == format_ident!("None")
 }
 
-fn filter_ssz_attrs<'a>(
+pub(crate) fn filter_ssz_attrs<'a>(
     attrs: impl Iterator<Item = &'a Attribute>,
 ) -> impl Iterator<Item = &'a Attribute> {
     attrs.filter(|&f| {
@@ -739,7 +1175,7 @@ fn filter_ssz_attrs<'a>(
     })
 }
 
-fn validate_no_attrs<'a>(fields: impl Iterator<Item = &'a Field>) {
+pub(crate) fn validate_no_attrs<'a>(fields: impl Iterator<Item = &'a Field>) {
     let mut ssz_attrs = fields.flat_map(|field| filter_ssz_attrs(field.attrs.iter()));
     if ssz_attrs.next().is_some() {
         panic!("macro attribute `{SSZ_HELPER_ATTRIBUTE}` is only allowed at enum level")
@@ -759,6 +1195,10 @@ fn validate_derive_input(data: &Data, helper_attrs: &[HelperAttr]) {
     if let Some(attr) = helper_attrs.first() {
         match attr {
             HelperAttr::Transparent => is_transparent = true,
+            HelperAttr::StableContainer(..) | HelperAttr::Profile(..) => {
+                return stable_container::validate_derive_input(data, attr)
+            }
+            HelperAttr::CompatibleUnion => return compatible_union::validate_derive_input(data),
         }
     }
     if is_transparent && !matches!(data, Data::Enum(..)) {
@@ -839,26 +1279,356 @@ fn validate_derive_input(data: &Data, helper_attrs: &[HelperAttr]) {
     }
 }
 
-fn derive_serializable_impl(
-    data: &Data,
-    name: &Ident,
-    generics: &Generics,
-    helper_attr: Option<&HelperAttr>,
-) -> proc_macro2::TokenStream {
-    let serialize_impl = derive_serialize_impl(data, helper_attr);
-    let deserialize_impl = derive_deserialize_impl(data, helper_attr);
-    let is_variable_size_impl = derive_variable_size_impl(data);
-    let size_hint_impl = derive_size_hint_impl(data);
-
-    let (impl_generics, ty_generics, _) = generics.split_for_impl();
-    quote! {
-        impl #impl_generics ssz_rs::Serialize for #name #ty_generics {
-            #serialize_impl
-        }
-
-        impl #impl_generics ssz_rs::Deserialize for #name #ty_generics {
-            #deserialize_impl
-        }
+fn derive_max_encoded_len_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> TokenStream {
+    match data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => {
+                let len_by_field = fields.named.iter().map(|f| {
+                    let field_type = &f.ty;
+                    quote_spanned! { f.span() =>
+                        .saturating_add(ssz_rs::__internal::element_max_encoded_len::<#field_type>())
+                    }
+                });
+                quote! {
+                    0usize #(#len_by_field)*
+                }
+            }
+            Fields::Unnamed(ref fields) => {
+                // SAFETY: index is safe because newtypes have exactly one field; qed
+                let field_type = &fields.unnamed[0].ty;
+                quote! {
+                    <#field_type as ssz_rs::Serializable>::max_encoded_len()
+                }
+            }
+            _ => unimplemented!(
+                "this type of struct is currently not supported by this derive macro"
+            ),
+        },
+        Data::Enum(ref data) => {
+            let len_by_variant = data.variants.iter().filter_map(|variant| match &variant.fields {
+                Fields::Unnamed(inner) => {
+                    // SAFETY: index is safe because Punctuated always has a first
+                    // element; qed
+                    let variant_type = &inner.unnamed[0].ty;
+                    Some(quote_spanned! { variant.span() =>
+                        .max(<#variant_type as ssz_rs::Serializable>::max_encoded_len())
+                    })
+                }
+                Fields::Unit => None,
+                _ => unreachable!(),
+            });
+            if matches!(helper_attr, Some(&HelperAttr::Transparent)) {
+                quote! {
+                    0usize #(#len_by_variant)*
+                }
+            } else {
+                // NOTE: the selector takes an additional byte
+                quote! {
+                    (0usize #(#len_by_variant)*).saturating_add(1)
+                }
+            }
+        }
+        Data::Union(..) => unreachable!("data was already validated to exclude union types"),
+    }
+}
+
+fn derive_encoded_len_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> TokenStream {
+    match data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => {
+                let len_by_field = fields.named.iter().map(|f| {
+                    let field_name = &f.ident;
+                    let field_name_str = field_name.as_ref().unwrap().to_string();
+                    quote_spanned! { f.span() =>
+                        ssz_rs::__internal::element_encoded_len(&self.#field_name).map_err(|err| {
+                            err.in_element(ssz_rs::PathElement::from(#field_name_str))
+                        })?
+                    }
+                });
+                quote! {
+                    Ok(0 #(+ #len_by_field)*)
+                }
+            }
+            Fields::Unnamed(..) => quote! {
+                ssz_rs::Serializable::encoded_len(&self.0)
+            },
+            _ => unimplemented!(
+                "this type of struct is currently not supported by this derive macro"
+            ),
+        },
+        Data::Enum(ref data) => {
+            let selectors = union_selectors(data, helper_attr);
+            let len_by_variant = data.variants.iter().enumerate().map(|(i, variant)| {
+                let variant_name = &variant.ident;
+                let selector_index = selectors[i] as usize;
+                match &variant.fields {
+                    Fields::Unnamed(..) => {
+                        if matches!(helper_attr, Some(&HelperAttr::Transparent)) {
+                            quote_spanned! { variant.span() =>
+                                Self::#variant_name(value) => ssz_rs::Serializable::encoded_len(value),
+                            }
+                        } else {
+                            quote_spanned! { variant.span() =>
+                                Self::#variant_name(value) => {
+                                    let value_len = ssz_rs::Serializable::encoded_len(value)
+                                        .map_err(|err| err.in_element(ssz_rs::PathElement::Index(#selector_index)))?;
+                                    Ok(1 + value_len)
+                                }
+                            }
+                        }
+                    }
+                    Fields::Unit => {
+                        quote_spanned! { variant.span() =>
+                            Self::None => Ok(1),
+                        }
+                    }
+                    _ => unreachable!(),
+                }
+            });
+
+            quote! {
+                match self {
+                    #(#len_by_variant)*
+                }
+            }
+        }
+        Data::Union(..) => unreachable!("data was already validated to exclude union types"),
+    }
+}
+
+fn derive_validate_prefix_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> TokenStream {
+    match data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => {
+                let len_by_field = fields.named.iter().map(|f| {
+                    let field_type = &f.ty;
+                    quote_spanned! { f.span() =>
+                        ssz_rs::__internal::fixed_portion_length::<#field_type>()
+                    }
+                });
+                let parse_by_field = fields.named.iter().map(|f| {
+                    let field_type = &f.ty;
+                    quote_spanned! { f.span() =>
+                        if !deserializer.parse_prefix::<#field_type>(prefix)? {
+                            return Ok(true)
+                        }
+                    }
+                });
+                quote! {
+                    // NOTE: the offsets can only be validated once the fixed part is complete
+                    if prefix.len() < 0 #(+ #len_by_field)* {
+                        return Ok(false)
+                    }
+
+                    let mut deserializer = ssz_rs::__internal::ContainerDeserializer::default();
+                    #(#parse_by_field)*
+                    Ok(true)
+                }
+            }
+            Fields::Unnamed(ref fields) => {
+                // SAFETY: index is safe because Punctuated always has a first element; qed
+                let field_type = &fields.unnamed[0].ty;
+                quote! {
+                    <#field_type as ssz_rs::Serializable>::validate_prefix(prefix)
+                }
+            }
+            _ => unimplemented!(
+                "this type of struct is currently not supported by this derive macro"
+            ),
+        },
+        Data::Enum(ref data) => {
+            if matches!(helper_attr, Some(&HelperAttr::Transparent)) {
+                // NOTE: the variant is only known once the encoding is complete
+                return quote! {
+                    let _ = prefix;
+                    Ok(true)
+                }
+            }
+            let selectors = union_selectors(data, helper_attr);
+            let validation_by_variant = data.variants.iter().enumerate().map(|(i, variant)| {
+                let i = selectors[i];
+                match &variant.fields {
+                    Fields::Unnamed(inner) => {
+                        // SAFETY: index is safe because Punctuated always has a first
+                        // element; qed
+                        let variant_type = &inner.unnamed[0].ty;
+                        quote_spanned! { variant.span() =>
+                            // SAFETY: index is safe because prefix isn't empty; qed
+                            #i => <#variant_type as ssz_rs::Serializable>::validate_prefix(&prefix[1..])
+                                .map_err(|err| err.in_element(ssz_rs::PathElement::Index(#i as usize), 1)),
+                        }
+                    }
+                    Fields::Unit => quote! {
+                        0 => Ok(true),
+                    },
+                    _ => unreachable!(),
+                }
+            });
+            quote! {
+                let Some(selector) = prefix.first() else { return Ok(false) };
+                match *selector {
+                    #(#validation_by_variant)*
+                    b => Err(ssz_rs::DeserializeError::InvalidByte(b)),
+                }
+            }
+        }
+        Data::Union(..) => unreachable!("data was already validated to exclude union types"),
+    }
+}
+
+fn derive_bytes_at_path_impl(data: &Data, helper_attr: Option<&HelperAttr>) -> TokenStream {
+    let body = match data {
+        Data::Struct(ref data) => {
+            let fields = match data.fields {
+                Fields::Named(ref fields) => &fields.named,
+                Fields::Unnamed(ref fields) => {
+                    // SAFETY: index is safe because Punctuated always has a first element; qed
+                    let field_type = &fields.unnamed[0].ty;
+                    return quote! {
+                        <#field_type as ssz_rs::Serializable>::bytes_at_path(encoding, path)
+                    }
+                }
+                _ => unimplemented!(
+                    "this type of struct is currently not supported by this derive macro"
+                ),
+            };
+            let parse_by_field = fields.iter().map(|f| {
+                let field_type = &f.ty;
+                quote_spanned! { f.span() =>
+                    deserializer.parse::<#field_type>(encoding)?;
+                }
+            });
+            let selection_by_field = fields.iter().enumerate().map(|(i, f)| {
+                let field_type = &f.ty;
+                // SAFETY: unwrap is safe because fields are named; qed
+                let field_name_str = f.ident.as_ref().unwrap().to_string();
+                quote_spanned! { f.span() =>
+                    #field_name_str => <#field_type as ssz_rs::Serializable>::bytes_at_path(&encoding[spans[2*#i]..spans[2*#i+1]], rest)
+                        .map_err(|err| err.in_element(next.clone(), spans[2*#i])),
+                }
+            });
+            quote! {
+                let mut deserializer = ssz_rs::__internal::ContainerDeserializer::default();
+
+                #(#parse_by_field)*
+
+                let spans = deserializer.finalize(encoding)?;
+
+                match next {
+                    ssz_rs::PathElement::Field(name) => match name.as_str() {
+                        #(#selection_by_field)*
+                        _ => Err(ssz_rs::DeserializeError::InvalidPath(path.to_vec())),
+                    },
+                    _ => Err(ssz_rs::DeserializeError::InvalidPath(path.to_vec())),
+                }
+            }
+        }
+        Data::Enum(ref data) => {
+            if matches!(helper_attr, Some(&HelperAttr::Transparent)) {
+                // NOTE: variants are tried in the same order as when deserializing, but only the
+                // offsets along `path` are checked for each variant
+                let selection_by_variant = data.variants.iter().rev().map(|variant| {
+                    match &variant.fields {
+                        Fields::Unnamed(inner) => {
+                            // SAFETY: index is safe because Punctuated always has a first
+                            // element; qed
+                            let variant_type = &inner.unnamed[0].ty;
+                            quote_spanned! { variant.span() =>
+                                if let Ok(bytes) = <#variant_type as ssz_rs::Serializable>::bytes_at_path(encoding, path) {
+                                    return Ok(bytes)
+                                }
+                            }
+                        }
+                        _ => unreachable!("validated to exclude this condition"),
+                    }
+                });
+                return quote! {
+                    if path.is_empty() {
+                        return Ok(encoding)
+                    }
+
+                    #(#selection_by_variant)*
+                    Err(ssz_rs::DeserializeError::NoMatchingVariant)
+                }
+            } else {
+                let selectors = union_selectors(data, helper_attr);
+                let selection_by_variant = data.variants.iter().enumerate().map(|(i, variant)| {
+                    let i = selectors[i];
+                    match &variant.fields {
+                        Fields::Unnamed(inner) => {
+                            // SAFETY: index is safe because Punctuated always has a first
+                            // element; qed
+                            let variant_type = &inner.unnamed[0].ty;
+                            quote_spanned! { variant.span() =>
+                                #i if *index == #i as usize => {
+                                    // SAFETY: index is safe because encoding isn't empty; qed
+                                    <#variant_type as ssz_rs::Serializable>::bytes_at_path(&encoding[1..], rest)
+                                        .map_err(|err| err.in_element(next.clone(), 1))
+                                }
+                            }
+                        }
+                        Fields::Unit => quote! {},
+                        _ => unreachable!(),
+                    }
+                });
+                quote! {
+                    if encoding.is_empty() {
+                        return Err(ssz_rs::DeserializeError::ExpectedFurtherInput {
+                            provided: 0,
+                            expected: 1,
+                        });
+                    }
+
+                    match next {
+                        // SAFETY: index is safe because encoding isn't empty; qed
+                        ssz_rs::PathElement::Selector if rest.is_empty() => Ok(&encoding[..1]),
+                        ssz_rs::PathElement::Index(index) => match encoding[0] {
+                            #(#selection_by_variant)*
+                            b if !matches!(b, #(#selectors)|*) => Err(ssz_rs::DeserializeError::InvalidByte(b)),
+                            _ => Err(ssz_rs::DeserializeError::InvalidPath(path.to_vec())),
+                        },
+                        _ => Err(ssz_rs::DeserializeError::InvalidPath(path.to_vec())),
+                    }
+                }
+            }
+        }
+        Data::Union(..) => unreachable!("data was already validated to exclude union types"),
+    };
+
+    quote! {
+        let Some((next, rest)) = path.split_first() else { return Ok(encoding) };
+
+        #body
+    }
+}
+
+fn derive_serializable_impl(
+    data: &Data,
+    name: &Ident,
+    generics: &Generics,
+    helper_attr: Option<&HelperAttr>,
+) -> proc_macro2::TokenStream {
+    if let Some(helper_attr) = helper_attr.filter(|attr| attr.is_stable()) {
+        return stable_container::derive_serializable_impl(data, name, generics, helper_attr)
+    }
+    let serialize_impl = derive_serialize_impl(data, helper_attr);
+    let deserialize_impl = derive_deserialize_impl(data, helper_attr);
+    let is_variable_size_impl = derive_variable_size_impl(data);
+    let size_hint_impl = derive_size_hint_impl(data);
+    let encoded_len_impl = derive_encoded_len_impl(data, helper_attr);
+    let max_encoded_len_impl = derive_max_encoded_len_impl(data, helper_attr);
+    let bytes_at_path_impl = derive_bytes_at_path_impl(data, helper_attr);
+    let validate_prefix_impl = derive_validate_prefix_impl(data, helper_attr);
+
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics ssz_rs::Serialize for #name #ty_generics {
+            #serialize_impl
+        }
+
+        impl #impl_generics ssz_rs::Deserialize for #name #ty_generics {
+            #deserialize_impl
+        }
 
         impl #impl_generics ssz_rs::Serializable for #name #ty_generics {
             fn is_variable_size() -> bool {
@@ -868,6 +1638,22 @@ fn derive_serializable_impl(
             fn size_hint() -> usize {
                 #size_hint_impl
             }
+
+            fn encoded_len(&self) -> Result<usize, ssz_rs::SerializeError> {
+                #encoded_len_impl
+            }
+
+            fn max_encoded_len() -> usize {
+                #max_encoded_len_impl
+            }
+
+            fn bytes_at_path<'a>(encoding: &'a [u8], path: ssz_rs::Path) -> Result<&'a [u8], ssz_rs::DeserializeError> {
+                #bytes_at_path_impl
+            }
+
+            fn validate_prefix(prefix: &[u8]) -> Result<bool, ssz_rs::DeserializeError> {
+                #validate_prefix_impl
+            }
         }
     }
 }
@@ -880,18 +1666,64 @@ fn derive_simple_serialize_impl(name: &Ident, generics: &Generics) -> proc_macro
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Clone)]
 enum HelperAttr {
     Transparent,
+    // A `CompatibleUnion` with an explicit selector for each variant.
+    CompatibleUnion,
+    // A `StableContainer[N]` with the given capacity `N`.
+    StableContainer(usize),
+    // A `Profile[B]` of the given base stable container `B`.
+    Profile(syn::Path),
+}
+
+impl HelperAttr {
+    fn is_stable(&self) -> bool {
+        matches!(self, Self::StableContainer(..) | Self::Profile(..))
+    }
 }
 
 fn parse_helper_attr(ident: &Ident) -> HelperAttr {
     match ident.to_string().as_str() {
         "transparent" => HelperAttr::Transparent,
+        "compatible_union" => HelperAttr::CompatibleUnion,
+        ident => panic!("unsupported helper attribute:{ident}"),
+    }
+}
+
+fn parse_helper_attr_with_value(ident: &Ident, value: &Lit) -> HelperAttr {
+    match (ident.to_string().as_str(), value) {
+        ("stable_container", Lit::Int(value)) => {
+            HelperAttr::StableContainer(value.base10_parse().expect("can parse capacity"))
+        }
+        ("stable_container", _) => panic!("`stable_container` requires an integer capacity"),
+        (ident, _) => panic!("unsupported helper attribute:{ident}"),
+    }
+}
+
+fn parse_helper_attr_with_args<'a>(
+    ident: &Ident,
+    mut args: impl Iterator<Item = &'a NestedMeta>,
+) -> HelperAttr {
+    match ident.to_string().as_str() {
+        "profile" => match (args.next(), args.next()) {
+            (Some(NestedMeta::Meta(Meta::Path(base))), None) => HelperAttr::Profile(base.clone()),
+            _ => panic!("`profile` requires exactly one base stable container"),
+        },
         ident => panic!("unsupported helper attribute:{ident}"),
     }
 }
 
+fn single_ident(path: &syn::Path) -> &Ident {
+    assert!(path.leading_colon.is_none());
+    assert_eq!(path.segments.len(), 1);
+    let path = &path.segments[0];
+    match path.arguments {
+        PathArguments::None => &path.ident,
+        _ => panic!("no arguments are supported to attribute symbols"),
+    }
+}
+
 fn extract_helper_attrs(input: &DeriveInput) -> Vec<HelperAttr> {
     filter_ssz_attrs(input.attrs.iter())
         .flat_map(|attr| {
@@ -901,18 +1733,12 @@ fn extract_helper_attrs(input: &DeriveInput) -> Vec<HelperAttr> {
                     .nested
                     .iter()
                     .map(|arg| match arg {
-                        NestedMeta::Meta(meta) => {
-                            if let Meta::Path(path) = meta {
-                                assert!(path.leading_colon.is_none());
-                                assert_eq!(path.segments.len(), 1);
-                                let path = &path.segments[0];
-                                match path.arguments {
-                                    PathArguments::None => parse_helper_attr(&path.ident),
-                                    _ => panic!("no arguments are supported to attribute symbols"),
-                                }
-                            } else {
-                                panic!("unsupported argument to helper attribute");
-                            }
+                        NestedMeta::Meta(Meta::Path(path)) => parse_helper_attr(single_ident(path)),
+                        NestedMeta::Meta(Meta::NameValue(arg)) => {
+                            parse_helper_attr_with_value(single_ident(&arg.path), &arg.lit)
+                        }
+                        NestedMeta::Meta(Meta::List(arg)) => {
+                            parse_helper_attr_with_args(single_ident(&arg.path), arg.nested.iter())
                         }
                         _ => panic!("literals unsupported in attributes"),
                     })
@@ -964,52 +1790,176 @@ pub fn derive_hash_tree_root(input: proc_macro::TokenStream) -> proc_macro::Toke
 
 /// Derive an implementation of the `GeneralizedIndexable` trait to support computation of
 /// generalized indices.
-#[proc_macro_derive(GeneralizedIndexable)]
+#[proc_macro_derive(GeneralizedIndexable, attributes(ssz))]
 pub fn derive_generalized_indexable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     let data = &input.data;
-    validate_derive_input(data, &[]);
+    let helper_attrs = extract_helper_attrs(&input);
+    validate_derive_input(data, &helper_attrs);
+    let helper_attr = helper_attrs.first();
+
     let name = &input.ident;
     let generics = &input.generics;
 
-    let expansion = derive_generalized_indexable_impl(data, name, generics);
+    let expansion = derive_generalized_indexable_impl(data, name, generics, helper_attr);
     proc_macro::TokenStream::from(expansion)
 }
 
 /// Derive an implementation of the `Prove` trait to support Merkle proofs.
-#[proc_macro_derive(Prove)]
+#[proc_macro_derive(Prove, attributes(ssz))]
 pub fn derive_prove(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     let data = &input.data;
-    validate_derive_input(data, &[]);
+    let helper_attrs = extract_helper_attrs(&input);
+    validate_derive_input(data, &helper_attrs);
+    let helper_attr = helper_attrs.first();
+
+    let name = &input.ident;
+    let generics = &input.generics;
+
+    let expansion = derive_prove_impl(data, name, generics, helper_attr);
+    proc_macro::TokenStream::from(expansion)
+}
+
+/// Derive an implementation of the `Diff` trait to support finding the differences between two
+/// values.
+#[proc_macro_derive(Diff, attributes(ssz))]
+pub fn derive_diff(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let data = &input.data;
+    let helper_attrs = extract_helper_attrs(&input);
+    validate_derive_input(data, &helper_attrs);
+    let helper_attr = helper_attrs.first();
+
+    let name = &input.ident;
+    let generics = &input.generics;
+
+    let expansion = derive_diff_impl(data, name, generics, helper_attr);
+    proc_macro::TokenStream::from(expansion)
+}
+
+/// Derive an implementation of the `Cacheable` trait so that values of a container can be wrapped
+/// in `Cached` to recompute their `hash_tree_root` incrementally. Only supported for structs that
+/// are not stable containers or profiles.
+#[proc_macro_derive(Cacheable, attributes(ssz))]
+pub fn derive_cacheable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let data = &input.data;
+    let helper_attrs = extract_helper_attrs(&input);
+    if helper_attrs.iter().any(HelperAttr::is_stable) {
+        panic!("`Cacheable` is not supported for stable containers or profiles")
+    }
+    validate_derive_input(data, &helper_attrs);
+
+    let name = &input.ident;
+    let generics = &input.generics;
+
+    let expansion = derive_cacheable_impl(data, name, generics);
+    proc_macro::TokenStream::from(expansion)
+}
+
+/// Derive an implementation of the `Viewable` trait to support lazy access to the fields of an
+/// encoded container.
+///
+/// For a struct `Foo`, generates a `FooView<'a>` borrowing the encoding with one accessor per
+/// field returning the view of the field's type. Only supported for structs.
+#[proc_macro_derive(Viewable)]
+pub fn derive_viewable(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let helper_attrs = extract_helper_attrs(&input);
+    if helper_attrs.iter().any(HelperAttr::is_stable) {
+        panic!("views are not supported for stable containers or profiles")
+    }
+    validate_derive_input(&input.data, &[]);
+
+    let expansion = derive_viewable_impl(&input);
+    proc_macro::TokenStream::from(expansion)
+}
+
+/// Derive an implementation of the `SszSchema` trait to describe the structure of a type at
+/// runtime.
+#[proc_macro_derive(SszSchema, attributes(ssz))]
+pub fn derive_ssz_schema(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let data = &input.data;
+    let helper_attrs = extract_helper_attrs(&input);
+    validate_derive_input(data, &helper_attrs);
+    let helper_attr = helper_attrs.first();
+
+    let name = &input.ident;
+    let generics = &input.generics;
+
+    let expansion = schema::derive_schema_impl(data, name, generics, helper_attr);
+    proc_macro::TokenStream::from(expansion)
+}
+
+/// Derive an implementation of the `ConsensusSerialize` trait to support the JSON representation of
+/// the consensus APIs. Requires the `serde` feature of `ssz_rs`.
+#[proc_macro_derive(ConsensusSerialize, attributes(ssz))]
+pub fn derive_consensus_serialize(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let data = &input.data;
+    let helper_attrs = extract_helper_attrs(&input);
+    validate_derive_input(data, &helper_attrs);
+    let helper_attr = helper_attrs.first();
+
+    let name = &input.ident;
+    let generics = &input.generics;
+
+    let expansion = consensus::derive_serialize_impl(data, name, generics, helper_attr);
+    proc_macro::TokenStream::from(expansion)
+}
+
+/// Derive an implementation of the `ConsensusDeserialize` trait to support the JSON representation
+/// of the consensus APIs. Requires the `serde` feature of `ssz_rs`.
+#[proc_macro_derive(ConsensusDeserialize, attributes(ssz))]
+pub fn derive_consensus_deserialize(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    let data = &input.data;
+    let helper_attrs = extract_helper_attrs(&input);
+    validate_derive_input(data, &helper_attrs);
+    let helper_attr = helper_attrs.first();
+
     let name = &input.ident;
     let generics = &input.generics;
 
-    let expansion = derive_prove_impl(data, name, generics);
+    let expansion = consensus::derive_deserialize_impl(data, name, generics, helper_attr);
     proc_macro::TokenStream::from(expansion)
 }
 
 /// Derive `SimpleSerialize` for the attached item, including the relevant additional traits
 /// required by the trait bound. Most common macro used from this crate.
-#[proc_macro_derive(SimpleSerialize)]
+#[proc_macro_derive(SimpleSerialize, attributes(ssz))]
 pub fn derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     let data = &input.data;
-    validate_derive_input(data, &[]);
+    let helper_attrs = extract_helper_attrs(&input);
+    validate_derive_input(data, &helper_attrs);
+    let helper_attr = helper_attrs.first();
+    if matches!(helper_attr, Some(HelperAttr::Transparent)) {
+        panic!("`transparent` option is not supported when deriving `SimpleSerialize`")
+    }
 
     let name = &input.ident;
     let generics = &input.generics;
 
-    let serializable_impl = derive_serializable_impl(data, name, generics, None);
+    let serializable_impl = derive_serializable_impl(data, name, generics, helper_attr);
 
-    let merkleization_impl = derive_merkleization_impl(data, name, generics, None);
+    let merkleization_impl = derive_merkleization_impl(data, name, generics, helper_attr);
 
-    let generalized_indexable_impl = derive_generalized_indexable_impl(data, name, generics);
+    let generalized_indexable_impl =
+        derive_generalized_indexable_impl(data, name, generics, helper_attr);
 
-    let prove_impl = derive_prove_impl(data, name, generics);
+    let prove_impl = derive_prove_impl(data, name, generics, helper_attr);
 
     let simple_serialize_impl = derive_simple_serialize_impl(name, generics);
 
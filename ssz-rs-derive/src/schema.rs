@@ -0,0 +1,105 @@
+//! Derivation of the `SszSchema` trait describing the structure of a type at runtime.
+use crate::{stable_container::option_inner_type, union_selectors, with_bounds, HelperAttr};
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{spanned::Spanned, Data, Fields, Generics, Ident};
+
+pub(crate) fn derive_schema_impl(
+    data: &Data,
+    name: &Ident,
+    generics: &Generics,
+    helper_attr: Option<&HelperAttr>,
+) -> TokenStream {
+    let name_str = name.to_string();
+    let body = match data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => {
+                let schema_by_field = fields.named.iter().map(|f| {
+                    // SAFETY: only named fields; qed
+                    let field_name_str = f.ident.as_ref().unwrap().to_string();
+                    let is_optional = option_inner_type(&f.ty).is_some();
+                    let field_type = match helper_attr {
+                        Some(attr) if attr.is_stable() => option_inner_type(&f.ty).unwrap_or(&f.ty),
+                        _ => &f.ty,
+                    };
+                    let schema = quote! { <#field_type as ssz_rs::SszSchema>::schema() };
+                    match helper_attr {
+                        Some(HelperAttr::Profile(..)) => quote_spanned! { f.span() =>
+                            (#field_name_str.to_string(), #schema, #is_optional),
+                        },
+                        _ => quote_spanned! { f.span() =>
+                            (#field_name_str.to_string(), #schema),
+                        },
+                    }
+                });
+                let fields = quote! { vec![#(#schema_by_field)*] };
+                match helper_attr {
+                    Some(HelperAttr::StableContainer(max_fields)) => quote! {
+                        ssz_rs::Schema::StableContainer {
+                            name: #name_str.to_string(),
+                            max_fields: #max_fields,
+                            fields: #fields,
+                        }
+                    },
+                    Some(HelperAttr::Profile(base)) => quote! {
+                        ssz_rs::Schema::Profile {
+                            name: #name_str.to_string(),
+                            base: Box::new(<#base as ssz_rs::SszSchema>::schema()),
+                            fields: #fields,
+                        }
+                    },
+                    _ => quote! {
+                        ssz_rs::Schema::Container { name: #name_str.to_string(), fields: #fields }
+                    },
+                }
+            }
+            Fields::Unnamed(ref fields) => {
+                // SAFETY: index is safe because Punctuated always has a first element; qed
+                let field_type = &fields.unnamed[0].ty;
+                quote! { <#field_type as ssz_rs::SszSchema>::schema() }
+            }
+            _ => unimplemented!(
+                "this type of struct is currently not supported by this derive macro"
+            ),
+        },
+        Data::Enum(ref data) => {
+            if matches!(helper_attr, Some(&HelperAttr::Transparent)) {
+                panic!("`transparent` option is not supported when deriving `SszSchema`")
+            }
+            let selectors = union_selectors(data, helper_attr);
+            let schema_by_variant = data.variants.iter().enumerate().map(|(i, variant)| {
+                let selector = selectors[i];
+                match &variant.fields {
+                    Fields::Unnamed(inner) => {
+                        // SAFETY: index is safe because Punctuated always has a first element; qed
+                        let variant_type = &inner.unnamed[0].ty;
+                        quote_spanned! { variant.span() =>
+                            (#selector, Some(<#variant_type as ssz_rs::SszSchema>::schema())),
+                        }
+                    }
+                    Fields::Unit => quote_spanned! { variant.span() =>
+                        (0, None),
+                    },
+                    _ => unreachable!(),
+                }
+            });
+            quote! {
+                ssz_rs::Schema::Union {
+                    name: #name_str.to_string(),
+                    variants: vec![#(#schema_by_variant)*],
+                }
+            }
+        }
+        Data::Union(..) => unreachable!("data was already validated to exclude union types"),
+    };
+
+    let generics = with_bounds(generics, quote! { ssz_rs::SszSchema });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics ssz_rs::SszSchema for #name #ty_generics #where_clause {
+            fn schema() -> ssz_rs::Schema {
+                #body
+            }
+        }
+    }
+}
@@ -0,0 +1,694 @@
+//! Derivation of the `StableContainer` and `Profile` types of EIP-7495.
+//!
+//! A struct with `#[ssz(stable_container = N)]` is a `StableContainer[N]` and every field must
+//! have type `Option<T>`. A struct with `#[ssz(profile(B))]` is a `Profile[B]` for the stable
+//! container `B`: fields of type `T` are required and fields of type `Option<T>` are optional, and
+//! each must have the same name, type and relative order as in `B`.
+//!
+//! Stable containers expose the index of each field to their profiles with a hidden `const fn` per
+//! field that takes the type of the field, so that a mismatched profile fails to compile.
+use crate::{HelperAttr, BYTES_PER_CHUNK};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{
+    spanned::Spanned, Data, Field, Fields, GenericArgument, Generics, Ident, PathArguments, Type,
+};
+
+const BITS_PER_BYTE: usize = 8;
+
+struct StableField<'a> {
+    field: &'a Field,
+    name: &'a Ident,
+    // the type of the field when present, i.e. `T` for a field of type `Option<T>`
+    ty: &'a Type,
+    // the position of the field in the serialized active fields, if it is optional
+    bit_index: Option<usize>,
+}
+
+impl StableField<'_> {
+    fn name_str(&self) -> String {
+        self.name.to_string()
+    }
+
+    // Returns an expression for an `Option<&T>` that is `Some` if the field is present.
+    fn access(&self) -> TokenStream {
+        let name = self.name;
+        if self.bit_index.is_some() {
+            quote! { self.#name.as_ref() }
+        } else {
+            quote! { Some(&self.#name) }
+        }
+    }
+
+    fn span_ident(&self) -> Ident {
+        format_ident!("__ssz_rs_span_{}", self.name)
+    }
+}
+
+fn index_fn_ident(name: &Ident) -> Ident {
+    format_ident!("__ssz_rs_stable_index_of_{}", name)
+}
+
+// Returns `T` if `ty` is written as `Option<T>`.
+pub(crate) fn option_inner_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(ty) = ty else { return None };
+    if ty.qself.is_some() {
+        return None
+    }
+    let segment = ty.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn named_fields(data: &Data) -> impl Iterator<Item = &Field> {
+    match data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter(),
+            _ => unreachable!("validated to only have named fields"),
+        },
+        _ => unreachable!("validated to only be a struct"),
+    }
+}
+
+fn stable_fields<'a>(data: &'a Data, helper_attr: &HelperAttr) -> Vec<StableField<'a>> {
+    let mut bit_count = 0;
+    named_fields(data)
+        .map(|field| {
+            let name = field.ident.as_ref().expect("only named fields");
+            let inner = option_inner_type(&field.ty);
+            if matches!(helper_attr, HelperAttr::StableContainer(..)) && inner.is_none() {
+                panic!("all fields of a stable container must have type `Option<T>`");
+            }
+            let bit_index = inner.map(|_| {
+                bit_count += 1;
+                bit_count - 1
+            });
+            StableField { field, name, ty: inner.unwrap_or(&field.ty), bit_index }
+        })
+        .collect()
+}
+
+// Returns the number of bits in the serialized active fields, which are `Bitvector[N]` for a
+// `StableContainer[N]` and have one bit per optional field for a profile.
+fn active_fields_bit_count(fields: &[StableField], helper_attr: &HelperAttr) -> usize {
+    match helper_attr {
+        HelperAttr::StableContainer(max_fields) => *max_fields,
+        _ => fields.iter().filter(|field| field.bit_index.is_some()).count(),
+    }
+}
+
+fn max_fields(helper_attr: &HelperAttr) -> TokenStream {
+    match helper_attr {
+        HelperAttr::StableContainer(max_fields) => quote! { #max_fields },
+        HelperAttr::Profile(base) => quote! { <#base as ssz_rs::StableContainer>::MAX_FIELDS },
+        _ => unreachable!("only called for stable containers or profiles"),
+    }
+}
+
+// Returns an expression for the index of `field` in the (base) stable container.
+fn field_index(field: &StableField, position: usize, helper_attr: &HelperAttr) -> TokenStream {
+    match helper_attr {
+        HelperAttr::Profile(base) => {
+            let index_fn = index_fn_ident(field.name);
+            let ty = field.ty;
+            quote_spanned! { field.field.span() =>
+                #base::#index_fn(core::marker::PhantomData::<#ty>)
+            }
+        }
+        _ => quote! { #position },
+    }
+}
+
+// Returns the number of chunks needed to merkleize the fields, i.e. one more than the largest
+// field index.
+fn chunk_count(fields: &[StableField], helper_attr: &HelperAttr) -> TokenStream {
+    let position = fields.len() - 1;
+    // SAFETY: index is safe because validated to have at least one field; qed
+    let last_index = field_index(&fields[position], position, helper_attr);
+    quote! { (#last_index + 1) }
+}
+
+// Decodes the active fields, if any, and the spans of the present fields from `encoding`.
+fn decode_spans_impl(fields: &[StableField], bit_count: usize) -> TokenStream {
+    let field_bit_count = fields.iter().filter(|field| field.bit_index.is_some()).count();
+    let prefix_impl = if bit_count > 0 {
+        quote! {
+            let (active_fields, prefix_length) = ssz_rs::__internal::deserialize_active_fields(
+                encoding,
+                #bit_count,
+                #field_bit_count,
+            )?;
+        }
+    } else {
+        quote! { let prefix_length = 0usize; }
+    };
+    let parse_by_field = fields.iter().map(|field| {
+        let ty = field.ty;
+        match field.bit_index {
+            Some(bit_index) => quote! {
+                if active_fields[#bit_index] {
+                    deserializer.parse::<#ty>(data)?;
+                }
+            },
+            None => quote! {
+                deserializer.parse::<#ty>(data)?;
+            },
+        }
+    });
+    let span_by_field = fields.iter().map(|field| {
+        let span_ident = field.span_ident();
+        match field.bit_index {
+            Some(bit_index) => quote! {
+                let #span_ident = if active_fields[#bit_index] { spans.next() } else { None };
+            },
+            None => quote! {
+                let #span_ident = spans.next();
+            },
+        }
+    });
+    quote! {
+        #prefix_impl
+        let data = &encoding[prefix_length..];
+
+        let mut deserializer = ssz_rs::__internal::ContainerDeserializer::default();
+        #(#parse_by_field)*
+        let spans = deserializer.finalize(data)?;
+
+        let mut spans = spans.chunks_exact(2).map(|span| (span[0], span[1]));
+        #(#span_by_field)*
+    }
+}
+
+pub(crate) fn derive_serializable_impl(
+    data: &Data,
+    name: &Ident,
+    generics: &Generics,
+    helper_attr: &HelperAttr,
+) -> TokenStream {
+    let fields = stable_fields(data, helper_attr);
+    let bit_count = active_fields_bit_count(&fields, helper_attr);
+
+    let prefix_impl = if bit_count > 0 {
+        let active_field_by_field =
+            fields.iter().filter(|field| field.bit_index.is_some()).map(|field| {
+                let name = field.name;
+                quote! { self.#name.is_some() }
+            });
+        quote! {
+            let active_fields = [#(#active_field_by_field),*];
+            let prefix_length = ssz_rs::__internal::serialize_active_fields(&active_fields, #bit_count, sink)?;
+        }
+    } else {
+        quote! { let prefix_length = 0; }
+    };
+    let fixed_length_by_field = fields.iter().map(|field| {
+        let access = field.access();
+        let ty = field.ty;
+        quote! {
+            #access.map_or(0, |_| ssz_rs::__internal::fixed_portion_length::<#ty>())
+        }
+    });
+    let fixed_part_by_field = fields.iter().map(|field| {
+        let access = field.access();
+        let name_str = field.name_str();
+        quote! {
+            if let Some(value) = #access {
+                serializer.with_fixed_part(value).map_err(|err| {
+                    err.in_element(ssz_rs::PathElement::from(#name_str))
+                })?;
+            }
+        }
+    });
+    let variable_part_by_field = fields.iter().map(|field| {
+        let access = field.access();
+        let name_str = field.name_str();
+        quote! {
+            if let Some(value) = #access {
+                serializer.with_variable_part(value).map_err(|err| {
+                    err.in_element(ssz_rs::PathElement::from(#name_str))
+                })?;
+            }
+        }
+    });
+
+    let decode_spans = decode_spans_impl(&fields, bit_count);
+    let initialization_by_field = fields.iter().map(|field| {
+        let name = field.name;
+        let name_str = field.name_str();
+        let ty = field.ty;
+        let span_ident = field.span_ident();
+        let decode = quote! {
+            <#ty as ssz_rs::Deserialize>::deserialize_with(&data[start..end], context)
+                .map_err(|err| err.in_element(ssz_rs::PathElement::from(#name_str), prefix_length + start))?
+        };
+        if field.bit_index.is_some() {
+            quote! {
+                #name: match #span_ident {
+                    Some((start, end)) => Some(#decode),
+                    None => None,
+                },
+            }
+        } else {
+            quote! {
+                #name: {
+                    let (start, end) = #span_ident.expect("required fields are always present");
+                    #decode
+                },
+            }
+        }
+    });
+
+    let is_variable_size_impl = if bit_count > 0 {
+        quote! { true }
+    } else {
+        let is_variable_size_by_field = fields.iter().map(|field| {
+            let ty = field.ty;
+            quote! { <#ty as ssz_rs::Serializable>::is_variable_size() }
+        });
+        quote! { #(#is_variable_size_by_field)||* }
+    };
+    let size_hint_by_field = fields.iter().map(|field| {
+        let ty = field.ty;
+        quote! { <#ty as ssz_rs::Serializable>::size_hint() }
+    });
+    let prefix_byte_length = bit_count.div_ceil(BITS_PER_BYTE);
+    let encoded_len_by_field = fields.iter().map(|field| {
+        let access = field.access();
+        let name_str = field.name_str();
+        quote! {
+            #access.map_or(Ok(0), |value| ssz_rs::__internal::element_encoded_len(value)).map_err(|err| {
+                err.in_element(ssz_rs::PathElement::from(#name_str))
+            })?
+        }
+    });
+    let max_encoded_len_by_field = fields.iter().map(|field| {
+        let ty = field.ty;
+        quote! {
+            .saturating_add(ssz_rs::__internal::element_max_encoded_len::<#ty>())
+        }
+    });
+    let selection_by_field = fields.iter().map(|field| {
+        let name_str = field.name_str();
+        let ty = field.ty;
+        let span_ident = field.span_ident();
+        let span = if field.bit_index.is_some() {
+            quote! { #span_ident }
+        } else {
+            quote! { Some(#span_ident.expect("required fields are always present")) }
+        };
+        quote! {
+            #name_str => match #span {
+                Some((start, end)) => <#ty as ssz_rs::Serializable>::bytes_at_path(&data[start..end], rest)
+                    .map_err(|err| err.in_element(next.clone(), prefix_length + start)),
+                None => Err(ssz_rs::DeserializeError::InvalidPath(path.to_vec())),
+            },
+        }
+    });
+
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics ssz_rs::Serialize for #name #ty_generics {
+            fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, ssz_rs::SerializeError> {
+                ssz_rs::Serialize::serialize_to(self, buffer)
+            }
+
+            fn serialize_to<S: ssz_rs::Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, ssz_rs::SerializeError> {
+                #prefix_impl
+
+                let fixed_length = 0 #(+ #fixed_length_by_field)*;
+                let mut serializer = ssz_rs::__internal::Serializer::new(sink, fixed_length);
+
+                #(#fixed_part_by_field)*
+
+                #(#variable_part_by_field)*
+
+                Ok(prefix_length + serializer.finalize()?)
+            }
+        }
+
+        impl #impl_generics ssz_rs::Deserialize for #name #ty_generics {
+            fn deserialize(encoding: &[u8]) -> Result<Self, ssz_rs::DeserializeError> {
+                Self::deserialize_with(encoding, &mut ssz_rs::DecodeContext::default())
+            }
+
+            fn deserialize_with(encoding: &[u8], context: &mut ssz_rs::DecodeContext) -> Result<Self, ssz_rs::DeserializeError> {
+                #decode_spans
+
+                context.nested(|context| {
+                    Ok(Self {
+                        #(#initialization_by_field)*
+                    })
+                })
+            }
+        }
+
+        impl #impl_generics ssz_rs::Serializable for #name #ty_generics {
+            fn is_variable_size() -> bool {
+                #is_variable_size_impl
+            }
+
+            fn size_hint() -> usize {
+                if Self::is_variable_size() {
+                    0
+                } else {
+                    #(#size_hint_by_field)+*
+                }
+            }
+
+            fn encoded_len(&self) -> Result<usize, ssz_rs::SerializeError> {
+                Ok(#prefix_byte_length #(+ #encoded_len_by_field)*)
+            }
+
+            fn max_encoded_len() -> usize {
+                #prefix_byte_length #(#max_encoded_len_by_field)*
+            }
+
+            fn bytes_at_path<'a>(encoding: &'a [u8], path: ssz_rs::Path) -> Result<&'a [u8], ssz_rs::DeserializeError> {
+                let Some((next, rest)) = path.split_first() else { return Ok(encoding) };
+
+                #decode_spans
+
+                match next {
+                    ssz_rs::PathElement::Field(name) => match name.as_str() {
+                        #(#selection_by_field)*
+                        _ => Err(ssz_rs::DeserializeError::InvalidPath(path.to_vec())),
+                    },
+                    _ => Err(ssz_rs::DeserializeError::InvalidPath(path.to_vec())),
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn derive_merkleization_impl(
+    data: &Data,
+    name: &Ident,
+    generics: &Generics,
+    helper_attr: &HelperAttr,
+) -> TokenStream {
+    let fields = stable_fields(data, helper_attr);
+    let max_fields = max_fields(helper_attr);
+    let chunk_count = chunk_count(&fields, helper_attr);
+
+    let chunk_by_field = fields.iter().enumerate().map(|(i, field)| {
+        let access = field.access();
+        let index = field_index(field, i, helper_attr);
+        quote! {
+            if let Some(value) = #access {
+                let index = #index;
+                let chunk = ssz_rs::HashTreeRoot::hash_tree_root_with::<H>(value)?;
+                chunks[index * #BYTES_PER_CHUNK..(index + 1) * #BYTES_PER_CHUNK].copy_from_slice(chunk.as_ref());
+            }
+        }
+    });
+    let active_field_by_field = fields.iter().enumerate().map(|(i, field)| {
+        let access = field.access();
+        let index = field_index(field, i, helper_attr);
+        quote! {
+            active_fields[#index] = #access.is_some();
+        }
+    });
+
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    let layout_impl = match helper_attr {
+        HelperAttr::StableContainer(..) => {
+            let index_fn_by_field = fields.iter().enumerate().map(|(i, field)| {
+                let index_fn = index_fn_ident(field.name);
+                let ty = field.ty;
+                quote! {
+                    #[doc(hidden)]
+                    #[allow(non_snake_case, private_interfaces)]
+                    pub const fn #index_fn(_: core::marker::PhantomData<#ty>) -> usize {
+                        #i
+                    }
+                }
+            });
+            quote! {
+                impl #impl_generics ssz_rs::StableContainer for #name #ty_generics {
+                    const MAX_FIELDS: usize = #max_fields;
+                }
+
+                impl #impl_generics ssz_rs::StableLayout for #name #ty_generics {
+                    type Base = Self;
+                }
+
+                impl #impl_generics #name #ty_generics {
+                    #(#index_fn_by_field)*
+                }
+            }
+        }
+        HelperAttr::Profile(base) => {
+            // NOTE: the fields must follow the order of the base to share its serialization
+            let order_checks = fields.windows(2).map(|pair| {
+                let previous = index_fn_ident(pair[0].name);
+                let next = index_fn_ident(pair[1].name);
+                quote_spanned! { pair[1].field.span() =>
+                    assert!(
+                        #base::#previous(core::marker::PhantomData) < #base::#next(core::marker::PhantomData),
+                        "fields of a profile must be in the same order as in its base stable container",
+                    );
+                }
+            });
+            quote! {
+                const _: () = {
+                    #(#order_checks)*
+                };
+
+                impl #impl_generics ssz_rs::StableLayout for #name #ty_generics {
+                    type Base = #base;
+                }
+            }
+        }
+        _ => unreachable!("only called for stable containers or profiles"),
+    };
+
+    quote! {
+        #layout_impl
+
+        impl #impl_generics #name #ty_generics {
+            fn assemble_chunks<H: ssz_rs::MerkleHasher>(&self) -> Result<Vec<u8>, ssz_rs::MerkleizationError> {
+                let mut chunks = vec![0u8; #chunk_count * #BYTES_PER_CHUNK];
+                #(#chunk_by_field)*
+                Ok(chunks)
+            }
+
+            fn __ssz_rs_active_fields(&self) -> Vec<bool> {
+                let mut active_fields = vec![false; #chunk_count];
+                #(#active_field_by_field)*
+                active_fields
+            }
+        }
+
+        impl #impl_generics ssz_rs::HashTreeRoot for #name #ty_generics {
+            fn hash_tree_root(&self) -> Result<ssz_rs::Node, ssz_rs::MerkleizationError> {
+                self.hash_tree_root_with::<ssz_rs::Sha256Hasher>()
+            }
+
+            fn hash_tree_root_with<H: ssz_rs::MerkleHasher>(&self) -> Result<ssz_rs::Node, ssz_rs::MerkleizationError> {
+                let chunks = self.assemble_chunks::<H>()?;
+                ssz_rs::__internal::merkleize_stable_container::<H>(
+                    &chunks,
+                    &self.__ssz_rs_active_fields(),
+                    #max_fields,
+                )
+            }
+
+            fn elements_to_chunks<H: ssz_rs::MerkleHasher>(elements: &[Self]) -> Result<Vec<u8>, ssz_rs::MerkleizationError> {
+                use ssz_rs::__internal::{ParallelElementsToChunks as _, SequentialElementsToChunks as _};
+                // NOTE: computes the roots in parallel when `Self` is `Sync`
+                (&&ssz_rs::__internal::Elements(elements)).elements_to_chunks::<H>()
+            }
+        }
+    }
+}
+
+pub(crate) fn derive_generalized_indexable_impl(
+    data: &Data,
+    name: &Ident,
+    generics: &Generics,
+    helper_attr: &HelperAttr,
+) -> TokenStream {
+    let fields = stable_fields(data, helper_attr);
+    let max_fields = max_fields(helper_attr);
+
+    let impl_by_field = fields.iter().enumerate().map(|(i, field)| {
+        let name_str = field.name_str();
+        let ty = field.ty;
+        let index = field_index(field, i, helper_attr);
+        quote! {
+            #name_str => {
+                // NOTE: the fields are under the left child of the root, next to the active fields
+                let child = parent * 2 * ssz_rs::__internal::get_power_of_two_ceil(Self::chunk_count()) + #index;
+                <#ty as ssz_rs::GeneralizedIndexable>::compute_generalized_index(child, rest)
+            }
+        }
+    });
+
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics ssz_rs::GeneralizedIndexable for #name #ty_generics {
+            fn chunk_count() -> usize {
+                #max_fields
+            }
+
+            fn compute_generalized_index(
+                parent: ssz_rs::GeneralizedIndex,
+                path: ssz_rs::Path,
+            ) -> Result<ssz_rs::GeneralizedIndex, ssz_rs::MerkleizationError> {
+                let Some((next, rest)) = path.split_first() else { return Ok(parent) };
+                match next {
+                    ssz_rs::PathElement::Field(field) => match field.as_str() {
+                        #(#impl_by_field)*
+                        s => Err(ssz_rs::MerkleizationError::InvalidPathElement(ssz_rs::PathElement::Field(s.to_string()))),
+                    },
+                    elem => Err(ssz_rs::MerkleizationError::InvalidPathElement(elem.clone())),
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn derive_prove_impl(
+    data: &Data,
+    name: &Ident,
+    generics: &Generics,
+    helper_attr: &HelperAttr,
+) -> TokenStream {
+    let fields = stable_fields(data, helper_attr);
+    let max_fields = max_fields(helper_attr);
+
+    let impl_by_field = fields.iter().enumerate().map(|(i, field)| {
+        let access = field.access();
+        let index = field_index(field, i, helper_attr);
+        quote! {
+            if index == #index {
+                return match #access {
+                    Some(value) => prover.compute_proof(value),
+                    None => Err(ssz_rs::MerkleizationError::InvalidInnerIndex),
+                }
+            }
+        }
+    });
+
+    let chunk_count = chunk_count(&fields, helper_attr);
+    let subtree_by_field = fields.iter().enumerate().map(|(i, field)| {
+        let access = field.access();
+        let index = field_index(field, i, helper_attr);
+        quote! {
+            if let Some(value) = #access {
+                elements[#index] = ssz_rs::Prove::subtree::<H>(value)?;
+            }
+        }
+    });
+
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics ssz_rs::Prove for #name #ty_generics {
+            fn chunks<H: ssz_rs::MerkleHasher>(&self) -> Result<Vec<u8>, ssz_rs::MerkleizationError> {
+                self.assemble_chunks::<H>()
+            }
+
+            fn prove_element<H: ssz_rs::MerkleHasher>(
+                &self,
+                index: usize,
+                prover: &mut ssz_rs::proofs::Prover<H>,
+            ) -> Result<(), ssz_rs::MerkleizationError> {
+                #(#impl_by_field)*
+                Err(ssz_rs::MerkleizationError::InvalidInnerIndex)
+            }
+
+            fn subtree<H: ssz_rs::MerkleHasher>(&self) -> Result<ssz_rs::Subtree<H>, ssz_rs::MerkleizationError> {
+                let mut elements = vec![ssz_rs::Subtree::default(); #chunk_count];
+                #(#subtree_by_field)*
+                let decoration = ssz_rs::Prove::decoration_root::<H>(self)?;
+                Ok(ssz_rs::Subtree::from_elements(elements, #max_fields, decoration))
+            }
+
+            fn decoration_root<H: ssz_rs::MerkleHasher>(&self) -> Result<Option<ssz_rs::Node>, ssz_rs::MerkleizationError> {
+                ssz_rs::__internal::active_fields_root::<H>(&self.__ssz_rs_active_fields(), #max_fields)
+                    .map(Some)
+            }
+        }
+    }
+}
+
+pub(crate) fn derive_diff_impl(
+    data: &Data,
+    name: &Ident,
+    generics: &Generics,
+    helper_attr: &HelperAttr,
+) -> TokenStream {
+    let fields = stable_fields(data, helper_attr);
+
+    let impl_by_field = fields.iter().map(|field| {
+        let name = field.name;
+        let name_str = field.name_str();
+        let ty = field.ty;
+        if field.bit_index.is_some() {
+            quote! {
+                differ.compare_optional_element::<Self, #ty>(
+                    ssz_rs::PathElement::Field(#name_str.to_string()),
+                    self.#name.as_ref(),
+                    other.#name.as_ref(),
+                )?;
+            }
+        } else {
+            quote! {
+                differ.compare_element::<Self, #ty>(
+                    ssz_rs::PathElement::Field(#name_str.to_string()),
+                    &self.#name,
+                    &other.#name,
+                )?;
+            }
+        }
+    });
+
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    quote! {
+        impl #impl_generics ssz_rs::Diff for #name #ty_generics {
+            fn diff_elements(
+                &self,
+                other: &Self,
+                differ: &mut ssz_rs::Differ,
+            ) -> Result<(), ssz_rs::MerkleizationError> {
+                #(#impl_by_field)*
+                Ok(())
+            }
+        }
+    }
+}
+
+// Validates a struct deriving a stable container or a profile.
+//
+// Panics if validation fails which aborts the macro derivation.
+pub(crate) fn validate_derive_input(data: &Data, helper_attr: &HelperAttr) {
+    let fields = match data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => panic!("stable containers and profiles must be structs with named fields"),
+        },
+        _ => panic!("stable containers and profiles must be structs with named fields"),
+    };
+    if fields.is_empty() {
+        panic!("ssz_rs containers with no fields are illegal")
+    }
+    crate::validate_no_attrs(fields.iter());
+    if let HelperAttr::StableContainer(max_fields) = helper_attr {
+        if *max_fields == 0 {
+            panic!("stable containers must have a capacity of at least one field")
+        }
+        if fields.len() > *max_fields {
+            panic!("stable container has more than its capacity of {max_fields} fields")
+        }
+    }
+    // NOTE: panics if a stable container has a field that is not `Option<T>`
+    stable_fields(data, helper_attr);
+}
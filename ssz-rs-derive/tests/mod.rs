@@ -2,7 +2,7 @@ use ssz_rs::{prelude::*, proofs::ProofAndWitness};
 use ssz_rs_derive::SimpleSerialize;
 use std::fmt;
 
-#[derive(Debug, Clone, SimpleSerialize, PartialEq, Eq)]
+#[derive(Debug, Clone, SimpleSerialize, Diff, Cacheable, PartialEq, Eq)]
 struct Foo {
     a: u8,
     b: u32,
@@ -34,10 +34,10 @@ fn prove_for_bar(object: &Bar, path: Path) -> Result<ProofAndWitness, Merkleizat
     }
 }
 
-#[derive(Debug, PartialEq, Eq, SimpleSerialize)]
+#[derive(Debug, PartialEq, Eq, SimpleSerialize, Diff, Cacheable)]
 struct Wrapper(Foo);
 
-#[derive(Debug, PartialEq, Eq, SimpleSerialize)]
+#[derive(Debug, PartialEq, Eq, SimpleSerialize, Cacheable)]
 struct WrappedList(List<u8, 23>);
 
 fn can_serde<T: Serializable + Eq + fmt::Debug>(data: &T) {
@@ -208,3 +208,233 @@ fn test_transparent_helper() {
         assert_eq!((proof, witness), inner_proofs[i]);
     }
 }
+
+#[test]
+fn test_decode_at_path() {
+    let container = Foo {
+        a: 23,
+        b: 445,
+        c: List::<usize, 45>::try_from(vec![9, 8, 7, 6, 5, 4]).unwrap(),
+        d: U256::from(234234),
+    };
+    let encoding = ssz_rs::serialize(&container).unwrap();
+    let path = &["c".into(), 2.into()];
+    assert_eq!(Foo::decode_at_path::<usize>(&encoding, path).unwrap(), 7);
+    assert_eq!(Wrapper::decode_at_path::<usize>(&encoding, path).unwrap(), 7);
+    assert_eq!(Bar::decode_at_path::<usize>(&encoding, path).unwrap(), 7);
+    assert_eq!(Bar::decode_at_path::<U256>(&encoding, &["d".into()]).unwrap(), container.d);
+
+    let encoding = ssz_rs::serialize(&Bar::A(12)).unwrap();
+    assert_eq!(Bar::decode_at_path::<u8>(&encoding, &[]).unwrap(), 12);
+    assert!(Bar::bytes_at_path(&encoding, path).is_err());
+}
+
+#[derive(
+    Debug, Default, PartialEq, Eq, Serializable, HashTreeRoot, GeneralizedIndexable, Prove, Diff,
+)]
+#[ssz(stable_container = 16)]
+struct StableFoo {
+    a: Option<u8>,
+    b: Option<List<usize, 45>>,
+}
+
+#[derive(
+    Debug, Default, PartialEq, Eq, Serializable, HashTreeRoot, GeneralizedIndexable, Prove, Diff,
+)]
+#[ssz(profile(StableFoo))]
+struct ProfileFoo {
+    b: List<usize, 45>,
+}
+
+#[test]
+fn test_stable_container_and_profile() {
+    let profile = ProfileFoo { b: List::try_from(vec![1, 2, 3]).unwrap() };
+    let stable = StableFoo { a: None, b: Some(profile.b.clone()) };
+    assert_eq!(profile.hash_tree_root().unwrap(), stable.hash_tree_root().unwrap());
+    assert_eq!(StableFoo::MAX_FIELDS, 16);
+
+    let encoding = ssz_rs::serialize(&stable).unwrap();
+    assert_eq!(&encoding[..2], [0b10, 0]);
+    assert_eq!(ssz_rs::serialize(&profile).unwrap(), &encoding[2..]);
+    assert_eq!(ProfileFoo::deserialize(&encoding[2..]).unwrap(), profile);
+
+    let path = &["b".into(), 2.into()];
+    assert_eq!(
+        ProfileFoo::generalized_index(path).unwrap(),
+        StableFoo::generalized_index(path).unwrap()
+    );
+    let (proof, witness) = profile.prove(path).unwrap();
+    assert_eq!((proof, witness), stable.prove(path).unwrap());
+}
+
+// `SimpleSerialize` does not imply `Diff`, so it can be implemented by hand
+#[derive(Debug, Clone, PartialEq, Eq, SimpleSerialize)]
+struct Opaque {
+    a: u8,
+    b: List<u8, 4>,
+}
+
+impl Diff for Opaque {}
+
+#[test]
+fn test_diff() {
+    let foo = Foo {
+        a: 1,
+        b: 2,
+        c: List::<usize, 45>::try_from(vec![9, 8, 7]).unwrap(),
+        d: U256::from(3),
+    };
+    let mut other = foo.clone();
+    other.b = 5;
+    other.c[1] = 4;
+    let paths = [vec!["b".into()], vec!["c".into(), 1.into()]];
+
+    let differences = ssz_rs::diff(&foo, &other).unwrap();
+    assert_eq!(differences.iter().map(|d| d.path.clone()).collect::<Vec<_>>(), paths);
+    assert_eq!(ssz_rs::diff(&Wrapper(foo), &Wrapper(other)).unwrap(), differences);
+
+    // without `diff_elements`, only the root of a value is compared
+    let opaque = Opaque { a: 1, b: List::try_from(vec![2]).unwrap() };
+    let other = Opaque { a: 3, ..opaque.clone() };
+    let differences = ssz_rs::diff(&opaque, &other).unwrap();
+    assert_eq!(differences.len(), 1);
+    assert!(differences[0].path.is_empty());
+
+    let profile = ProfileFoo { b: List::try_from(vec![1, 2, 3]).unwrap() };
+    let other = ProfileFoo { b: List::try_from(vec![1, 2, 4]).unwrap() };
+    let differences = ssz_rs::diff(&profile, &other).unwrap();
+    let path = &["b".into(), 2.into()];
+    assert_eq!(differences.len(), 1);
+    assert_eq!(differences[0].path, path);
+    assert_eq!(differences[0].index, StableFoo::generalized_index(path).unwrap());
+
+    let stable = StableFoo { a: None, b: Some(profile.b.clone()) };
+    let other = StableFoo { a: Some(1), b: None };
+    let differences = ssz_rs::diff(&stable, &other).unwrap();
+    assert_eq!(
+        differences.iter().map(|d| d.path.clone()).collect::<Vec<_>>(),
+        [vec!["a".into()], vec!["b".into()]]
+    );
+    assert_eq!(differences[1].left, profile.b.hash_tree_root().unwrap());
+    assert_eq!(differences[1].right, Node::default());
+}
+
+#[test]
+fn test_cacheable() {
+    let foo = Foo {
+        a: 1,
+        b: 2,
+        c: List::<usize, 45>::try_from(vec![9, 8, 7]).unwrap(),
+        d: U256::from(3),
+    };
+    let mut cached = Cached::new(foo.clone());
+    assert_eq!(cached.commit().unwrap(), foo.hash_tree_root().unwrap());
+    cached.modify(2).c.push(6);
+    cached.modify(3).d = U256::from(4);
+    assert_eq!(cached.commit().unwrap(), cached.clone().into_inner().hash_tree_root().unwrap());
+
+    let mut wrapped = Cached::new(WrappedList(List::try_from(vec![1; 20]).unwrap()));
+    wrapped.commit().unwrap();
+    wrapped.modify(17).0[17] = 2;
+    let expected = WrappedList(List::try_from(wrapped.0.to_vec()).unwrap());
+    assert_eq!(wrapped.commit().unwrap(), expected.hash_tree_root().unwrap());
+
+    let mut wrapper = Cached::new(Wrapper(foo));
+    wrapper.commit().unwrap();
+    wrapper.modify(0).0.a = 7;
+    assert_eq!(wrapper.hash_tree_root().unwrap(), wrapper.0.hash_tree_root().unwrap());
+}
+
+#[derive(Debug, PartialEq, Eq, SimpleSerialize)]
+enum Baz {
+    None,
+    A(Foo),
+    B(List<u16, 8>),
+}
+
+#[test]
+fn test_merkle_tree() {
+    let foo = Foo {
+        a: 1,
+        b: 2,
+        c: List::<usize, 45>::try_from(vec![9, 8, 7]).unwrap(),
+        d: U256::from(3),
+    };
+    let wrapper = Wrapper(foo.clone());
+    let tree = MerkleTree::new(&wrapper).unwrap();
+    for path in [&["c".into(), 2.into()][..], &["d".into()], &["c".into(), PathElement::Length]] {
+        assert_eq!(tree.prove(path).unwrap(), wrapper.prove(path).unwrap());
+    }
+
+    for (value, path) in [
+        (Baz::A(foo), &[1.into(), "c".into(), 1.into()][..]),
+        (Baz::B(List::try_from(vec![5, 6]).unwrap()), &[2.into(), 1.into()]),
+        (Baz::None, &[PathElement::Selector]),
+    ] {
+        let tree = MerkleTree::new(&value).unwrap();
+        assert_eq!(tree.root(), value.hash_tree_root().unwrap());
+        assert_eq!(tree.prove(path).unwrap(), value.prove(path).unwrap());
+    }
+
+    let profile = ProfileFoo { b: List::try_from(vec![1, 2, 3]).unwrap() };
+    let stable = StableFoo { a: None, b: Some(profile.b.clone()) };
+    let path = &["b".into(), 2.into()];
+    let mut tree = MerkleTree::new(&profile).unwrap();
+    assert_eq!(tree.prove(path).unwrap(), stable.prove(path).unwrap());
+    assert_eq!(tree.prove(&["a".into()]).ok(), None);
+    let (proof, witness) =
+        profile.multi_prove(&[path, &["b".into(), PathElement::Length]]).unwrap();
+    assert_eq!(witness, stable.hash_tree_root().unwrap());
+    assert!(proof.verify(witness).is_ok());
+
+    let other = ProfileFoo { b: List::try_from(vec![1, 2, 4]).unwrap() };
+    let index = ProfileFoo::generalized_index(&["b".into()]).unwrap();
+    tree.set_subtree(index, other.b.subtree().unwrap()).unwrap();
+    assert_eq!(tree.root(), other.hash_tree_root().unwrap());
+}
+
+#[derive(Debug, PartialEq, Eq, Serializable)]
+#[ssz(transparent)]
+enum Values {
+    Numbers(List<u64, 64>),
+    Flags(List<bool, 256>),
+}
+
+#[test]
+fn test_transparent_decode_refunds_failed_variants() {
+    let numbers = List::<u64, 64>::try_from(vec![2u64; 32]).unwrap();
+    let encoding = serialize(&numbers).unwrap();
+
+    // `Flags` is tried first and accounts for 256 bytes before failing on the byte `2`
+    let options = DecodeOptions { max_allocation: 256, ..Default::default() };
+    let value = deserialize_with_options::<Values>(&encoding, &options).unwrap();
+    assert_eq!(value, Values::Numbers(numbers));
+}
+
+#[derive(Debug, PartialEq, Eq, Serializable)]
+struct Record {
+    id: u64,
+    flags: List<bool, 4096>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serializable)]
+#[ssz(transparent)]
+enum Entry {
+    Small(u8),
+    Record(Record),
+}
+
+#[test]
+fn test_transparent_bytes_at_path_does_not_decode() {
+    let record = Record { id: 7, flags: List::try_from(vec![false; 4000]).unwrap() };
+    let mut encoding = serialize(&record).unwrap();
+    // corrupt every flag so that decoding the whole value fails
+    let offset = u64::size_hint() + 4;
+    encoding[offset..].fill(2);
+    assert!(Entry::deserialize(&encoding).is_err());
+
+    let id = Entry::decode_at_path::<u64>(&encoding, &["id".into()]).unwrap();
+    assert_eq!(id, 7);
+    let bytes = Entry::bytes_at_path(&encoding, &["flags".into()]).unwrap();
+    assert_eq!(bytes, &encoding[offset..]);
+}
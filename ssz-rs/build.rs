@@ -23,7 +23,8 @@ fn compute_zero_hashes() -> [u8; MAX_MERKLE_TREE_DEPTH * BYTES_PER_CHUNK] {
     buffer
 }
 
-// This function derives a set of bytes corresponding to "zero hashes" at build-time
+// This function derives a set of bytes corresponding to "zero hashes" of the default `Sha256Hasher`
+// at build-time
 // in lieu of needing to declare any sort of runtime static memory or similar technique.
 // If any of the hashing code changes significantly for the SSZ accumulator scheme,
 // this code will need to be updated as well.
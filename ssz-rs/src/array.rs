@@ -1,14 +1,23 @@
 use crate::{
-    de::{deserialize_homogeneous_composite, Deserialize, DeserializeError},
+    de::{
+        deserialize_homogeneous_composite, validate_variable_offsets_prefix, DecodeContext,
+        Deserialize, DeserializeError,
+    },
     error::{InstanceError, TypeError},
     lib::*,
     merkleization::{
-        elements_to_chunks, get_power_of_two_ceil, merkleize, pack,
+        diff::{diff_sequences, Diff, Differ},
+        get_power_of_two_ceil, merkleize_with, pack,
         proofs::{Prove, Prover},
-        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleizationError, Node, Path,
-        PathElement,
+        tree::Subtree,
+        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleHasher, MerkleizationError,
+        Node, Path, PathElement, Sha256Hasher,
+    },
+    ser::{
+        homogeneous_composite_encoded_len, homogeneous_composite_max_encoded_len,
+        serialize_homogeneous_composite, Serialize, SerializeError, Sink,
     },
-    ser::{Serialize, SerializeError, Serializer},
+    view::{SequenceView, Viewable},
     Serializable, SimpleSerialize,
 };
 
@@ -23,6 +32,38 @@ where
     fn size_hint() -> usize {
         T::size_hint() * N
     }
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        if N == 0 {
+            return Err(TypeError::InvalidBound(N).into())
+        }
+        homogeneous_composite_encoded_len(self)
+    }
+
+    fn max_encoded_len() -> usize {
+        homogeneous_composite_max_encoded_len::<T>(N)
+    }
+
+    fn bytes_at_path<'a>(encoding: &'a [u8], path: Path) -> Result<&'a [u8], DeserializeError> {
+        if path.is_empty() {
+            return Ok(encoding)
+        }
+        SequenceView::<T>::new_exact(encoding, N)?.bytes_at_path(path)
+    }
+
+    fn validate_prefix(prefix: &[u8]) -> Result<bool, DeserializeError> {
+        if N == 0 {
+            return Err(TypeError::InvalidBound(N).into())
+        }
+        if !T::is_variable_size() {
+            return Ok(true)
+        }
+        validate_variable_offsets_prefix(prefix, |len| {
+            if len != N {
+                return Err(InstanceError::Exact { required: N, provided: len }.into())
+            }
+            Ok(())
+        })
+    }
 }
 
 impl<T, const N: usize> Serialize for [T; N]
@@ -30,14 +71,14 @@ where
     T: Serializable,
 {
     fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
+        self.serialize_to(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
         if N == 0 {
             return Err(TypeError::InvalidBound(N).into())
         }
-        let mut serializer = Serializer::default();
-        for element in self {
-            serializer.with_element(element)?;
-        }
-        serializer.serialize(buffer)
+        serialize_homogeneous_composite(self, sink)
     }
 }
 
@@ -46,6 +87,13 @@ where
     T: Serializable,
 {
     fn deserialize(encoding: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_with(encoding, &mut DecodeContext::default())
+    }
+
+    fn deserialize_with(
+        encoding: &[u8],
+        context: &mut DecodeContext,
+    ) -> Result<Self, DeserializeError> {
         if N == 0 {
             return Err(TypeError::InvalidBound(N).into())
         }
@@ -65,7 +113,12 @@ where
                 })
             }
         }
-        let elements = deserialize_homogeneous_composite(encoding)?;
+        let elements = deserialize_homogeneous_composite(encoding, context, |len| {
+            if len != N {
+                return Err(InstanceError::Exact { required: N, provided: len }.into())
+            }
+            Ok(())
+        })?;
         elements.try_into().map_err(|elements: Vec<T>| {
             InstanceError::Exact { required: N, provided: elements.len() }.into()
         })
@@ -77,8 +130,12 @@ where
     T: SimpleSerialize,
 {
     fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
-        let chunks = self.chunks()?;
-        merkleize(&chunks, None)
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        let chunks = self.chunks::<H>()?;
+        merkleize_with::<H>(&chunks, None)
     }
 
     fn is_composite_type() -> bool {
@@ -121,16 +178,19 @@ impl<T, const N: usize> Prove for [T; N]
 where
     T: SimpleSerialize,
 {
-    fn chunks(&self) -> Result<Vec<u8>, MerkleizationError> {
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
         if T::is_composite_type() {
-            let count = self.len();
-            elements_to_chunks(self.iter().enumerate(), count)
+            T::elements_to_chunks::<H>(self)
         } else {
             pack(self)
         }
     }
 
-    fn prove_element(&self, index: usize, prover: &mut Prover) -> Result<(), MerkleizationError> {
+    fn prove_element<H: MerkleHasher>(
+        &self,
+        index: usize,
+        prover: &mut Prover<H>,
+    ) -> Result<(), MerkleizationError> {
         if index >= N {
             Err(MerkleizationError::InvalidInnerIndex)
         } else {
@@ -138,6 +198,30 @@ where
             prover.compute_proof(child)
         }
     }
+
+    fn subtree<H: MerkleHasher>(&self) -> Result<Subtree<H>, MerkleizationError> {
+        Subtree::from_sequence(self, self.iter())
+    }
+}
+
+impl<T, const N: usize> Viewable for [T; N]
+where
+    T: Viewable,
+{
+    type View<'a> = SequenceView<'a, T>;
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        SequenceView::new_exact(encoding, N)
+    }
+}
+
+impl<T, const N: usize> Diff for [T; N]
+where
+    T: SimpleSerialize + Diff,
+{
+    fn diff_elements(&self, other: &Self, differ: &mut Differ) -> Result<(), MerkleizationError> {
+        diff_sequences::<Self, T>(self, other, false, differ)
+    }
 }
 
 impl<T, const N: usize> SimpleSerialize for [T; N] where T: SimpleSerialize {}
@@ -1,13 +1,18 @@
+#[cfg(feature = "rayon")]
+use crate::merkleization::elements_to_chunks_in_parallel;
 use crate::{
     de::{Deserialize, DeserializeError},
     error::{Error, InstanceError},
     lib::*,
     merkleization::{
-        get_power_of_two_ceil, merkleize, mix_in_length, pack_bytes, proofs::Prove,
-        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleizationError, Node, Path,
-        PathElement, BITS_PER_CHUNK,
+        diff::{diff_packed_chunks, Diff, Differ},
+        get_power_of_two_ceil, merkleize_with, mix_in_length, pack_bytes,
+        proofs::Prove,
+        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleHasher, MerkleizationError,
+        Node, Path, PathElement, Sha256Hasher, BITS_PER_CHUNK,
     },
-    ser::{Serialize, SerializeError},
+    ser::{Serialize, SerializeError, Sink},
+    view::Viewable,
     Serializable, SimpleSerialize,
 };
 #[cfg(feature = "serde")]
@@ -75,29 +80,32 @@ impl<const N: usize> Bitlist<N> {
         Ok(data)
     }
 
-    fn serialize_with_length(
+    fn serialize_with_length<S: Sink + ?Sized>(
         &self,
-        buffer: &mut Vec<u8>,
+        sink: &mut S,
         with_length_bit: bool,
     ) -> Result<usize, SerializeError> {
         if self.len() > N {
             return Err(InstanceError::Bounded { bound: N, provided: self.len() }.into())
         }
-        let start_len = buffer.len();
-        buffer.extend_from_slice(self.as_raw_slice());
-
-        if with_length_bit {
-            let element_count = self.len();
-            let marker_index = element_count % BITS_PER_BYTE;
-            if marker_index == 0 {
-                buffer.push(1u8);
-            } else {
-                let last = buffer.last_mut().expect("bitlist cannot be empty");
-                *last |= 1u8 << marker_index;
-            }
+        let data = self.as_raw_slice();
+        if !with_length_bit {
+            sink.write_bytes(data)?;
+            return Ok(data.len())
+        }
+
+        let element_count = self.len();
+        let marker_index = element_count % BITS_PER_BYTE;
+        if marker_index == 0 {
+            sink.write_bytes(data)?;
+            sink.write_bytes(&[1u8])?;
+            Ok(data.len() + 1)
+        } else {
+            let (last, prefix) = data.split_last().expect("bitlist cannot be empty");
+            sink.write_bytes(prefix)?;
+            sink.write_bytes(&[*last | 1u8 << marker_index])?;
+            Ok(data.len())
         }
-        // SAFETY: checked subtraction is unnecessary, as buffer.len() > start_len; qed
-        Ok(buffer.len() - start_len)
     }
 
     fn chunk_count() -> usize {
@@ -127,11 +135,24 @@ impl<const N: usize> Serializable for Bitlist<N> {
     fn size_hint() -> usize {
         0
     }
+
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        // the length bit always needs an additional bit
+        Ok(self.len() / BITS_PER_BYTE + 1)
+    }
+
+    fn max_encoded_len() -> usize {
+        N / BITS_PER_BYTE + 1
+    }
 }
 
 impl<const N: usize> Serialize for Bitlist<N> {
     fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
-        self.serialize_with_length(buffer, true)
+        self.serialize_to(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
+        self.serialize_with_length(sink, true)
     }
 }
 
@@ -180,9 +201,20 @@ impl<const N: usize> Deserialize for Bitlist<N> {
 
 impl<const N: usize> HashTreeRoot for Bitlist<N> {
     fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
         let chunks = self.pack_bits()?;
-        let data_root = merkleize(&chunks, Some(Self::chunk_count()))?;
-        Ok(mix_in_length(data_root, self.len()))
+        let data_root = merkleize_with::<H>(&chunks, Some(Self::chunk_count()))?;
+        Ok(mix_in_length::<H>(data_root, self.len()))
+    }
+
+    #[cfg(feature = "rayon")]
+    fn elements_to_chunks<H: MerkleHasher>(
+        elements: &[Self],
+    ) -> Result<Vec<u8>, MerkleizationError> {
+        elements_to_chunks_in_parallel::<H, _>(elements)
     }
 }
 
@@ -209,6 +241,13 @@ impl<const N: usize> GeneralizedIndexable for Bitlist<N> {
                     // NOTE: use `bool` as effective type of element
                     bool::compute_generalized_index(child, rest)
                 }
+                PathElement::Length => {
+                    if rest.is_empty() {
+                        Ok(parent * 2 + 1)
+                    } else {
+                        Err(MerkleizationError::InvalidPath(rest.to_vec()))
+                    }
+                }
                 elem => Err(MerkleizationError::InvalidPathElement(elem.clone())),
             }
         } else {
@@ -218,7 +257,7 @@ impl<const N: usize> GeneralizedIndexable for Bitlist<N> {
 }
 
 impl<const N: usize> Prove for Bitlist<N> {
-    fn chunks(&self) -> Result<Vec<u8>, MerkleizationError> {
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
         self.pack_bits()
     }
 
@@ -227,6 +266,28 @@ impl<const N: usize> Prove for Bitlist<N> {
     }
 }
 
+impl<const N: usize> Viewable for Bitlist<N> {
+    type View<'a> = Self;
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        Self::deserialize(encoding)
+    }
+}
+
+impl<const N: usize> Diff for Bitlist<N> {
+    fn diff_elements(&self, other: &Self, differ: &mut Differ) -> Result<(), MerkleizationError> {
+        diff_packed_chunks::<Self>(&self.pack_bits()?, &other.pack_bits()?, 0, 1, differ)?;
+        if self.len() != other.len() {
+            differ.compare_element::<Self, usize>(
+                PathElement::Length,
+                &self.len(),
+                &other.len(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
 impl<const N: usize> SimpleSerialize for Bitlist<N> {}
 
 impl<const N: usize> TryFrom<&[u8]> for Bitlist<N> {
@@ -1,13 +1,18 @@
+#[cfg(feature = "rayon")]
+use crate::merkleization::elements_to_chunks_in_parallel;
 use crate::{
     de::{Deserialize, DeserializeError},
     error::{Error, InstanceError, TypeError},
     lib::*,
     merkleization::{
-        get_power_of_two_ceil, merkleize, pack_bytes, proofs::Prove, GeneralizedIndex,
-        GeneralizedIndexable, HashTreeRoot, MerkleizationError, Node, Path, PathElement,
-        BITS_PER_CHUNK,
+        diff::{diff_packed_chunks, Diff, Differ},
+        get_power_of_two_ceil, merkleize_with, pack_bytes,
+        proofs::Prove,
+        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleHasher, MerkleizationError,
+        Node, Path, PathElement, Sha256Hasher, BITS_PER_CHUNK,
     },
-    ser::{Serialize, SerializeError},
+    ser::{Serialize, SerializeError, Sink},
+    view::Viewable,
     Serializable, SimpleSerialize,
 };
 #[cfg(feature = "serde")]
@@ -115,18 +120,27 @@ impl<const N: usize> Serializable for Bitvector<N> {
     fn size_hint() -> usize {
         byte_length(N)
     }
+
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        Ok(Self::size_hint())
+    }
 }
 
 impl<const N: usize> Serialize for Bitvector<N> {
     fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
+        self.serialize_to(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
         if N == 0 {
             return Err(TypeError::InvalidBound(N).into())
         }
         let bytes_to_write = Self::size_hint();
-        buffer.reserve(bytes_to_write);
+        let mut encoding = Vec::with_capacity(bytes_to_write);
         for byte in self.0.chunks(BITS_PER_BYTE) {
-            buffer.push(byte.load());
+            encoding.push(byte.load());
         }
+        sink.write_bytes(&encoding)?;
         Ok(bytes_to_write)
     }
 }
@@ -169,8 +183,19 @@ impl<const N: usize> Deserialize for Bitvector<N> {
 
 impl<const N: usize> HashTreeRoot for Bitvector<N> {
     fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
         let chunks = self.pack_bits()?;
-        merkleize(&chunks, Some(Self::chunk_count()))
+        merkleize_with::<H>(&chunks, Some(Self::chunk_count()))
+    }
+
+    #[cfg(feature = "rayon")]
+    fn elements_to_chunks<H: MerkleHasher>(
+        elements: &[Self],
+    ) -> Result<Vec<u8>, MerkleizationError> {
+        elements_to_chunks_in_parallel::<H, _>(elements)
     }
 }
 
@@ -205,11 +230,25 @@ impl<const N: usize> GeneralizedIndexable for Bitvector<N> {
 }
 
 impl<const N: usize> Prove for Bitvector<N> {
-    fn chunks(&self) -> Result<Vec<u8>, MerkleizationError> {
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
         self.pack_bits()
     }
 }
 
+impl<const N: usize> Viewable for Bitvector<N> {
+    type View<'a> = Self;
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        Self::deserialize(encoding)
+    }
+}
+
+impl<const N: usize> Diff for Bitvector<N> {
+    fn diff_elements(&self, other: &Self, differ: &mut Differ) -> Result<(), MerkleizationError> {
+        diff_packed_chunks::<Self>(&self.pack_bits()?, &other.pack_bits()?, 0, 1, differ)
+    }
+}
+
 impl<const N: usize> SimpleSerialize for Bitvector<N> {}
 
 impl<const N: usize> TryFrom<&[u8]> for Bitvector<N> {
@@ -2,10 +2,11 @@ use crate::{
     de::{Deserialize, DeserializeError},
     lib::*,
     merkleization::{
-        proofs::Prove, GeneralizedIndexable, HashTreeRoot, MerkleizationError, Node,
-        BYTES_PER_CHUNK,
+        diff::Diff, proofs::Prove, GeneralizedIndexable, HashTreeRoot, MerkleHasher,
+        MerkleizationError, Node, Sha256Hasher, BYTES_PER_CHUNK,
     },
-    ser::{Serialize, SerializeError},
+    ser::{Serialize, SerializeError, Sink},
+    view::Viewable,
     Serializable, SimpleSerialize,
 };
 
@@ -17,12 +18,20 @@ impl Serializable for bool {
     fn size_hint() -> usize {
         1
     }
+
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        Ok(Self::size_hint())
+    }
 }
 
 impl Serialize for bool {
     fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
+        self.serialize_to(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
         let value = u8::from(*self);
-        buffer.push(value);
+        sink.write_bytes(&[value])?;
         Ok(1)
     }
 }
@@ -44,6 +53,10 @@ impl Deserialize for bool {
 
 impl HashTreeRoot for bool {
     fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
         let mut node = Node::default();
         if *self {
             node[0] = 1;
@@ -63,7 +76,7 @@ impl GeneralizedIndexable for bool {
 }
 
 impl Prove for bool {
-    fn chunks(&self) -> Result<Vec<u8>, MerkleizationError> {
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
         let mut vec = vec![0u8; BYTES_PER_CHUNK];
         if *self {
             vec[0] = 1;
@@ -72,6 +85,16 @@ impl Prove for bool {
     }
 }
 
+impl Viewable for bool {
+    type View<'a> = Self;
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        Self::deserialize(encoding)
+    }
+}
+
+impl Diff for bool {}
+
 impl SimpleSerialize for bool {}
 
 #[cfg(test)]
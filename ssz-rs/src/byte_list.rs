@@ -0,0 +1,322 @@
+#[cfg(feature = "rayon")]
+use crate::merkleization::elements_to_chunks_in_parallel;
+use crate::{
+    de::{Deserialize, DeserializeError},
+    error::{Error, InstanceError},
+    lib::*,
+    merkleization::{
+        diff::Diff, merkleize_bytes, mix_in_length, pack_bytes, proofs::Prove, GeneralizedIndex,
+        GeneralizedIndexable, HashTreeRoot, MerkleHasher, MerkleizationError, Node, Path,
+        Sha256Hasher, BYTES_PER_CHUNK,
+    },
+    ser::{Serialize, SerializeError, Sink},
+    view::Viewable,
+    List, Serializable, SimpleSerialize,
+};
+
+/// A variable number of bytes, up to `N` bytes.
+///
+/// A `ByteList<N>` has the same encoding and Merkle tree as a `List<u8, N>` but stores its data as
+/// a contiguous buffer that is encoded, decoded and merkleized without visiting each byte
+/// individually. Under the `serde` feature, it is represented as a `0x`-prefixed hex string.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Default)]
+pub struct ByteList<const N: usize> {
+    data: Vec<u8>,
+}
+
+impl<const N: usize> AsRef<[u8]> for ByteList<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl<const N: usize> fmt::Debug for ByteList<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "ByteList<{}>(len={})0x", N, self.len())?;
+        for byte in &self.data {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> TryFrom<Vec<u8>> for ByteList<N> {
+    type Error = (Vec<u8>, Error);
+
+    fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
+        if data.len() > N {
+            let len = data.len();
+            Err((data, Error::Instance(InstanceError::Bounded { bound: N, provided: len })))
+        } else {
+            Ok(Self { data })
+        }
+    }
+}
+
+impl<const N: usize> TryFrom<&[u8]> for ByteList<N> {
+    type Error = Error;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() > N {
+            let len = data.len();
+            Err(Error::Instance(InstanceError::Bounded { bound: N, provided: len }))
+        } else {
+            Ok(Self { data: data.to_vec() })
+        }
+    }
+}
+
+impl<const N: usize> From<ByteList<N>> for Vec<u8> {
+    fn from(value: ByteList<N>) -> Self {
+        value.data
+    }
+}
+
+impl<const N: usize> From<ByteList<N>> for List<u8, N> {
+    fn from(value: ByteList<N>) -> Self {
+        // SAFETY: `value` has at most `N` bytes; qed
+        value.data.try_into().map_err(|(_, err)| err).expect("data is within bound")
+    }
+}
+
+impl<const N: usize> From<List<u8, N>> for ByteList<N> {
+    fn from(value: List<u8, N>) -> Self {
+        Self { data: value.to_vec() }
+    }
+}
+
+impl<const N: usize> Deref for ByteList<N> {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl<const N: usize> DerefMut for ByteList<N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+impl<Idx: SliceIndex<[u8]>, const N: usize> Index<Idx> for ByteList<N> {
+    type Output = <Idx as SliceIndex<[u8]>>::Output;
+
+    fn index(&self, index: Idx) -> &Self::Output {
+        &self.data[index]
+    }
+}
+
+impl<Idx: SliceIndex<[u8]>, const N: usize> IndexMut<Idx> for ByteList<N> {
+    fn index_mut(&mut self, index: Idx) -> &mut Self::Output {
+        &mut self.data[index]
+    }
+}
+
+impl<const N: usize> Serializable for ByteList<N> {
+    fn is_variable_size() -> bool {
+        true
+    }
+
+    fn size_hint() -> usize {
+        0
+    }
+
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        Ok(self.len())
+    }
+
+    fn max_encoded_len() -> usize {
+        N
+    }
+
+    fn bytes_at_path<'a>(encoding: &'a [u8], path: Path) -> Result<&'a [u8], DeserializeError> {
+        List::<u8, N>::bytes_at_path(encoding, path)
+    }
+}
+
+impl<const N: usize> Serialize for ByteList<N> {
+    fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
+        self.serialize_to(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
+        if self.len() > N {
+            return Err(InstanceError::Bounded { bound: N, provided: self.len() }.into())
+        }
+        sink.write_bytes(&self.data)?;
+        Ok(self.len())
+    }
+}
+
+impl<const N: usize> Deserialize for ByteList<N> {
+    fn deserialize(encoding: &[u8]) -> Result<Self, DeserializeError> {
+        if encoding.len() > N {
+            return Err(InstanceError::Bounded { bound: N, provided: encoding.len() }.into())
+        }
+        Ok(Self { data: encoding.to_vec() })
+    }
+}
+
+impl<const N: usize> ByteList<N> {
+    // Number of chunks for this type, rounded up to a complete number of chunks
+    fn chunk_count() -> usize {
+        N.div_ceil(BYTES_PER_CHUNK)
+    }
+}
+
+impl<const N: usize> HashTreeRoot for ByteList<N> {
+    fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        let data_root = merkleize_bytes::<H>(&self.data, Some(Self::chunk_count()))?;
+        Ok(mix_in_length::<H>(data_root, self.len()))
+    }
+
+    #[cfg(feature = "rayon")]
+    fn elements_to_chunks<H: MerkleHasher>(
+        elements: &[Self],
+    ) -> Result<Vec<u8>, MerkleizationError> {
+        elements_to_chunks_in_parallel::<H, _>(elements)
+    }
+}
+
+impl<const N: usize> GeneralizedIndexable for ByteList<N> {
+    fn chunk_count() -> usize {
+        Self::chunk_count()
+    }
+
+    fn compute_generalized_index(
+        parent: GeneralizedIndex,
+        path: Path,
+    ) -> Result<GeneralizedIndex, MerkleizationError> {
+        List::<u8, N>::compute_generalized_index(parent, path)
+    }
+}
+
+impl<const N: usize> Prove for ByteList<N> {
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
+        let mut chunks = self.data.clone();
+        pack_bytes(&mut chunks);
+        Ok(chunks)
+    }
+
+    fn decoration(&self) -> Option<usize> {
+        Some(self.len())
+    }
+}
+
+impl<const N: usize> Viewable for ByteList<N> {
+    type View<'a> = &'a [u8];
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        if encoding.len() > N {
+            return Err(InstanceError::Bounded { bound: N, provided: encoding.len() }.into())
+        }
+        Ok(encoding)
+    }
+}
+
+// NOTE: bytes are reported as a whole rather than byte by byte
+impl<const N: usize> Diff for ByteList<N> {}
+
+impl<const N: usize> SimpleSerialize for ByteList<N> {}
+
+#[cfg(feature = "serde")]
+impl<const N: usize> serde::Serialize for ByteList<N> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        alloy_primitives::serde_hex::serialize(&self.data, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const N: usize> serde::Deserialize<'de> for ByteList<N> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let data: Vec<u8> = alloy_primitives::serde_hex::deserialize(deserializer)?;
+        Self::try_from(data).map_err(|(_, err)| serde::de::Error::custom(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        merkleization::{proofs::tests::compute_and_verify_proof_for_path, PathElement},
+        serialize,
+    };
+
+    fn roundtrip_with_list<const N: usize>(data: &[u8]) {
+        let value = ByteList::<N>::try_from(data).unwrap();
+        let list = List::<u8, N>::try_from(data).unwrap();
+
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(encoding, serialize(&list).unwrap());
+        assert_eq!(value.encoded_len().unwrap(), encoding.len());
+        assert_eq!(ByteList::<N>::deserialize(&encoding).unwrap(), value);
+        assert_eq!(value.hash_tree_root().unwrap(), list.hash_tree_root().unwrap());
+        assert_eq!(List::<u8, N>::from(value), list);
+    }
+
+    #[test]
+    fn test_matches_list() {
+        roundtrip_with_list::<0>(&[]);
+        roundtrip_with_list::<1>(&[]);
+        roundtrip_with_list::<1>(&[7]);
+        roundtrip_with_list::<32>(&[7; 32]);
+        roundtrip_with_list::<33>(&[7; 32]);
+        roundtrip_with_list::<100>(&[7; 33]);
+        roundtrip_with_list::<1024>(&(0..=255).collect::<Vec<_>>());
+        roundtrip_with_list::<1048576>(&[3; 1000]);
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert!(ByteList::<4>::try_from([1u8; 5].as_ref()).is_err());
+        assert!(ByteList::<4>::try_from(vec![1u8; 5]).is_err());
+        assert!(ByteList::<4>::deserialize(&[1; 5]).is_err());
+        assert!(ByteList::<4>::view(&[1; 5]).is_err());
+        assert_eq!(ByteList::<4>::view(&[1, 2]).unwrap(), [1, 2]);
+
+        let mut value = ByteList::<4>::default();
+        value.extend_from_slice(&[1; 5]);
+        assert!(serialize(&value).is_err());
+    }
+
+    #[test]
+    fn test_paths_and_proofs() {
+        let data = (0..100).collect::<Vec<u8>>();
+        let value = ByteList::<256>::try_from(data.as_ref()).unwrap();
+        let list = List::<u8, 256>::try_from(data).unwrap();
+        let encoding = serialize(&value).unwrap();
+
+        for path in [vec![], vec![40.into()], vec![PathElement::Length]] {
+            assert_eq!(
+                ByteList::<256>::generalized_index(&path).unwrap(),
+                List::<u8, 256>::generalized_index(&path).unwrap()
+            );
+            assert_eq!(value.prove(&path).unwrap(), list.prove(&path).unwrap());
+            compute_and_verify_proof_for_path(&value, &path);
+        }
+        assert_eq!(ByteList::<256>::decode_at_path::<u8>(&encoding, &[40.into()]).unwrap(), 40);
+        assert!(ByteList::<256>::bytes_at_path(&encoding, &[100.into()]).is_err());
+    }
+
+    #[test]
+    fn test_serde() {
+        let value = ByteList::<8>::try_from([0xab, 0x01, 0xff].as_ref()).unwrap();
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, "\"0xab01ff\"");
+        assert_eq!(serde_json::from_str::<ByteList<8>>(&json).unwrap(), value);
+        assert_eq!(serde_json::to_string(&ByteList::<8>::default()).unwrap(), "\"0x\"");
+        assert!(serde_json::from_str::<ByteList<2>>(&json).is_err());
+        assert!(serde_json::from_str::<ByteList<8>>("[171, 1, 255]").is_err());
+    }
+}
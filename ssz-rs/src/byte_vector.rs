@@ -0,0 +1,338 @@
+#[cfg(feature = "rayon")]
+use crate::merkleization::elements_to_chunks_in_parallel;
+use crate::{
+    de::{Deserialize, DeserializeError},
+    error::{Error, InstanceError, TypeError},
+    lib::*,
+    merkleization::{
+        diff::Diff, merkleize_bytes, pack_bytes, proofs::Prove, GeneralizedIndex,
+        GeneralizedIndexable, HashTreeRoot, MerkleHasher, MerkleizationError, Node, Path,
+        Sha256Hasher, BYTES_PER_CHUNK,
+    },
+    ser::{Serialize, SerializeError, Sink},
+    view::Viewable,
+    Serializable, SimpleSerialize, Vector,
+};
+
+/// A fixed number `N` of bytes.
+///
+/// A `ByteVector<N>` has the same encoding and Merkle tree as a `Vector<u8, N>` but stores its data
+/// as a contiguous buffer that is encoded, decoded and merkleized without visiting each byte
+/// individually. Under the `serde` feature, it is represented as a `0x`-prefixed hex string.
+///
+/// NOTE: a `ByteVector` of length `0` is illegal.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub struct ByteVector<const N: usize> {
+    data: Vec<u8>,
+}
+
+impl<const N: usize> AsRef<[u8]> for ByteVector<N> {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl<const N: usize> AsMut<[u8]> for ByteVector<N> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+impl<const N: usize> fmt::Debug for ByteVector<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "ByteVector<{}>0x", N)?;
+        for byte in &self.data {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for ByteVector<N> {
+    fn default() -> Self {
+        // SAFETY: there is currently no way to enforce statically
+        // that `N` is non-zero with const generics so panics are possible.
+        assert!(N > 0);
+
+        Self { data: vec![0u8; N] }
+    }
+}
+
+impl<const N: usize> TryFrom<Vec<u8>> for ByteVector<N> {
+    type Error = (Vec<u8>, Error);
+
+    fn try_from(data: Vec<u8>) -> Result<Self, Self::Error> {
+        if N == 0 {
+            return Err((data, Error::Type(TypeError::InvalidBound(N))))
+        }
+        if data.len() != N {
+            let len = data.len();
+            Err((data, Error::Instance(InstanceError::Exact { required: N, provided: len })))
+        } else {
+            Ok(Self { data })
+        }
+    }
+}
+
+impl<const N: usize> TryFrom<&[u8]> for ByteVector<N> {
+    type Error = Error;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if N == 0 {
+            return Err(Error::Type(TypeError::InvalidBound(N)))
+        }
+        if data.len() != N {
+            let len = data.len();
+            Err(Error::Instance(InstanceError::Exact { required: N, provided: len }))
+        } else {
+            Ok(Self { data: data.to_vec() })
+        }
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for ByteVector<N> {
+    fn from(data: [u8; N]) -> Self {
+        // SAFETY: there is currently no way to enforce statically
+        // that `N` is non-zero with const generics so panics are possible.
+        assert!(N > 0);
+
+        Self { data: data.to_vec() }
+    }
+}
+
+impl<const N: usize> From<ByteVector<N>> for Vec<u8> {
+    fn from(value: ByteVector<N>) -> Self {
+        value.data
+    }
+}
+
+impl<const N: usize> From<ByteVector<N>> for Vector<u8, N> {
+    fn from(value: ByteVector<N>) -> Self {
+        // SAFETY: `value` has exactly `N` bytes and `N` is non-zero; qed
+        value.data.try_into().map_err(|(_, err)| err).expect("data has the correct length")
+    }
+}
+
+impl<const N: usize> From<Vector<u8, N>> for ByteVector<N> {
+    fn from(value: Vector<u8, N>) -> Self {
+        Self { data: value.to_vec() }
+    }
+}
+
+// NOTE: unlike `Vector`, dereference to a slice so the length of the data can not change
+impl<const N: usize> Deref for ByteVector<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl<const N: usize> DerefMut for ByteVector<N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+impl<Idx: SliceIndex<[u8]>, const N: usize> Index<Idx> for ByteVector<N> {
+    type Output = <Idx as SliceIndex<[u8]>>::Output;
+
+    fn index(&self, index: Idx) -> &Self::Output {
+        &self.data[index]
+    }
+}
+
+impl<Idx: SliceIndex<[u8]>, const N: usize> IndexMut<Idx> for ByteVector<N> {
+    fn index_mut(&mut self, index: Idx) -> &mut Self::Output {
+        &mut self.data[index]
+    }
+}
+
+impl<const N: usize> Serializable for ByteVector<N> {
+    fn is_variable_size() -> bool {
+        false
+    }
+
+    fn size_hint() -> usize {
+        N
+    }
+
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        Ok(N)
+    }
+
+    fn bytes_at_path<'a>(encoding: &'a [u8], path: Path) -> Result<&'a [u8], DeserializeError> {
+        Vector::<u8, N>::bytes_at_path(encoding, path)
+    }
+}
+
+impl<const N: usize> Serialize for ByteVector<N> {
+    fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
+        self.serialize_to(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
+        if N == 0 {
+            return Err(TypeError::InvalidBound(N).into())
+        }
+        sink.write_bytes(&self.data)?;
+        Ok(N)
+    }
+}
+
+impl<const N: usize> Deserialize for ByteVector<N> {
+    fn deserialize(encoding: &[u8]) -> Result<Self, DeserializeError> {
+        Ok(Self { data: Self::view(encoding)?.to_vec() })
+    }
+}
+
+impl<const N: usize> HashTreeRoot for ByteVector<N> {
+    fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        merkleize_bytes::<H>(&self.data, None)
+    }
+
+    #[cfg(feature = "rayon")]
+    fn elements_to_chunks<H: MerkleHasher>(
+        elements: &[Self],
+    ) -> Result<Vec<u8>, MerkleizationError> {
+        elements_to_chunks_in_parallel::<H, _>(elements)
+    }
+}
+
+impl<const N: usize> GeneralizedIndexable for ByteVector<N> {
+    fn chunk_count() -> usize {
+        N.div_ceil(BYTES_PER_CHUNK)
+    }
+
+    fn compute_generalized_index(
+        parent: GeneralizedIndex,
+        path: Path,
+    ) -> Result<GeneralizedIndex, MerkleizationError> {
+        Vector::<u8, N>::compute_generalized_index(parent, path)
+    }
+}
+
+impl<const N: usize> Prove for ByteVector<N> {
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
+        let mut chunks = self.data.clone();
+        pack_bytes(&mut chunks);
+        Ok(chunks)
+    }
+}
+
+impl<const N: usize> Viewable for ByteVector<N> {
+    type View<'a> = &'a [u8];
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        if N == 0 {
+            return Err(TypeError::InvalidBound(N).into())
+        }
+        if encoding.len() < N {
+            return Err(DeserializeError::ExpectedFurtherInput {
+                provided: encoding.len(),
+                expected: N,
+            })
+        }
+        if encoding.len() > N {
+            return Err(DeserializeError::AdditionalInput { provided: encoding.len(), expected: N })
+        }
+        Ok(encoding)
+    }
+}
+
+// NOTE: bytes are reported as a whole rather than byte by byte
+impl<const N: usize> Diff for ByteVector<N> {}
+
+impl<const N: usize> SimpleSerialize for ByteVector<N> {}
+
+#[cfg(feature = "serde")]
+impl<const N: usize> serde::Serialize for ByteVector<N> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        alloy_primitives::serde_hex::serialize(&self.data, serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const N: usize> serde::Deserialize<'de> for ByteVector<N> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let data: Vec<u8> = alloy_primitives::serde_hex::deserialize(deserializer)?;
+        Self::try_from(data).map_err(|(_, err)| serde::de::Error::custom(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{merkleization::proofs::tests::compute_and_verify_proof_for_path, serialize};
+
+    fn roundtrip_with_vector<const N: usize>(data: &[u8]) {
+        let value = ByteVector::<N>::try_from(data).unwrap();
+        let vector = Vector::<u8, N>::try_from(data).unwrap();
+
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(encoding, serialize(&vector).unwrap());
+        assert_eq!(ByteVector::<N>::deserialize(&encoding).unwrap(), value);
+        assert_eq!(value.hash_tree_root().unwrap(), vector.hash_tree_root().unwrap());
+        assert_eq!(Vector::<u8, N>::from(value), vector);
+    }
+
+    #[test]
+    fn test_matches_vector() {
+        roundtrip_with_vector::<1>(&[7]);
+        roundtrip_with_vector::<20>(&[7; 20]);
+        roundtrip_with_vector::<32>(&[7; 32]);
+        roundtrip_with_vector::<48>(&[7; 48]);
+        roundtrip_with_vector::<256>(&(0..=255).collect::<Vec<_>>());
+        roundtrip_with_vector::<131072>(&[3; 131072]);
+    }
+
+    #[test]
+    fn test_bounds() {
+        assert!(ByteVector::<4>::try_from([1u8; 5].as_ref()).is_err());
+        assert!(ByteVector::<4>::try_from(vec![1u8; 3]).is_err());
+        assert!(ByteVector::<0>::try_from(vec![]).is_err());
+        assert!(ByteVector::<4>::deserialize(&[1; 5]).is_err());
+        assert!(ByteVector::<4>::deserialize(&[1; 3]).is_err());
+        assert_eq!(ByteVector::<4>::default().as_ref(), [0; 4]);
+        assert_eq!(ByteVector::from([1, 2]).as_ref(), [1, 2]);
+    }
+
+    #[test]
+    fn test_paths_and_proofs() {
+        let data = (0..100).collect::<Vec<u8>>();
+        let value = ByteVector::<100>::try_from(data.as_ref()).unwrap();
+        let vector = Vector::<u8, 100>::try_from(data).unwrap();
+        let encoding = serialize(&value).unwrap();
+
+        for path in [vec![], vec![40.into()], vec![99.into()]] {
+            assert_eq!(
+                ByteVector::<100>::generalized_index(&path).unwrap(),
+                Vector::<u8, 100>::generalized_index(&path).unwrap()
+            );
+            assert_eq!(value.prove(&path).unwrap(), vector.prove(&path).unwrap());
+            compute_and_verify_proof_for_path(&value, &path);
+        }
+        assert_eq!(ByteVector::<100>::decode_at_path::<u8>(&encoding, &[40.into()]).unwrap(), 40);
+        assert!(ByteVector::<100>::bytes_at_path(&encoding, &[100.into()]).is_err());
+    }
+
+    #[test]
+    fn test_serde() {
+        let value = ByteVector::<3>::from([0xab, 0x01, 0xff]);
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, "\"0xab01ff\"");
+        assert_eq!(serde_json::from_str::<ByteVector<3>>(&json).unwrap(), value);
+        assert!(serde_json::from_str::<ByteVector<4>>(&json).is_err());
+        assert!(serde_json::from_str::<ByteVector<3>>("[171, 1, 255]").is_err());
+    }
+}
@@ -0,0 +1,652 @@
+use crate::{
+    de::{DecodeContext, Deserialize, DeserializeError},
+    lib::*,
+    merkleization::{
+        diff::{Diff, Differ},
+        hash_node_pair, is_default_hasher, mix_in_aux, pack,
+        proofs::{Prove, Prover},
+        tree::Subtree,
+        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleHasher, MerkleizationError,
+        Node, Path, Sha256Hasher, BITS_PER_CHUNK, BYTES_PER_CHUNK,
+    },
+    ser::{Serialize, SerializeError, Sink},
+    view::Viewable,
+    Bitlist, Bitvector, List, Serializable, SimpleSerialize, Vector,
+};
+
+/// Types whose Merkle tree can be cached by `Cached`.
+///
+/// The chunks of a type are the leaves of its Merkle tree: the roots of the fields of a container,
+/// the roots of the elements of a list or vector of composite types, or otherwise the packed
+/// elements or bits.
+pub trait Cacheable: HashTreeRoot + Prove {
+    /// Returns the number of chunks of `self`, excluding the zero chunks padding its Merkle tree.
+    fn chunk_len(&self) -> usize;
+
+    /// Compute the chunk at `index` with the hasher `H`, where `index < self.chunk_len()`.
+    fn chunk<H: MerkleHasher>(&self, index: usize) -> Result<Node, MerkleizationError>;
+
+    /// Returns the index of the chunk containing the element at `index`.
+    /// Default implementation is for types with one element per chunk.
+    fn chunk_index(index: usize) -> usize {
+        index
+    }
+}
+
+/// A value along with a cache of its Merkle tree, so that its `hash_tree_root` can be recomputed
+/// at a cost proportional to the number of modified elements rather than to the size of the value.
+///
+/// Elements are modified through `Cached::modify`, which marks the chunk of an element as dirty.
+/// `Cached::commit` updates the cached tree with the dirty chunks and returns the root; until then,
+/// `hash_tree_root` recomputes the nodes above the dirty chunks on each call. The cache is built on
+/// the first commit. The cache is computed with the default `Sha256Hasher`; roots computed with
+/// another `MerkleHasher` are computed from `T` without the cache.
+///
+/// A `Cached<T>` has the same encoding and Merkle tree as `T`, so it can be used in place of `T`,
+/// e.g. as a field of a container.
+#[derive(Clone)]
+pub struct Cached<T> {
+    value: T,
+    // the layers of the Merkle tree from the chunks up to the root, without the nodes above the
+    // zero chunks past the chunks of `value`, if built
+    layers: Option<Vec<Vec<Node>>>,
+    dirty: Vec<usize>,
+}
+
+impl<T> Cached<T> {
+    pub fn new(value: T) -> Self {
+        Self { value, layers: None, dirty: vec![] }
+    }
+
+    /// Returns the underlying value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    /// Returns the underlying value for modifications of all of its elements, discarding the
+    /// cache.
+    pub fn modify_all(&mut self) -> &mut T {
+        self.layers = None;
+        self.dirty.clear();
+        &mut self.value
+    }
+}
+
+impl<T: Cacheable> Cached<T> {
+    /// Returns the underlying value to modify the element at `index`, e.g. a field of a container
+    /// by its position or an element of a list. Elements appended to or removed from a list must be
+    /// marked by their index as well.
+    ///
+    /// NOTE: modifications of any other element are not reflected in the `hash_tree_root`.
+    pub fn modify(&mut self, index: usize) -> &mut T {
+        if self.layers.is_some() {
+            self.dirty.push(T::chunk_index(index));
+        }
+        &mut self.value
+    }
+
+    /// Update the cached Merkle tree with the dirty chunks, building it if necessary, and return
+    /// the `hash_tree_root` of the value.
+    pub fn commit(&mut self) -> Result<Node, MerkleizationError> {
+        let layers = match self.layers.take() {
+            Some(mut layers) => {
+                let changes = self.changes(&layers)?;
+                let chunk_len = self.value.chunk_len();
+                for (depth, (layer, changes)) in layers.iter_mut().zip(changes).enumerate() {
+                    for (index, node) in changes {
+                        if index >= layer.len() {
+                            layer.resize(index + 1, Sha256Hasher::zero_hash(depth));
+                        }
+                        layer[index] = node;
+                    }
+                    // NOTE: drop the nodes above removed chunks, which are now zero
+                    layer.truncate(chunk_len.div_ceil(1 << depth));
+                }
+                layers
+            }
+            None => self.build()?,
+        };
+        self.dirty.clear();
+        let data_root = layers[Self::depth()]
+            .first()
+            .copied()
+            .unwrap_or(Sha256Hasher::zero_hash(Self::depth()));
+        self.layers = Some(layers);
+        self.decorate(data_root)
+    }
+
+    fn depth() -> usize {
+        T::chunk_count().next_power_of_two().trailing_zeros() as usize
+    }
+
+    fn build(&self) -> Result<Vec<Vec<Node>>, MerkleizationError> {
+        let mut hasher = Sha256Hasher::default();
+        let mut layer = (0..self.value.chunk_len())
+            .map(|index| self.value.chunk::<Sha256Hasher>(index))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut layers = Vec::with_capacity(Self::depth() + 1);
+        for depth in 0..Self::depth() {
+            let parents = layer
+                .chunks(2)
+                .map(|pair| {
+                    let right = pair.get(1).copied().unwrap_or(Sha256Hasher::zero_hash(depth));
+                    hash_node_pair(&mut hasher, &pair[0], &right)
+                })
+                .collect();
+            layers.push(mem::replace(&mut layer, parents));
+        }
+        layers.push(layer);
+        Ok(layers)
+    }
+
+    // Returns the nodes of each layer of the Merkle tree that change with the dirty chunks, in
+    // order.
+    fn changes(&self, layers: &[Vec<Node>]) -> Result<Vec<Vec<(usize, Node)>>, MerkleizationError> {
+        let mut hasher = Sha256Hasher::default();
+        let leaf_count = 1 << Self::depth();
+        let chunk_len = self.value.chunk_len();
+        let mut dirty = self.dirty.clone();
+        dirty.sort_unstable();
+        dirty.dedup();
+        let mut changes = dirty
+            .into_iter()
+            .filter(|&index| index < leaf_count)
+            .map(|index| {
+                let chunk = if index < chunk_len {
+                    self.value.chunk::<Sha256Hasher>(index)?
+                } else {
+                    Node::default()
+                };
+                Ok((index, chunk))
+            })
+            .collect::<Result<Vec<_>, MerkleizationError>>()?;
+        let mut all_changes = Vec::with_capacity(layers.len());
+        for (depth, layer) in layers.iter().enumerate().take(Self::depth()) {
+            let node =
+                |index: usize| layer.get(index).copied().unwrap_or(Sha256Hasher::zero_hash(depth));
+            let mut parents = Vec::with_capacity(changes.len());
+            let mut i = 0;
+            while i < changes.len() {
+                // SAFETY: index is safe because i < changes.len(); qed
+                let (index, changed) = changes[i];
+                let (left, right) = if index % 2 == 1 {
+                    (node(index - 1), changed)
+                } else {
+                    match changes.get(i + 1) {
+                        Some(&(sibling, right)) if sibling == index + 1 => {
+                            i += 1;
+                            (changed, right)
+                        }
+                        _ => (changed, node(index + 1)),
+                    }
+                };
+                parents.push((index / 2, hash_node_pair(&mut hasher, &left, &right)));
+                i += 1;
+            }
+            all_changes.push(mem::replace(&mut changes, parents));
+        }
+        all_changes.push(changes);
+        Ok(all_changes)
+    }
+
+    fn decorate(&self, data_root: Node) -> Result<Node, MerkleizationError> {
+        Ok(match self.value.decoration_root::<Sha256Hasher>()? {
+            Some(decoration) => mix_in_aux::<Sha256Hasher>(data_root, decoration),
+            None => data_root,
+        })
+    }
+}
+
+impl<T> Deref for Cached<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Cached<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        self.value.fmt(f)
+    }
+}
+
+impl<T: Default> Default for Cached<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: PartialEq> PartialEq for Cached<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: Eq> Eq for Cached<T> {}
+
+impl<T> From<T> for Cached<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T: Serializable> Serializable for Cached<T> {
+    fn is_variable_size() -> bool {
+        T::is_variable_size()
+    }
+
+    fn size_hint() -> usize {
+        T::size_hint()
+    }
+
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        self.value.encoded_len()
+    }
+
+    fn max_encoded_len() -> usize {
+        T::max_encoded_len()
+    }
+
+    fn bytes_at_path<'a>(encoding: &'a [u8], path: Path) -> Result<&'a [u8], DeserializeError> {
+        T::bytes_at_path(encoding, path)
+    }
+
+    fn validate_prefix(prefix: &[u8]) -> Result<bool, DeserializeError> {
+        T::validate_prefix(prefix)
+    }
+}
+
+impl<T: Serialize> Serialize for Cached<T> {
+    fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
+        self.value.serialize(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
+        self.value.serialize_to(sink)
+    }
+}
+
+impl<T: Deserialize> Deserialize for Cached<T> {
+    fn deserialize(encoding: &[u8]) -> Result<Self, DeserializeError> {
+        T::deserialize(encoding).map(Self::new)
+    }
+
+    fn deserialize_with(
+        encoding: &[u8],
+        context: &mut DecodeContext,
+    ) -> Result<Self, DeserializeError> {
+        T::deserialize_with(encoding, context).map(Self::new)
+    }
+}
+
+impl<T: Cacheable> HashTreeRoot for Cached<T> {
+    fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        // NOTE: the cached Merkle tree is only valid for the default hasher
+        let Some(layers) = self.layers.as_ref().filter(|_| is_default_hasher::<H>()) else {
+            return self.value.hash_tree_root_with::<H>()
+        };
+        let depth = Self::depth();
+        let changes = self.changes(layers)?;
+        // SAFETY: index is safe because there is a layer of changes per layer of the tree; qed
+        let data_root = match changes[depth].first() {
+            Some((_, root)) => *root,
+            None => layers[depth].first().copied().unwrap_or(Sha256Hasher::zero_hash(depth)),
+        };
+        self.decorate(data_root)
+    }
+
+    fn is_composite_type() -> bool {
+        T::is_composite_type()
+    }
+}
+
+impl<T: GeneralizedIndexable> GeneralizedIndexable for Cached<T> {
+    fn item_length() -> usize {
+        T::item_length()
+    }
+
+    fn chunk_count() -> usize {
+        T::chunk_count()
+    }
+
+    fn compute_generalized_index(
+        parent: GeneralizedIndex,
+        path: Path,
+    ) -> Result<GeneralizedIndex, MerkleizationError> {
+        T::compute_generalized_index(parent, path)
+    }
+}
+
+impl<T: Prove> Prove for Cached<T> {
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
+        self.value.chunks::<H>()
+    }
+
+    fn prove_element<H: MerkleHasher>(
+        &self,
+        index: usize,
+        prover: &mut Prover<H>,
+    ) -> Result<(), MerkleizationError> {
+        self.value.prove_element(index, prover)
+    }
+
+    fn subtree<H: MerkleHasher>(&self) -> Result<Subtree<H>, MerkleizationError> {
+        self.value.subtree::<H>()
+    }
+
+    fn decoration(&self) -> Option<usize> {
+        self.value.decoration()
+    }
+
+    fn decoration_root<H: MerkleHasher>(&self) -> Result<Option<Node>, MerkleizationError> {
+        self.value.decoration_root::<H>()
+    }
+
+    fn extend_proof<H: MerkleHasher>(
+        &self,
+        prover: &mut Prover<H>,
+    ) -> Result<(), MerkleizationError> {
+        self.value.extend_proof(prover)
+    }
+}
+
+impl<T: Diff + Cacheable> Diff for Cached<T> {
+    fn diff_elements(&self, other: &Self, differ: &mut Differ) -> Result<(), MerkleizationError> {
+        self.value.diff_elements(&other.value, differ)
+    }
+}
+
+impl<T: Viewable> Viewable for Cached<T> {
+    type View<'a> = T::View<'a>;
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        T::view(encoding)
+    }
+}
+
+impl<T> SimpleSerialize for Cached<T> where T: SimpleSerialize + Cacheable {}
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for Cached<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.value.serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for Cached<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(Self::new)
+    }
+}
+
+// Returns the number of chunks of a sequence of `len` elements of type `T`.
+fn sequence_chunk_len<T: SimpleSerialize>(len: usize) -> usize {
+    if T::is_composite_type() {
+        len
+    } else {
+        (len * T::size_hint()).div_ceil(BYTES_PER_CHUNK)
+    }
+}
+
+// Compute the chunk at `index` of the sequence of `elements`.
+fn sequence_chunk<H: MerkleHasher, T: SimpleSerialize>(
+    elements: &[T],
+    index: usize,
+) -> Result<Node, MerkleizationError> {
+    if T::is_composite_type() {
+        return elements[index].hash_tree_root_with::<H>()
+    }
+    let elements_per_chunk = BYTES_PER_CHUNK / T::size_hint();
+    let start = index * elements_per_chunk;
+    let end = (start + elements_per_chunk).min(elements.len());
+    let chunk = pack(&elements[start..end])?;
+    Ok(Node::from_slice(&chunk))
+}
+
+fn sequence_chunk_index<T: SimpleSerialize>(index: usize) -> usize {
+    if T::is_composite_type() {
+        index
+    } else {
+        index * T::size_hint() / BYTES_PER_CHUNK
+    }
+}
+
+// Compute the chunk at `index` of the bits packed in `bytes`.
+fn bits_chunk(bytes: &[u8], index: usize) -> Node {
+    let start = index * BYTES_PER_CHUNK;
+    let end = (start + BYTES_PER_CHUNK).min(bytes.len());
+    let mut chunk = Node::default();
+    chunk[..end - start].copy_from_slice(&bytes[start..end]);
+    chunk
+}
+
+impl<T, const N: usize> Cacheable for List<T, N>
+where
+    T: SimpleSerialize,
+{
+    fn chunk_len(&self) -> usize {
+        sequence_chunk_len::<T>(self.len())
+    }
+
+    fn chunk<H: MerkleHasher>(&self, index: usize) -> Result<Node, MerkleizationError> {
+        sequence_chunk::<H, T>(self, index)
+    }
+
+    fn chunk_index(index: usize) -> usize {
+        sequence_chunk_index::<T>(index)
+    }
+}
+
+impl<T, const N: usize> Cacheable for Vector<T, N>
+where
+    T: SimpleSerialize,
+{
+    fn chunk_len(&self) -> usize {
+        sequence_chunk_len::<T>(N)
+    }
+
+    fn chunk<H: MerkleHasher>(&self, index: usize) -> Result<Node, MerkleizationError> {
+        sequence_chunk::<H, T>(self, index)
+    }
+
+    fn chunk_index(index: usize) -> usize {
+        sequence_chunk_index::<T>(index)
+    }
+}
+
+impl<const N: usize> Cacheable for Bitlist<N> {
+    fn chunk_len(&self) -> usize {
+        self.len().div_ceil(BITS_PER_CHUNK)
+    }
+
+    fn chunk<H: MerkleHasher>(&self, index: usize) -> Result<Node, MerkleizationError> {
+        Ok(bits_chunk(self.as_raw_slice(), index))
+    }
+
+    fn chunk_index(index: usize) -> usize {
+        index / BITS_PER_CHUNK
+    }
+}
+
+impl<const N: usize> Cacheable for Bitvector<N> {
+    fn chunk_len(&self) -> usize {
+        N.div_ceil(BITS_PER_CHUNK)
+    }
+
+    fn chunk<H: MerkleHasher>(&self, index: usize) -> Result<Node, MerkleizationError> {
+        Ok(bits_chunk(self.as_raw_slice(), index))
+    }
+
+    fn chunk_index(index: usize) -> usize {
+        index / BITS_PER_CHUNK
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        merkleization::{
+            hasher::tests::TestHasher, proofs::tests::compute_and_verify_proof_for_path,
+        },
+        prelude::*,
+    };
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, SimpleSerialize, Cacheable)]
+    struct Validator {
+        pubkey: ByteVector<48>,
+        effective_balance: u64,
+        slashed: bool,
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, SimpleSerialize, Cacheable)]
+    struct State {
+        slot: u64,
+        validators: Cached<List<Validator, 1024>>,
+        balances: Cached<List<u64, 1024>>,
+        participation: Cached<Bitlist<2048>>,
+        roots: Cached<Vector<Node, 8>>,
+    }
+
+    fn validator(i: usize) -> Validator {
+        Validator {
+            pubkey: ByteVector::try_from([i as u8; 48].as_ref()).unwrap(),
+            effective_balance: 32 + i as u64,
+            slashed: false,
+        }
+    }
+
+    // Assert that the root of `value` matches its root without a cache, before and after a commit.
+    fn assert_root<T: Cacheable + Clone>(value: &mut Cached<T>) {
+        let expected = value.value.hash_tree_root().unwrap();
+        assert_eq!(value.hash_tree_root().unwrap(), expected);
+        assert_eq!(value.commit().unwrap(), expected);
+        assert_eq!(value.hash_tree_root().unwrap(), expected);
+        assert_eq!(Cached::new(value.value.clone()).commit().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_cached_list() {
+        let validators = (0..5).map(validator).collect::<Vec<_>>();
+        let mut list = Cached::new(List::<Validator, 32>::try_from(validators).unwrap());
+        assert_root(&mut list);
+
+        list.modify(3)[3].slashed = true;
+        list.modify(0)[0].effective_balance = 0;
+        assert_root(&mut list);
+
+        let len = list.len();
+        list.modify(len).push(validator(5));
+        assert_root(&mut list);
+
+        for _ in 0..6 {
+            let len = list.len();
+            list.modify(len - 1).pop();
+            assert_root(&mut list);
+        }
+        list.modify(0).push(validator(0));
+        assert_root(&mut list);
+
+        let mut balances = Cached::new(List::<u64, 100>::try_from(vec![1; 9]).unwrap());
+        assert_root(&mut balances);
+        for index in [0, 4, 8] {
+            balances.modify(index)[index] = 2;
+            assert_root(&mut balances);
+        }
+        balances.modify(9).push(3);
+        assert_root(&mut balances);
+        balances.modify_all().clear();
+        assert_root(&mut balances);
+    }
+
+    #[test]
+    fn test_cached_vector_and_bitfields() {
+        let mut vector = Cached::new(Vector::<u16, 40>::default());
+        assert_root(&mut vector);
+        vector.modify(39)[39] = 7;
+        vector.modify(17)[17] = 3;
+        assert_root(&mut vector);
+
+        let mut bits = Cached::new(Bitlist::<1000>::default());
+        assert_root(&mut bits);
+        for index in [0, 255, 256, 700] {
+            let len = bits.len();
+            bits.modify(len).resize(index + 1, false);
+            bits.modify(index).set(index, true);
+            assert_root(&mut bits);
+        }
+        bits.modify(700).set(700, false);
+        bits.modify(700).pop();
+        assert_root(&mut bits);
+
+        let mut bits = Cached::new(Bitvector::<600>::default());
+        assert_root(&mut bits);
+        bits.modify(599).set(599, true);
+        assert_root(&mut bits);
+    }
+
+    #[test]
+    fn test_cached_container() {
+        let mut state = State::default();
+        state.validators.modify_all().extend((0..10).map(validator));
+        state.balances.modify_all().extend(0..10);
+        state.participation.modify_all().resize(10, false);
+        let root = state.hash_tree_root().unwrap();
+
+        state.validators.commit().unwrap();
+        state.balances.commit().unwrap();
+        state.participation.commit().unwrap();
+        state.roots.commit().unwrap();
+        assert_eq!(state.hash_tree_root().unwrap(), root);
+
+        state.balances.modify(7)[7] = 100;
+        state.validators.modify(2)[2].slashed = true;
+        state.participation.modify(9).set(9, true);
+        state.roots.modify(3)[3] = Node::repeat_byte(1);
+        let mut cached_state = Cached::new(state.clone());
+        assert_root(&mut cached_state);
+
+        cached_state.modify(0).slot = 4;
+        assert_root(&mut cached_state);
+        assert_eq!(serialize(&cached_state).unwrap(), serialize(&*cached_state).unwrap());
+        let decoded = Cached::<State>::deserialize(&serialize(&state).unwrap()).unwrap();
+        assert_eq!(decoded.hash_tree_root().unwrap(), state.hash_tree_root().unwrap());
+
+        compute_and_verify_proof_for_path(&state, &["balances".into(), 7.into()]);
+        compute_and_verify_proof_for_path(
+            &state,
+            &["validators".into(), 2.into(), "slashed".into()],
+        );
+    }
+
+    #[test]
+    fn test_chunks_with_hasher() {
+        let validator = validator(1);
+        assert_eq!(
+            validator.chunk::<TestHasher>(0).unwrap(),
+            validator.pubkey.hash_tree_root_with::<TestHasher>().unwrap()
+        );
+        assert_ne!(
+            validator.chunk::<TestHasher>(0).unwrap(),
+            validator.chunk::<Sha256Hasher>(0).unwrap()
+        );
+
+        let validators = List::<Validator, 32>::try_from(vec![validator.clone()]).unwrap();
+        assert_eq!(
+            validators.chunk::<TestHasher>(0).unwrap(),
+            validator.hash_tree_root_with::<TestHasher>().unwrap()
+        );
+    }
+}
@@ -25,7 +25,7 @@ mod tests {
         c: List<bool, 32>,
     }
 
-    #[derive(Default, Debug, PartialEq, Eq, SimpleSerialize)]
+    #[derive(Default, Debug, PartialEq, Eq, SimpleSerialize, Viewable)]
     struct AnotherContainer {
         a: u32,
         b: bool,
@@ -44,7 +44,7 @@ mod tests {
         f: List<u32, 32>,
     }
 
-    #[derive(Default, Debug, PartialEq, Eq, SimpleSerialize)]
+    #[derive(Default, Debug, PartialEq, Eq, SimpleSerialize, Viewable)]
     struct VarTestStruct {
         a: u16,
         b: List<u16, 1024>,
@@ -58,7 +58,7 @@ mod tests {
         c: u8,
     }
 
-    #[derive(Default, Debug, PartialEq, Eq, SimpleSerialize)]
+    #[derive(Default, Debug, PartialEq, Eq, SimpleSerialize, Viewable)]
     struct TupleStruct(u8);
 
     #[derive(Default, Debug, PartialEq, Eq, SimpleSerialize)]
@@ -76,6 +76,36 @@ mod tests {
         d: BasicContainer,
     }
 
+    #[derive(Debug, PartialEq, Eq, Serializable, Viewable)]
+    struct ViewableContainer<const N: usize> {
+        a: u32,
+        b: List<AnotherContainer, N>,
+        c: Vector<u16, 3>,
+        d: Option<VarTestStruct>,
+        e: TupleStruct,
+    }
+
+    #[derive(Default, Debug, PartialEq, Eq, Serializable, Viewable)]
+    struct ViewableTupleStruct(List<u8, 8>);
+
+    #[derive(Default, Debug, PartialEq, Eq, SimpleSerialize)]
+    struct Attestation {
+        data: u32,
+        extra: List<u8, 1>,
+        aggregation_bits: Bitlist<16>,
+    }
+
+    #[derive(Default, Debug, PartialEq, Eq, SimpleSerialize)]
+    struct Body {
+        attestations: List<Attestation, 8>,
+    }
+
+    #[derive(Default, Debug, PartialEq, Eq, SimpleSerialize)]
+    struct Block {
+        slot: u64,
+        body: Body,
+    }
+
     #[test]
     fn encode_container() {
         let value = Foo { a: 5u32 };
@@ -133,6 +163,199 @@ mod tests {
         assert_eq!(buffer, expected);
     }
 
+    #[test]
+    fn container_encoded_len() {
+        let value = YetAnotherContainer {
+            a: 5u32,
+            b: true,
+            c: List::try_from(vec![true, false, false]).unwrap(),
+            d: Vector::try_from(vec![true, false, false, true]).unwrap(),
+            e: 24u8,
+            f: List::try_from(vec![234u32, 567u32]).unwrap(),
+        };
+        assert_eq!(value.encoded_len().unwrap(), serialize(&value).unwrap().len());
+
+        let value = BasicContainer { a: 5u32, d: true };
+        assert_eq!(value.encoded_len().unwrap(), BasicContainer::size_hint());
+
+        let value = TupleStruct(3);
+        assert_eq!(value.encoded_len().unwrap(), 1);
+    }
+
+    #[test]
+    fn encode_container_to_writer() {
+        let value = YetAnotherContainer {
+            a: 5u32,
+            b: true,
+            c: List::try_from(vec![true, false, false, false, true, true]).unwrap(),
+            d: Vector::try_from(vec![true, false, false, true]).unwrap(),
+            e: 24u8,
+            f: List::try_from(vec![234u32, 567u32]).unwrap(),
+        };
+        let expected = serialize(&value).expect("can serialize");
+
+        let mut writer = std::io::Cursor::new(vec![]);
+        let result = value.serialize_to(&mut writer).expect("can serialize");
+        assert_eq!(result, expected.len());
+        assert_eq!(writer.into_inner(), expected);
+    }
+
+    #[test]
+    fn view_container() {
+        let value = ViewableContainer::<4> {
+            a: 7,
+            b: List::try_from(vec![
+                AnotherContainer {
+                    a: 5u32,
+                    b: true,
+                    c: List::try_from(vec![true, false]).unwrap(),
+                    d: Vector::try_from(vec![true, false, false, true]).unwrap(),
+                    e: 24u8,
+                },
+                AnotherContainer::default(),
+            ])
+            .unwrap(),
+            c: Vector::try_from(vec![1u16, 2, 3]).unwrap(),
+            d: Some(VarTestStruct { a: 1, b: List::try_from(vec![2u16, 3]).unwrap(), c: 4 }),
+            e: TupleStruct(9),
+        };
+        let encoding = serialize(&value).unwrap();
+
+        let view = ViewableContainer::<4>::view(&encoding).unwrap();
+        assert_eq!(view.a().unwrap(), 7);
+        let b = view.b().unwrap();
+        assert_eq!(b.len(), 2);
+        assert_eq!(b.get(0).unwrap().unwrap().decode().unwrap(), value.b[0]);
+        assert!(b.get(2).is_none());
+        assert_eq!(b.decode().unwrap(), value.b);
+        let c = view.c().unwrap().iter().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(c, [1, 2, 3]);
+        let d = view.d().unwrap().unwrap();
+        assert_eq!(d.c().unwrap(), 4);
+        assert_eq!(d.decode().unwrap(), *value.d.as_ref().unwrap());
+        assert_eq!(view.e().unwrap(), 9);
+        assert_eq!(view.decode().unwrap(), value);
+
+        let inner = ViewableTupleStruct(List::try_from(vec![1u8, 2]).unwrap());
+        let encoding = serialize(&inner).unwrap();
+        let view = ViewableTupleStruct::view(&encoding).unwrap();
+        assert_eq!(view.len(), 2);
+    }
+
+    #[test]
+    fn view_container_with_invalid_offsets() {
+        let value = ViewableContainer::<4> {
+            a: 7,
+            b: List::try_from(vec![AnotherContainer::default()]).unwrap(),
+            c: Vector::default(),
+            d: None,
+            e: TupleStruct(9),
+        };
+        let mut encoding = serialize(&value).unwrap();
+        encoding.truncate(encoding.len() - 1);
+        // only the truncated (last) field is affected
+        let view = ViewableContainer::<4>::view(&encoding).unwrap();
+        assert_eq!(view.b().unwrap().len(), 1);
+        assert!(view.d().is_err());
+        encoding.truncate(10);
+        assert!(ViewableContainer::<4>::view(&encoding).is_err());
+
+        let value = List::<List<u8, 4>, 4>::try_from(vec![List::default(), List::default()]);
+        let mut encoding = serialize(&value.unwrap()).unwrap();
+        // point the second offset beyond the end of the input
+        encoding[4] = 255;
+        assert!(matches!(
+            List::<List<u8, 4>, 4>::view(&encoding),
+            Err(DeserializeError::ExpectedFurtherInput { .. })
+        ));
+    }
+
+    #[test]
+    fn decode_at_path() {
+        let value = Block {
+            slot: 12,
+            body: Body {
+                attestations: List::try_from(vec![
+                    Attestation::default(),
+                    Attestation {
+                        data: 33,
+                        extra: List::try_from(vec![7u8]).unwrap(),
+                        aggregation_bits: Bitlist::try_from([true, false, true].as_ref()).unwrap(),
+                    },
+                ])
+                .unwrap(),
+            },
+        };
+        let encoding = serialize(&value).unwrap();
+
+        assert_eq!(Block::decode_at_path::<u64>(&encoding, &["slot".into()]).unwrap(), 12);
+        let path = &["body".into(), "attestations".into(), 1.into()];
+        let attestation = Block::decode_at_path::<Attestation>(&encoding, path).unwrap();
+        assert_eq!(attestation, value.body.attestations[1]);
+        let path = &["body".into(), "attestations".into(), 1.into(), "data".into()];
+        assert_eq!(Block::decode_at_path::<u32>(&encoding, path).unwrap(), 33);
+        let path = &["body".into(), "attestations".into(), 1.into(), "extra".into(), 0.into()];
+        assert_eq!(Block::decode_at_path::<u8>(&encoding, path).unwrap(), 7);
+        let path = &["body".into(), "attestations".into(), 1.into(), "aggregation_bits".into()];
+        let bytes = Block::bytes_at_path(&encoding, path).unwrap();
+        assert_eq!(bytes, serialize(&value.body.attestations[1].aggregation_bits).unwrap());
+
+        let path = &["body".into(), "attestations".into(), 2.into()];
+        let err = Block::bytes_at_path(&encoding, path).unwrap_err();
+        assert!(
+            matches!(err.innermost(), DeserializeError::InvalidPath(rest) if rest == &[2.into()])
+        );
+        assert_eq!(err.path(), &["body".into(), "attestations".into()]);
+        let err = Block::bytes_at_path(&encoding, &["missing".into()]).unwrap_err();
+        assert!(matches!(err, DeserializeError::InvalidPath(..)));
+        let err = Block::bytes_at_path(&encoding, &["slot".into(), 0.into()]).unwrap_err();
+        assert_eq!(err.path(), &["slot".into()]);
+
+        // only the offsets along the path are validated
+        let mut encoding = encoding;
+        let last = encoding.len() - 1;
+        encoding[last] = 0;
+        assert!(Block::deserialize(&encoding).is_err());
+        assert_eq!(Block::decode_at_path::<u64>(&encoding, &["slot".into()]).unwrap(), 12);
+    }
+
+    #[test]
+    fn errors_include_path() {
+        let mut value = Block {
+            slot: 12,
+            body: Body {
+                attestations: List::try_from(vec![Attestation::default(), Attestation::default()])
+                    .unwrap(),
+            },
+        };
+        let mut encoding = serialize(&value).unwrap();
+        // the last byte is the (empty) `aggregation_bits` of the last attestation
+        let offset = encoding.len() - 1;
+        encoding[offset] = 0;
+        let err = Block::deserialize(&encoding).unwrap_err();
+        assert!(matches!(err.innermost(), DeserializeError::InvalidByte(0)));
+        assert_eq!(
+            err.path(),
+            &["body".into(), "attestations".into(), 1.into(), "aggregation_bits".into()]
+        );
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "body.attestations[1].aggregation_bits @ byte {offset}: {}",
+                DeserializeError::InvalidByte(0)
+            )
+        );
+
+        value.body.attestations[0].extra.push(1);
+        value.body.attestations[0].extra.push(2);
+        let err = serialize(&value).unwrap_err();
+        assert!(matches!(
+            err.innermost(),
+            SerializeError::InvalidInstance(InstanceError::Bounded { bound: 1, provided: 2 })
+        ));
+        assert!(err.to_string().starts_with("body.attestations[0].extra: "));
+    }
+
     #[test]
     fn decode_container() {
         let data = vec![5u8, 0u8, 0u8, 0u8, 1u8, 9u8, 0u8, 0u8, 0u8, 1u8, 0u8];
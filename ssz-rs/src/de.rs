@@ -1,6 +1,7 @@
 use crate::{
     error::{InstanceError, TypeError},
     lib::*,
+    merkleization::{generalized_index::DisplayPath, Path, PathElement},
     ser::BYTES_PER_LENGTH_OFFSET,
     Serializable,
 };
@@ -27,6 +28,51 @@ pub enum DeserializeError {
     /// No corresponding variant of the requested enum was present. (refer to `transparent`
     /// attribute of `ssz-rs-derive` macro)
     NoMatchingVariant,
+    /// An error was encountered when reading from the source of the encoding.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// The encoding was larger than the configured limit.
+    InputTooLarge { provided: usize, limit: usize },
+    /// Composite types were nested deeper than the configured limit.
+    DepthLimitExceeded(usize),
+    /// Decoding would allocate more memory than the configured limit.
+    AllocationLimitExceeded { requested: usize, limit: usize },
+    /// The path does not refer to an element of the encoded value.
+    InvalidPath(Vec<PathElement>),
+    /// An error was encountered when deserializing the element at `path`, whose encoding starts
+    /// at byte `offset` of the input.
+    AtPath { path: Vec<PathElement>, offset: usize, source: Box<DeserializeError> },
+}
+
+impl DeserializeError {
+    /// Record that this error was encountered when deserializing `element` of some composite
+    /// type, where the encoding of `element` starts at byte `offset` of the encoding of the
+    /// composite type.
+    pub fn in_element(self, element: PathElement, offset: usize) -> Self {
+        match self {
+            Self::AtPath { mut path, offset: inner_offset, source } => {
+                path.insert(0, element);
+                Self::AtPath { path, offset: offset + inner_offset, source }
+            }
+            err => Self::AtPath { path: vec![element], offset, source: Box::new(err) },
+        }
+    }
+
+    /// Returns the path to the element where this error was encountered, if known.
+    pub fn path(&self) -> Path {
+        match self {
+            Self::AtPath { path, .. } => path,
+            _ => &[],
+        }
+    }
+
+    /// Returns the underlying error without any location context.
+    pub fn innermost(&self) -> &Self {
+        match self {
+            Self::AtPath { source, .. } => source,
+            err => err,
+        }
+    }
 }
 
 impl From<InstanceError> for DeserializeError {
@@ -56,12 +102,101 @@ impl Display for DeserializeError {
             DeserializeError::OffsetNotIncreasing { start, end } => write!(f, "invalid offset points to byte {end} before byte {start}"),
             DeserializeError::MissingOffset => write!(f, "an offset was missing when deserializing a variable-sized type"),
             DeserializeError::NoMatchingVariant => write!(f, "no corresponding variant of the requested enum was present"),
+            #[cfg(feature = "std")]
+            DeserializeError::Io(err) => write!(f, "could not read encoding: {err}"),
+            DeserializeError::InputTooLarge { provided, limit } => write!(f, "{provided} byte(s) given but only up to {limit} byte(s) are allowed"),
+            DeserializeError::DepthLimitExceeded(limit) => write!(f, "nesting of composite types exceeds the depth limit of {limit}"),
+            DeserializeError::AllocationLimitExceeded { requested, limit } => write!(f, "decoding requires allocating {requested} byte(s) which exceeds the limit of {limit} byte(s)"),
+            DeserializeError::InvalidPath(path) => write!(f, "invalid path {path:?}"),
+            DeserializeError::AtPath { path, offset, source } => write!(f, "{} @ byte {offset}: {source}", DisplayPath(path)),
         }
     }
 }
 
 #[cfg(feature = "std")]
-impl std::error::Error for DeserializeError {}
+impl std::error::Error for DeserializeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::AtPath { source, .. } => Some(source.as_ref()),
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Limits to enforce when decoding untrusted input.
+///
+/// The default options do not impose any limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeOptions {
+    /// The maximum number of bytes in the encoding.
+    pub max_bytes: usize,
+    /// The maximum depth of nested composite types.
+    pub max_depth: usize,
+    /// The maximum number of bytes to allocate for the elements of `List`s and `Vector`s.
+    pub max_allocation: usize,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self { max_bytes: usize::MAX, max_depth: usize::MAX, max_allocation: usize::MAX }
+    }
+}
+
+/// Tracks resource usage against the configured `DecodeOptions` while decoding a value.
+#[derive(Debug, Default)]
+pub struct DecodeContext {
+    options: DecodeOptions,
+    depth: usize,
+    allocated: usize,
+}
+
+impl DecodeContext {
+    pub fn new(options: DecodeOptions) -> Self {
+        Self { options, depth: 0, allocated: 0 }
+    }
+
+    /// Run `f` one level deeper in the nesting of composite types.
+    pub fn nested<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, DeserializeError>,
+    ) -> Result<T, DeserializeError> {
+        if self.depth >= self.options.max_depth {
+            return Err(DeserializeError::DepthLimitExceeded(self.options.max_depth))
+        }
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    /// Account for an allocation of `count` values of type `T` against the allocation budget.
+    pub fn allocate<T>(&mut self, count: usize) -> Result<(), DeserializeError> {
+        let requested = self.allocated.saturating_add(count.saturating_mul(mem::size_of::<T>()));
+        if requested > self.options.max_allocation {
+            return Err(DeserializeError::AllocationLimitExceeded {
+                requested,
+                limit: self.options.max_allocation,
+            })
+        }
+        self.allocated = requested;
+        Ok(())
+    }
+
+    /// Run `f`, refunding any allocations it accounted for if it fails, e.g. when trying one
+    /// of several candidate decodings of the same input.
+    pub fn attempt<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, DeserializeError>,
+    ) -> Result<T, DeserializeError> {
+        let allocated = self.allocated;
+        let result = f(self);
+        if result.is_err() {
+            self.allocated = allocated;
+        }
+        result
+    }
+}
 
 /// A data structure that can be deserialized using SSZ.
 pub trait Deserialize {
@@ -69,31 +204,29 @@ pub trait Deserialize {
     fn deserialize(encoding: &[u8]) -> Result<Self, DeserializeError>
     where
         Self: Sized;
-}
-
-fn deserialize_fixed_homogeneous_composite<T>(encoding: &[u8]) -> Result<Vec<T>, DeserializeError>
-where
-    T: Serializable,
-{
-    // NOTE: Callers have already validated `encoding` is correctly sized
-    debug_assert_eq!(encoding.len() % T::size_hint(), 0);
 
-    let mut elements = vec![];
-    for chunk in encoding.chunks_exact(T::size_hint()) {
-        let element = T::deserialize(chunk)?;
-        elements.push(element);
+    /// Deserialize this value from the given SSZ-encoded buffer, enforcing the limits tracked by
+    /// `context`.
+    ///
+    /// The default implementation ignores `context`; composite types should override it and
+    /// pass `context` along to their elements.
+    fn deserialize_with(
+        encoding: &[u8],
+        context: &mut DecodeContext,
+    ) -> Result<Self, DeserializeError>
+    where
+        Self: Sized,
+    {
+        let _ = context;
+        Self::deserialize(encoding)
     }
-    Ok(elements)
 }
 
-fn deserialize_variable_homogeneous_composite<T>(
-    encoding: &[u8],
-) -> Result<Vec<T>, DeserializeError>
-where
-    T: Deserialize,
-{
+// Validates the offsets in the encoding of a homogeneous composite type with variable-size
+// elements and returns the number of elements in the encoding.
+pub(crate) fn validate_variable_offsets(encoding: &[u8]) -> Result<usize, DeserializeError> {
     if encoding.is_empty() {
-        return Ok(vec![])
+        return Ok(0)
     }
 
     let offsets_len = encoding.get(..BYTES_PER_LENGTH_OFFSET).ok_or({
@@ -114,38 +247,117 @@ where
         return Err(DeserializeError::InvalidOffsetsLength(offsets_len))
     }
 
-    let offsets = &mut encoding[..offsets_len]
-        .chunks_exact(BYTES_PER_LENGTH_OFFSET)
-        .map(|chunk| u32::deserialize(chunk).map(|offset| offset as usize))
-        .collect::<Result<Vec<usize>, DeserializeError>>()?;
-    offsets.push(encoding.len());
-
     let element_count = offsets_len / BYTES_PER_LENGTH_OFFSET;
-    let mut result = Vec::with_capacity(element_count);
-    for span in offsets.windows(2) {
-        // SAFETY: index is safe because span is a pair; qed
-        let start = span[0];
-        let end = span[1];
+    for index in 0..element_count {
+        let (start, end) = variable_element_span(encoding, index, element_count);
         if start > end {
             return Err(DeserializeError::OffsetNotIncreasing { start, end })
         }
+        if end > encoding.len() {
+            return Err(DeserializeError::ExpectedFurtherInput {
+                provided: encoding.len(),
+                expected: end,
+            })
+        }
+    }
+    Ok(element_count)
+}
 
-        // SAFETY: index is safe because start <= end; qed
-        let element = T::deserialize(&encoding[start..end])?;
-        result.push(element);
+// Like `validate_variable_offsets` over `prefix`, the start of the encoding of a homogeneous
+// composite type with variable-size elements, once all of the offsets have arrived. Only reports
+// the errors `deserialize_homogeneous_composite` reports for any encoding starting with `prefix`,
+// calling `check_len` with the number of elements if the offsets are valid for any such encoding.
+// Returns `false` if the offsets are not complete yet.
+pub(crate) fn validate_variable_offsets_prefix<F>(
+    prefix: &[u8],
+    check_len: F,
+) -> Result<bool, DeserializeError>
+where
+    F: FnOnce(usize) -> Result<(), DeserializeError>,
+{
+    let Some(offsets_len) = prefix.get(..BYTES_PER_LENGTH_OFFSET) else { return Ok(false) };
+    let offsets_len = u32::deserialize(offsets_len)? as usize;
+    if prefix.len() < offsets_len {
+        return Ok(false)
+    }
+    if offsets_len % BYTES_PER_LENGTH_OFFSET != 0 {
+        return Err(DeserializeError::InvalidOffsetsLength(offsets_len))
     }
-    Ok(result)
+
+    let element_count = offsets_len / BYTES_PER_LENGTH_OFFSET;
+    for index in 0..element_count {
+        let (start, end) = variable_element_span(prefix, index, element_count);
+        // NOTE: the last element ends with the rest of the encoding, so whether it is valid
+        // depends on the rest of the encoding, as does whether `end` is within the encoding
+        if start > end && index + 1 < element_count {
+            return Err(DeserializeError::OffsetNotIncreasing { start, end })
+        }
+        if start > end || end > prefix.len() {
+            return Ok(true)
+        }
+    }
+    check_len(element_count)?;
+    Ok(true)
+}
+
+// Returns the `(start, end)` span of the element at `index` in the encoding of a homogeneous
+// composite type with variable-size elements.
+// NOTE: callers must ensure `index < element_count` and the offsets have been validated with
+// `validate_variable_offsets`.
+pub(crate) fn variable_element_span(
+    encoding: &[u8],
+    index: usize,
+    element_count: usize,
+) -> (usize, usize) {
+    let read_offset = |index: usize| {
+        let start = index * BYTES_PER_LENGTH_OFFSET;
+        // SAFETY: index is safe because the offsets fit within `encoding`; qed
+        let bytes = encoding[start..start + BYTES_PER_LENGTH_OFFSET].try_into().expect("is offset");
+        u32::from_le_bytes(bytes) as usize
+    };
+    let start = read_offset(index);
+    let end = if index + 1 < element_count { read_offset(index + 1) } else { encoding.len() };
+    (start, end)
 }
 
-pub fn deserialize_homogeneous_composite<T>(encoding: &[u8]) -> Result<Vec<T>, DeserializeError>
+// Deserialize `encoding` as a homogeneous composite type (i.e. a `List` or `Vector`).
+// `check_len` is called with the number of elements in the encoding before any are decoded, so
+// that callers can enforce bounds before any allocation.
+// NOTE: callers must ensure the length of `encoding` is a multiple of `T::size_hint()` for
+// fixed-size `T`.
+pub fn deserialize_homogeneous_composite<T, F>(
+    encoding: &[u8],
+    context: &mut DecodeContext,
+    check_len: F,
+) -> Result<Vec<T>, DeserializeError>
 where
     T: Serializable,
+    F: FnOnce(usize) -> Result<(), DeserializeError>,
 {
-    if T::is_variable_size() {
-        deserialize_variable_homogeneous_composite(encoding)
+    let element_count = if T::is_variable_size() {
+        validate_variable_offsets(encoding)?
     } else {
-        deserialize_fixed_homogeneous_composite(encoding)
-    }
+        debug_assert_eq!(encoding.len() % T::size_hint(), 0);
+        encoding.len() / T::size_hint()
+    };
+    check_len(element_count)?;
+
+    context.nested(|context| {
+        context.allocate::<T>(element_count)?;
+        let mut elements = Vec::with_capacity(element_count);
+        for index in 0..element_count {
+            let (start, end) = if T::is_variable_size() {
+                variable_element_span(encoding, index, element_count)
+            } else {
+                (index * T::size_hint(), (index + 1) * T::size_hint())
+            };
+            // SAFETY: index is safe because spans have been validated; qed
+            let element = T::deserialize_with(&encoding[start..end], context)
+                .map_err(|err| err.in_element(PathElement::Index(index), start))?;
+            elements.push(element);
+        }
+        Ok(elements)
+    })
 }
 
 #[derive(Debug)]
@@ -171,8 +383,19 @@ pub struct ContainerDeserializer {
 impl ContainerDeserializer {
     // NOTE: segments must be parsed in order following the order of the fields of the container.
     pub fn parse<T: Serializable>(&mut self, encoding: &[u8]) -> Result<(), DeserializeError> {
+        self.parse_segment(encoding, T::is_variable_size(), T::size_hint())
+    }
+
+    // Like `parse` for a field described at runtime by whether it is variable-size and, if not,
+    // the length of its encoding.
+    pub(crate) fn parse_segment(
+        &mut self,
+        encoding: &[u8],
+        is_variable_size: bool,
+        size_hint: usize,
+    ) -> Result<(), DeserializeError> {
         let start = self.total_bytes_read;
-        if T::is_variable_size() {
+        if is_variable_size {
             let end = start + BYTES_PER_LENGTH_OFFSET;
 
             let target =
@@ -192,15 +415,15 @@ impl ContainerDeserializer {
 
                 if *previous_offset > encoding.len() {
                     return Err(DeserializeError::ExpectedFurtherInput {
-                        provided: encoding.len() - previous_offset,
-                        expected: next_offset - previous_offset,
+                        provided: encoding.len(),
+                        expected: *previous_offset,
                     })
                 }
 
                 if next_offset > encoding.len() {
                     return Err(DeserializeError::ExpectedFurtherInput {
-                        provided: encoding.len() - next_offset,
-                        expected: next_offset - previous_offset,
+                        provided: encoding.len(),
+                        expected: next_offset,
                     })
                 }
             }
@@ -209,7 +432,7 @@ impl ContainerDeserializer {
             self.offsets.push(next_offset);
             self.segments.push(Segment::Offset);
         } else {
-            let encoded_length = T::size_hint();
+            let encoded_length = size_hint;
             let end = self.total_bytes_read + encoded_length;
             if encoding.len() < self.total_bytes_read {
                 return Err(DeserializeError::ExpectedFurtherInput {
@@ -230,6 +453,42 @@ impl ContainerDeserializer {
         Ok(())
     }
 
+    // Like `parse` over `prefix`, the start of an encoding that is still arriving which contains
+    // the fixed part of the container, only reporting the errors `parse` reports for any
+    // encoding starting with `prefix`.
+    // Returns `false` if whether the segment is valid depends on the rest of the encoding, after
+    // which no further segments can be checked.
+    pub fn parse_prefix<T: Serializable>(
+        &mut self,
+        prefix: &[u8],
+    ) -> Result<bool, DeserializeError> {
+        if !T::is_variable_size() {
+            self.total_bytes_read += T::size_hint();
+            return Ok(true)
+        }
+        let start = self.total_bytes_read;
+        let end = start + BYTES_PER_LENGTH_OFFSET;
+        let target = prefix.get(start..end).ok_or(DeserializeError::ExpectedFurtherInput {
+            provided: prefix.len(),
+            expected: end,
+        })?;
+        let next_offset = u32::deserialize(target)? as usize;
+        if let Some(previous_offset) = self.offsets.last() {
+            if next_offset < *previous_offset {
+                return Err(DeserializeError::OffsetNotIncreasing {
+                    start: *previous_offset,
+                    end: next_offset,
+                })
+            }
+        }
+        if next_offset > prefix.len() {
+            return Ok(false)
+        }
+        self.total_bytes_read = end;
+        self.offsets.push(next_offset);
+        Ok(true)
+    }
+
     // Assembles a validated list of (pairs of) indices into `encoding` that point to the
     // slice containing the encoding for each field of the target container.
     // For example, if some container has three fields, the result will have 6 indices into
@@ -286,3 +545,462 @@ impl ContainerDeserializer {
         Ok(spans)
     }
 }
+
+/// `BufferedDecoder` buffers an SSZ encoding of some `T` that arrives in pieces, e.g. from a
+/// network connection or a decompression stream, and decodes it once the input is complete.
+///
+/// Bytes can be pushed into the `BufferedDecoder` with `push` or pulled from a `std::io::Read`
+/// with `read_from` and `read_to_end`. As SSZ encodings are not self-delimiting, the caller signals
+/// the end of the input by calling `finish`, which decodes the buffered input with
+/// `deserialize_with_options`.
+///
+/// The input is rejected before it is complete once it exceeds the `max_bytes` of the
+/// `DecodeOptions`, or once the offsets in the fixed part of the encoding are invalid regardless
+/// of any further input (see `Serializable::validate_prefix`), so that invalid input need not be
+/// buffered to the end. The error is the one `deserialize_with_options` reports for the input
+/// pushed up to and including the rejected bytes; the decoder then accepts no further input and
+/// `finish` reports the same error.
+#[derive(Debug)]
+pub struct BufferedDecoder<T> {
+    buffer: Vec<u8>,
+    options: DecodeOptions,
+    // whether the prefix of the input has been validated
+    validated: bool,
+    rejected: Option<Rejection>,
+    _p: PhantomData<T>,
+}
+
+// The reason a `BufferedDecoder` rejected its input.
+#[derive(Debug, Clone, Copy)]
+enum Rejection {
+    // the input of the given length exceeds `max_bytes`, so the last bytes were not buffered
+    TooLarge(usize),
+    // the buffered input is invalid regardless of any further input
+    Invalid,
+}
+
+impl<T> Default for BufferedDecoder<T> {
+    fn default() -> Self {
+        Self {
+            buffer: Vec::new(),
+            options: DecodeOptions::default(),
+            validated: false,
+            rejected: None,
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<T: Serializable> BufferedDecoder<T> {
+    pub fn new() -> Self {
+        Self::with_options(DecodeOptions::default())
+    }
+
+    /// Construct a decoder enforcing the limits in `options`.
+    pub fn with_options(options: DecodeOptions) -> Self {
+        let mut decoder = Self { options, ..Default::default() };
+        if !T::is_variable_size() {
+            decoder.buffer.reserve_exact(T::size_hint().min(options.max_bytes));
+        }
+        decoder
+    }
+
+    /// Append `bytes` to the input for this decoder.
+    ///
+    /// Returns an error if the input exceeds the `max_bytes` of the options for this decoder or is
+    /// known to be invalid regardless of any further input, which `finish` then reports as well.
+    pub fn push(&mut self, bytes: &[u8]) -> Result<(), DeserializeError> {
+        if self.rejected.is_none() {
+            self.accept(bytes);
+        }
+        match self.rejected {
+            None => Ok(()),
+            Some(Rejection::TooLarge(provided)) => {
+                Err(DeserializeError::InputTooLarge { provided, limit: self.options.max_bytes })
+            }
+            Some(Rejection::Invalid) => self.decode().map(|_| ()),
+        }
+    }
+
+    fn accept(&mut self, bytes: &[u8]) {
+        let provided = self.buffer.len().saturating_add(bytes.len());
+        if provided > self.options.max_bytes {
+            self.rejected = Some(Rejection::TooLarge(provided));
+            return
+        }
+        self.buffer.extend_from_slice(bytes);
+        if !self.validated {
+            match T::validate_prefix(&self.buffer) {
+                Ok(validated) => self.validated = validated,
+                // NOTE: only reject input that fails to decode so the error is the one of `finish`
+                Err(_) if self.decode().is_err() => self.rejected = Some(Rejection::Invalid),
+                Err(_) => {}
+            }
+        }
+    }
+
+    fn decode(&self) -> Result<T, DeserializeError> {
+        crate::deserialize_with_options(&self.buffer, &self.options)
+    }
+
+    /// Returns the number of bytes buffered by this decoder so far.
+    pub fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
+    /// Returns the number of further bytes required to complete the encoding of a fixed-size
+    /// type, or `None` if `T` is variable-size and so the length is only known by the caller.
+    pub fn remaining(&self) -> Option<usize> {
+        if T::is_variable_size() {
+            None
+        } else {
+            Some(T::size_hint().saturating_sub(self.buffer.len()))
+        }
+    }
+
+    /// Pull at most one chunk of bytes from `reader` into this decoder.
+    ///
+    /// Returns the number of bytes read, where `0` indicates `reader` has reached the end of its
+    /// input.
+    #[cfg(feature = "std")]
+    pub fn read_from<R: std::io::Read + ?Sized>(
+        &mut self,
+        reader: &mut R,
+    ) -> Result<usize, DeserializeError> {
+        const CHUNK_SIZE: usize = 4096;
+
+        let mut chunk = [0u8; CHUNK_SIZE];
+        loop {
+            match reader.read(&mut chunk) {
+                Ok(count) => {
+                    self.push(&chunk[..count])?;
+                    return Ok(count)
+                }
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(DeserializeError::Io(err)),
+            }
+        }
+    }
+
+    /// Pull all remaining bytes from `reader` into this decoder, stopping with an error as soon
+    /// as the input exceeds the `max_bytes` of the options for this decoder.
+    ///
+    /// Returns the number of bytes read.
+    #[cfg(feature = "std")]
+    pub fn read_to_end<R: std::io::Read + ?Sized>(
+        &mut self,
+        reader: &mut R,
+    ) -> Result<usize, DeserializeError> {
+        let mut total = 0;
+        loop {
+            match self.read_from(reader)? {
+                0 => return Ok(total),
+                count => total += count,
+            }
+        }
+    }
+
+    /// Decode a value from the input provided to this decoder.
+    pub fn finish(self) -> Result<T, DeserializeError> {
+        if let Some(Rejection::TooLarge(provided)) = self.rejected {
+            return Err(DeserializeError::InputTooLarge { provided, limit: self.options.max_bytes })
+        }
+        self.decode()
+    }
+}
+
+/// `deserialize_from_reader` decodes a value of type `T` from all of the bytes remaining in
+/// `reader`.
+#[cfg(feature = "std")]
+pub fn deserialize_from_reader<T, R>(reader: &mut R) -> Result<T, DeserializeError>
+where
+    T: Serializable,
+    R: std::io::Read + ?Sized,
+{
+    let mut decoder = BufferedDecoder::<T>::new();
+    decoder.read_to_end(reader)?;
+    decoder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[derive(Debug, PartialEq, Eq, SimpleSerialize)]
+    struct Foo {
+        a: u32,
+        b: List<u16, 8>,
+        c: Vector<u8, 3>,
+        d: List<List<u8, 4>, 4>,
+    }
+
+    fn sample() -> Foo {
+        Foo {
+            a: 12,
+            b: List::try_from(vec![1u16, 2, 3]).unwrap(),
+            c: Vector::try_from(vec![4u8, 5, 6]).unwrap(),
+            d: List::try_from(vec![
+                List::try_from(vec![7u8]).unwrap(),
+                List::default(),
+                List::try_from(vec![8u8, 9]).unwrap(),
+            ])
+            .unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_decode_in_chunks() {
+        let value = sample();
+        let encoding = serialize(&value).unwrap();
+        for chunk_size in 1..=encoding.len() {
+            let mut decoder = BufferedDecoder::<Foo>::new();
+            for chunk in encoding.chunks(chunk_size) {
+                decoder.push(chunk).unwrap();
+            }
+            assert_eq!(decoder.len(), encoding.len());
+            assert_eq!(decoder.finish().unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_decode_matches_slice_path() {
+        let mut encoding = serialize(&sample()).unwrap();
+        encoding.push(0u8);
+        for end in 0..=encoding.len() {
+            let input = &encoding[..end];
+            let expected = format!("{:?}", Foo::deserialize(input));
+            let mut decoder = BufferedDecoder::<Foo>::new();
+            for chunk in input.chunks(3) {
+                decoder.push(chunk).unwrap();
+            }
+            assert_eq!(format!("{:?}", decoder.finish()), expected);
+        }
+
+        // any error reported before the input is complete is the error for the input so far
+        let encoding = serialize(&sample()).unwrap();
+        assert_errors_match_slice_path::<Foo>(&encoding, &[0, 1, 13, 14, 200]);
+        let value = Mixed {
+            a: true,
+            b: List::try_from(vec![1u8]).unwrap(),
+            c: List::try_from(vec![2u8, 3]).unwrap(),
+        };
+        assert_errors_match_slice_path::<Mixed>(&serialize(&value).unwrap(), &[0, 2, 8, 10]);
+    }
+
+    #[derive(Debug, PartialEq, Eq, SimpleSerialize)]
+    struct Mixed {
+        a: bool,
+        b: List<u8, 4>,
+        c: List<u8, 4>,
+    }
+
+    // Check that the errors of a decoder fed one byte at a time with `encoding`, with each byte in
+    // turn replaced by each of `values`, are the errors for the input so far.
+    fn assert_errors_match_slice_path<T: Serializable + fmt::Debug>(
+        encoding: &[u8],
+        values: &[u8],
+    ) {
+        for (index, value) in (0..encoding.len()).flat_map(|i| values.iter().map(move |v| (i, *v)))
+        {
+            let mut input = encoding.to_vec();
+            input[index] = value;
+            let mut decoder = BufferedDecoder::<T>::new();
+            for (end, byte) in input.iter().enumerate() {
+                if let Err(err) = decoder.push(&[*byte]) {
+                    let expected = format!("{:?}", T::deserialize(&input[..=end]).map(|_| ()));
+                    assert_eq!(format!("{:?}", Err::<(), _>(err)), expected);
+                    let err = decoder.push(&input[end + 1..]).unwrap_err();
+                    assert_eq!(format!("{:?}", Err::<(), _>(err)), expected);
+                    assert_eq!(format!("{:?}", decoder.finish().map(|_| ())), expected);
+                    break
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_from_reader() {
+        let value = sample();
+        let encoding = serialize(&value).unwrap();
+
+        let mut reader = std::io::Cursor::new(&encoding);
+        let mut decoder = BufferedDecoder::<Foo>::new();
+        while decoder.read_from(&mut reader).unwrap() > 0 {}
+        assert_eq!(decoder.finish().unwrap(), value);
+
+        let mut reader = std::io::Cursor::new(&encoding);
+        let recovered: Foo = deserialize_from_reader(&mut reader).unwrap();
+        assert_eq!(recovered, value);
+    }
+
+    #[test]
+    fn test_decode_with_default_options() {
+        let encoding = serialize(&sample()).unwrap();
+        let value = deserialize_with_options::<Foo>(&encoding, &DecodeOptions::default());
+        assert_eq!(value.unwrap(), sample());
+    }
+
+    #[test]
+    fn test_decode_max_bytes() {
+        let encoding = serialize(&sample()).unwrap();
+        let options = DecodeOptions { max_bytes: encoding.len() - 1, ..Default::default() };
+        let result = deserialize_with_options::<Foo>(&encoding, &options);
+        assert!(matches!(result, Err(DeserializeError::InputTooLarge { .. })));
+
+        let mut decoder = BufferedDecoder::<Foo>::with_options(options);
+        decoder.push(&encoding[..options.max_bytes]).unwrap();
+        let result = decoder.push(&encoding[options.max_bytes..]);
+        assert!(matches!(
+            result,
+            Err(DeserializeError::InputTooLarge { provided, limit }) if provided == encoding.len() && limit == options.max_bytes
+        ));
+        // the decoder accepts no further input and reports the same error when finished
+        let result = decoder.push(&[0u8]);
+        assert!(matches!(
+            result,
+            Err(DeserializeError::InputTooLarge { provided, .. }) if provided == encoding.len()
+        ));
+        assert_eq!(decoder.len(), options.max_bytes);
+        assert!(matches!(
+            decoder.finish(),
+            Err(DeserializeError::InputTooLarge { provided, .. }) if provided == encoding.len()
+        ));
+
+        // reading stops once the input exceeds the limit
+        let options = DecodeOptions { max_bytes: 4096, ..Default::default() };
+        let mut reader = std::io::Read::take(std::io::repeat(1u8), 1 << 20);
+        let mut decoder = BufferedDecoder::<List<u8, { 1 << 20 }>>::with_options(options);
+        let result = decoder.read_to_end(&mut reader);
+        assert!(matches!(result, Err(DeserializeError::InputTooLarge { limit: 4096, .. })));
+        assert_eq!(decoder.len(), 4096);
+
+        let options = DecodeOptions { max_bytes: encoding.len(), ..Default::default() };
+        assert!(deserialize_with_options::<Foo>(&encoding, &options).is_ok());
+    }
+
+    #[test]
+    fn test_decode_max_depth() {
+        type Nested = List<List<List<u8, 4>, 4>, 4>;
+        let value = Nested::try_from(vec![List::try_from(vec![List::default()]).unwrap()]).unwrap();
+        let encoding = serialize(&value).unwrap();
+
+        let options = DecodeOptions { max_depth: 2, ..Default::default() };
+        let err = deserialize_with_options::<Nested>(&encoding, &options).unwrap_err();
+        assert!(matches!(err.innermost(), DeserializeError::DepthLimitExceeded(2)));
+        assert_eq!(err.path(), &[PathElement::Index(0), PathElement::Index(0)]);
+
+        let options = DecodeOptions { max_depth: 3, ..Default::default() };
+        assert_eq!(deserialize_with_options::<Nested>(&encoding, &options).unwrap(), value);
+
+        // containers count towards the depth
+        let encoding = serialize(&sample()).unwrap();
+        let options = DecodeOptions { max_depth: 2, ..Default::default() };
+        let err = deserialize_with_options::<Foo>(&encoding, &options).unwrap_err();
+        assert!(matches!(err.innermost(), DeserializeError::DepthLimitExceeded(2)));
+        assert_eq!(err.path(), &[PathElement::from("d"), PathElement::Index(0)]);
+    }
+
+    #[test]
+    fn test_decode_max_allocation() {
+        let value = List::<u64, 64>::try_from(vec![1u64; 32]).unwrap();
+        let encoding = serialize(&value).unwrap();
+
+        let options = DecodeOptions { max_allocation: 255, ..Default::default() };
+        let result = deserialize_with_options::<List<u64, 64>>(&encoding, &options);
+        assert!(matches!(
+            result,
+            Err(DeserializeError::AllocationLimitExceeded { requested: 256, limit: 255 })
+        ));
+
+        let options = DecodeOptions { max_allocation: 256, ..Default::default() };
+        assert_eq!(deserialize_with_options::<List<u64, 64>>(&encoding, &options).unwrap(), value);
+    }
+
+    #[test]
+    fn test_decode_checks_bound_before_elements() {
+        // an offset table claiming 1000 elements, each with invalid contents
+        let element_count = 1000u32;
+        let mut encoding = vec![];
+        for _ in 0..element_count {
+            encoding.extend_from_slice(&(element_count * 4).to_le_bytes());
+        }
+        encoding.push(0u8);
+
+        let options = DecodeOptions { max_allocation: 0, ..Default::default() };
+        let result = deserialize_with_options::<List<Bitlist<8>, 2>>(&encoding, &options);
+        assert!(matches!(
+            result,
+            Err(DeserializeError::InvalidInstance(InstanceError::Bounded {
+                bound: 2,
+                provided: 1000
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_decoder_remaining() {
+        let mut decoder = BufferedDecoder::<u64>::new();
+        assert_eq!(decoder.remaining(), Some(8));
+        decoder.push(&[1u8, 0, 0]).unwrap();
+        assert_eq!(decoder.remaining(), Some(5));
+        decoder.push(&[0u8; 5]).unwrap();
+        assert_eq!(decoder.remaining(), Some(0));
+        assert_eq!(BufferedDecoder::<Foo>::new().remaining(), None);
+        assert_eq!(decoder.finish().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_decoder_validates_fixed_part() {
+        // the offset of `d` points before the offset of `b`
+        let mut encoding = serialize(&sample()).unwrap();
+        let fixed_len = 4 + 4 + 3 + 4;
+        encoding[11..15].copy_from_slice(&(fixed_len as u32 - 1).to_le_bytes());
+        let expected = format!("{:?}", Foo::deserialize(&encoding));
+        assert!(expected.contains("OffsetNotIncreasing"));
+
+        let mut decoder = BufferedDecoder::<Foo>::new();
+        decoder.push(&encoding[..fixed_len - 1]).unwrap();
+        let result = decoder.push(&encoding[fixed_len - 1..fixed_len]);
+        assert_eq!(format!("{:?}", result.map(|_| ())), expected);
+
+        // the bound of a list is checked once its offsets are complete, before its elements
+        let element_count = 1000u32;
+        let mut encoding = vec![];
+        for _ in 0..element_count {
+            encoding.extend_from_slice(&(element_count * 4).to_le_bytes());
+        }
+        let mut decoder = BufferedDecoder::<List<Bitlist<8>, 2>>::new();
+        let (offsets, rest) = encoding.split_at(encoding.len() - 1);
+        decoder.push(offsets).unwrap();
+        let result = decoder.push(rest);
+        assert!(matches!(
+            result,
+            Err(DeserializeError::InvalidInstance(InstanceError::Bounded {
+                bound: 2,
+                provided: 1000
+            }))
+        ));
+
+        // an invalid selector is reported as soon as it arrives
+        let mut decoder = BufferedDecoder::<Option<List<u8, 4>>>::new();
+        assert!(matches!(decoder.push(&[2u8]), Err(DeserializeError::InvalidByte(2))));
+
+        // offsets that are only valid with further input are left to `finish`
+        let value = List::<List<u8, 4>, 4>::try_from(vec![
+            List::try_from(vec![1u8, 2]).unwrap(),
+            List::try_from(vec![3u8]).unwrap(),
+        ])
+        .unwrap();
+        let encoding = serialize(&value).unwrap();
+        let mut decoder = BufferedDecoder::<List<List<u8, 4>, 4>>::new();
+        for byte in &encoding {
+            decoder.push(&[*byte]).unwrap();
+        }
+        assert_eq!(decoder.finish().unwrap(), value);
+    }
+}
@@ -0,0 +1,926 @@
+//! Values of SSZ types described at runtime by a `Schema`.
+use crate::{
+    de::{
+        validate_variable_offsets, variable_element_span, ContainerDeserializer, DecodeContext,
+        Deserialize, DeserializeError,
+    },
+    error::{InstanceError, TypeError},
+    lib::*,
+    merkleization::{
+        merkleize, merkleize_progressive, mix_in_length, mix_in_selector, pack_bytes,
+        MerkleizationError, Node, PathElement, Sha256Hasher, BYTES_PER_CHUNK,
+    },
+    schema::{Schema, StableFields},
+    ser::{Serialize, SerializeError, Serializer, Sink},
+    stable_container::{
+        deserialize_active_fields, merkleize_stable_container, serialize_active_fields,
+    },
+    BITS_PER_BYTE, U256,
+};
+
+/// A value of an SSZ type described at runtime by a `Schema`.
+///
+/// A `DynValue` only records the data of a value; its type is given by the `Schema` passed to each
+/// method, which must describe the structure of the value. The encoding and the `hash_tree_root`
+/// of a `DynValue` are the same as those of the equivalent value of a static type. Generalized
+/// indices only depend on the type, see `Schema::generalized_index`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DynValue {
+    /// A value of any `uint` type.
+    Uint(U256),
+    Boolean(bool),
+    /// The bits of a `Bitvector` or `Bitlist`.
+    Bits(Vec<bool>),
+    /// The elements of a `Vector`, `List` or `ProgressiveList`.
+    Sequence(Vec<DynValue>),
+    Optional(Option<Box<DynValue>>),
+    /// The fields of a container.
+    Container(Vec<DynValue>),
+    /// The fields of a stable container or profile, where `None` marks an inactive field.
+    StableContainer(Vec<Option<DynValue>>),
+    /// The selector of a variant of a union and its data, if any.
+    Union(u8, Option<Box<DynValue>>),
+}
+
+fn mismatch(schema: &Schema) -> TypeError {
+    TypeError::SchemaMismatch(schema.to_string())
+}
+
+fn uint_byte_length(bits: usize) -> Result<usize, TypeError> {
+    match bits {
+        8 | 16 | 32 | 64 | 128 | 256 => Ok(bits / BITS_PER_BYTE as usize),
+        _ => Err(TypeError::InvalidSchema(format!("uint{bits} is not a valid type"))),
+    }
+}
+
+// Returns the size of the encoding of the elements of a sequence of `elem`, or `None` if it is
+// variable.
+fn element_size(elem: &Schema) -> Result<Option<usize>, TypeError> {
+    if elem.is_variable_size() {
+        return Ok(None)
+    }
+    match elem.size_hint()? {
+        0 => Err(TypeError::InvalidSchema(format!("{elem} has an empty encoding"))),
+        size => Ok(Some(size)),
+    }
+}
+
+// Checks the number of elements, or bits, of a value of `schema`.
+fn check_length(schema: &Schema, provided: usize) -> Result<(), InstanceError> {
+    match schema {
+        Schema::Vector { len: required, .. } | Schema::Bitvector { len: required }
+            if provided != *required =>
+        {
+            Err(InstanceError::Exact { required: *required, provided })
+        }
+        Schema::List { limit: bound, .. } | Schema::Bitlist { limit: bound }
+            if provided > *bound =>
+        {
+            Err(InstanceError::Bounded { bound: *bound, provided })
+        }
+        _ => Ok(()),
+    }
+}
+
+// Checks that `values` has an entry for each field in `layout` and that required fields are
+// present.
+fn check_stable_fields(
+    schema: &Schema,
+    layout: &StableFields,
+    values: &[Option<DynValue>],
+) -> Result<(), TypeError> {
+    let is_valid = values.len() == layout.fields.len() &&
+        layout
+            .fields
+            .iter()
+            .zip(values)
+            .all(|(field, value)| field.is_optional || value.is_some());
+    if is_valid {
+        Ok(())
+    } else {
+        Err(mismatch(schema))
+    }
+}
+
+fn bits_to_bytes(bits: &[bool]) -> Vec<u8> {
+    let mut bytes = vec![0u8; bits.len().div_ceil(BITS_PER_BYTE as usize)];
+    for (i, _) in bits.iter().enumerate().filter(|(_, &bit)| bit) {
+        bytes[i / BITS_PER_BYTE as usize] |= 1 << (i % BITS_PER_BYTE as usize);
+    }
+    bytes
+}
+
+fn bytes_to_bits(bytes: &[u8], bit_count: usize) -> Vec<bool> {
+    (0..bit_count)
+        .map(|i| bytes[i / BITS_PER_BYTE as usize] & (1 << (i % BITS_PER_BYTE as usize)) != 0)
+        .collect()
+}
+
+impl DynValue {
+    /// Deserialize a value of the type described by `schema` from `encoding`.
+    pub fn deserialize(schema: &Schema, encoding: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_with(schema, encoding, &mut DecodeContext::default())
+    }
+
+    /// Deserialize a value of the type described by `schema` from `encoding`, enforcing the
+    /// limits tracked by `context`.
+    pub fn deserialize_with(
+        schema: &Schema,
+        encoding: &[u8],
+        context: &mut DecodeContext,
+    ) -> Result<Self, DeserializeError> {
+        match schema {
+            Schema::Uint { bits } => {
+                let expected = uint_byte_length(*bits)?;
+                if encoding.len() < expected {
+                    return Err(DeserializeError::ExpectedFurtherInput {
+                        provided: encoding.len(),
+                        expected,
+                    })
+                }
+                if encoding.len() > expected {
+                    return Err(DeserializeError::AdditionalInput {
+                        provided: encoding.len(),
+                        expected,
+                    })
+                }
+                Ok(Self::Uint(U256::from_le_slice(encoding)))
+            }
+            Schema::Boolean => Ok(Self::Boolean(bool::deserialize(encoding)?)),
+            Schema::Bitvector { len } => {
+                if *len == 0 {
+                    return Err(TypeError::InvalidBound(*len).into())
+                }
+                let expected = schema.size_hint()?;
+                if encoding.len() < expected {
+                    return Err(DeserializeError::ExpectedFurtherInput {
+                        provided: encoding.len(),
+                        expected,
+                    })
+                }
+                if encoding.len() > expected {
+                    return Err(DeserializeError::AdditionalInput {
+                        provided: encoding.len(),
+                        expected,
+                    })
+                }
+                let remainder_count = len % BITS_PER_BYTE as usize;
+                if remainder_count != 0 {
+                    // SAFETY: `encoding` is not empty as `len > 0`; qed
+                    let last_byte = encoding.last().expect("is not empty");
+                    if last_byte >> remainder_count != 0 {
+                        return Err(DeserializeError::InvalidByte(*last_byte))
+                    }
+                }
+                Ok(Self::Bits(bytes_to_bits(encoding, *len)))
+            }
+            Schema::Bitlist { limit } => {
+                let Some((last_byte, _)) = encoding.split_last() else {
+                    return Err(DeserializeError::ExpectedFurtherInput { provided: 0, expected: 1 })
+                };
+                // NOTE: the encoding has room for the bit marking the length
+                let max_len = limit / BITS_PER_BYTE as usize + 1;
+                if encoding.len() > max_len {
+                    return Err(DeserializeError::AdditionalInput {
+                        provided: encoding.len(),
+                        expected: max_len,
+                    })
+                }
+                if *last_byte == 0 {
+                    return Err(DeserializeError::InvalidByte(*last_byte))
+                }
+                // NOTE: the highest set bit marks the length of the bitlist
+                let marker_index = (BITS_PER_BYTE - 1 - last_byte.leading_zeros()) as usize;
+                let bit_count = (encoding.len() - 1) * BITS_PER_BYTE as usize + marker_index;
+                if bit_count > *limit {
+                    return Err(InstanceError::Bounded { bound: *limit, provided: bit_count }.into())
+                }
+                Ok(Self::Bits(bytes_to_bits(encoding, bit_count)))
+            }
+            Schema::Vector { elem, len } => {
+                if *len == 0 {
+                    return Err(TypeError::InvalidBound(*len).into())
+                }
+                if element_size(elem)?.is_some() {
+                    let expected = schema.size_hint()?;
+                    if encoding.len() < expected {
+                        return Err(DeserializeError::ExpectedFurtherInput {
+                            provided: encoding.len(),
+                            expected,
+                        })
+                    }
+                    if encoding.len() > expected {
+                        return Err(DeserializeError::AdditionalInput {
+                            provided: encoding.len(),
+                            expected,
+                        })
+                    }
+                }
+                let elements = Self::deserialize_sequence(elem, encoding, context, |provided| {
+                    if provided != *len {
+                        return Err(InstanceError::Exact { required: *len, provided }.into())
+                    }
+                    Ok(())
+                })?;
+                Ok(Self::Sequence(elements))
+            }
+            Schema::List { elem, .. } | Schema::ProgressiveList { elem } => {
+                if let Some(size) = element_size(elem)? {
+                    let remainder = encoding.len() % size;
+                    if remainder != 0 {
+                        return Err(DeserializeError::AdditionalInput {
+                            provided: encoding.len(),
+                            // SAFETY: checked subtraction is unnecessary, as encoding.len() >
+                            // remainder; qed
+                            expected: encoding.len() - remainder,
+                        })
+                    }
+                }
+                let elements = Self::deserialize_sequence(elem, encoding, context, |provided| {
+                    Ok(check_length(schema, provided)?)
+                })?;
+                Ok(Self::Sequence(elements))
+            }
+            Schema::Optional { elem } => {
+                if encoding.is_empty() {
+                    return Ok(Self::Optional(None))
+                }
+                let value = context
+                    .nested(|context| Self::deserialize_with(elem, encoding, context))
+                    .map_err(|err| err.in_element(PathElement::Index(0), 0))?;
+                Ok(Self::Optional(Some(Box::new(value))))
+            }
+            Schema::Container { fields, .. } => {
+                let mut deserializer = ContainerDeserializer::default();
+                for (_, schema) in fields {
+                    deserializer.parse_segment(
+                        encoding,
+                        schema.is_variable_size(),
+                        schema.size_hint()?,
+                    )?;
+                }
+                let spans = deserializer.finalize(encoding)?;
+                context.nested(|context| {
+                    let values = fields
+                        .iter()
+                        .zip(spans.chunks_exact(2))
+                        .map(|((name, schema), span)| {
+                            let (start, end) = (span[0], span[1]);
+                            Self::deserialize_with(schema, &encoding[start..end], context).map_err(
+                                |err| err.in_element(PathElement::from(name.as_str()), start),
+                            )
+                        })
+                        .collect::<Result<_, _>>()?;
+                    Ok(Self::Container(values))
+                })
+            }
+            Schema::StableContainer { .. } | Schema::Profile { .. } => {
+                let layout = schema.stable_fields()?;
+                let optional_count = layout.fields.iter().filter(|field| field.is_optional).count();
+                let (active_fields, prefix_length) = if layout.bit_count > 0 {
+                    deserialize_active_fields(encoding, layout.bit_count, optional_count)?
+                } else {
+                    (vec![], 0)
+                };
+                let data = &encoding[prefix_length..];
+
+                let mut active_fields = active_fields.into_iter();
+                let is_present = layout
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        // SAFETY: there is an active field for each optional field; qed
+                        !field.is_optional || active_fields.next().expect("has active field")
+                    })
+                    .collect::<Vec<_>>();
+                let mut deserializer = ContainerDeserializer::default();
+                for (field, _) in layout.fields.iter().zip(&is_present).filter(|(_, &p)| p) {
+                    deserializer.parse_segment(
+                        data,
+                        field.schema.is_variable_size(),
+                        field.schema.size_hint()?,
+                    )?;
+                }
+                let spans = deserializer.finalize(data)?;
+
+                let mut spans = spans.chunks_exact(2).map(|span| (span[0], span[1]));
+                context.nested(|context| {
+                    let values = layout
+                        .fields
+                        .iter()
+                        .zip(is_present)
+                        .map(|(field, is_present)| {
+                            if !is_present {
+                                return Ok(None)
+                            }
+                            // SAFETY: there is a span for each present field; qed
+                            let (start, end) = spans.next().expect("has span");
+                            Self::deserialize_with(field.schema, &data[start..end], context)
+                                .map(Some)
+                                .map_err(|err| {
+                                    err.in_element(
+                                        PathElement::from(field.name),
+                                        prefix_length + start,
+                                    )
+                                })
+                        })
+                        .collect::<Result<_, _>>()?;
+                    Ok(Self::StableContainer(values))
+                })
+            }
+            Schema::Union { variants, .. } => {
+                let Some((selector, data)) = encoding.split_first() else {
+                    return Err(DeserializeError::ExpectedFurtherInput { provided: 0, expected: 1 })
+                };
+                match variants.iter().find(|(s, _)| s == selector) {
+                    Some((_, None)) => {
+                        if !data.is_empty() {
+                            return Err(DeserializeError::AdditionalInput {
+                                provided: encoding.len(),
+                                expected: 1,
+                            })
+                        }
+                        Ok(Self::Union(*selector, None))
+                    }
+                    Some((_, Some(schema))) => {
+                        let value = context
+                            .nested(|context| Self::deserialize_with(schema, data, context))
+                            .map_err(|err| {
+                                err.in_element(PathElement::Index(*selector as usize), 1)
+                            })?;
+                        Ok(Self::Union(*selector, Some(Box::new(value))))
+                    }
+                    None => Err(DeserializeError::InvalidByte(*selector)),
+                }
+            }
+        }
+    }
+
+    // Deserialize `encoding` as a homogeneous composite type of `elem`, following
+    // `deserialize_homogeneous_composite`.
+    fn deserialize_sequence<F>(
+        elem: &Schema,
+        encoding: &[u8],
+        context: &mut DecodeContext,
+        check_len: F,
+    ) -> Result<Vec<Self>, DeserializeError>
+    where
+        F: FnOnce(usize) -> Result<(), DeserializeError>,
+    {
+        let size = element_size(elem)?;
+        let element_count = match size {
+            Some(size) => encoding.len() / size,
+            None => validate_variable_offsets(encoding)?,
+        };
+        check_len(element_count)?;
+
+        context.nested(|context| {
+            context.allocate::<Self>(element_count)?;
+            let mut elements = Vec::with_capacity(element_count);
+            for index in 0..element_count {
+                let (start, end) = match size {
+                    Some(size) => (index * size, (index + 1) * size),
+                    None => variable_element_span(encoding, index, element_count),
+                };
+                // SAFETY: index is safe because spans have been validated; qed
+                let element = Self::deserialize_with(elem, &encoding[start..end], context)
+                    .map_err(|err| err.in_element(PathElement::Index(index), start))?;
+                elements.push(element);
+            }
+            Ok(elements)
+        })
+    }
+
+    /// Returns the encoding of `self` as a value of the type described by `schema`.
+    pub fn serialize(&self, schema: &Schema) -> Result<Vec<u8>, SerializeError> {
+        let mut buffer = Vec::with_capacity(self.encoded_len(schema)?);
+        self.serialize_to(schema, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// Write the encoding of `self` as a value of the type described by `schema` to the `sink`.
+    ///
+    /// Returns the number of bytes written.
+    pub fn serialize_to<S: Sink + ?Sized>(
+        &self,
+        schema: &Schema,
+        sink: &mut S,
+    ) -> Result<usize, SerializeError> {
+        match (schema, self) {
+            (Schema::Uint { bits }, Self::Uint(value)) => {
+                let byte_length = uint_byte_length(*bits)?;
+                if value.bit_len() > *bits {
+                    return Err(mismatch(schema).into())
+                }
+                sink.write_bytes(&value.to_le_bytes::<32>()[..byte_length])?;
+                Ok(byte_length)
+            }
+            (Schema::Boolean, Self::Boolean(value)) => value.serialize_to(sink),
+            (Schema::Bitvector { .. }, Self::Bits(bits)) => {
+                check_length(schema, bits.len())?;
+                let bytes = bits_to_bytes(bits);
+                sink.write_bytes(&bytes)?;
+                Ok(bytes.len())
+            }
+            (Schema::Bitlist { .. }, Self::Bits(bits)) => {
+                check_length(schema, bits.len())?;
+                let mut bits = bits.clone();
+                // NOTE: mark the length of the bitlist with a set bit after the last bit
+                bits.push(true);
+                let bytes = bits_to_bytes(&bits);
+                sink.write_bytes(&bytes)?;
+                Ok(bytes.len())
+            }
+            (Schema::Vector { elem, .. }, Self::Sequence(values)) |
+            (Schema::List { elem, .. }, Self::Sequence(values)) |
+            (Schema::ProgressiveList { elem }, Self::Sequence(values)) => {
+                check_length(schema, values.len())?;
+                let elements = values
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| (PathElement::Index(i), elem.as_ref(), value))
+                    .collect::<Vec<_>>();
+                Self::serialize_elements(&elements, sink)
+            }
+            (Schema::Optional { elem }, Self::Optional(value)) => match value {
+                Some(value) => value
+                    .serialize_to(elem, sink)
+                    .map_err(|err| err.in_element(PathElement::Index(0))),
+                None => Ok(0),
+            },
+            (Schema::Container { fields, .. }, Self::Container(values)) => {
+                if fields.len() != values.len() {
+                    return Err(mismatch(schema).into())
+                }
+                let elements = fields
+                    .iter()
+                    .zip(values)
+                    .map(|((name, schema), value)| {
+                        (PathElement::from(name.as_str()), schema, value)
+                    })
+                    .collect::<Vec<_>>();
+                Self::serialize_elements(&elements, sink)
+            }
+            (Schema::StableContainer { .. }, Self::StableContainer(values)) |
+            (Schema::Profile { .. }, Self::StableContainer(values)) => {
+                let layout = schema.stable_fields()?;
+                check_stable_fields(schema, &layout, values)?;
+                let prefix_length = if layout.bit_count > 0 {
+                    let active_fields = layout
+                        .fields
+                        .iter()
+                        .zip(values)
+                        .filter(|(field, _)| field.is_optional)
+                        .map(|(_, value)| value.is_some())
+                        .collect::<Vec<_>>();
+                    serialize_active_fields(&active_fields, layout.bit_count, sink)?
+                } else {
+                    0
+                };
+                let elements = layout
+                    .fields
+                    .iter()
+                    .zip(values)
+                    .filter_map(|(field, value)| {
+                        value
+                            .as_ref()
+                            .map(|value| (PathElement::from(field.name), field.schema, value))
+                    })
+                    .collect::<Vec<_>>();
+                Ok(prefix_length + Self::serialize_elements(&elements, sink)?)
+            }
+            (Schema::Union { variants, .. }, Self::Union(selector, value)) => {
+                let variant = variants.iter().find(|(s, _)| s == selector);
+                match (variant, value) {
+                    (Some((_, None)), None) => selector.serialize_to(sink),
+                    (Some((_, Some(schema))), Some(value)) => {
+                        let selector_bytes = selector.serialize_to(sink)?;
+                        let value_bytes = value.serialize_to(schema, sink).map_err(|err| {
+                            err.in_element(PathElement::Index(*selector as usize))
+                        })?;
+                        Ok(selector_bytes + value_bytes)
+                    }
+                    _ => Err(mismatch(schema).into()),
+                }
+            }
+            _ => Err(mismatch(schema).into()),
+        }
+    }
+
+    // Serialize `elements` as a composite type to `sink`, following
+    // `serialize_homogeneous_composite` and the encoding of containers.
+    fn serialize_elements<S: Sink + ?Sized>(
+        elements: &[(PathElement, &Schema, &Self)],
+        sink: &mut S,
+    ) -> Result<usize, SerializeError> {
+        let fixed_length = elements
+            .iter()
+            .map(|(element, schema, _)| {
+                schema
+                    .fixed_portion_length()
+                    .map_err(|err| SerializeError::from(err).in_element(element.clone()))
+            })
+            .sum::<Result<_, _>>()?;
+        let mut serializer = Serializer::new(sink, fixed_length);
+        for (element, schema, value) in elements {
+            let result = if schema.is_variable_size() {
+                value.encoded_len(schema).and_then(|len| serializer.with_offset(len))
+            } else {
+                value.serialize_to(schema, serializer.sink()).map(|_| ())
+            };
+            result.map_err(|err| err.in_element(element.clone()))?;
+        }
+        for (element, schema, value) in elements.iter().filter(|(_, s, _)| s.is_variable_size()) {
+            value
+                .serialize_to(schema, serializer.sink())
+                .map_err(|err| err.in_element(element.clone()))?;
+        }
+        serializer.finalize()
+    }
+
+    /// Returns the exact number of bytes in the encoding of `self` as a value of the type
+    /// described by `schema`.
+    ///
+    /// The result is unspecified if `self` does not match `schema`, which is reported when
+    /// serializing `self`. Returns an error if the size of the encoding of values of `schema`
+    /// does not fit in a `usize`.
+    pub fn encoded_len(&self, schema: &Schema) -> Result<usize, SerializeError> {
+        if !schema.is_variable_size() {
+            return Ok(schema.size_hint()?)
+        }
+        let element_len = |schema: &Schema, value: &Self| -> Result<usize, SerializeError> {
+            let variable_length =
+                if schema.is_variable_size() { value.encoded_len(schema)? } else { 0 };
+            Ok(schema.fixed_portion_length()? + variable_length)
+        };
+        match (schema, self) {
+            (Schema::Bitlist { .. }, Self::Bits(bits)) => {
+                Ok(bits.len() / BITS_PER_BYTE as usize + 1)
+            }
+            (Schema::Vector { elem, .. }, Self::Sequence(values)) |
+            (Schema::List { elem, .. }, Self::Sequence(values)) |
+            (Schema::ProgressiveList { elem }, Self::Sequence(values)) => {
+                values.iter().map(|value| element_len(elem, value)).sum()
+            }
+            (Schema::Optional { elem }, Self::Optional(value)) => {
+                value.as_ref().map_or(Ok(0), |value| value.encoded_len(elem))
+            }
+            (Schema::Container { fields, .. }, Self::Container(values)) => fields
+                .iter()
+                .zip(values)
+                .map(|((_, schema), value)| element_len(schema, value))
+                .sum(),
+            (Schema::StableContainer { .. }, Self::StableContainer(values)) |
+            (Schema::Profile { .. }, Self::StableContainer(values)) => {
+                let Ok(layout) = schema.stable_fields() else { return Ok(0) };
+                let prefix_length = layout.bit_count.div_ceil(BITS_PER_BYTE as usize);
+                let fields_length: usize = layout
+                    .fields
+                    .iter()
+                    .zip(values)
+                    .filter_map(|(field, value)| {
+                        value.as_ref().map(|value| element_len(field.schema, value))
+                    })
+                    .sum::<Result<_, _>>()?;
+                Ok(prefix_length + fields_length)
+            }
+            (Schema::Union { variants, .. }, Self::Union(selector, value)) => {
+                let variant = variants.iter().find(|(s, _)| s == selector);
+                let value_length = match (variant, value) {
+                    (Some((_, Some(schema))), Some(value)) => value.encoded_len(schema)?,
+                    _ => 0,
+                };
+                Ok(1 + value_length)
+            }
+            _ => Ok(0),
+        }
+    }
+
+    /// Compute the "hash tree root" of `self` as a value of the type described by `schema`.
+    pub fn hash_tree_root(&self, schema: &Schema) -> Result<Node, MerkleizationError> {
+        let invalid = || MerkleizationError::from(SerializeError::from(mismatch(schema)));
+        match (schema, self) {
+            (Schema::Uint { .. }, Self::Uint(..)) | (Schema::Boolean, Self::Boolean(..)) => {
+                let mut chunk = self.serialize(schema)?;
+                pack_bytes(&mut chunk);
+                Ok(Node::try_from(chunk.as_slice()).expect("is one chunk"))
+            }
+            (Schema::Bitvector { .. }, Self::Bits(bits)) |
+            (Schema::Bitlist { .. }, Self::Bits(bits)) => {
+                check_length(schema, bits.len()).map_err(SerializeError::from)?;
+                let mut chunks = bits_to_bytes(bits);
+                pack_bytes(&mut chunks);
+                let chunk_count = schema.chunk_count().map_err(SerializeError::from)?;
+                let data_root = merkleize(&chunks, Some(chunk_count))?;
+                if matches!(schema, Schema::Bitlist { .. }) {
+                    Ok(mix_in_length::<Sha256Hasher>(data_root, bits.len()))
+                } else {
+                    Ok(data_root)
+                }
+            }
+            (Schema::Vector { elem, .. }, Self::Sequence(values)) |
+            (Schema::List { elem, .. }, Self::Sequence(values)) |
+            (Schema::ProgressiveList { elem }, Self::Sequence(values)) => {
+                check_length(schema, values.len()).map_err(SerializeError::from)?;
+                let chunks = if elem.is_basic() {
+                    let mut chunks = vec![];
+                    for (i, value) in values.iter().enumerate() {
+                        value
+                            .serialize_to(elem, &mut chunks)
+                            .map_err(|err| err.in_element(PathElement::Index(i)))?;
+                    }
+                    pack_bytes(&mut chunks);
+                    chunks
+                } else {
+                    let mut chunks = Vec::with_capacity(values.len() * BYTES_PER_CHUNK);
+                    for value in values {
+                        chunks.extend_from_slice(value.hash_tree_root(elem)?.as_ref());
+                    }
+                    chunks
+                };
+                match schema {
+                    Schema::Vector { .. } => {
+                        let chunk_count = schema.chunk_count().map_err(SerializeError::from)?;
+                        merkleize(&chunks, Some(chunk_count))
+                    }
+                    Schema::List { .. } => {
+                        let chunk_count = schema.chunk_count().map_err(SerializeError::from)?;
+                        let data_root = merkleize(&chunks, Some(chunk_count))?;
+                        Ok(mix_in_length::<Sha256Hasher>(data_root, values.len()))
+                    }
+                    _ => {
+                        let data_root = merkleize_progressive(&chunks, 1)?;
+                        Ok(mix_in_length::<Sha256Hasher>(data_root, values.len()))
+                    }
+                }
+            }
+            (Schema::Optional { elem }, Self::Optional(value)) => {
+                let root = match value {
+                    Some(value) => value.hash_tree_root(elem)?,
+                    None => Node::default(),
+                };
+                Ok(mix_in_length::<Sha256Hasher>(root, value.is_some() as usize))
+            }
+            (Schema::Container { fields, .. }, Self::Container(values)) => {
+                if fields.len() != values.len() {
+                    return Err(invalid())
+                }
+                let mut chunks = Vec::with_capacity(values.len() * BYTES_PER_CHUNK);
+                for ((_, schema), value) in fields.iter().zip(values) {
+                    chunks.extend_from_slice(value.hash_tree_root(schema)?.as_ref());
+                }
+                merkleize(&chunks, None)
+            }
+            (Schema::StableContainer { .. }, Self::StableContainer(values)) |
+            (Schema::Profile { .. }, Self::StableContainer(values)) => {
+                let layout = schema.stable_fields().map_err(SerializeError::from)?;
+                check_stable_fields(schema, &layout, values).map_err(SerializeError::from)?;
+                let chunk_count =
+                    layout.fields.iter().map(|field| field.index + 1).max().unwrap_or(0);
+                let mut chunks = vec![0u8; chunk_count * BYTES_PER_CHUNK];
+                let mut active_fields = vec![false; chunk_count];
+                for (field, value) in layout.fields.iter().zip(values) {
+                    if let Some(value) = value {
+                        let chunk = value.hash_tree_root(field.schema)?;
+                        chunks[field.index * BYTES_PER_CHUNK..(field.index + 1) * BYTES_PER_CHUNK]
+                            .copy_from_slice(chunk.as_ref());
+                        active_fields[field.index] = true;
+                    }
+                }
+                merkleize_stable_container::<Sha256Hasher>(
+                    &chunks,
+                    &active_fields,
+                    layout.max_fields,
+                )
+            }
+            (Schema::Union { variants, .. }, Self::Union(selector, value)) => {
+                let variant = variants.iter().find(|(s, _)| s == selector);
+                let root = match (variant, value) {
+                    (Some((_, None)), None) => Node::default(),
+                    (Some((_, Some(schema))), Some(value)) => value.hash_tree_root(schema)?,
+                    _ => return Err(invalid()),
+                };
+                Ok(mix_in_selector(root, *selector as usize))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[derive(Debug, Default, PartialEq, Eq, SimpleSerialize, SszSchema)]
+    struct Inner {
+        a: u16,
+        b: List<u32, 5>,
+    }
+
+    #[derive(Debug, Default, PartialEq, Eq, SimpleSerialize, SszSchema)]
+    struct Outer {
+        a: u8,
+        b: U256,
+        c: bool,
+        d: Vector<u64, 3>,
+        e: List<Inner, 4>,
+        f: Bitvector<10>,
+        g: Bitlist<20>,
+        h: ByteList<40>,
+        i: Optional<Inner>,
+        j: Option<u128>,
+        k: ProgressiveList<u16>,
+        l: Vector<Inner, 2>,
+    }
+
+    #[derive(Debug, PartialEq, Eq, SimpleSerialize, SszSchema)]
+    enum Choice {
+        None,
+        Small(u8),
+        Large(Inner),
+    }
+
+    #[derive(Debug, Default, PartialEq, Eq, SimpleSerialize, SszSchema)]
+    #[ssz(stable_container = 8)]
+    struct Shape {
+        side: Option<u16>,
+        color: Option<u8>,
+        radius: Option<List<u8, 4>>,
+    }
+
+    #[derive(Debug, Default, PartialEq, Eq, SimpleSerialize, SszSchema)]
+    #[ssz(profile(Shape))]
+    struct Circle {
+        color: u8,
+        radius: Option<List<u8, 4>>,
+    }
+
+    fn outer() -> Outer {
+        let inner = Inner { a: 7, b: List::try_from(vec![1, 2, 3]).unwrap() };
+        let g =
+            Bitlist::<20>::try_from((0..13).map(|i| i % 3 == 0).collect::<Vec<bool>>().as_slice())
+                .unwrap();
+        Outer {
+            a: 3,
+            b: U256::from(123456789u64) << 100,
+            c: true,
+            d: Vector::try_from(vec![u64::MAX, 0, 42]).unwrap(),
+            e: List::try_from(vec![Inner::default(), inner]).unwrap(),
+            f: Bitvector::try_from(
+                [true, false, true, true, false, false, false, false, false, true].as_ref(),
+            )
+            .unwrap(),
+            g,
+            h: ByteList::try_from([9u8; 33].as_ref()).unwrap(),
+            i: Some(Inner { a: 1, b: List::default() }).into(),
+            j: Some(5),
+            k: ProgressiveList::from(vec![
+                1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17,
+            ]),
+            l: Vector::try_from(vec![Inner::default(), Inner { a: 2, b: List::default() }])
+                .unwrap(),
+        }
+    }
+
+    fn assert_matches_static<T: SimpleSerialize + SszSchema>(value: &T) -> DynValue {
+        let schema = T::schema();
+        let encoding = serialize(value).unwrap();
+        let dyn_value = DynValue::deserialize(&schema, &encoding).unwrap();
+        assert_eq!(dyn_value.encoded_len(&schema).unwrap(), encoding.len());
+        assert_eq!(dyn_value.serialize(&schema).unwrap(), encoding);
+        assert_eq!(dyn_value.hash_tree_root(&schema).unwrap(), value.hash_tree_root().unwrap());
+        dyn_value
+    }
+
+    #[test]
+    fn test_matches_static_types() {
+        let value = outer();
+        let dyn_value = assert_matches_static(&value);
+        let DynValue::Container(fields) = &dyn_value else { panic!("is a container") };
+        assert_eq!(fields[0], DynValue::Uint(U256::from(3)));
+        assert_eq!(fields[2], DynValue::Boolean(true));
+        assert_eq!(fields[9], DynValue::Union(1, Some(Box::new(DynValue::Uint(U256::from(5))))));
+
+        assert_matches_static(&Outer::default());
+        assert_matches_static(&Choice::None);
+        assert_matches_static(&Choice::Small(3));
+        assert_matches_static(&Choice::Large(Inner { a: 3, b: List::try_from(vec![4]).unwrap() }));
+        assert_matches_static(&Shape::default());
+        assert_matches_static(&Shape {
+            side: None,
+            color: Some(2),
+            radius: Some(List::try_from(vec![1, 2]).unwrap()),
+        });
+        assert_matches_static(&Circle::default());
+        assert_matches_static(&Circle { color: 1, radius: Some(List::try_from(vec![3]).unwrap()) });
+    }
+
+    #[test]
+    fn test_generalized_indices() {
+        let paths: [Path; 13] = [
+            &[],
+            &["a".into()],
+            &["d".into(), 2.into()],
+            &["e".into(), 1.into(), "b".into(), 4.into()],
+            &["e".into(), PathElement::Length],
+            &["f".into(), 9.into()],
+            &["g".into(), 19.into()],
+            &["h".into(), 39.into()],
+            &["i".into(), 0.into(), "a".into()],
+            &["j".into(), 1.into()],
+            &["j".into(), PathElement::Selector],
+            &["k".into(), 16.into()],
+            &["l".into(), 1.into(), "b".into(), PathElement::Length],
+        ];
+        let schema = Outer::schema();
+        for path in paths {
+            assert_eq!(
+                schema.generalized_index(path).unwrap(),
+                Outer::generalized_index(path).unwrap()
+            );
+        }
+        assert!(schema.generalized_index(&["d".into(), 3.into()]).is_err());
+        assert!(schema.generalized_index(&["z".into()]).is_err());
+
+        for path in [&["color".into()][..], &["radius".into(), 3.into()]] {
+            assert_eq!(
+                Circle::schema().generalized_index(path).unwrap(),
+                Circle::generalized_index(path).unwrap()
+            );
+            assert_eq!(
+                Shape::schema().generalized_index(path).unwrap(),
+                Shape::generalized_index(path).unwrap()
+            );
+        }
+        let path = &[2.into(), "b".into(), 0.into()];
+        assert_eq!(
+            Choice::schema().generalized_index(path).unwrap(),
+            Choice::generalized_index(path).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_schema_from_json() {
+        let schema: Schema = serde_json::from_str(
+            r#"{"list": {"elem": {"container": {"name": "Inner", "fields": [
+                ["a", {"uint": {"bits": 16}}],
+                ["b", {"list": {"elem": {"uint": {"bits": 32}}, "limit": 5}}]
+            ]}}, "limit": 4}}"#,
+        )
+        .unwrap();
+        assert_eq!(schema, List::<Inner, 4>::schema());
+
+        let value = outer().e;
+        let encoding = serialize(&value).unwrap();
+        let dyn_value = DynValue::deserialize(&schema, &encoding).unwrap();
+        assert_eq!(dyn_value.serialize(&schema).unwrap(), encoding);
+        assert_eq!(dyn_value.hash_tree_root(&schema).unwrap(), value.hash_tree_root().unwrap());
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        let schema = Outer::schema();
+        let encoding = serialize(&outer()).unwrap();
+        let truncated = &encoding[..encoding.len() - 1];
+        assert_eq!(
+            DynValue::deserialize(&schema, truncated).unwrap_err().to_string(),
+            Outer::deserialize(truncated).unwrap_err().to_string()
+        );
+        assert_eq!(
+            DynValue::deserialize(&List::<u8, 2>::schema(), &[1, 2, 3]).unwrap_err().to_string(),
+            List::<u8, 2>::deserialize(&[1, 2, 3]).unwrap_err().to_string()
+        );
+
+        let schema = Schema::Uint { bits: 8 };
+        assert!(DynValue::Uint(U256::from(256)).serialize(&schema).is_err());
+        assert!(DynValue::Boolean(true).serialize(&schema).is_err());
+        assert!(DynValue::Boolean(true).hash_tree_root(&schema).is_err());
+        assert!(DynValue::deserialize(&Schema::Uint { bits: 7 }, &[0]).is_err());
+
+        let schema = Circle::schema();
+        let missing_color = DynValue::StableContainer(vec![None, None]);
+        assert!(missing_color.serialize(&schema).is_err());
+        assert!(missing_color.hash_tree_root(&schema).is_err());
+
+        let schema = Choice::schema();
+        assert!(DynValue::Union(3, None).serialize(&schema).is_err());
+        assert!(DynValue::deserialize(&schema, &[3]).is_err());
+
+        // the size of the encoding of values of the schema does not fit in a `usize`
+        let schema =
+            Schema::Vector { elem: Box::new(Schema::Uint { bits: 64 }), len: usize::MAX / 4 };
+        let result = DynValue::deserialize(&schema, &[0; 8]);
+        assert!(matches!(result, Err(DeserializeError::InvalidType(TypeError::InvalidSchema(_)))));
+        assert!(DynValue::Sequence(vec![]).encoded_len(&schema).is_err());
+        let schema =
+            Schema::List { elem: Box::new(Schema::Uint { bits: 64 }), limit: usize::MAX / 4 };
+        assert!(DynValue::Sequence(vec![]).hash_tree_root(&schema).is_err());
+        let schema = Schema::Bitlist { limit: usize::MAX };
+        assert_eq!(
+            DynValue::deserialize(&schema, &[0b101]).unwrap(),
+            DynValue::Bits(vec![true, false])
+        );
+    }
+}
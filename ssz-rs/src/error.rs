@@ -53,6 +53,10 @@ impl std::error::Error for Error {}
 pub enum TypeError {
     /// A type is invalid for the given bounds.
     InvalidBound(usize),
+    /// A `Schema` does not describe a valid type.
+    InvalidSchema(String),
+    /// A value does not have the structure described by the given `Schema`.
+    SchemaMismatch(String),
 }
 
 impl Display for TypeError {
@@ -61,6 +65,10 @@ impl Display for TypeError {
             Self::InvalidBound(size) => {
                 write!(f, "the type for this value is invalid with bound {size}")
             }
+            Self::InvalidSchema(reason) => write!(f, "invalid schema: {reason}"),
+            Self::SchemaMismatch(schema) => {
+                write!(f, "the value does not match the schema {schema}")
+            }
         }
     }
 }
@@ -91,17 +91,31 @@ mod array;
 mod bitlist;
 mod bitvector;
 mod boolean;
+mod byte_list;
+mod byte_vector;
+mod cached;
 mod container;
 mod de;
+mod dyn_value;
 mod error;
 mod list;
 mod merkleization;
+mod optional;
+mod persistent_tree;
+mod progressive_list;
+mod schema;
 mod ser;
 #[cfg(feature = "serde")]
 mod serde;
+#[cfg(feature = "snappy")]
+pub mod snappy;
+mod stable_container;
+mod tree_list;
+mod tree_vector;
 mod uint;
 mod union;
 mod vector;
+mod view;
 
 mod lib {
     mod core {
@@ -111,7 +125,7 @@ mod lib {
         pub use std::*;
     }
 
-    pub use self::core::{any, cmp, fmt};
+    pub use self::core::{any, cmp, fmt, iter, mem};
 
     pub use self::{
         cmp::Ordering,
@@ -128,13 +142,21 @@ mod lib {
     #[cfg(feature = "std")]
     pub use std::vec::Vec;
 
+    #[cfg(not(feature = "std"))]
+    pub use alloc::boxed::Box;
+
+    #[cfg(not(feature = "std"))]
+    pub use alloc::sync::Arc;
+
+    #[cfg(feature = "std")]
+    pub use std::sync::Arc;
+
     #[cfg(not(feature = "std"))]
     pub use alloc::collections::{BTreeMap as HashMap, BTreeSet as HashSet};
 
     #[cfg(feature = "std")]
     pub use std::collections::{HashMap, HashSet};
 
-    #[cfg(feature = "serde")]
     pub use self::core::marker::PhantomData;
 }
 
@@ -149,6 +171,68 @@ pub trait Serializable: Serialize + Deserialize {
     // expected number of bytes for the serialization of this type
     // or 0 if unknown ahead of time
     fn size_hint() -> usize;
+
+    /// Returns the exact number of bytes in the serialization of `self`.
+    ///
+    /// The default implementation serializes variable-size values to measure their encoding,
+    /// returning any error encountered when serializing `self`; implementing types should override
+    /// it with a computation that does not serialize `self`.
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        if Self::is_variable_size() {
+            let mut buffer = lib::Vec::new();
+            self.serialize(&mut buffer)
+        } else {
+            Ok(Self::size_hint())
+        }
+    }
+
+    /// Returns the maximum number of bytes in the serialization of any value of this type,
+    /// saturating at `usize::MAX`.
+    ///
+    /// The default implementation returns `size_hint` for fixed-size types and `usize::MAX`,
+    /// i.e. no bound, for variable-size types.
+    fn max_encoded_len() -> usize {
+        if Self::is_variable_size() {
+            usize::MAX
+        } else {
+            Self::size_hint()
+        }
+    }
+
+    /// Returns the part of `encoding`, the encoding of a value of this type, that encodes the
+    /// element at `path`.
+    ///
+    /// Only the offsets along `path` are validated; the returned bytes are not decoded.
+    /// The default implementation only supports the empty path, as is the case for basic types.
+    fn bytes_at_path<'a>(encoding: &'a [u8], path: Path) -> Result<&'a [u8], DeserializeError> {
+        if path.is_empty() {
+            Ok(encoding)
+        } else {
+            Err(DeserializeError::InvalidPath(path.to_vec()))
+        }
+    }
+
+    /// Checks `prefix`, the start of an encoding of a value of this type that is still arriving,
+    /// for errors that `deserialize` reports for any encoding starting with `prefix`, e.g. invalid
+    /// offsets in the fixed part of the encoding. See `BufferedDecoder`.
+    ///
+    /// Returns `false` if more input is required to complete the check.
+    /// The default implementation checks nothing, as is the case for fixed-size types.
+    fn validate_prefix(prefix: &[u8]) -> Result<bool, DeserializeError> {
+        let _ = prefix;
+        Ok(true)
+    }
+
+    /// Decodes the element at `path` from `encoding`, the encoding of a value of this type,
+    /// without decoding any other part of `encoding`.
+    ///
+    /// `U` must be the type of the element at `path`.
+    fn decode_at_path<U: Deserialize>(encoding: &[u8], path: Path) -> Result<U, DeserializeError>
+    where
+        Self: Sized,
+    {
+        U::deserialize(Self::bytes_at_path(encoding, path)?)
+    }
 }
 
 /// `SimpleSerialize` is a trait for types conforming to the SSZ spec.
@@ -160,19 +244,33 @@ mod exports {
     pub use crate::{
         bitlist::Bitlist,
         bitvector::Bitvector,
-        de::{Deserialize, DeserializeError},
+        byte_list::ByteList,
+        byte_vector::ByteVector,
+        cached::{Cacheable, Cached},
+        de::{BufferedDecoder, DecodeContext, DecodeOptions, Deserialize, DeserializeError},
+        dyn_value::DynValue,
         error::{Error as SimpleSerializeError, InstanceError, TypeError},
-        list::List,
+        list::{List, ListView},
         merkleization::{
+            diff::{diff, Diff, Differ, Difference},
             generalized_index::default_generalized_index,
             multiproofs,
             proofs::{self, is_valid_merkle_branch, Prove},
-            GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleizationError, Node, Path,
-            PathElement,
+            tree::{MerkleTree, Subtree},
+            GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleHasher, MerkleizationError,
+            Node, Path, PathElement, Sha256Hasher,
         },
-        ser::{Serialize, SerializeError},
+        optional::Optional,
+        persistent_tree::TreeIter,
+        progressive_list::ProgressiveList,
+        schema::{Schema, SszSchema},
+        ser::{Serialize, SerializeError, Sink},
+        stable_container::{StableContainer, StableLayout},
+        tree_list::TreeList,
+        tree_vector::TreeVector,
         uint::U256,
-        vector::Vector,
+        vector::{Vector, VectorView},
+        view::{SequenceIter, SequenceView, Viewable},
     };
 
     /// `serialize` is a convenience function for taking a value that
@@ -182,8 +280,8 @@ mod exports {
     where
         T: crate::Serializable,
     {
-        let mut result = crate::lib::Vec::new();
-        value.serialize(&mut result)?;
+        let mut result = crate::lib::Vec::with_capacity(value.encoded_len()?);
+        value.serialize_to(&mut result)?;
         Ok(result)
     }
 
@@ -196,6 +294,30 @@ mod exports {
     {
         T::deserialize(encoding)
     }
+
+    /// `deserialize_with_options` is like `deserialize` but enforces the limits in `options`,
+    /// for use when decoding untrusted input.
+    pub fn deserialize_with_options<T>(
+        encoding: &[u8],
+        options: &DecodeOptions,
+    ) -> Result<T, DeserializeError>
+    where
+        T: crate::Serializable,
+    {
+        if encoding.len() > options.max_bytes {
+            return Err(DeserializeError::InputTooLarge {
+                provided: encoding.len(),
+                limit: options.max_bytes,
+            })
+        }
+        T::deserialize_with(encoding, &mut DecodeContext::new(*options))
+    }
+
+    #[cfg(feature = "std")]
+    pub use crate::de::deserialize_from_reader;
+
+    #[cfg(feature = "serde")]
+    pub use crate::serde::{consensus, Consensus, ConsensusDeserialize, ConsensusSerialize};
 }
 
 pub use crate::exports::*;
@@ -210,17 +332,31 @@ pub mod prelude {
     #[doc(hidden)]
     pub use crate as ssz_rs;
     pub use ssz_rs_derive::{
-        GeneralizedIndexable, HashTreeRoot, Prove, Serializable, SimpleSerialize,
+        Cacheable, Diff, GeneralizedIndexable, HashTreeRoot, Prove, Serializable, SimpleSerialize,
+        SszSchema, Viewable,
     };
+    #[cfg(feature = "serde")]
+    pub use ssz_rs_derive::{ConsensusDeserialize, ConsensusSerialize};
 }
 
 #[doc(hidden)]
 /// `internal` contains functionality that is exposed purely for the derive proc macro crate
 pub mod __internal {
     // exported for derive macro to avoid code duplication...
+    #[cfg(feature = "serde")]
+    pub use crate::serde::{deserialize_union, invalid_selector, serialize_union, ConsensusUnion};
     pub use crate::{
         de::ContainerDeserializer,
-        merkleization::{generalized_index::get_power_of_two_ceil, merkleize, mix_in_selector},
-        ser::Serializer,
+        merkleization::{
+            generalized_index::get_power_of_two_ceil, merkleize, merkleize_with, mix_in_selector,
+            mix_in_selector_with, Elements, ParallelElementsToChunks, SequentialElementsToChunks,
+        },
+        ser::{element_encoded_len, element_max_encoded_len, fixed_portion_length, Serializer},
+        stable_container::{
+            active_fields_root, deserialize_active_fields, merkleize_stable_container,
+            serialize_active_fields, IdenticalLayouts, StableLayouts, VariantLayouts,
+        },
     };
+    #[cfg(feature = "serde")]
+    pub use ::serde;
 }
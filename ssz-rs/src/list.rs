@@ -1,14 +1,25 @@
+#[cfg(feature = "rayon")]
+use crate::merkleization::elements_to_chunks_in_parallel;
 use crate::{
-    de::{deserialize_homogeneous_composite, Deserialize, DeserializeError},
+    de::{
+        deserialize_homogeneous_composite, validate_variable_offsets_prefix, DecodeContext,
+        Deserialize, DeserializeError,
+    },
     error::{Error, InstanceError},
     lib::*,
     merkleization::{
-        elements_to_chunks, get_power_of_two_ceil, merkleize, mix_in_length, pack,
+        diff::{diff_sequences, Diff, Differ},
+        get_power_of_two_ceil, merkleize_with, mix_in_length, pack,
         proofs::{Prove, Prover},
-        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleizationError, Node, Path,
-        PathElement, BYTES_PER_CHUNK,
+        tree::Subtree,
+        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleHasher, MerkleizationError,
+        Node, Path, PathElement, Sha256Hasher, BYTES_PER_CHUNK,
+    },
+    ser::{
+        homogeneous_composite_encoded_len, homogeneous_composite_max_encoded_len,
+        serialize_homogeneous_composite, Serialize, SerializeError, Sink,
     },
-    ser::{Serialize, SerializeError, Serializer},
+    view::{SequenceIter, SequenceView, Viewable},
     Serializable, SimpleSerialize,
 };
 
@@ -145,6 +156,36 @@ where
     fn size_hint() -> usize {
         0
     }
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        homogeneous_composite_encoded_len(&self.data)
+    }
+
+    fn max_encoded_len() -> usize {
+        homogeneous_composite_max_encoded_len::<T>(N)
+    }
+
+    fn bytes_at_path<'a>(encoding: &'a [u8], path: Path) -> Result<&'a [u8], DeserializeError> {
+        if path.is_empty() {
+            return Ok(encoding)
+        }
+        let elements = SequenceView::<T>::new(encoding)?;
+        if elements.len() > N {
+            return Err(InstanceError::Bounded { bound: N, provided: elements.len() }.into())
+        }
+        elements.bytes_at_path(path)
+    }
+
+    fn validate_prefix(prefix: &[u8]) -> Result<bool, DeserializeError> {
+        if !T::is_variable_size() {
+            return Ok(true)
+        }
+        validate_variable_offsets_prefix(prefix, |len| {
+            if len > N {
+                return Err(InstanceError::Bounded { bound: N, provided: len }.into())
+            }
+            Ok(())
+        })
+    }
 }
 
 impl<T, const N: usize> Serialize for List<T, N>
@@ -152,14 +193,14 @@ where
     T: Serializable,
 {
     fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
+        self.serialize_to(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
         if self.len() > N {
             return Err(InstanceError::Bounded { bound: N, provided: self.len() }.into())
         }
-        let mut serializer = Serializer::default();
-        for element in &self.data {
-            serializer.with_element(element)?;
-        }
-        serializer.serialize(buffer)
+        serialize_homogeneous_composite(&self.data, sink)
     }
 }
 
@@ -168,6 +209,13 @@ where
     T: Serializable,
 {
     fn deserialize(encoding: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_with(encoding, &mut DecodeContext::default())
+    }
+
+    fn deserialize_with(
+        encoding: &[u8],
+        context: &mut DecodeContext,
+    ) -> Result<Self, DeserializeError> {
         if !T::is_variable_size() {
             let remainder = encoding.len() % T::size_hint();
             if remainder != 0 {
@@ -180,10 +228,12 @@ where
             }
         }
 
-        let result = deserialize_homogeneous_composite(encoding)?;
-        if result.len() > N {
-            return Err(InstanceError::Bounded { bound: N, provided: result.len() }.into())
-        }
+        let result = deserialize_homogeneous_composite(encoding, context, |len| {
+            if len > N {
+                return Err(InstanceError::Bounded { bound: N, provided: len }.into())
+            }
+            Ok(())
+        })?;
         let result = result.try_into().map_err(|(_, err)| match err {
             Error::Instance(err) => DeserializeError::InvalidInstance(err),
             _ => unreachable!("no other error variant allowed here"),
@@ -196,10 +246,9 @@ impl<T, const N: usize> List<T, N>
 where
     T: SimpleSerialize,
 {
-    fn assemble_chunks(&self) -> Result<Vec<u8>, MerkleizationError> {
+    fn assemble_chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
         if T::is_composite_type() {
-            let count = self.len();
-            elements_to_chunks(self.data.iter().enumerate(), count)
+            T::elements_to_chunks::<H>(&self.data)
         } else {
             pack(self)
         }
@@ -215,14 +264,43 @@ where
         (N * T::size_hint()).div_ceil(BYTES_PER_CHUNK)
     }
 
-    fn compute_hash_tree_root(&self) -> Result<Node, MerkleizationError> {
-        let chunks = self.assemble_chunks()?;
+    fn compute_hash_tree_root<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        let chunks = self.assemble_chunks::<H>()?;
+        self.root_of_chunks::<H>(&chunks)
+    }
+
+    fn root_of_chunks<H: MerkleHasher>(&self, chunks: &[u8]) -> Result<Node, MerkleizationError> {
         let data_root = if T::is_composite_type() {
-            merkleize(&chunks, Some(N))?
+            merkleize_with::<H>(chunks, Some(N))?
         } else {
-            merkleize(&chunks, Some(Self::chunk_count()))?
+            merkleize_with::<H>(chunks, Some(Self::chunk_count()))?
         };
-        Ok(mix_in_length(data_root, self.len()))
+        Ok(mix_in_length::<H>(data_root, self.len()))
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T, const N: usize> List<T, N>
+where
+    T: SimpleSerialize + Sync,
+{
+    /// Compute the "hash tree root" of `self` like `HashTreeRoot::hash_tree_root`, computing the
+    /// roots of the elements in parallel on the `rayon` thread pool when there are enough of them.
+    pub fn hash_tree_root_in_parallel(&self) -> Result<Node, MerkleizationError> {
+        self.hash_tree_root_in_parallel_with::<Sha256Hasher>()
+    }
+
+    /// Compute the "hash tree root" of `self` like `hash_tree_root_in_parallel`, with the
+    /// hasher `H`.
+    pub fn hash_tree_root_in_parallel_with<H: MerkleHasher>(
+        &self,
+    ) -> Result<Node, MerkleizationError> {
+        let chunks = if T::is_composite_type() {
+            elements_to_chunks_in_parallel::<H, _>(&self.data)?
+        } else {
+            pack(&self.data)?
+        };
+        self.root_of_chunks::<H>(&chunks)
     }
 }
 
@@ -231,7 +309,11 @@ where
     T: SimpleSerialize,
 {
     fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
-        self.compute_hash_tree_root()
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        self.compute_hash_tree_root::<H>()
     }
 }
 
@@ -279,11 +361,15 @@ impl<T, const N: usize> Prove for List<T, N>
 where
     T: SimpleSerialize,
 {
-    fn chunks(&self) -> Result<Vec<u8>, MerkleizationError> {
-        self.assemble_chunks()
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
+        self.assemble_chunks::<H>()
     }
 
-    fn prove_element(&self, index: usize, prover: &mut Prover) -> Result<(), MerkleizationError> {
+    fn prove_element<H: MerkleHasher>(
+        &self,
+        index: usize,
+        prover: &mut Prover<H>,
+    ) -> Result<(), MerkleizationError> {
         if index >= N {
             Err(MerkleizationError::InvalidInnerIndex)
         } else {
@@ -292,13 +378,86 @@ where
         }
     }
 
+    fn subtree<H: MerkleHasher>(&self) -> Result<Subtree<H>, MerkleizationError> {
+        Subtree::from_sequence(self, self.iter())
+    }
+
     fn decoration(&self) -> Option<usize> {
         Some(self.len())
     }
 }
 
+impl<T, const N: usize> Viewable for List<T, N>
+where
+    T: Viewable,
+{
+    type View<'a> = ListView<'a, T, N>;
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        let elements = SequenceView::new(encoding)?;
+        if elements.len() > N {
+            return Err(InstanceError::Bounded { bound: N, provided: elements.len() }.into())
+        }
+        Ok(ListView { elements })
+    }
+}
+
+impl<T, const N: usize> Diff for List<T, N>
+where
+    T: SimpleSerialize + Diff,
+{
+    fn diff_elements(&self, other: &Self, differ: &mut Differ) -> Result<(), MerkleizationError> {
+        diff_sequences::<Self, T>(self, other, true, differ)
+    }
+}
+
 impl<T, const N: usize> SimpleSerialize for List<T, N> where T: SimpleSerialize {}
 
+/// A borrowed view over the encoding of a `List<T, N>`.
+///
+/// Dereferences to a `SequenceView` for access to the elements of the list.
+pub struct ListView<'a, T, const N: usize> {
+    elements: SequenceView<'a, T>,
+}
+
+impl<'a, T, const N: usize> Clone for ListView<'a, T, N> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T, const N: usize> Copy for ListView<'a, T, N> {}
+
+impl<'a, T, const N: usize> fmt::Debug for ListView<'a, T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ListView<{}, {}>({:?})", any::type_name::<T>(), N, self.elements)
+    }
+}
+
+impl<'a, T, const N: usize> Deref for ListView<'a, T, N> {
+    type Target = SequenceView<'a, T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.elements
+    }
+}
+
+impl<'a, T: Viewable, const N: usize> ListView<'a, T, N> {
+    /// Decode the entire `List` this view refers to.
+    pub fn decode(&self) -> Result<List<T, N>, DeserializeError> {
+        List::deserialize(self.elements.as_bytes())
+    }
+}
+
+impl<'a, T: Viewable, const N: usize> IntoIterator for ListView<'a, T, N> {
+    type Item = Result<T::View<'a>, DeserializeError>;
+    type IntoIter = SequenceIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.iter()
+    }
+}
+
 #[cfg(feature = "serde")]
 struct ListVisitor<T: Serializable>(PhantomData<Vec<T>>);
 
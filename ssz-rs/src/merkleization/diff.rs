@@ -0,0 +1,418 @@
+//! Support for finding the differences between two values of the same type.
+use crate::{
+    lib::*,
+    merkleization::{
+        generalized_index::DisplayPath, pack, GeneralizedIndex, GeneralizedIndexable, HashTreeRoot,
+        MerkleizationError as Error, Node, PathElement, BYTES_PER_CHUNK,
+    },
+    ser::Serialize,
+    BITS_PER_BYTE,
+};
+
+/// A node in the Merkle tree of a type where two values of the type have different roots.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// The path to the node.
+    pub path: Vec<PathElement>,
+    /// The generalized index of the node.
+    pub index: GeneralizedIndex,
+    /// The root of the node in the first value.
+    pub left: Node,
+    /// The root of the node in the second value.
+    pub right: Node,
+}
+
+impl Display for Difference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` (generalized index {}): {} != {}",
+            DisplayPath(&self.path),
+            self.index,
+            self.left,
+            self.right
+        )
+    }
+}
+
+/// A type that knows how to find the differences between two values assuming their type is
+/// `Diff`.
+#[derive(Debug)]
+pub struct Differ {
+    path: Vec<PathElement>,
+    index: GeneralizedIndex,
+    differences: Vec<Difference>,
+}
+
+impl Default for Differ {
+    fn default() -> Self {
+        Self { path: vec![], index: 1, differences: vec![] }
+    }
+}
+
+impl Differ {
+    /// Compare `left` and `right`, the values at the path tracked by `self`, recursing into their
+    /// elements if their roots differ.
+    /// If no differing element is found, the values themselves are recorded as a difference.
+    pub fn compare<T: Diff + ?Sized>(&mut self, left: &T, right: &T) -> Result<(), Error> {
+        let left_root = left.hash_tree_root()?;
+        let right_root = right.hash_tree_root()?;
+        if left_root == right_root {
+            return Ok(())
+        }
+        let count = self.differences.len();
+        left.diff_elements(right, self)?;
+        if self.differences.len() == count {
+            self.record(left_root, right_root);
+        }
+        Ok(())
+    }
+
+    /// Compare `left` and `right`, the values of `element` of the values of type `P` at the path
+    /// tracked by `self`.
+    pub fn compare_element<P, T>(
+        &mut self,
+        element: PathElement,
+        left: &T,
+        right: &T,
+    ) -> Result<(), Error>
+    where
+        P: GeneralizedIndexable + ?Sized,
+        T: Diff + ?Sized,
+    {
+        let parent = self.enter::<P>(element)?;
+        let result = self.compare(left, right);
+        self.leave(parent);
+        result
+    }
+
+    /// Compare `left` and `right`, the values of `element` of the values of type `P` at the path
+    /// tracked by `self`, where an absent value has the zero node as its root, as do the absent
+    /// fields of a stable container.
+    pub fn compare_optional_element<P, T>(
+        &mut self,
+        element: PathElement,
+        left: Option<&T>,
+        right: Option<&T>,
+    ) -> Result<(), Error>
+    where
+        P: GeneralizedIndexable + ?Sized,
+        T: Diff + ?Sized,
+    {
+        match (left, right) {
+            (Some(left), Some(right)) => self.compare_element::<P, T>(element, left, right),
+            (None, None) => Ok(()),
+            (left, right) => {
+                let left = left.map(T::hash_tree_root).transpose()?.unwrap_or_default();
+                let right = right.map(T::hash_tree_root).transpose()?.unwrap_or_default();
+                let parent = self.enter::<P>(element)?;
+                self.record(left, right);
+                self.leave(parent);
+                Ok(())
+            }
+        }
+    }
+
+    // Record the chunks `left` and `right` of the values of type `P` at the path tracked by
+    // `self`, which pack basic elements and differ first at the element at `index`.
+    fn record_chunk<P: GeneralizedIndexable + ?Sized>(
+        &mut self,
+        index: usize,
+        left: Node,
+        right: Node,
+    ) -> Result<(), Error> {
+        let parent = self.enter::<P>(PathElement::Index(index))?;
+        self.record(left, right);
+        self.leave(parent);
+        Ok(())
+    }
+
+    // Move the path tracked by `self` to `element` of the values of type `P` at the current path,
+    // returning the generalized index of the current path.
+    fn enter<P: GeneralizedIndexable + ?Sized>(
+        &mut self,
+        element: PathElement,
+    ) -> Result<GeneralizedIndex, Error> {
+        let parent = self.index;
+        self.index = P::compute_generalized_index(parent, core::slice::from_ref(&element))?;
+        self.path.push(element);
+        Ok(parent)
+    }
+
+    fn leave(&mut self, parent: GeneralizedIndex) {
+        self.path.pop();
+        self.index = parent;
+    }
+
+    fn record(&mut self, left: Node, right: Node) {
+        self.differences.push(Difference {
+            path: self.path.clone(),
+            index: self.index,
+            left,
+            right,
+        });
+    }
+}
+
+impl From<Differ> for Vec<Difference> {
+    fn from(differ: Differ) -> Self {
+        differ.differences
+    }
+}
+
+/// Required functionality to support finding the differences between two values of a type.
+///
+/// Unlike the other merkleization traits, `Diff` is not implied by `SimpleSerialize`; custom types
+/// opt in with `#[derive(Diff)]`.
+pub trait Diff: HashTreeRoot + GeneralizedIndexable {
+    /// Compare the elements of `self` and `other`, whose roots differ, with the context in
+    /// `differ`.
+    /// Default implementation compares no elements so that differing values of the type are
+    /// reported as a whole, as is the case for basic types. Composite types should override to
+    /// compare their fields or elements with `Differ::compare_element`.
+    #[allow(unused)]
+    fn diff_elements(&self, other: &Self, differ: &mut Differ) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+// Compare the elements `left` and `right` of sequences of type `P` that share a common prefix
+// and the lengths of the sequences, if `P` has a length.
+// Basic elements are packed into chunks, so the differing chunks are compared instead (see
+// `diff_packed_chunks`).
+pub(crate) fn diff_sequences<P, T>(
+    left: &[T],
+    right: &[T],
+    has_length: bool,
+    differ: &mut Differ,
+) -> Result<(), Error>
+where
+    P: GeneralizedIndexable + ?Sized,
+    T: Diff + Serialize,
+{
+    if T::is_composite_type() {
+        for (i, (left, right)) in left.iter().zip(right).enumerate() {
+            differ.compare_element::<P, T>(PathElement::Index(i), left, right)?;
+        }
+    } else {
+        let element_bits = T::item_length() * BITS_PER_BYTE as usize;
+        diff_packed_chunks::<P>(&pack(left)?, &pack(right)?, 0, element_bits, differ)?;
+    }
+    if has_length && left.len() != right.len() {
+        differ.compare_element::<P, usize>(PathElement::Length, &left.len(), &right.len())?;
+    }
+    Ok(())
+}
+
+// Compare the chunks `left` and `right` of sequences of type `P`, starting with the chunk at
+// `first_chunk`, which pack basic elements of `element_bits` bits each.
+// As the elements in a chunk share a node of the Merkle tree, each differing chunk is recorded
+// once with its roots, at the path to the first differing element in the chunk. Chunks present in
+// only one of the sequences are summarized by the difference in the lengths of the sequences.
+pub(crate) fn diff_packed_chunks<P: GeneralizedIndexable + ?Sized>(
+    left: &[u8],
+    right: &[u8],
+    first_chunk: usize,
+    element_bits: usize,
+    differ: &mut Differ,
+) -> Result<(), Error> {
+    let chunks = left.chunks_exact(BYTES_PER_CHUNK).zip(right.chunks_exact(BYTES_PER_CHUNK));
+    for (i, (left, right)) in chunks.enumerate() {
+        let Some(byte) = left.iter().zip(right).position(|(left, right)| left != right) else {
+            continue
+        };
+        let bit =
+            byte * BITS_PER_BYTE as usize + (left[byte] ^ right[byte]).trailing_zeros() as usize;
+        let index =
+            ((first_chunk + i) * BYTES_PER_CHUNK * BITS_PER_BYTE as usize + bit) / element_bits;
+        // SAFETY: the slices have the length of a chunk; qed
+        let left = Node::try_from(left).expect("is one chunk");
+        let right = Node::try_from(right).expect("is one chunk");
+        differ.record_chunk::<P>(index, left, right)?;
+    }
+    Ok(())
+}
+
+/// Find the differences between `left` and `right`.
+///
+/// Identical subtrees are pruned by comparing the roots of the fields and elements of both
+/// values, and the deepest nodes where the values differ are returned in order, along with their
+/// generalized indices and the roots of both values at each node. Elements present in only one
+/// of two lists are summarized by the difference in the lengths of the lists. Basic elements
+/// packed into a chunk share its node, so a differing chunk is reported once, at the path to its
+/// first differing element.
+pub fn diff<T: Diff + ?Sized>(left: &T, right: &T) -> Result<Vec<Difference>, Error> {
+    let mut differ = Differ::default();
+    differ.compare(left, right)?;
+    Ok(differ.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, SimpleSerialize, Diff)]
+    struct Validator {
+        pubkey: ByteVector<48>,
+        effective_balance: u64,
+        slashed: bool,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq, SimpleSerialize, Diff)]
+    enum Choice {
+        None,
+        Balance(u64),
+        Validator(Validator),
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, SimpleSerialize, Diff)]
+    struct State {
+        slot: u64,
+        validators: List<Validator, 16>,
+        balances: Vector<u64, 4>,
+        participation: Bitlist<32>,
+        justification: Bitvector<4>,
+        choice: Option<Choice>,
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, SimpleSerialize, Diff)]
+    #[ssz(stable_container = 4)]
+    struct Shape {
+        side: Option<u16>,
+        color: Option<u8>,
+    }
+
+    fn state() -> State {
+        let validators = (0..3)
+            .map(|i| Validator {
+                pubkey: ByteVector::try_from([i as u8; 48].as_ref()).unwrap(),
+                effective_balance: 32,
+                slashed: false,
+            })
+            .collect::<Vec<_>>();
+        State {
+            slot: 10,
+            validators: validators.try_into().unwrap(),
+            balances: Vector::try_from(vec![32, 32, 32, 32]).unwrap(),
+            participation: Bitlist::try_from([true, false, true].as_ref()).unwrap(),
+            justification: Bitvector::default(),
+            choice: Some(Choice::Balance(1)),
+        }
+    }
+
+    fn assert_differences(left: &State, right: &State, paths: &[Vec<PathElement>]) {
+        let differences = diff(left, right).unwrap();
+        assert_eq!(differences.iter().map(|d| d.path.clone()).collect::<Vec<_>>(), paths);
+        for difference in differences {
+            assert_eq!(difference.index, State::generalized_index(&difference.path).unwrap());
+            assert_ne!(difference.left, difference.right);
+            let (proof, root) = left.prove(&difference.path).unwrap();
+            assert_eq!(root, left.hash_tree_root().unwrap());
+            assert_eq!(proof.leaf, difference.left);
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let left = state();
+        assert!(diff(&left, &left.clone()).unwrap().is_empty());
+
+        let mut right = left.clone();
+        right.slot = 11;
+        right.validators[1].slashed = true;
+        right.validators[2].pubkey[0] = 7;
+        right.balances[3] = 31;
+        right.participation.set(1, true);
+        right.justification.set(0, true);
+        assert_differences(
+            &left,
+            &right,
+            &[
+                vec!["slot".into()],
+                vec!["validators".into(), 1.into(), "slashed".into()],
+                vec!["validators".into(), 2.into(), "pubkey".into()],
+                vec!["balances".into(), 3.into()],
+                vec!["participation".into(), 1.into()],
+                vec!["justification".into(), 0.into()],
+            ],
+        );
+
+        let differences = diff(&left, &right).unwrap();
+        assert_eq!(differences[0].left, 10u64.hash_tree_root().unwrap());
+        assert_eq!(differences[0].right, 11u64.hash_tree_root().unwrap());
+        assert_eq!(
+            differences[0].to_string().split(':').next().unwrap(),
+            "`slot` (generalized index 8)"
+        );
+    }
+
+    #[test]
+    fn test_diff_lengths_and_unions() {
+        let left = state();
+
+        let mut right = left.clone();
+        right.validators.push(Validator::default());
+        assert_differences(&left, &right, &[vec!["validators".into(), PathElement::Length]]);
+
+        let mut right = left.clone();
+        right.choice = Some(Choice::Balance(2));
+        assert_differences(&left, &right, &[vec!["choice".into(), 1.into(), 1.into()]]);
+
+        right.choice = Some(Choice::Validator(Validator::default()));
+        assert_differences(&left, &right, &[vec!["choice".into(), 1.into()]]);
+
+        right.choice = None;
+        assert_differences(&left, &right, &[vec!["choice".into()]]);
+
+        let mut right = left.clone();
+        right.participation.push(false);
+        assert_differences(&left, &right, &[vec!["participation".into(), PathElement::Length]]);
+
+        right.participation.push(true);
+        assert_differences(
+            &left,
+            &right,
+            &[
+                vec!["participation".into(), 4.into()],
+                vec!["participation".into(), PathElement::Length],
+            ],
+        );
+    }
+
+    #[test]
+    fn test_diff_packed_elements() {
+        let left = state();
+
+        // elements packed into the same chunk are reported once
+        let mut right = left.clone();
+        right.balances[1] = 1;
+        right.balances[3] = 31;
+        right.participation.set(0, false);
+        right.participation.set(2, false);
+        assert_differences(
+            &left,
+            &right,
+            &[vec!["balances".into(), 1.into()], vec!["participation".into(), 0.into()]],
+        );
+
+        let differences = diff(&left, &right).unwrap();
+        let chunk = |balances: &Vector<u64, 4>| Node::try_from(pack(balances).unwrap().as_slice());
+        assert_eq!(differences[0].left, chunk(&left.balances).unwrap());
+        assert_eq!(differences[0].right, chunk(&right.balances).unwrap());
+    }
+
+    #[test]
+    fn test_diff_stable_container() {
+        let left = Shape { side: Some(1), color: None };
+        let right = Shape { side: Some(2), color: Some(3) };
+        let differences = diff(&left, &right).unwrap();
+        assert_eq!(
+            differences.iter().map(|d| d.path.clone()).collect::<Vec<_>>(),
+            vec![vec!["side".into()], vec!["color".into()]]
+        );
+        assert_eq!(differences[1].index, Shape::generalized_index(&["color".into()]).unwrap());
+        assert_eq!(differences[1].left, Node::default());
+        assert_eq!(differences[1].right, 3u8.hash_tree_root().unwrap());
+    }
+}
@@ -33,6 +33,24 @@ impl From<usize> for PathElement {
 /// A collection of `PathElement`s that navigate a `GeneralizedIndexable` type.
 pub type Path<'a> = &'a [PathElement];
 
+// Formats a `Path` like an expression accessing the target, e.g. `body.attestations[3]`.
+pub(crate) struct DisplayPath<'a>(pub Path<'a>);
+
+impl<'a> Display for DisplayPath<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, element) in self.0.iter().enumerate() {
+            match element {
+                PathElement::Index(index) => write!(f, "[{index}]")?,
+                PathElement::Field(name) if i == 0 => write!(f, "{name}")?,
+                PathElement::Field(name) => write!(f, ".{name}")?,
+                PathElement::Length => write!(f, ".<length>")?,
+                PathElement::Selector => write!(f, ".<selector>")?,
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Types that can compute generalized indices given a `Path`.
 pub trait GeneralizedIndexable {
     fn item_length() -> usize {
@@ -0,0 +1,185 @@
+//! Support for the hash function used to compute Merkle trees.
+use crate::{
+    lib::*,
+    merkleization::{Node, BYTES_PER_CHUNK},
+};
+use sha2::{Digest, Sha256};
+
+pub(crate) const MAX_MERKLE_TREE_DEPTH: usize = 64;
+
+/// A hash function computing the nodes of SSZ Merkle trees.
+///
+/// Merkleization and proofs are generic over this trait so that implementations of SHA-256 other
+/// than the default `Sha256Hasher` can be used, e.g. the precompiles exposed to zkVM guests.
+pub trait MerkleHasher: Default + 'static {
+    /// Return the hash of the concatenation of the 32-byte nodes `left` and `right`.
+    fn hash_pair(&mut self, left: &[u8], right: &[u8]) -> Node;
+
+    /// Return the root of a tree of zero chunks with `depth` layers below the root.
+    ///
+    /// The default implementation computes the table of these "zero hashes" for every depth once
+    /// per hasher with the `std` feature, and otherwise computes the root with `depth` hashes.
+    fn zero_hash(depth: usize) -> Node {
+        #[cfg(feature = "std")]
+        if let Some(node) = cached_zero_hashes::<Self>().get(depth) {
+            return *node
+        }
+        compute_zero_hashes::<Self>().nth(depth).expect("is unbounded")
+    }
+}
+
+// Iterate over the roots of the trees of zero chunks with `0, 1, 2, ...` layers below the root.
+fn compute_zero_hashes<H: MerkleHasher>() -> impl Iterator<Item = Node> {
+    let mut hasher = H::default();
+    iter::successors(Some(Node::default()), move |node| {
+        Some(hasher.hash_pair(node.as_ref(), node.as_ref()))
+    })
+}
+
+// Return the table of zero hashes of `H` up to `MAX_MERKLE_TREE_DEPTH`, computing it on the first
+// call for each hasher.
+#[cfg(feature = "std")]
+fn cached_zero_hashes<H: MerkleHasher>() -> &'static [Node] {
+    use std::sync::{OnceLock, PoisonError, RwLock};
+
+    // NOTE: a `static` in a generic function is shared by all instantiations, so the tables are
+    // keyed by the type of the hasher
+    static TABLES: OnceLock<RwLock<HashMap<any::TypeId, &'static [Node]>>> = OnceLock::new();
+
+    let tables = TABLES.get_or_init(Default::default);
+    let key = any::TypeId::of::<H>();
+    if let Some(table) = tables.read().unwrap_or_else(PoisonError::into_inner).get(&key) {
+        return table
+    }
+    let zero_hashes = compute_zero_hashes::<H>().take(MAX_MERKLE_TREE_DEPTH).collect::<Vec<_>>();
+    // NOTE: each table is leaked, which is bounded by the number of hasher types
+    let mut tables = tables.write().unwrap_or_else(PoisonError::into_inner);
+    let table: &'static [Node] =
+        tables.entry(key).or_insert_with(|| Box::leak(zero_hashes.into_boxed_slice()));
+    table
+}
+
+#[derive(Debug)]
+struct Context {
+    zero_hashes: [u8; MAX_MERKLE_TREE_DEPTH * BYTES_PER_CHUNK],
+}
+
+impl Index<usize> for Context {
+    type Output = [u8];
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.zero_hashes[index * BYTES_PER_CHUNK..(index + 1) * BYTES_PER_CHUNK]
+    }
+}
+
+// Grab the precomputed context from the build stage
+include!(concat!(env!("OUT_DIR"), "/context.rs"));
+
+/// The default `MerkleHasher`, using the SHA-256 implementation of the `sha2` crate.
+#[derive(Debug, Default, Clone)]
+pub struct Sha256Hasher(Sha256);
+
+impl MerkleHasher for Sha256Hasher {
+    fn hash_pair(&mut self, left: &[u8], right: &[u8]) -> Node {
+        self.0.update(left);
+        self.0.update(right);
+        Node::from_slice(&self.0.finalize_reset())
+    }
+
+    // Invariant: `depth < MAX_MERKLE_TREE_DEPTH`
+    fn zero_hash(depth: usize) -> Node {
+        CONTEXT[depth].try_into().expect("can produce a single root chunk")
+    }
+}
+
+// Indicate if `H` is the default `Sha256Hasher`, e.g. to reuse roots cached with it.
+pub(crate) fn is_default_hasher<H: MerkleHasher>() -> bool {
+    any::TypeId::of::<H>() == any::TypeId::of::<Sha256Hasher>()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    // A `MerkleHasher` relying on the default computation of zero hashes, whose nodes differ from
+    // those of `Sha256Hasher`.
+    #[derive(Default)]
+    pub(crate) struct TestHasher(Sha256Hasher);
+
+    impl MerkleHasher for TestHasher {
+        fn hash_pair(&mut self, left: &[u8], right: &[u8]) -> Node {
+            let mut node = self.0.hash_pair(left, right);
+            node[0] ^= 0xff;
+            node
+        }
+    }
+
+    #[derive(Default)]
+    struct UncachedHasher(Sha256Hasher);
+
+    impl MerkleHasher for UncachedHasher {
+        fn hash_pair(&mut self, left: &[u8], right: &[u8]) -> Node {
+            self.0.hash_pair(left, right)
+        }
+    }
+
+    #[test]
+    fn test_zero_hashes() {
+        for depth in 0..MAX_MERKLE_TREE_DEPTH {
+            assert_eq!(UncachedHasher::zero_hash(depth), Sha256Hasher::zero_hash(depth));
+        }
+        assert_ne!(TestHasher::zero_hash(1), Sha256Hasher::zero_hash(1));
+        assert!(is_default_hasher::<Sha256Hasher>());
+        assert!(!is_default_hasher::<TestHasher>());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_zero_hashes_are_computed_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static PAIRS_HASHED: AtomicUsize = AtomicUsize::new(0);
+
+        // A `MerkleHasher` counting the pairs it hashes.
+        #[derive(Default)]
+        struct CountingHasher(Sha256Hasher);
+
+        impl MerkleHasher for CountingHasher {
+            fn hash_pair(&mut self, left: &[u8], right: &[u8]) -> Node {
+                PAIRS_HASHED.fetch_add(1, Ordering::Relaxed);
+                self.0.hash_pair(left, right)
+            }
+        }
+
+        let depth = MAX_MERKLE_TREE_DEPTH - 1;
+        let root = CountingHasher::zero_hash(depth);
+        assert_eq!(root, Sha256Hasher::zero_hash(depth));
+        let pairs_hashed = PAIRS_HASHED.load(Ordering::Relaxed);
+        for depth in 0..MAX_MERKLE_TREE_DEPTH {
+            assert_eq!(CountingHasher::zero_hash(depth), Sha256Hasher::zero_hash(depth));
+        }
+        assert_eq!(PAIRS_HASHED.load(Ordering::Relaxed), pairs_hashed);
+    }
+
+    #[test]
+    fn test_hash_tree_root_with_hasher() {
+        let elements = vec![List::try_from(vec![1, 2, 3]).unwrap(), List::default()];
+        let list = List::<List<u16, 8>, 1024>::try_from(elements.clone()).unwrap();
+        let root = list.hash_tree_root_with::<TestHasher>().unwrap();
+        assert_ne!(root, list.hash_tree_root().unwrap());
+        assert_eq!(
+            list.hash_tree_root_with::<UncachedHasher>().unwrap(),
+            list.hash_tree_root().unwrap()
+        );
+
+        let tree_list = TreeList::<List<u16, 8>, 1024>::try_from(elements).unwrap();
+        assert_eq!(tree_list.hash_tree_root_with::<TestHasher>().unwrap(), root);
+        assert_eq!(tree_list.hash_tree_root().unwrap(), list.hash_tree_root().unwrap());
+
+        let mut cached = Cached::new(list.clone());
+        cached.commit().unwrap();
+        assert_eq!(cached.hash_tree_root_with::<TestHasher>().unwrap(), root);
+        assert_eq!(cached.hash_tree_root().unwrap(), list.hash_tree_root().unwrap());
+    }
+}
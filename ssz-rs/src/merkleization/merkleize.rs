@@ -1,29 +1,51 @@
 //! Support for computing Merkle trees.
 use crate::{
     lib::*,
-    merkleization::{MerkleizationError as Error, Node, BYTES_PER_CHUNK},
+    merkleization::{
+        is_default_hasher, MerkleHasher, MerkleizationError as Error, Node, Sha256Hasher,
+        BYTES_PER_CHUNK, MAX_MERKLE_TREE_DEPTH,
+    },
     ser::Serialize,
     GeneralizedIndex,
 };
-#[cfg(feature = "serde")]
-use alloy_primitives::hex::FromHex;
-use sha2::{Digest, Sha256};
 
-// The generalized index for the root of the "decorated" type in any Merkleized type that supports
-// decoration.
-const INNER_ROOT_GENERALIZED_INDEX: GeneralizedIndex = 2;
-// The generalized index for the "decoration" in any Merkleized type that supports decoration.
-const DECORATION_GENERALIZED_INDEX: GeneralizedIndex = 3;
+// The number of chunks from which the Merkle tree of the chunks is computed in parallel.
+#[cfg(feature = "rayon")]
+const PARALLEL_CHUNK_THRESHOLD: usize = 1 << 12;
+// The number of elements from which the roots of the elements are computed in parallel.
+#[cfg(feature = "rayon")]
+const PARALLEL_ELEMENT_THRESHOLD: usize = 1 << 8;
 
 /// Types that can provide the root of their corresponding Merkle tree following the SSZ spec.
 pub trait HashTreeRoot {
     /// Compute the "hash tree root" of `Self`.
     fn hash_tree_root(&self) -> Result<Node, Error>;
 
+    /// Compute the "hash tree root" of `Self` with the hasher `H`.
+    /// Default implementation supports only the default `Sha256Hasher`, returning
+    /// `hash_tree_root`. Types should override to support other hashers.
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, Error> {
+        if is_default_hasher::<H>() {
+            self.hash_tree_root()
+        } else {
+            Err(Error::UnsupportedHasher)
+        }
+    }
+
     /// Indicate the "composite" nature of `Self`.
     fn is_composite_type() -> bool {
         true
     }
+
+    /// Compute the roots of `elements` with the hasher `H` as the chunks of a sequence of
+    /// composite `Self`. Default implementation computes the roots one at a time; types that are
+    /// `Sync` override it to compute large numbers of roots in parallel with the `rayon` feature.
+    fn elements_to_chunks<H: MerkleHasher>(elements: &[Self]) -> Result<Vec<u8>, Error>
+    where
+        Self: Sized,
+    {
+        elements_to_chunks::<H, _>(elements)
+    }
 }
 
 // Ensures `buffer` can be exactly broken up into `BYTES_PER_CHUNK` chunks of bytes
@@ -52,38 +74,42 @@ where
     Ok(buffer)
 }
 
-fn hash_nodes(hasher: &mut Sha256, a: impl AsRef<[u8]>, b: impl AsRef<[u8]>, out: &mut [u8]) {
-    hasher.update(a);
-    hasher.update(b);
-    out.copy_from_slice(&hasher.finalize_reset());
+// Return the root of the Merkle tree formed from the chunks of `bytes`, padding any partial chunk
+// at the end with zeros. Avoids an intermediate copy when `bytes` is already chunk-aligned.
+pub(crate) fn merkleize_bytes<H: MerkleHasher>(
+    bytes: &[u8],
+    limit: Option<usize>,
+) -> Result<Node, Error> {
+    if bytes.len() % BYTES_PER_CHUNK == 0 {
+        return merkleize_with::<H>(bytes, limit)
+    }
+    let mut chunks = bytes.to_vec();
+    pack_bytes(&mut chunks);
+    merkleize_with::<H>(&chunks, limit)
 }
 
-const MAX_MERKLE_TREE_DEPTH: usize = 64;
-
-#[derive(Debug)]
-struct Context {
-    zero_hashes: [u8; MAX_MERKLE_TREE_DEPTH * BYTES_PER_CHUNK],
+fn hash_nodes<H: MerkleHasher>(
+    hasher: &mut H,
+    a: impl AsRef<[u8]>,
+    b: impl AsRef<[u8]>,
+    out: &mut [u8],
+) {
+    out.copy_from_slice(hasher.hash_pair(a.as_ref(), b.as_ref()).as_ref());
 }
 
-impl Index<usize> for Context {
-    type Output = [u8];
-
-    fn index(&self, index: usize) -> &Self::Output {
-        &self.zero_hashes[index * BYTES_PER_CHUNK..(index + 1) * BYTES_PER_CHUNK]
-    }
+// Return the root of a tree with `left` and `right` as children.
+pub(crate) fn hash_node_pair<H: MerkleHasher>(hasher: &mut H, left: &Node, right: &Node) -> Node {
+    hasher.hash_pair(left.as_ref(), right.as_ref())
 }
 
-// Grab the precomputed context from the build stage
-include!(concat!(env!("OUT_DIR"), "/context.rs"));
-
 /// Return the root of the root node of a binary tree formed from `chunks`.
 ///
 /// `chunks` forms the bottom layer of this tree.
 ///
-/// This implementation is memory efficient by relying on pre-computed subtrees of all
-/// "zero" leaves stored in the `CONTEXT`. SSZ specifies that `chunks` is padded to the next power
-/// of two and this can be quite large for some types. "Zero" subtrees are virtualized to avoid the
-/// memory and computation cost of large trees with partially empty leaves.
+/// This implementation is memory efficient by relying on the roots of subtrees of all
+/// "zero" leaves provided by `H::zero_hash`. SSZ specifies that `chunks` is padded to the next
+/// power of two and this can be quite large for some types. "Zero" subtrees are virtualized to
+/// avoid the memory and computation cost of large trees with partially empty leaves.
 ///
 /// The implementation approach treats `chunks` as the bottom layer of a perfect binary tree
 /// and for each height performs the hashing required to compute the parent layer in place.
@@ -93,7 +119,10 @@ include!(concat!(env!("OUT_DIR"), "/context.rs"));
 /// Invariant: `leaf_count.next_power_of_two() == leaf_count`
 /// Invariant: `leaf_count != 0`
 /// Invariant: `leaf_count.trailing_zeros() < MAX_MERKLE_TREE_DEPTH`
-fn merkleize_chunks_with_virtual_padding(chunks: &[u8], leaf_count: usize) -> Result<Node, Error> {
+fn merkleize_chunks_with_virtual_padding<H: MerkleHasher>(
+    chunks: &[u8],
+    leaf_count: usize,
+) -> Result<Node, Error> {
     debug_assert!(chunks.len() % BYTES_PER_CHUNK == 0);
     // NOTE: This also asserts that leaf_count != 0
     debug_assert!(leaf_count.next_power_of_two() == leaf_count);
@@ -106,15 +135,15 @@ fn merkleize_chunks_with_virtual_padding(chunks: &[u8], leaf_count: usize) -> Re
     if chunk_count == 0 {
         // SAFETY: checked subtraction is unnecessary, as height >= 1; qed
         let depth = height - 1;
-        // SAFETY: index is safe while depth == leaf_count.trailing_zeros() < MAX_MERKLE_TREE_DEPTH;
-        // qed
-        return Ok(CONTEXT[depth as usize].try_into().expect("can produce a single root chunk"))
+        // SAFETY: depth == leaf_count.trailing_zeros() < MAX_MERKLE_TREE_DEPTH; qed
+        return Ok(H::zero_hash(depth as usize))
     }
 
     let mut layer = chunks.to_vec();
     // SAFETY: checked subtraction is unnecessary, as we return early when chunk_count == 0; qed
     let mut last_index = chunk_count - 1;
-    let mut hasher = Sha256::new();
+    let mut zero_hash;
+    let mut hasher = H::default();
     // for each layer of the tree, starting from the bottom and walking up to the root:
     for k in (1..height).rev() {
         // for each pair of nodes in this layer:
@@ -159,11 +188,11 @@ fn merkleize_chunks_with_virtual_padding(chunks: &[u8], leaf_count: usize) -> Re
                     // so depth >= height - (height - 1) - 1
                     //           = 0; qed
                     let depth = height - k - 1;
-                    // SAFETY: index is safe because depth < CONTEXT.len():
+                    // SAFETY: depth < MAX_MERKLE_TREE_DEPTH:
                     // depth <= height - 1 == leaf_count.trailing_zeros()
-                    // leaf_count.trailing_zeros() < MAX_MERKLE_TREE_DEPTH == CONTEXT.len(); qed
-                    let right = &CONTEXT[depth as usize];
-                    (parent, left, right)
+                    // leaf_count.trailing_zeros() < MAX_MERKLE_TREE_DEPTH; qed
+                    zero_hash = H::zero_hash(depth as usize);
+                    (parent, left, zero_hash.as_slice())
                 }
                 _ => break,
             };
@@ -171,9 +200,8 @@ fn merkleize_chunks_with_virtual_padding(chunks: &[u8], leaf_count: usize) -> Re
                 // NOTE: nodes share memory here and so we can't use the `hash_nodes` utility
                 // as the disjunct nature is reflect in that functions type signature
                 // so instead we will just replicate here.
-                hasher.update(&left);
-                hasher.update(right);
-                left.copy_from_slice(&hasher.finalize_reset());
+                let node = hasher.hash_pair(left, right);
+                left.copy_from_slice(node.as_ref());
             } else {
                 // SAFETY: index is safe because parent.len() % BYTES_PER_CHUNK == 0 and
                 // parent isn't empty; qed
@@ -193,6 +221,12 @@ fn merkleize_chunks_with_virtual_padding(chunks: &[u8], leaf_count: usize) -> Re
 // Return the root of the Merklization of a binary tree formed from `chunks`.
 // Invariant: `chunks.len() % BYTES_PER_CHUNK == 0`
 pub fn merkleize(chunks: &[u8], limit: Option<usize>) -> Result<Node, Error> {
+    merkleize_with::<Sha256Hasher>(chunks, limit)
+}
+
+// Return the root of the Merklization of a binary tree formed from `chunks` with the hasher `H`.
+// Invariant: `chunks.len() % BYTES_PER_CHUNK == 0`
+pub fn merkleize_with<H: MerkleHasher>(chunks: &[u8], limit: Option<usize>) -> Result<Node, Error> {
     debug_assert!(chunks.len() % BYTES_PER_CHUNK == 0);
     let chunk_count = chunks.len() / BYTES_PER_CHUNK;
     let mut leaf_count = chunk_count.next_power_of_two();
@@ -202,127 +236,202 @@ pub fn merkleize(chunks: &[u8], limit: Option<usize>) -> Result<Node, Error> {
         }
         leaf_count = limit.next_power_of_two();
     }
-    merkleize_chunks_with_virtual_padding(chunks, leaf_count)
+    #[cfg(feature = "rayon")]
+    return merkleize_chunks_in_parallel::<H>(chunks, leaf_count);
+    #[cfg(not(feature = "rayon"))]
+    merkleize_chunks_with_virtual_padding::<H>(chunks, leaf_count)
+}
+
+// Return the root of the binary tree formed from `chunks` like
+// `merkleize_chunks_with_virtual_padding`, computing the roots of both halves of the tree in
+// parallel while there are enough chunks to be worth the overhead.
+// Invariant: the invariants of `merkleize_chunks_with_virtual_padding` hold
+#[cfg(feature = "rayon")]
+fn merkleize_chunks_in_parallel<H: MerkleHasher>(
+    chunks: &[u8],
+    leaf_count: usize,
+) -> Result<Node, Error> {
+    if chunks.len() < PARALLEL_CHUNK_THRESHOLD * BYTES_PER_CHUNK {
+        return merkleize_chunks_with_virtual_padding::<H>(chunks, leaf_count)
+    }
+    // NOTE: leaf_count >= chunk count >= PARALLEL_CHUNK_THRESHOLD, so the subtrees are not empty
+    let subtree_leaf_count = leaf_count / 2;
+    let (left, right) = chunks.split_at((subtree_leaf_count * BYTES_PER_CHUNK).min(chunks.len()));
+    let (left, right) = rayon::join(
+        || merkleize_chunks_in_parallel::<H>(left, subtree_leaf_count),
+        || merkleize_chunks_in_parallel::<H>(right, subtree_leaf_count),
+    );
+    Ok(hash_node_pair(&mut H::default(), &left?, &right?))
+}
+
+/// Return the root of the "progressive" Merkle tree formed from `chunks`, following EIP-7916.
+///
+/// The first `leaf_count` chunks are merkleized into a subtree forming the right child of the
+/// root, while the remaining chunks are merkleized progressively into the left child, with
+/// `4 * leaf_count` chunks in its right subtree, and so on. An empty set of `chunks` has a root
+/// of all zeros.
+///
+/// Invariant: `chunks.len() % BYTES_PER_CHUNK == 0`
+/// Invariant: `leaf_count.next_power_of_two() == leaf_count`
+pub fn merkleize_progressive(chunks: &[u8], leaf_count: usize) -> Result<Node, Error> {
+    merkleize_progressive_with::<Sha256Hasher>(chunks, leaf_count)
+}
+
+/// Return the root of the "progressive" Merkle tree formed from `chunks` like
+/// `merkleize_progressive`, with the hasher `H`.
+pub fn merkleize_progressive_with<H: MerkleHasher>(
+    chunks: &[u8],
+    leaf_count: usize,
+) -> Result<Node, Error> {
+    debug_assert!(chunks.len() % BYTES_PER_CHUNK == 0);
+    if chunks.is_empty() {
+        return Ok(Node::default())
+    }
+    let (subtree_chunks, remaining_chunks) =
+        chunks.split_at((leaf_count * BYTES_PER_CHUNK).min(chunks.len()));
+    let left = merkleize_progressive_with::<H>(remaining_chunks, leaf_count * 4)?;
+    let right = merkleize_with::<H>(subtree_chunks, Some(leaf_count))?;
+    Ok(hash_node_pair(&mut H::default(), &left, &right))
 }
 
-fn mix_in_decoration(root: Node, decoration: usize) -> Node {
-    let decoration_data = decoration.hash_tree_root().expect("can merkleize usize");
+fn mix_in_decoration<H: MerkleHasher>(root: Node, decoration: usize) -> Node {
+    let decoration_data = decoration.hash_tree_root_with::<H>().expect("can merkleize usize");
+    mix_in_aux::<H>(root, decoration_data)
+}
 
-    let mut hasher = Sha256::new();
-    let mut output = vec![0u8; BYTES_PER_CHUNK];
-    hash_nodes(&mut hasher, root, decoration_data, &mut output);
-    output.as_slice().try_into().expect("can extract root")
+// Return the root of a tree with `root` as the left child and some auxiliary node `aux` as the
+// right child.
+pub(crate) fn mix_in_aux<H: MerkleHasher>(root: Node, aux: Node) -> Node {
+    hash_node_pair(&mut H::default(), &root, &aux)
 }
 
-pub(crate) fn mix_in_length(root: Node, length: usize) -> Node {
-    mix_in_decoration(root, length)
+pub(crate) fn mix_in_length<H: MerkleHasher>(root: Node, length: usize) -> Node {
+    mix_in_decoration::<H>(root, length)
 }
 
 pub fn mix_in_selector(root: Node, selector: usize) -> Node {
-    mix_in_decoration(root, selector)
+    mix_in_selector_with::<Sha256Hasher>(root, selector)
+}
+
+pub fn mix_in_selector_with<H: MerkleHasher>(root: Node, selector: usize) -> Node {
+    mix_in_decoration::<H>(root, selector)
 }
 
-pub(crate) fn elements_to_chunks<'a, T: HashTreeRoot + 'a>(
-    elements: impl Iterator<Item = (usize, &'a T)>,
-    count: usize,
+// Return the roots of `elements` as a sequence of chunks.
+pub(crate) fn elements_to_chunks<H: MerkleHasher, T: HashTreeRoot>(
+    elements: &[T],
 ) -> Result<Vec<u8>, Error> {
-    let mut chunks = vec![0u8; count * BYTES_PER_CHUNK];
-    for (i, elem) in elements {
-        let chunk = elem.hash_tree_root()?;
-        let range = i * BYTES_PER_CHUNK..(i + 1) * BYTES_PER_CHUNK;
-        chunks[range].copy_from_slice(chunk.as_ref());
+    let mut chunks = vec![0u8; elements.len() * BYTES_PER_CHUNK];
+    for (chunk, elem) in chunks.chunks_exact_mut(BYTES_PER_CHUNK).zip(elements) {
+        chunk.copy_from_slice(elem.hash_tree_root_with::<H>()?.as_ref());
     }
     Ok(chunks)
 }
 
-pub struct Tree(Vec<u8>);
+// Return the roots of `elements` as a sequence of chunks like `elements_to_chunks`, computing the
+// roots in parallel when there are enough elements to be worth the overhead.
+#[cfg(feature = "rayon")]
+pub(crate) fn elements_to_chunks_in_parallel<H: MerkleHasher, T: HashTreeRoot + Sync>(
+    elements: &[T],
+) -> Result<Vec<u8>, Error> {
+    use rayon::prelude::*;
 
-impl Tree {
-    pub fn mix_in_decoration(
-        &mut self,
-        decoration: usize,
-        hasher: &mut Sha256,
-    ) -> Result<(), Error> {
-        let target_node = &mut self[DECORATION_GENERALIZED_INDEX];
-        let decoration_node = decoration.hash_tree_root()?;
-        target_node.copy_from_slice(decoration_node.as_ref());
-        hasher.update(&self[INNER_ROOT_GENERALIZED_INDEX]);
-        hasher.update(&self[DECORATION_GENERALIZED_INDEX]);
-        self[1].copy_from_slice(&hasher.finalize_reset());
-        Ok(())
+    if elements.len() < PARALLEL_ELEMENT_THRESHOLD {
+        return elements_to_chunks::<H, _>(elements)
     }
+    let mut chunks = vec![0u8; elements.len() * BYTES_PER_CHUNK];
+    chunks.par_chunks_mut(BYTES_PER_CHUNK).zip(elements).try_for_each(|(chunk, elem)| {
+        chunk.copy_from_slice(elem.hash_tree_root_with::<H>()?.as_ref());
+        Ok::<_, Error>(())
+    })?;
+    Ok(chunks)
+}
+
+// Elements whose roots are computed by `ParallelElementsToChunks` when the elements are `Sync` and
+// by `SequentialElementsToChunks` otherwise, as selected by method resolution on
+// `(&&Elements(elements)).elements_to_chunks::<H>()` in the derived `HashTreeRoot`.
+#[doc(hidden)]
+pub struct Elements<'a, T>(pub &'a [T]);
+
+#[doc(hidden)]
+pub trait ParallelElementsToChunks {
+    fn elements_to_chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, Error>;
+}
 
-    #[cfg(feature = "serde")]
-    fn nodes(&self) -> impl Iterator<Item = Node> + '_ {
-        self.0.chunks(BYTES_PER_CHUNK).map(|chunk| Node::from_hex(chunk).unwrap())
+impl<T: HashTreeRoot + Sync> ParallelElementsToChunks for &Elements<'_, T> {
+    fn elements_to_chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, Error> {
+        #[cfg(feature = "rayon")]
+        return elements_to_chunks_in_parallel::<H, _>(self.0);
+        #[cfg(not(feature = "rayon"))]
+        elements_to_chunks::<H, _>(self.0)
     }
 }
 
-impl Index<GeneralizedIndex> for Tree {
-    type Output = [u8];
+#[doc(hidden)]
+pub trait SequentialElementsToChunks {
+    fn elements_to_chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, Error>;
+}
 
-    fn index(&self, index: GeneralizedIndex) -> &Self::Output {
-        let start = (index - 1) * BYTES_PER_CHUNK;
-        let end = index * BYTES_PER_CHUNK;
-        &self.0[start..end]
+impl<T: HashTreeRoot> SequentialElementsToChunks for Elements<'_, T> {
+    fn elements_to_chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, Error> {
+        elements_to_chunks::<H, _>(self.0)
     }
 }
 
-impl IndexMut<GeneralizedIndex> for Tree {
-    fn index_mut(&mut self, index: GeneralizedIndex) -> &mut Self::Output {
-        let start = (index - 1) * BYTES_PER_CHUNK;
-        let end = index * BYTES_PER_CHUNK;
-        &mut self.0[start..end]
-    }
+// The Merkle tree of some chunks with only the populated nodes, i.e. those with at least one chunk
+// below them, materialized; any other node is the root of a tree of zero chunks.
+pub(crate) struct SparseTree<H: MerkleHasher> {
+    // NOTE: layers of populated nodes from the leaves to the root
+    layers: Vec<Vec<Node>>,
+    _hasher: PhantomData<H>,
 }
 
-#[cfg(feature = "serde")]
-impl std::fmt::Debug for Tree {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.debug_list().entries(self.nodes()).finish()
+impl<H: MerkleHasher> SparseTree<H> {
+    // Return the tree of the `chunks` padded to `leaf_count` leaves, computing only the
+    // populated nodes.
+    // Invariant: `chunks.len() % BYTES_PER_CHUNK == 0`
+    // Invariant: `leaf_count.next_power_of_two() == leaf_count`
+    // Invariant: `chunks.len() <= leaf_count * BYTES_PER_CHUNK`
+    pub(crate) fn new(hasher: &mut H, chunks: &[u8], leaf_count: usize) -> Self {
+        debug_assert!(chunks.len() % BYTES_PER_CHUNK == 0);
+        debug_assert!(leaf_count.next_power_of_two() == leaf_count);
+        debug_assert!(chunks.len() <= leaf_count * BYTES_PER_CHUNK);
+
+        let depth = leaf_count.trailing_zeros() as usize;
+        let mut layers = Vec::with_capacity(depth + 1);
+        layers.push(chunks.chunks_exact(BYTES_PER_CHUNK).map(Node::from_slice).collect::<Vec<_>>());
+        for height in 0..depth {
+            let zero_hash = H::zero_hash(height);
+            let layer = layers[height]
+                .chunks(2)
+                .map(|pair| hash_node_pair(hasher, &pair[0], pair.get(1).unwrap_or(&zero_hash)))
+                .collect();
+            layers.push(layer);
+        }
+        Self { layers, _hasher: PhantomData }
     }
-}
 
-// Return the full Merkle tree of the `chunks`.
-// Invariant: `chunks.len() % BYTES_PER_CHUNK == 0`
-// Invariant: `leaf_count.next_power_of_two() == leaf_count`
-// NOTE: naive implementation, can make much more efficient
-pub fn compute_merkle_tree(
-    hasher: &mut Sha256,
-    chunks: &[u8],
-    leaf_count: usize,
-) -> Result<Tree, Error> {
-    debug_assert!(chunks.len() % BYTES_PER_CHUNK == 0);
-    debug_assert!(leaf_count.next_power_of_two() == leaf_count);
+    // Replace the root with the root of a tree with the current root as the left child and
+    // `decoration` as the right child.
+    pub(crate) fn mix_in_decoration(&mut self, decoration: Node, hasher: &mut H) {
+        let data_root = self.root();
+        let root = hash_node_pair(hasher, &data_root, &decoration);
+        *self.layers.last_mut().expect("has a root layer") = vec![data_root, decoration];
+        self.layers.push(vec![root]);
+    }
+
+    pub(crate) fn root(&self) -> Node {
+        self.node(1)
+    }
 
-    // SAFETY: checked subtraction is unnecessary,
-    // as leaf_count != 0 (0.next_power_of_two() == 1); qed
-    let node_count = 2 * leaf_count - 1;
-    // SAFETY: checked subtraction is unnecessary, as node_count >= leaf_count; qed
-    let interior_count = node_count - leaf_count;
-    let leaf_start = interior_count * BYTES_PER_CHUNK;
-
-    let mut buffer = vec![0u8; node_count * BYTES_PER_CHUNK];
-    buffer[leaf_start..leaf_start + chunks.len()].copy_from_slice(chunks);
-
-    for i in (1..node_count).rev().step_by(2) {
-        // SAFETY: checked subtraction is unnecessary, as i >= 1; qed
-        let parent_index = (i - 1) / 2;
-        let focus = &mut buffer[parent_index * BYTES_PER_CHUNK..(i + 1) * BYTES_PER_CHUNK];
-        // SAFETY: checked subtraction is unnecessary:
-        // focus.len() = (i + 1 - parent_index) * BYTES_PER_CHUNK
-        //             = ((2*i + 2 - i + 1) / 2) * BYTES_PER_CHUNK
-        //             = ((i + 3) / 2) * BYTES_PER_CHUNK
-        // and
-        // i >= 1
-        // so focus.len() >= 2 * BYTES_PER_CHUNK; qed
-        let children_index = focus.len() - 2 * BYTES_PER_CHUNK;
-        // NOTE: children.len() == 2 * BYTES_PER_CHUNK
-        let (parent, children) = focus.split_at_mut(children_index);
-        let (left, right) = children.split_at(BYTES_PER_CHUNK);
-        hash_nodes(hasher, left, right, &mut parent[..BYTES_PER_CHUNK]);
-    }
-    Ok(Tree(buffer))
+    // Return the node at the generalized `index`, which must be within the tree.
+    pub(crate) fn node(&self, index: GeneralizedIndex) -> Node {
+        let depth = index.ilog2() as usize;
+        debug_assert!(depth < self.layers.len());
+        let height = self.layers.len() - 1 - depth;
+        let position = index - (1 << depth);
+        self.layers[height].get(position).copied().unwrap_or_else(|| H::zero_hash(height))
+    }
 }
 
 #[cfg(test)]
@@ -332,10 +441,8 @@ mod tests {
 
     // Return the root of the Merklization of a binary tree formed from `chunks`.
     fn merkleize_chunks(chunks: &[u8], leaf_count: usize) -> Result<Node, Error> {
-        let mut hasher = Sha256::new();
-        let tree = compute_merkle_tree(&mut hasher, chunks, leaf_count)?;
-        let root_index = default_generalized_index();
-        Ok(tree[root_index].try_into().expect("can produce a single root chunk"))
+        let mut hasher = Sha256Hasher::default();
+        Ok(SparseTree::new(&mut hasher, chunks, leaf_count).root())
     }
 
     #[test]
@@ -446,7 +553,8 @@ mod tests {
     #[test]
     fn test_merkleize_chunks() {
         let chunks = vec![1u8; 3 * BYTES_PER_CHUNK];
-        let root = merkleize_chunks_with_virtual_padding(&chunks, 4).expect("can merkleize");
+        let root = merkleize_chunks_with_virtual_padding::<Sha256Hasher>(&chunks, 4)
+            .expect("can merkleize");
         assert_eq!(
             root,
             decode_node_from_hex(
@@ -455,7 +563,8 @@ mod tests {
         );
 
         let chunks = vec![1u8; 5 * BYTES_PER_CHUNK];
-        let root = merkleize_chunks_with_virtual_padding(&chunks, 8).expect("can merkleize");
+        let root = merkleize_chunks_with_virtual_padding::<Sha256Hasher>(&chunks, 8)
+            .expect("can merkleize");
         assert_eq!(
             root,
             decode_node_from_hex(
@@ -464,7 +573,8 @@ mod tests {
         );
 
         let chunks = vec![1u8; 6 * BYTES_PER_CHUNK];
-        let root = merkleize_chunks_with_virtual_padding(&chunks, 8).expect("can merkleize");
+        let root = merkleize_chunks_with_virtual_padding::<Sha256Hasher>(&chunks, 8)
+            .expect("can merkleize");
         assert_eq!(
             root,
             decode_node_from_hex(
@@ -476,8 +586,8 @@ mod tests {
     #[test]
     fn test_merkleize_chunks_with_many_virtual_nodes() {
         let chunks = vec![1u8; 5 * BYTES_PER_CHUNK];
-        let root =
-            merkleize_chunks_with_virtual_padding(&chunks, 2usize.pow(10)).expect("can merkleize");
+        let root = merkleize_chunks_with_virtual_padding::<Sha256Hasher>(&chunks, 2usize.pow(10))
+            .expect("can merkleize");
         assert_eq!(
             root,
             decode_node_from_hex(
@@ -486,8 +596,8 @@ mod tests {
         );
 
         let chunks = vec![1u8; 70 * BYTES_PER_CHUNK];
-        let root =
-            merkleize_chunks_with_virtual_padding(&chunks, 2usize.pow(63)).expect("can merkleize");
+        let root = merkleize_chunks_with_virtual_padding::<Sha256Hasher>(&chunks, 2usize.pow(63))
+            .expect("can merkleize");
         assert_eq!(
             root,
             decode_node_from_hex(
@@ -496,6 +606,71 @@ mod tests {
         );
     }
 
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_merkleize_chunks_in_parallel() {
+        let chunks = (0..3 * PARALLEL_CHUNK_THRESHOLD + 5)
+            .flat_map(|i| (i as u64).hash_tree_root().unwrap())
+            .collect::<Vec<_>>();
+        for leaf_count in [4 * PARALLEL_CHUNK_THRESHOLD, 2usize.pow(40)] {
+            assert_eq!(
+                merkleize_chunks_in_parallel::<Sha256Hasher>(&chunks, leaf_count).unwrap(),
+                merkleize_chunks_with_virtual_padding::<Sha256Hasher>(&chunks, leaf_count).unwrap()
+            );
+        }
+
+        let elements = (0..PARALLEL_ELEMENT_THRESHOLD as u64 + 3)
+            .map(|i| List::<u64, 4>::try_from(vec![i; 3]).unwrap())
+            .collect::<Vec<_>>();
+        let expected =
+            elements.iter().flat_map(|elem| elem.hash_tree_root().unwrap()).collect::<Vec<_>>();
+        assert_eq!(elements_to_chunks_in_parallel::<Sha256Hasher, _>(&elements).unwrap(), expected);
+
+        let list = List::<List<u64, 4>, 1024>::try_from(elements.clone()).unwrap();
+        assert_eq!(list.hash_tree_root_in_parallel().unwrap(), list.hash_tree_root().unwrap());
+        let vector =
+            Vector::<List<u64, 4>, { PARALLEL_ELEMENT_THRESHOLD + 3 }>::try_from(elements).unwrap();
+        assert_eq!(vector.hash_tree_root_in_parallel().unwrap(), vector.hash_tree_root().unwrap());
+
+        #[derive(Debug, PartialEq, Eq, SimpleSerialize)]
+        struct Pair {
+            a: u64,
+            b: ByteList<4>,
+        }
+
+        let pairs = (0..PARALLEL_ELEMENT_THRESHOLD as u64 + 3)
+            .map(|i| Pair { a: i, b: ByteList::try_from(vec![i as u8; 3]).unwrap() })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            Pair::elements_to_chunks::<Sha256Hasher>(&pairs).unwrap(),
+            elements_to_chunks::<Sha256Hasher, _>(&pairs).unwrap()
+        );
+        let byte_lists = pairs.into_iter().map(|pair| pair.b).collect::<Vec<_>>();
+        assert_eq!(
+            ByteList::<4>::elements_to_chunks::<Sha256Hasher>(&byte_lists).unwrap(),
+            elements_to_chunks::<Sha256Hasher, _>(&byte_lists).unwrap()
+        );
+    }
+
+    // types that are not `Send` or `Sync` can still be merkleized with the `rayon` feature
+    #[test]
+    fn test_hash_tree_root_of_unsync_type() {
+        struct Unsync(PhantomData<*const u8>);
+
+        impl HashTreeRoot for Unsync {
+            fn hash_tree_root(&self) -> Result<Node, Error> {
+                Ok(Node::default())
+            }
+        }
+
+        assert_eq!(Unsync(PhantomData).hash_tree_root().unwrap(), Node::default());
+        let elements = [Unsync(PhantomData), Unsync(PhantomData)];
+        // NOTE: the borrows select the implementation like in the derived `HashTreeRoot`
+        #[allow(clippy::needless_borrow)]
+        let chunks = (&&Elements(&elements)).elements_to_chunks::<Sha256Hasher>().unwrap();
+        assert_eq!(chunks, vec![0u8; 2 * BYTES_PER_CHUNK]);
+    }
+
     #[test]
     fn test_hash_tree_root_of_list() {
         let a_list = List::<u16, 1024>::try_from(vec![
@@ -1,13 +1,17 @@
+pub mod diff;
 pub mod generalized_index;
+pub(crate) mod hasher;
 mod merkleize;
 pub mod multiproofs;
 mod node;
 pub mod proofs;
+pub mod tree;
 
 use crate::{lib::*, ser::SerializeError};
 pub use generalized_index::{
     get_power_of_two_ceil, GeneralizedIndex, GeneralizedIndexable, Path, PathElement,
 };
+pub use hasher::*;
 pub use merkleize::*;
 pub use node::*;
 
@@ -36,6 +40,9 @@ pub enum MerkleizationError {
     NoInnerElement,
     /// Attempt to turn an instance of a type in Merkle chunks when this is not supported
     NotChunkable,
+    /// Attempt to merkleize a type with a `MerkleHasher` other than `Sha256Hasher` when it only
+    /// implements merkleization with the default hasher
+    UnsupportedHasher,
 }
 
 impl From<SerializeError> for MerkleizationError {
@@ -63,6 +70,9 @@ impl Display for MerkleizationError {
             Self::NotChunkable => {
                 write!(f, "requested to compute chunks for a type which does not support this")
             }
+            Self::UnsupportedHasher => {
+                write!(f, "requested to merkleize a type with a hasher it does not support")
+            }
         }
     }
 }
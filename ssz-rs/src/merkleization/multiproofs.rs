@@ -1,12 +1,12 @@
-//! Experimental support for multiproofs.
+//! Support for constructing and verifying multiproofs.
 use crate::{
     lib::*,
     merkleization::{
         generalized_index::{get_bit, get_path_length, parent, sibling},
-        GeneralizedIndex, MerkleizationError as Error, Node,
+        hash_node_pair, GeneralizedIndex, MerkleHasher, MerkleizationError as Error, Node,
+        Sha256Hasher,
     },
 };
-use sha2::{Digest, Sha256};
 
 fn get_branch_indices(tree_index: GeneralizedIndex) -> Vec<GeneralizedIndex> {
     let mut focus = sibling(tree_index);
@@ -30,7 +30,9 @@ fn get_path_indices(tree_index: GeneralizedIndex) -> Vec<GeneralizedIndex> {
     result
 }
 
-fn get_helper_indices(indices: &[GeneralizedIndex]) -> Vec<GeneralizedIndex> {
+/// Return the generalized indices of the nodes needed to prove the nodes at `indices`, in the order
+/// they are expected in a multiproof.
+pub fn get_helper_indices(indices: &[GeneralizedIndex]) -> Vec<GeneralizedIndex> {
     let mut all_helper_indices = HashSet::new();
     let mut all_path_indices = HashSet::new();
 
@@ -45,10 +47,45 @@ fn get_helper_indices(indices: &[GeneralizedIndex]) -> Vec<GeneralizedIndex> {
     all_branch_indices
 }
 
+/// Convenience type for a Merkle multiproof and the root of the Merkle tree, which serves as
+/// "witness" that the proof is valid.
+pub type MultiproofAndWitness = (Multiproof, Node);
+
+/// Contains data necessary to verify the `leaves` were included under some witness "root" node
+/// at the generalized positions `indices`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Multiproof {
+    pub leaves: Vec<Node>,
+    /// The helper nodes, in the order given by `get_helper_indices`.
+    pub branch: Vec<Node>,
+    pub indices: Vec<GeneralizedIndex>,
+}
+
+impl Multiproof {
+    /// Verify `self` against the provided `root` witness node.
+    /// This `root` is the hash tree root of the SSZ object that produced the proof.
+    pub fn verify(&self, root: Node) -> Result<(), Error> {
+        self.verify_with::<Sha256Hasher>(root)
+    }
+
+    /// Verify `self` against the provided `root` witness node like `verify`, with the hasher `H`.
+    pub fn verify_with<H: MerkleHasher>(&self, root: Node) -> Result<(), Error> {
+        verify_merkle_multiproof_with::<H>(&self.leaves, &self.branch, &self.indices, root)
+    }
+}
+
 pub fn calculate_merkle_root(
     leaf: Node,
     proof: &[Node],
     index: GeneralizedIndex,
+) -> Result<Node, Error> {
+    calculate_merkle_root_with::<Sha256Hasher>(leaf, proof, index)
+}
+
+pub fn calculate_merkle_root_with<H: MerkleHasher>(
+    leaf: Node,
+    proof: &[Node],
+    index: GeneralizedIndex,
 ) -> Result<Node, Error> {
     let path_length = get_path_length(index)?;
     if path_length != proof.len() {
@@ -56,16 +93,13 @@ pub fn calculate_merkle_root(
     }
     let mut result = leaf;
 
-    let mut hasher = Sha256::new();
+    let mut hasher = H::default();
     for (i, next) in proof.iter().enumerate() {
-        if get_bit(index, i) {
-            hasher.update(next);
-            hasher.update(result);
+        result = if get_bit(index, i) {
+            hash_node_pair(&mut hasher, next, &result)
         } else {
-            hasher.update(result);
-            hasher.update(next);
-        }
-        result.copy_from_slice(&hasher.finalize_reset());
+            hash_node_pair(&mut hasher, &result, next)
+        };
     }
     Ok(result)
 }
@@ -76,7 +110,16 @@ pub fn verify_merkle_proof(
     index: GeneralizedIndex,
     root: Node,
 ) -> Result<(), Error> {
-    if calculate_merkle_root(leaf, proof, index)? == root {
+    verify_merkle_proof_with::<Sha256Hasher>(leaf, proof, index, root)
+}
+
+pub fn verify_merkle_proof_with<H: MerkleHasher>(
+    leaf: Node,
+    proof: &[Node],
+    index: GeneralizedIndex,
+    root: Node,
+) -> Result<(), Error> {
+    if calculate_merkle_root_with::<H>(leaf, proof, index)? == root {
         Ok(())
     } else {
         Err(Error::InvalidProof)
@@ -87,6 +130,14 @@ pub fn calculate_multi_merkle_root(
     leaves: &[Node],
     proof: &[Node],
     indices: &[GeneralizedIndex],
+) -> Result<Node, Error> {
+    calculate_multi_merkle_root_with::<Sha256Hasher>(leaves, proof, indices)
+}
+
+pub fn calculate_multi_merkle_root_with<H: MerkleHasher>(
+    leaves: &[Node],
+    proof: &[Node],
+    indices: &[GeneralizedIndex],
 ) -> Result<Node, Error> {
     if leaves.len() != indices.len() {
         return Err(Error::InvalidProof)
@@ -107,7 +158,7 @@ pub fn calculate_multi_merkle_root(
     let mut keys = objects.keys().cloned().collect::<Vec<_>>();
     keys.sort_by(|a, b| b.cmp(a));
 
-    let mut hasher = Sha256::new();
+    let mut hasher = H::default();
     let mut pos = 0;
     while pos < keys.len() {
         let key = keys.get(pos).unwrap();
@@ -121,11 +172,9 @@ pub fn calculate_multi_merkle_root(
             let left_index = sibling(right_index);
             let left_input = objects.get(&left_index).expect("contains index");
             let right_input = objects.get(&right_index).expect("contains index");
-            hasher.update(left_input);
-            hasher.update(right_input);
+            let parent = hash_node_pair(&mut hasher, left_input, right_input);
 
-            let parent = objects.entry(parent_index).or_default();
-            parent.copy_from_slice(&hasher.finalize_reset());
+            objects.insert(parent_index, parent);
             keys.push(parent_index);
         }
         pos += 1;
@@ -141,9 +190,116 @@ pub fn verify_merkle_multiproof(
     indices: &[GeneralizedIndex],
     root: Node,
 ) -> Result<(), Error> {
-    if calculate_multi_merkle_root(leaves, proof, indices)? == root {
+    verify_merkle_multiproof_with::<Sha256Hasher>(leaves, proof, indices, root)
+}
+
+pub fn verify_merkle_multiproof_with<H: MerkleHasher>(
+    leaves: &[Node],
+    proof: &[Node],
+    indices: &[GeneralizedIndex],
+    root: Node,
+) -> Result<(), Error> {
+    if calculate_multi_merkle_root_with::<H>(leaves, proof, indices)? == root {
         Ok(())
     } else {
         Err(Error::InvalidProof)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{merkleization::hasher::tests::TestHasher, prelude::*};
+
+    #[derive(PartialEq, Eq, Debug, Default, Clone, SimpleSerialize)]
+    struct Checkpoint {
+        epoch: u64,
+        root: Node,
+    }
+
+    #[derive(PartialEq, Eq, Debug, SimpleSerialize)]
+    enum Vote {
+        None,
+        Source(Checkpoint),
+        Targets(List<Checkpoint, 8>),
+    }
+
+    #[derive(PartialEq, Eq, Debug, SimpleSerialize)]
+    struct State {
+        slot: u64,
+        checkpoints: Vector<Checkpoint, 3>,
+        balances: List<u64, 1024>,
+        participation: Bitlist<32>,
+        justification: Bitvector<4>,
+        latest: Option<Checkpoint>,
+        vote: Vote,
+    }
+
+    fn checkpoint(epoch: u64) -> Checkpoint {
+        Checkpoint { epoch, root: Node::try_from([epoch as u8; 32].as_ref()).unwrap() }
+    }
+
+    fn state() -> State {
+        State {
+            slot: 33,
+            checkpoints: Vector::try_from(vec![checkpoint(1), checkpoint(2), checkpoint(3)])
+                .unwrap(),
+            balances: List::try_from((0..100).collect::<Vec<_>>()).unwrap(),
+            participation: Bitlist::try_from([true, false, true, true].as_ref()).unwrap(),
+            justification: Bitvector::try_from([false, true, true, false].as_ref()).unwrap(),
+            latest: Some(checkpoint(4)),
+            vote: Vote::Targets(List::try_from(vec![checkpoint(5), checkpoint(6)]).unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_multi_prove() {
+        let value = state();
+        let paths: &[&[PathElement]] = &[
+            &["slot".into()],
+            &["checkpoints".into(), 1.into(), "epoch".into()],
+            &["checkpoints".into(), 2.into(), "root".into()],
+            &["balances".into(), 57.into()],
+            &["balances".into(), PathElement::Length],
+            &["participation".into(), 3.into()],
+            &["justification".into(), 1.into()],
+            &["latest".into(), 1.into(), "root".into()],
+            &["vote".into(), 2.into(), 1.into(), "epoch".into()],
+            &["vote".into(), PathElement::Selector],
+        ];
+        let (proof, witness) = value.multi_prove(paths).unwrap();
+        assert_eq!(witness, value.hash_tree_root().unwrap());
+        assert_eq!(proof.branch.len(), get_helper_indices(&proof.indices).len());
+        assert!(proof.verify(witness).is_ok());
+        for (i, path) in paths.iter().enumerate() {
+            let (single_proof, _) = value.prove(path).unwrap();
+            assert_eq!(proof.indices[i], single_proof.index);
+            assert_eq!(proof.leaves[i], single_proof.leaf);
+        }
+        // NOTE: nodes shared by the paths are not repeated in the proof
+        let branch_len = paths.iter().map(|path| value.prove(path).unwrap().0.branch.len());
+        assert!(proof.branch.len() < branch_len.sum());
+
+        let mut invalid_proof = value.multi_prove(paths).unwrap().0;
+        invalid_proof.leaves[3] = Node::default();
+        assert!(invalid_proof.verify(witness).is_err());
+        assert!(value.multi_prove(&[&["missing".into()]]).is_err());
+
+        let (proof, witness) = value.multi_prove(&[&[]]).unwrap();
+        assert_eq!(proof.leaves, [witness]);
+        assert!(proof.branch.is_empty());
+        assert!(proof.verify(witness).is_ok());
+    }
+
+    #[test]
+    fn test_multi_prove_with_hasher() {
+        let value = state();
+        let paths: &[&[PathElement]] =
+            &[&["balances".into(), 3.into()], &["vote".into(), 2.into(), 0.into()]];
+        let tree = MerkleTree::<State, TestHasher>::new_with(&value).unwrap();
+        let (proof, witness) = tree.multi_prove(paths).unwrap();
+        assert_eq!(witness, value.hash_tree_root_with::<TestHasher>().unwrap());
+        assert!(proof.verify_with::<TestHasher>(witness).is_ok());
+        assert!(proof.verify(witness).is_err());
+    }
+}
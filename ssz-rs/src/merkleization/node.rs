@@ -5,7 +5,11 @@ pub type Node = alloy_primitives::B256;
 
 impl Serialize for Node {
     fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
-        buffer.extend_from_slice(self.as_slice());
+        self.serialize_to(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
+        sink.write_bytes(self.as_slice())?;
         Ok(Self::size_hint())
     }
 }
@@ -38,11 +42,19 @@ impl Serializable for Node {
     fn size_hint() -> usize {
         BYTES_PER_CHUNK
     }
+
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        Ok(Self::size_hint())
+    }
 }
 
 impl HashTreeRoot for Node {
     fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
-        let chunks = self.chunks()?;
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        let chunks = self.chunks::<H>()?;
         Ok(Node::try_from(chunks.as_slice()).expect("is right size"))
     }
 
@@ -54,11 +66,21 @@ impl HashTreeRoot for Node {
 impl GeneralizedIndexable for Node {}
 
 impl Prove for Node {
-    fn chunks(&self) -> Result<Vec<u8>, MerkleizationError> {
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
         Ok(self.to_vec())
     }
 }
 
+impl Viewable for Node {
+    type View<'a> = Self;
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        Self::deserialize(encoding)
+    }
+}
+
+impl Diff for Node {}
+
 impl SimpleSerialize for Node {}
 
 #[cfg(test)]
@@ -3,11 +3,13 @@ pub use crate::merkleization::generalized_index::log_2;
 use crate::{
     lib::*,
     merkleization::{
-        compute_merkle_tree, GeneralizedIndex, GeneralizedIndexable, MerkleizationError as Error,
-        Node, Path,
+        hash_node_pair, merkleize_progressive_with, merkleize_with, mix_in_length,
+        multiproofs::MultiproofAndWitness,
+        tree::{MerkleTree, Subtree},
+        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleHasher,
+        MerkleizationError as Error, Node, Path, Sha256Hasher, SparseTree, BYTES_PER_CHUNK,
     },
 };
-use sha2::{Digest, Sha256};
 
 /// Convenience type for a Merkle proof and the root of the Merkle tree, which serves as
 /// "witness" that the proof is valid.
@@ -42,15 +44,25 @@ pub(crate) fn compute_local_merkle_coordinates(
     Ok((depth, get_index(i, depth), i))
 }
 
-/// A type that knows how to compute Merkle proofs assuming a target type is `Prove`.
+/// A type that knows how to compute Merkle proofs assuming a target type is `Prove`, hashing with
+/// `H`.
 #[derive(Debug)]
-pub struct Prover {
-    hasher: Sha256,
+pub struct Prover<H: MerkleHasher = Sha256Hasher> {
+    hasher: H,
     proof: Proof,
     witness: Node,
 }
 
-impl Prover {
+impl<H: MerkleHasher> Prover<H> {
+    /// Create a `Prover` of the node at the generalized index `index`, hashing with `H`.
+    pub fn new(index: GeneralizedIndex) -> Self {
+        Self {
+            hasher: H::default(),
+            proof: Proof { leaf: Default::default(), branch: vec![], index },
+            witness: Default::default(),
+        }
+    }
+
     fn set_leaf(&mut self, leaf: &[u8]) {
         self.proof.leaf = leaf.try_into().expect("is correct size");
     }
@@ -67,10 +79,16 @@ impl Prover {
 
     /// Derive a Merkle proof relative to `data` given the parameters in `self`.
     pub fn compute_proof<T: Prove + ?Sized>(&mut self, data: &T) -> Result<(), Error> {
+        data.extend_proof(self)
+    }
+
+    // Derive a Merkle proof relative to `data`, whose chunks form a perfect binary tree with an
+    // optional decoration.
+    fn compute_binary_proof<T: Prove + ?Sized>(&mut self, data: &T) -> Result<(), Error> {
         let chunk_count = T::chunk_count();
         let mut leaf_count = chunk_count.next_power_of_two();
         let parent_index = self.proof.index;
-        let decoration = data.decoration();
+        let decoration = data.decoration_root::<H>()?;
         if decoration.is_some() {
             // double to account for decoration layer
             leaf_count *= 2;
@@ -93,59 +111,160 @@ impl Prover {
             // NOTE: leaf is within the current object, set a flag to grab from merkle tree later
             is_leaf_local = true;
         }
-        let chunks = data.chunks()?;
-        let mut tree = compute_merkle_tree(&mut self.hasher, &chunks, leaf_count)?;
+        let chunks = data.chunks::<H>()?;
+        // NOTE: only the populated part of the tree is computed, so proofs into types with a
+        // large capacity do not need memory proportional to it
+        let mut tree = SparseTree::new(&mut self.hasher, &chunks, chunk_count.next_power_of_two());
         if let Some(decoration) = decoration {
-            tree.mix_in_decoration(decoration, &mut self.hasher)?;
+            tree.mix_in_decoration(decoration, &mut self.hasher);
         }
 
         if is_leaf_local {
-            self.set_leaf(&tree[parent_index]);
+            self.set_leaf(tree.node(parent_index).as_ref());
         }
 
         let mut target = local_generalized_index;
         for _ in 0..local_depth {
-            let sibling = if target % 2 != 0 { &tree[target - 1] } else { &tree[target + 1] };
-            self.extend_branch(sibling);
+            self.extend_branch(tree.node(target ^ 1).as_ref());
             target /= 2;
         }
 
-        let root = &tree[1];
-        self.set_witness(root);
+        self.set_witness(tree.root().as_ref());
 
         Ok(())
     }
+
+    // Derive a Merkle proof relative to `data`, whose chunks form a progressive Merkle tree (see
+    // `merkleize_progressive`) with the `length` of `data` mixed in.
+    pub(crate) fn compute_progressive_proof<T: Prove + ?Sized>(
+        &mut self,
+        data: &T,
+        length: usize,
+    ) -> Result<(), Error> {
+        let target = self.proof.index;
+        let target_depth = get_depth(target)?;
+        let chunks = data.chunks::<H>()?;
+        let chunk_count = chunks.len() / BYTES_PER_CHUNK;
+
+        let data_root = merkleize_progressive_with::<H>(&chunks, 1)?;
+        let length_root = length.hash_tree_root_with::<H>()?;
+        let root = mix_in_length::<H>(data_root, length);
+        self.set_witness(root.as_ref());
+
+        match target {
+            1 => {
+                self.set_leaf(root.as_ref());
+                return Ok(())
+            }
+            3 => {
+                self.set_leaf(length_root.as_ref());
+                self.extend_branch(data_root.as_ref());
+                return Ok(())
+            }
+            // NOTE: the target must be under the data root
+            _ if target >> (target_depth - 1) != 2 => return Err(Error::InvalidGeneralizedIndex),
+            _ => {}
+        }
+
+        // NOTE: collect the siblings along the progressive "spine" from the top of the tree down,
+        // so they can be added to the proof in the reverse order
+        let mut spine_branch = vec![length_root];
+        // NOTE: start from the data root with generalized index `2`
+        let mut depth = 1;
+        let mut offset = 0;
+        let mut subtree_leaf_count = 1;
+        loop {
+            let remaining_chunks = &chunks[(offset * BYTES_PER_CHUNK).min(chunks.len())..];
+            if depth == target_depth {
+                self.set_leaf(
+                    merkleize_progressive_with::<H>(remaining_chunks, subtree_leaf_count)?.as_ref(),
+                );
+                break
+            }
+            if offset >= chunk_count {
+                // NOTE: an empty progressive tree is a single zero node without any children
+                return Err(Error::InvalidInnerIndex)
+            }
+            let (subtree_chunks, next_chunks) = remaining_chunks
+                .split_at((subtree_leaf_count * BYTES_PER_CHUNK).min(remaining_chunks.len()));
+            let is_left = (target >> (target_depth - depth - 1)) & 1 == 0;
+            depth += 1;
+            if is_left {
+                spine_branch.push(merkleize_with::<H>(subtree_chunks, Some(subtree_leaf_count))?);
+                offset += subtree_leaf_count;
+                subtree_leaf_count *= 4;
+                continue
+            }
+
+            spine_branch
+                .push(merkleize_progressive_with::<H>(next_chunks, subtree_leaf_count * 4)?);
+            // NOTE: the subtree is a perfect binary tree, with the target at or below its root
+            let subtree_depth = subtree_leaf_count.trailing_zeros();
+            let local_depth = (target_depth - depth).min(subtree_depth);
+            let local_index =
+                get_index(target >> (target_depth - depth - local_depth), local_depth);
+            let local_generalized_index = 2usize.pow(local_depth) + local_index;
+            if depth + local_depth < target_depth {
+                // NOTE: need to recurse to children to find ultimate leaf
+                let child_depth = target_depth - depth - local_depth;
+                let child_index = 2usize.pow(child_depth) + get_index(target, child_depth);
+                self.proof.index = child_index;
+                data.prove_element(offset + local_index, self)?;
+                self.proof.index = target;
+            }
+            let tree = SparseTree::new(&mut self.hasher, subtree_chunks, subtree_leaf_count);
+            if depth + local_depth == target_depth {
+                self.set_leaf(tree.node(local_generalized_index).as_ref());
+            }
+            let mut node = local_generalized_index;
+            for _ in 0..local_depth {
+                self.extend_branch(tree.node(node ^ 1).as_ref());
+                node /= 2;
+            }
+            break
+        }
+        for node in spine_branch.iter().rev() {
+            self.extend_branch(node.as_ref());
+        }
+        // NOTE: restore witness in case it was overwritten when recursing into an element
+        self.set_witness(root.as_ref());
+        Ok(())
+    }
 }
 
-impl From<Prover> for ProofAndWitness {
-    fn from(value: Prover) -> Self {
+impl<H: MerkleHasher> From<Prover<H>> for ProofAndWitness {
+    fn from(value: Prover<H>) -> Self {
         (value.proof, value.witness)
     }
 }
 
 impl From<GeneralizedIndex> for Prover {
     fn from(index: GeneralizedIndex) -> Self {
-        Self {
-            hasher: Sha256::new(),
-            proof: Proof { leaf: Default::default(), branch: vec![], index },
-            witness: Default::default(),
-        }
+        Self::new(index)
     }
 }
 
 /// Required functionality to support computing Merkle proofs.
+///
+/// Each method is generic over the `MerkleHasher` `H` of the Merkle tree, which is the default
+/// `Sha256Hasher` unless stated otherwise, e.g. `value.chunks::<Sha256Hasher>()`.
 pub trait Prove: GeneralizedIndexable {
-    /// Compute the "chunks" of this type as required for the SSZ merkle tree computation.
+    /// Compute the "chunks" of this type as required for the SSZ merkle tree computation, with the
+    /// hasher `H`.
     /// Default implementation signals an error. Implementing types should override
     /// to provide the correct behavior.
-    fn chunks(&self) -> Result<Vec<u8>, Error> {
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, Error> {
         Err(Error::NotChunkable)
     }
 
     /// Construct a proof of the member element located at the type-specific `index` assuming the
     /// context in `prover`.
     #[allow(unused)]
-    fn prove_element(&self, index: usize, prover: &mut Prover) -> Result<(), Error> {
+    fn prove_element<H: MerkleHasher>(
+        &self,
+        index: usize,
+        prover: &mut Prover<H>,
+    ) -> Result<(), Error> {
         Err(Error::NoInnerElement)
     }
 
@@ -157,6 +276,31 @@ pub trait Prove: GeneralizedIndexable {
         None
     }
 
+    /// Returns the root of the "decoration" if this type has any in the Merkle tree, with the
+    /// hasher `H`.
+    /// Default implementation merkleizes the `decoration`. Types decorated with something other
+    /// than a `usize`, like the active fields of a stable container, should override.
+    fn decoration_root<H: MerkleHasher>(&self) -> Result<Option<Node>, Error> {
+        self.decoration().map(|decoration| decoration.hash_tree_root_with::<H>()).transpose()
+    }
+
+    /// Extend the proof in `prover` with the path from the generalized index it tracks to the root
+    /// of the Merkle tree of `self`.
+    /// Default implementation for types whose chunks form a perfect binary tree, possibly with a
+    /// decoration. Types with a Merkle tree of another shape should override.
+    fn extend_proof<H: MerkleHasher>(&self, prover: &mut Prover<H>) -> Result<(), Error> {
+        prover.compute_binary_proof(self)
+    }
+
+    /// Compute the Merkle tree of `self` with the hasher `H`, e.g. to build a `MerkleTree`.
+    /// Default implementation builds the tree of the chunks of `self` without any nodes below
+    /// them, as for basic types. Types with composite elements should override to include the trees
+    /// of the elements, as should types with a Merkle tree of another shape.
+    fn subtree<H: MerkleHasher>(&self) -> Result<Subtree<H>, Error> {
+        let chunks = self.chunks::<H>()?;
+        Ok(Subtree::from_chunks(&chunks, Self::chunk_count(), self.decoration_root::<H>()?))
+    }
+
     /// Compute a Merkle proof of `Self` at the type's `path`, along with the root of the Merkle
     /// tree as a witness value.
     fn prove(&self, path: Path) -> Result<ProofAndWitness, Error> {
@@ -165,6 +309,17 @@ pub trait Prove: GeneralizedIndexable {
         prover.compute_proof(self)?;
         Ok(prover.into())
     }
+
+    /// Compute a Merkle multiproof of `Self` at the type's `paths`, along with the root of the
+    /// Merkle tree as a witness value.
+    /// The Merkle tree of `self` is computed once, so the nodes shared by the `paths` are only
+    /// hashed once.
+    fn multi_prove(&self, paths: &[Path]) -> Result<MultiproofAndWitness, Error>
+    where
+        Self: Sized,
+    {
+        MerkleTree::new(self)?.multi_prove(paths)
+    }
 }
 
 /// Contains data necessary to verify `leaf` was included under some witness "root" node
@@ -181,7 +336,17 @@ impl Proof {
     /// This `root` is the hash tree root of the SSZ object that produced the proof.
     /// See `Prover` for further information.
     pub fn verify(&self, root: Node) -> Result<(), Error> {
-        is_valid_merkle_branch_for_generalized_index(self.leaf, &self.branch, self.index, root)
+        self.verify_with::<Sha256Hasher>(root)
+    }
+
+    /// Verify `self` against the provided `root` witness node like `verify`, with the hasher `H`.
+    pub fn verify_with<H: MerkleHasher>(&self, root: Node) -> Result<(), Error> {
+        is_valid_merkle_branch_for_generalized_index_with::<H>(
+            self.leaf,
+            &self.branch,
+            self.index,
+            root,
+        )
     }
 }
 
@@ -192,10 +357,26 @@ pub fn is_valid_merkle_branch_for_generalized_index(
     branch: &[Node],
     generalized_index: GeneralizedIndex,
     root: Node,
+) -> Result<(), Error> {
+    is_valid_merkle_branch_for_generalized_index_with::<Sha256Hasher>(
+        leaf,
+        branch,
+        generalized_index,
+        root,
+    )
+}
+
+/// Verifies the Merkle proof like `is_valid_merkle_branch_for_generalized_index`, with the hasher
+/// `H`.
+pub fn is_valid_merkle_branch_for_generalized_index_with<H: MerkleHasher>(
+    leaf: Node,
+    branch: &[Node],
+    generalized_index: GeneralizedIndex,
+    root: Node,
 ) -> Result<(), Error> {
     let depth = log_2(generalized_index).ok_or(Error::InvalidGeneralizedIndex)? as usize;
     let index = get_subtree_index(generalized_index)?;
-    is_valid_merkle_branch(leaf, branch, depth, index, root)
+    is_valid_merkle_branch_with::<H>(leaf, branch, depth, index, root)
 }
 
 /// `is_valid_merkle_branch` verifies the Merkle proof against the `root` given the other metadata.
@@ -205,23 +386,32 @@ pub fn is_valid_merkle_branch(
     depth: usize,
     index: usize,
     root: Node,
+) -> Result<(), Error> {
+    is_valid_merkle_branch_with::<Sha256Hasher>(leaf, branch, depth, index, root)
+}
+
+/// `is_valid_merkle_branch_with` verifies the Merkle proof like `is_valid_merkle_branch`, with the
+/// hasher `H`.
+pub fn is_valid_merkle_branch_with<H: MerkleHasher>(
+    leaf: Node,
+    branch: &[Node],
+    depth: usize,
+    index: usize,
+    root: Node,
 ) -> Result<(), Error> {
     if branch.len() != depth {
         return Err(Error::InvalidProof)
     }
 
     let mut derived_root = leaf;
-    let mut hasher = Sha256::new();
+    let mut hasher = H::default();
 
     for (i, node) in branch.iter().enumerate() {
-        if (index / 2usize.pow(i as u32)) % 2 != 0 {
-            hasher.update(node);
-            hasher.update(derived_root);
+        derived_root = if (index / 2usize.pow(i as u32)) % 2 != 0 {
+            hash_node_pair(&mut hasher, node, &derived_root)
         } else {
-            hasher.update(derived_root);
-            hasher.update(node);
-        }
-        derived_root.copy_from_slice(&hasher.finalize_reset());
+            hash_node_pair(&mut hasher, &derived_root, node)
+        };
     }
 
     if derived_root == root {
@@ -234,7 +424,10 @@ pub fn is_valid_merkle_branch(
 #[cfg(test)]
 pub(crate) mod tests {
     use super::*;
-    use crate::prelude::*;
+    use crate::{
+        merkleization::{hasher::tests::TestHasher, merkleize},
+        prelude::*,
+    };
     use alloy_primitives::hex::FromHex;
 
     pub(crate) fn decode_node_from_hex(hex: &str) -> Node {
@@ -323,6 +516,43 @@ pub(crate) mod tests {
         println!("Verified proof in {:?}", start_verify.elapsed());
     }
 
+    #[test]
+    fn test_sparse_proving() {
+        const LIMIT: usize = 1 << 40;
+
+        #[derive(PartialEq, Eq, Debug, Default, Clone, SimpleSerialize)]
+        struct Validator {
+            balance: u64,
+            history: List<u64, 1048576>,
+        }
+
+        let validator =
+            Validator { balance: 32, history: List::try_from(vec![1, 2, 3, 4, 5]).unwrap() };
+        let data =
+            List::<Validator, LIMIT>::try_from(vec![validator.clone(), Validator::default()])
+                .unwrap();
+        for path in [
+            &[PathElement::Length][..],
+            &[0.into()],
+            &[1.into(), "balance".into()],
+            &[0.into(), "history".into(), 4.into()],
+            &[0.into(), "history".into(), 1000.into()],
+            &[1.into(), "history".into(), PathElement::Length],
+        ] {
+            compute_and_verify_proof_for_path(&data, path);
+        }
+
+        let empty = List::<Validator, LIMIT>::default();
+        compute_and_verify_proof_for_path(&empty, &[PathElement::Length]);
+
+        let index = Validator::generalized_index(&["history".into(), 3.into()]).unwrap();
+        let mut prover = Prover::<TestHasher>::new(index);
+        prover.compute_proof(&validator).unwrap();
+        let (proof, witness): ProofAndWitness = prover.into();
+        assert_eq!(witness, validator.hash_tree_root_with::<TestHasher>().unwrap());
+        assert!(proof.verify_with::<TestHasher>(witness).is_ok());
+    }
+
     #[test]
     fn test_proving_primitives_fails_with_bad_path() {
         let data = 8u8;
@@ -334,6 +564,75 @@ pub(crate) mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_prove_with_hasher() {
+        #[derive(PartialEq, Eq, Debug, Default, SimpleSerialize)]
+        struct Foo {
+            a: u64,
+            b: List<List<u16, 8>, 32>,
+            c: Option<Vector<u8, 4>>,
+        }
+
+        let data = Foo {
+            a: 7,
+            b: List::try_from(vec![List::try_from(vec![1, 2, 3]).unwrap(), List::default()])
+                .unwrap(),
+            c: Some(Vector::try_from(vec![4, 5, 6, 7]).unwrap()),
+        };
+        for path in [&["b".into(), 0.into(), 2.into()][..], &["c".into(), 1.into()]] {
+            let index = Foo::generalized_index(path).unwrap();
+            let mut prover = Prover::<TestHasher>::new(index);
+            prover.compute_proof(&data).unwrap();
+            let (proof, witness): ProofAndWitness = prover.into();
+            assert_eq!(witness, data.hash_tree_root_with::<TestHasher>().unwrap());
+            assert!(proof.verify_with::<TestHasher>(witness).is_ok());
+            assert!(proof.verify(witness).is_err());
+        }
+    }
+
+    // types implementing `hash_tree_root` only with the default hasher can still be proven with
+    // any hasher from their chunks
+    #[test]
+    fn test_prove_with_default_hash_tree_root_only() {
+        struct Pair(u64, u64);
+
+        impl HashTreeRoot for Pair {
+            fn hash_tree_root(&self) -> Result<Node, Error> {
+                merkleize(&self.chunks::<Sha256Hasher>()?, None)
+            }
+        }
+
+        impl GeneralizedIndexable for Pair {
+            fn chunk_count() -> usize {
+                2
+            }
+        }
+
+        impl Prove for Pair {
+            fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, Error> {
+                let mut chunks = vec![0u8; 2 * BYTES_PER_CHUNK];
+                chunks[..8].copy_from_slice(&self.0.to_le_bytes());
+                chunks[BYTES_PER_CHUNK..BYTES_PER_CHUNK + 8].copy_from_slice(&self.1.to_le_bytes());
+                Ok(chunks)
+            }
+        }
+
+        let pair = Pair(1, 2);
+        let mut prover = Prover::from(3);
+        prover.compute_proof(&pair).unwrap();
+        let (proof, witness): ProofAndWitness = prover.into();
+        assert_eq!(witness, pair.hash_tree_root().unwrap());
+        assert_eq!(proof.leaf, 2u64.hash_tree_root().unwrap());
+        assert!(proof.verify(witness).is_ok());
+
+        let mut prover = Prover::<TestHasher>::new(3);
+        prover.compute_proof(&pair).unwrap();
+        let (proof, witness): ProofAndWitness = prover.into();
+        assert!(proof.verify_with::<TestHasher>(witness).is_ok());
+        assert!(proof.verify(witness).is_err());
+        assert!(matches!(pair.hash_tree_root_with::<TestHasher>(), Err(Error::UnsupportedHasher)));
+    }
+
     #[test]
     fn test_prove_primitives() {
         let data = 8u8;
@@ -0,0 +1,502 @@
+//! Support for Merkle trees of values which are computed once and then queried and updated.
+use crate::{
+    lib::*,
+    merkleization::{
+        generalized_index::log_2,
+        hash_node_pair,
+        multiproofs::{get_helper_indices, Multiproof, MultiproofAndWitness},
+        proofs::{Proof, ProofAndWitness, Prove},
+        GeneralizedIndex, MerkleHasher, MerkleizationError as Error, Node, Path, Sha256Hasher,
+        BYTES_PER_CHUNK,
+    },
+    SimpleSerialize,
+};
+
+// A node of a Merkle tree along with the nodes below it that are materialized.
+#[derive(Debug, Clone)]
+enum TreeNode {
+    // The root of a tree of zero chunks with the given number of layers below it.
+    Zero(usize),
+    // A node without any materialized children, e.g. a chunk.
+    Leaf(Node),
+    Branch(Box<Branch>),
+}
+
+#[derive(Debug, Clone)]
+struct Branch {
+    root: Node,
+    left: TreeNode,
+    right: TreeNode,
+}
+
+impl TreeNode {
+    fn root<H: MerkleHasher>(&self) -> Node {
+        match self {
+            Self::Zero(depth) => H::zero_hash(*depth),
+            Self::Leaf(node) => *node,
+            Self::Branch(branch) => branch.root,
+        }
+    }
+
+    // Returns the parent of `left` and `right`.
+    fn branch<H: MerkleHasher>(hasher: &mut H, left: Self, right: Self) -> Self {
+        match (&left, &right) {
+            (Self::Zero(left), Self::Zero(right)) if left == right => Self::Zero(left + 1),
+            _ => {
+                let root = hash_node_pair(hasher, &left.root::<H>(), &right.root::<H>());
+                Self::Branch(Box::new(Branch { root, left, right }))
+            }
+        }
+    }
+
+    // Returns the root of the perfect binary tree with `depth` layers below the root and the
+    // `nodes` as the first leaves, padded with zero chunks.
+    fn merkleize<H: MerkleHasher>(hasher: &mut H, nodes: Vec<Self>, depth: usize) -> Self {
+        let mut layer = nodes;
+        for height in 0..depth {
+            let mut nodes = layer.into_iter();
+            let mut parents = Vec::with_capacity(nodes.len().div_ceil(2));
+            while let Some(left) = nodes.next() {
+                let right = nodes.next().unwrap_or(Self::Zero(height));
+                parents.push(Self::branch(hasher, left, right));
+            }
+            layer = parents;
+        }
+        layer.pop().unwrap_or(Self::Zero(depth))
+    }
+
+    // Returns the root of the "progressive" Merkle tree of the `nodes`, following
+    // `merkleize_progressive`.
+    fn merkleize_progressive<H: MerkleHasher>(
+        hasher: &mut H,
+        mut nodes: Vec<Self>,
+        leaf_count: usize,
+    ) -> Self {
+        if nodes.is_empty() {
+            return Self::Zero(0)
+        }
+        let remaining_nodes = nodes.split_off(leaf_count.min(nodes.len()));
+        let left = Self::merkleize_progressive(hasher, remaining_nodes, leaf_count * 4);
+        let right = Self::merkleize(hasher, nodes, leaf_count.trailing_zeros() as usize);
+        Self::branch(hasher, left, right)
+    }
+
+    // Walks from `self` to the node at the generalized `index` relative to `self`, passing the
+    // sibling of each node along the way to `visit` from the top of the tree, and returns the
+    // node at `index`.
+    fn walk<H: MerkleHasher>(
+        &self,
+        index: GeneralizedIndex,
+        mut visit: impl FnMut(Node),
+    ) -> Result<Node, Error> {
+        let depth = log_2(index).ok_or(Error::InvalidGeneralizedIndex)? as usize;
+        let mut node = self;
+        for remaining in (0..depth).rev() {
+            match node {
+                Self::Branch(branch) => {
+                    let (next, sibling) = if (index >> remaining) & 1 == 0 {
+                        (&branch.left, &branch.right)
+                    } else {
+                        (&branch.right, &branch.left)
+                    };
+                    visit(sibling.root::<H>());
+                    node = next;
+                }
+                // NOTE: every node below a zero subtree is the root of a smaller zero subtree
+                Self::Zero(zero_depth) if *zero_depth > remaining => {
+                    let target_depth = zero_depth - remaining - 1;
+                    for height in (target_depth..*zero_depth).rev() {
+                        visit(H::zero_hash(height));
+                    }
+                    return Ok(H::zero_hash(target_depth))
+                }
+                _ => return Err(Error::InvalidGeneralizedIndex),
+            }
+        }
+        Ok(node.root::<H>())
+    }
+
+    // Replace the node at the generalized `index` relative to `self`, with `depth` layers above
+    // it, with `node` and recompute the roots above it.
+    // Invariant: `index` is a valid generalized index for `self`
+    fn replace<H: MerkleHasher>(
+        &mut self,
+        index: GeneralizedIndex,
+        depth: usize,
+        node: Self,
+        hasher: &mut H,
+    ) {
+        if depth == 0 {
+            *self = node;
+            return
+        }
+        let (mut left, mut right) = match mem::replace(self, Self::Zero(0)) {
+            Self::Branch(branch) => (branch.left, branch.right),
+            Self::Zero(zero_depth) => (Self::Zero(zero_depth - 1), Self::Zero(zero_depth - 1)),
+            Self::Leaf(..) => unreachable!("validated `index` to be within the tree"),
+        };
+        if (index >> (depth - 1)) & 1 == 0 {
+            left.replace(index, depth - 1, node, hasher);
+        } else {
+            right.replace(index, depth - 1, node, hasher);
+        }
+        *self = Self::branch(hasher, left, right);
+    }
+}
+
+/// The nodes of the Merkle tree of a value, hashed with `H`, from which a `MerkleTree` is built.
+///
+/// See `Prove::subtree`.
+#[derive(Debug)]
+pub struct Subtree<H: MerkleHasher = Sha256Hasher> {
+    node: TreeNode,
+    _hasher: PhantomData<H>,
+}
+
+impl<H: MerkleHasher> Clone for Subtree<H> {
+    fn clone(&self) -> Self {
+        Self::new(self.node.clone())
+    }
+}
+
+/// A single zero chunk, e.g. for an absent element.
+impl<H: MerkleHasher> Default for Subtree<H> {
+    fn default() -> Self {
+        Self::new(TreeNode::Zero(0))
+    }
+}
+
+impl<H: MerkleHasher> Subtree<H> {
+    fn new(node: TreeNode) -> Self {
+        Self { node, _hasher: PhantomData }
+    }
+
+    fn leaves(chunks: &[u8]) -> Vec<Self> {
+        debug_assert!(chunks.len() % BYTES_PER_CHUNK == 0);
+        chunks
+            .chunks_exact(BYTES_PER_CHUNK)
+            .map(|chunk| Self::new(TreeNode::Leaf(Node::from_slice(chunk))))
+            .collect()
+    }
+
+    /// Build the tree of a type with `chunk_count` chunks from its `chunks`, without any nodes
+    /// below them, with the `decoration` mixed in if any.
+    ///
+    /// Invariant: `chunks.len() % BYTES_PER_CHUNK == 0`
+    pub fn from_chunks(chunks: &[u8], chunk_count: usize, decoration: Option<Node>) -> Self {
+        Self::from_elements(Self::leaves(chunks), chunk_count, decoration)
+    }
+
+    /// Build the tree of a type with `chunk_count` chunks from the trees of its `elements`, one
+    /// per chunk, with the `decoration` mixed in if any.
+    pub fn from_elements(
+        elements: Vec<Self>,
+        chunk_count: usize,
+        decoration: Option<Node>,
+    ) -> Self {
+        let mut hasher = H::default();
+        let nodes = elements.into_iter().map(|element| element.node).collect();
+        let depth = chunk_count.next_power_of_two().trailing_zeros() as usize;
+        let node = TreeNode::merkleize(&mut hasher, nodes, depth);
+        Self::new(node).mix_in_decoration(&mut hasher, decoration)
+    }
+
+    // Returns the trees of the `elements` of `value` if they are composite, or else the chunks of
+    // `value` which pack them.
+    fn sequence_elements<'a, S, T>(
+        value: &S,
+        elements: impl Iterator<Item = &'a T>,
+    ) -> Result<Vec<Self>, Error>
+    where
+        S: Prove + ?Sized,
+        T: SimpleSerialize + 'a,
+    {
+        if T::is_composite_type() {
+            elements.map(|element| element.subtree::<H>()).collect()
+        } else {
+            Ok(Self::leaves(&value.chunks::<H>()?))
+        }
+    }
+
+    // Build the tree of `value`, a sequence of the `elements`.
+    pub(crate) fn from_sequence<'a, S, T>(
+        value: &S,
+        elements: impl Iterator<Item = &'a T>,
+    ) -> Result<Self, Error>
+    where
+        S: Prove + ?Sized,
+        T: SimpleSerialize + 'a,
+    {
+        let elements = Self::sequence_elements(value, elements)?;
+        Ok(Self::from_elements(elements, S::chunk_count(), value.decoration_root::<H>()?))
+    }
+
+    // Build the tree of `value`, a sequence of the `elements` forming a progressive Merkle tree
+    // with a decoration.
+    pub(crate) fn from_progressive_sequence<'a, S, T>(
+        value: &S,
+        elements: impl Iterator<Item = &'a T>,
+    ) -> Result<Self, Error>
+    where
+        S: Prove + ?Sized,
+        T: SimpleSerialize + 'a,
+    {
+        let nodes =
+            Self::sequence_elements(value, elements)?.into_iter().map(|element| element.node);
+        let mut hasher = H::default();
+        let node = TreeNode::merkleize_progressive(&mut hasher, nodes.collect(), 1);
+        Ok(Self::new(node).mix_in_decoration(&mut hasher, value.decoration_root::<H>()?))
+    }
+
+    fn mix_in_decoration(self, hasher: &mut H, decoration: Option<Node>) -> Self {
+        match decoration {
+            Some(decoration) => {
+                Self::new(TreeNode::branch(hasher, self.node, TreeNode::Leaf(decoration)))
+            }
+            None => self,
+        }
+    }
+
+    /// Returns the root of the tree.
+    pub fn root(&self) -> Node {
+        self.node.root::<H>()
+    }
+}
+
+/// The Merkle tree of a value of type `T`, hashed with `H`.
+///
+/// The nodes are computed once when the tree is built, so that any number of nodes can be looked
+/// up and proven without hashing, and updating a node only rehashes the nodes above it.
+#[derive(Debug)]
+pub struct MerkleTree<T: Prove, H: MerkleHasher = Sha256Hasher> {
+    subtree: Subtree<H>,
+    _type: PhantomData<T>,
+}
+
+impl<T: Prove, H: MerkleHasher> Clone for MerkleTree<T, H> {
+    fn clone(&self) -> Self {
+        Self { subtree: self.subtree.clone(), _type: PhantomData }
+    }
+}
+
+impl<T: Prove> MerkleTree<T> {
+    /// Build the Merkle tree of `value` with the default `Sha256Hasher`.
+    pub fn new(value: &T) -> Result<Self, Error> {
+        Self::new_with(value)
+    }
+}
+
+impl<T: Prove, H: MerkleHasher> MerkleTree<T, H> {
+    /// Build the Merkle tree of `value` with the hasher `H`.
+    pub fn new_with(value: &T) -> Result<Self, Error> {
+        Ok(Self { subtree: value.subtree::<H>()?, _type: PhantomData })
+    }
+
+    /// Returns the root of the tree, i.e. the hash tree root of the value.
+    pub fn root(&self) -> Node {
+        self.subtree.root()
+    }
+
+    /// Returns the node at the generalized `index`.
+    pub fn node(&self, index: GeneralizedIndex) -> Result<Node, Error> {
+        self.subtree.node.walk::<H>(index, |_| {})
+    }
+
+    /// Returns the node at the type's `path`.
+    pub fn node_at_path(&self, path: Path) -> Result<Node, Error> {
+        self.node(T::generalized_index(path)?)
+    }
+
+    /// Compute a Merkle proof of the node at the type's `path`, along with the root of the tree as
+    /// a witness value.
+    pub fn prove(&self, path: Path) -> Result<ProofAndWitness, Error> {
+        self.prove_generalized_index(T::generalized_index(path)?)
+    }
+
+    /// Compute a Merkle proof of the node at the generalized `index`, along with the root of the
+    /// tree as a witness value.
+    pub fn prove_generalized_index(
+        &self,
+        index: GeneralizedIndex,
+    ) -> Result<ProofAndWitness, Error> {
+        let mut branch = vec![];
+        let leaf = self.subtree.node.walk::<H>(index, |sibling| branch.push(sibling))?;
+        branch.reverse();
+        Ok((Proof { leaf, branch, index }, self.root()))
+    }
+
+    /// Compute a Merkle multiproof of the nodes at the type's `paths`, along with the root of the
+    /// tree as a witness value.
+    pub fn multi_prove(&self, paths: &[Path]) -> Result<MultiproofAndWitness, Error> {
+        let indices =
+            paths.iter().map(|path| T::generalized_index(path)).collect::<Result<Vec<_>, _>>()?;
+        self.multi_prove_generalized_indices(&indices)
+    }
+
+    /// Compute a Merkle multiproof of the nodes at the generalized `indices`, along with the root
+    /// of the tree as a witness value.
+    pub fn multi_prove_generalized_indices(
+        &self,
+        indices: &[GeneralizedIndex],
+    ) -> Result<MultiproofAndWitness, Error> {
+        let leaves = indices.iter().map(|index| self.node(*index)).collect::<Result<_, _>>()?;
+        let branch = get_helper_indices(indices)
+            .into_iter()
+            .map(|index| self.node(index))
+            .collect::<Result<_, _>>()?;
+        let proof = Multiproof { leaves, branch, indices: indices.to_vec() };
+        Ok((proof, self.root()))
+    }
+
+    /// Replace the node at the generalized `index`, along with any nodes below it, with `node`.
+    pub fn set_node(&mut self, index: GeneralizedIndex, node: Node) -> Result<(), Error> {
+        self.set_subtree(index, Subtree::new(TreeNode::Leaf(node)))
+    }
+
+    /// Replace the subtree at the generalized `index` with `subtree`, e.g. the tree of a new value
+    /// of the element at `index`.
+    pub fn set_subtree(
+        &mut self,
+        index: GeneralizedIndex,
+        subtree: Subtree<H>,
+    ) -> Result<(), Error> {
+        let depth = log_2(index).ok_or(Error::InvalidGeneralizedIndex)? as usize;
+        // NOTE: validate `index` before modifying the tree
+        self.node(index)?;
+        self.subtree.node.replace(index, depth, subtree.node, &mut H::default());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{merkleization::hasher::tests::TestHasher, prelude::*};
+
+    #[derive(PartialEq, Eq, Debug, Default, Clone, SimpleSerialize)]
+    struct Validator {
+        balance: u64,
+        history: List<u16, 1024>,
+        keys: Vector<Vector<u8, 48>, 2>,
+    }
+
+    #[derive(PartialEq, Eq, Debug, Default, Clone, SimpleSerialize)]
+    struct State {
+        slot: u64,
+        validators: List<Validator, 1099511627776>,
+        flags: Bitlist<64>,
+        recent: ProgressiveList<Validator>,
+        latest: Option<Validator>,
+    }
+
+    fn validator(i: u8) -> Validator {
+        Validator {
+            balance: i as u64 * 32,
+            history: List::try_from(vec![i as u16; i as usize]).unwrap(),
+            keys: Vector::try_from(vec![Vector::try_from(vec![i; 48]).unwrap(); 2]).unwrap(),
+        }
+    }
+
+    fn state() -> State {
+        State {
+            slot: 12,
+            validators: List::try_from((0..5).map(validator).collect::<Vec<_>>()).unwrap(),
+            flags: Bitlist::try_from([true, false, true].as_ref()).unwrap(),
+            recent: ProgressiveList::from((0..7).map(validator).collect::<Vec<_>>()),
+            latest: Some(validator(3)),
+        }
+    }
+
+    fn paths() -> Vec<Vec<PathElement>> {
+        vec![
+            vec!["slot".into()],
+            vec!["validators".into()],
+            vec!["validators".into(), PathElement::Length],
+            vec!["validators".into(), 3.into()],
+            vec!["validators".into(), 3.into(), "balance".into()],
+            vec!["validators".into(), 4.into(), "history".into(), 3.into()],
+            vec!["validators".into(), 1.into(), "keys".into(), 1.into(), 47.into()],
+            vec!["validators".into(), 1000.into()],
+            vec!["flags".into(), 2.into()],
+            vec!["recent".into(), 6.into(), "history".into(), 5.into()],
+            vec!["recent".into(), PathElement::Length],
+            vec!["latest".into(), 1.into(), "balance".into()],
+        ]
+    }
+
+    #[test]
+    fn test_merkle_tree() {
+        let value = state();
+        let tree = MerkleTree::new(&value).unwrap();
+        assert_eq!(tree.root(), value.hash_tree_root().unwrap());
+
+        for path in paths() {
+            let (proof, witness) = tree.prove(&path).unwrap();
+            assert_eq!(witness, tree.root());
+            assert_eq!(tree.node_at_path(&path).unwrap(), proof.leaf);
+            assert!(proof.verify(witness).is_ok());
+            assert_eq!((proof, witness), value.prove(&path).unwrap());
+        }
+        assert_eq!(
+            tree.node_at_path(&["validators".into(), 2.into()]).unwrap(),
+            validator(2).hash_tree_root().unwrap()
+        );
+        assert_eq!(tree.node(1).unwrap(), tree.root());
+        // NOTE: elements beyond the length of a list are zero chunks without any nodes below them
+        assert!(tree.prove(&["validators".into(), 1000.into(), "balance".into()]).is_err());
+        assert!(tree.node(0).is_err());
+        // NOTE: there are no nodes below the chunks of basic values
+        let index = State::generalized_index(&["slot".into()]).unwrap();
+        assert!(tree.node(2 * index).is_err());
+    }
+
+    #[test]
+    fn test_merkle_tree_updates() {
+        let mut value = state();
+        let mut tree = MerkleTree::new(&value).unwrap();
+        let snapshot = tree.clone();
+
+        value.slot = 13;
+        let index = State::generalized_index(&["slot".into()]).unwrap();
+        tree.set_node(index, 13u64.hash_tree_root().unwrap()).unwrap();
+        assert_eq!(tree.root(), value.hash_tree_root().unwrap());
+
+        value.validators[2] = validator(9);
+        let index = State::generalized_index(&["validators".into(), 2.into()]).unwrap();
+        tree.set_subtree(index, validator(9).subtree().unwrap()).unwrap();
+        assert_eq!(tree.root(), value.hash_tree_root().unwrap());
+        compute_proofs_match(&tree, &value);
+
+        // NOTE: elements can be added to the zero subtrees beyond the length of a list
+        value.validators.push(validator(5));
+        let index = State::generalized_index(&["validators".into(), 5.into()]).unwrap();
+        tree.set_subtree(index, validator(5).subtree().unwrap()).unwrap();
+        let index = State::generalized_index(&["validators".into(), PathElement::Length]).unwrap();
+        tree.set_node(index, 6usize.hash_tree_root().unwrap()).unwrap();
+        assert_eq!(tree.root(), value.hash_tree_root().unwrap());
+        compute_proofs_match(&tree, &value);
+
+        assert_eq!(snapshot.root(), state().hash_tree_root().unwrap());
+        assert!(tree.set_node(0, Node::default()).is_err());
+        let index = State::generalized_index(&["slot".into()]).unwrap();
+        assert!(tree.set_node(2 * index, Node::default()).is_err());
+        assert_eq!(tree.root(), value.hash_tree_root().unwrap());
+    }
+
+    fn compute_proofs_match(tree: &MerkleTree<State>, value: &State) {
+        for path in paths() {
+            assert_eq!(tree.prove(&path).unwrap(), value.prove(&path).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_merkle_tree_with_hasher() {
+        let value = state();
+        let tree = MerkleTree::<State, TestHasher>::new_with(&value).unwrap();
+        assert_eq!(tree.root(), value.hash_tree_root_with::<TestHasher>().unwrap());
+        for path in paths() {
+            let (proof, witness) = tree.prove(&path).unwrap();
+            assert!(proof.verify_with::<TestHasher>(witness).is_ok());
+        }
+    }
+}
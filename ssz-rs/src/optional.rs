@@ -0,0 +1,355 @@
+use crate::{
+    de::{DecodeContext, Deserialize, DeserializeError},
+    lib::*,
+    merkleization::{
+        diff::{Diff, Differ},
+        mix_in_length,
+        proofs::{Prove, Prover},
+        tree::Subtree,
+        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleHasher, MerkleizationError,
+        Node, Path, PathElement, Sha256Hasher,
+    },
+    ser::{Serialize, SerializeError, Sink},
+    view::Viewable,
+    List, Serializable, SimpleSerialize,
+};
+
+/// An optional value following EIP-6475.
+///
+/// An `Optional` is encoded as the empty string if there is no value and as the encoding of the
+/// value otherwise, and is merkleized as a `List` with a capacity of one element. This differs from
+/// `Option`, which represents `Union[None, T]` with a leading selector byte; containers can use
+/// either type for a field depending on the schema.
+///
+/// NOTE: the value must not have an empty encoding, as it would be decoded as no value.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct Optional<T: Serializable> {
+    data: Option<T>,
+}
+
+impl<T: Serializable> Optional<T> {
+    /// Returns the underlying `Option`.
+    pub fn into_inner(self) -> Option<T> {
+        self.data
+    }
+}
+
+impl<T> fmt::Debug for Optional<T>
+where
+    T: Serializable + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if f.alternate() {
+            write!(f, "Optional<{}>{:#?}", any::type_name::<T>(), self.data)
+        } else {
+            write!(f, "Optional<{}>{:?}", any::type_name::<T>(), self.data)
+        }
+    }
+}
+
+impl<T> Default for Optional<T>
+where
+    T: Serializable,
+{
+    fn default() -> Self {
+        Self { data: None }
+    }
+}
+
+impl<T> From<Option<T>> for Optional<T>
+where
+    T: Serializable,
+{
+    fn from(data: Option<T>) -> Self {
+        Self { data }
+    }
+}
+
+impl<T> From<Optional<T>> for Option<T>
+where
+    T: Serializable,
+{
+    fn from(value: Optional<T>) -> Self {
+        value.data
+    }
+}
+
+impl<T> Deref for Optional<T>
+where
+    T: Serializable,
+{
+    type Target = Option<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl<T> DerefMut for Optional<T>
+where
+    T: Serializable,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+impl<T> Serializable for Optional<T>
+where
+    T: Serializable,
+{
+    fn is_variable_size() -> bool {
+        true
+    }
+
+    fn size_hint() -> usize {
+        0
+    }
+
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        self.data.as_ref().map_or(Ok(0), Serializable::encoded_len)
+    }
+
+    fn max_encoded_len() -> usize {
+        T::max_encoded_len()
+    }
+
+    fn bytes_at_path<'a>(encoding: &'a [u8], path: Path) -> Result<&'a [u8], DeserializeError> {
+        let Some((next, rest)) = path.split_first() else { return Ok(encoding) };
+        match next {
+            PathElement::Index(0) if !encoding.is_empty() => T::bytes_at_path(encoding, rest)
+                .map_err(|err| err.in_element(PathElement::Index(0), 0)),
+            _ => Err(DeserializeError::InvalidPath(path.to_vec())),
+        }
+    }
+}
+
+impl<T> Serialize for Optional<T>
+where
+    T: Serializable,
+{
+    fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
+        self.serialize_to(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
+        match &self.data {
+            Some(data) => {
+                data.serialize_to(sink).map_err(|err| err.in_element(PathElement::Index(0)))
+            }
+            None => Ok(0),
+        }
+    }
+}
+
+impl<T> Deserialize for Optional<T>
+where
+    T: Serializable,
+{
+    fn deserialize(encoding: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_with(encoding, &mut DecodeContext::default())
+    }
+
+    fn deserialize_with(
+        encoding: &[u8],
+        context: &mut DecodeContext,
+    ) -> Result<Self, DeserializeError> {
+        if encoding.is_empty() {
+            return Ok(Self { data: None })
+        }
+        let data = context
+            .nested(|context| T::deserialize_with(encoding, context))
+            .map_err(|err| err.in_element(PathElement::Index(0), 0))?;
+        Ok(Self { data: Some(data) })
+    }
+}
+
+impl<T> HashTreeRoot for Optional<T>
+where
+    T: SimpleSerialize,
+{
+    fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        // NOTE: the single chunk of a `List` with a capacity of one element is either the packed
+        // value, which is the root of a basic type, or the root of a composite type
+        let root = match &self.data {
+            Some(data) => data.hash_tree_root_with::<H>()?,
+            None => Node::default(),
+        };
+        Ok(mix_in_length::<H>(root, self.data.is_some() as usize))
+    }
+}
+
+impl<T> GeneralizedIndexable for Optional<T>
+where
+    T: SimpleSerialize,
+{
+    fn chunk_count() -> usize {
+        1
+    }
+
+    fn compute_generalized_index(
+        parent: GeneralizedIndex,
+        path: Path,
+    ) -> Result<GeneralizedIndex, MerkleizationError> {
+        List::<T, 1>::compute_generalized_index(parent, path)
+    }
+}
+
+impl<T> Prove for Optional<T>
+where
+    T: SimpleSerialize,
+{
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
+        match &self.data {
+            Some(data) => Ok(data.hash_tree_root_with::<H>()?.to_vec()),
+            None => Ok(vec![]),
+        }
+    }
+
+    fn prove_element<H: MerkleHasher>(
+        &self,
+        index: usize,
+        prover: &mut Prover<H>,
+    ) -> Result<(), MerkleizationError> {
+        match &self.data {
+            Some(data) if index == 0 => prover.compute_proof(data),
+            _ => Err(MerkleizationError::InvalidInnerIndex),
+        }
+    }
+
+    fn subtree<H: MerkleHasher>(&self) -> Result<Subtree<H>, MerkleizationError> {
+        let elements = match &self.data {
+            Some(data) => vec![data.subtree::<H>()?],
+            None => vec![],
+        };
+        Ok(Subtree::from_elements(elements, Self::chunk_count(), self.decoration_root::<H>()?))
+    }
+
+    fn decoration(&self) -> Option<usize> {
+        Some(self.data.is_some() as usize)
+    }
+}
+
+impl<T> Viewable for Optional<T>
+where
+    T: Viewable,
+{
+    type View<'a> = Option<T::View<'a>>;
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        if encoding.is_empty() {
+            return Ok(None)
+        }
+        T::view(encoding).map(Some)
+    }
+}
+
+impl<T> Diff for Optional<T>
+where
+    T: SimpleSerialize + Diff,
+{
+    fn diff_elements(&self, other: &Self, differ: &mut Differ) -> Result<(), MerkleizationError> {
+        match (&self.data, &other.data) {
+            (Some(left), Some(right)) => {
+                differ.compare_element::<Self, T>(PathElement::Index(0), left, right)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<T> SimpleSerialize for Optional<T> where T: SimpleSerialize {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{merkleization::proofs::tests::compute_and_verify_proof_for_path, prelude::*};
+
+    #[derive(PartialEq, Eq, Debug, Default, Clone, SimpleSerialize)]
+    struct Foo {
+        a: Option<u16>,
+        b: Optional<u16>,
+        c: Optional<List<u8, 4>>,
+    }
+
+    #[test]
+    fn test_serialization() {
+        let value = Optional::from(Some(0x1234u16));
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(encoding, [0x34, 0x12]);
+        assert_eq!(value.encoded_len().unwrap(), 2);
+        assert_eq!(Optional::<u16>::deserialize(&encoding).unwrap(), value);
+
+        let value = Optional::<u16>::default();
+        assert!(serialize(&value).unwrap().is_empty());
+        assert_eq!(Optional::<u16>::deserialize(&[]).unwrap(), value);
+        assert!(Optional::<u16>::deserialize(&[1]).is_err());
+        assert!(Optional::<u16>::deserialize(&[1, 2, 3]).is_err());
+
+        // unlike `Option`, there is no selector
+        let value = Foo {
+            a: Some(1),
+            b: Some(2).into(),
+            c: Some(List::try_from(vec![3u8, 4]).unwrap()).into(),
+        };
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(encoding, [12, 0, 0, 0, 15, 0, 0, 0, 17, 0, 0, 0, 1, 1, 0, 2, 0, 3, 4]);
+        assert_eq!(Foo::deserialize(&encoding).unwrap(), value);
+        assert_eq!(
+            Foo::decode_at_path::<u8>(&encoding, &["c".into(), 0.into(), 1.into()]).unwrap(),
+            4
+        );
+
+        let value = Foo { a: None, ..Default::default() };
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(encoding, [12, 0, 0, 0, 13, 0, 0, 0, 13, 0, 0, 0, 0]);
+        assert_eq!(Foo::deserialize(&encoding).unwrap(), value);
+        assert!(Foo::bytes_at_path(&encoding, &["b".into(), 0.into()]).is_err());
+    }
+
+    #[test]
+    fn test_merkleization_matches_list() {
+        let value = Optional::from(Some(0x1234u16));
+        let list = List::<u16, 1>::try_from(vec![0x1234]).unwrap();
+        assert_eq!(value.hash_tree_root().unwrap(), list.hash_tree_root().unwrap());
+
+        let value = Optional::<u16>::default();
+        let list = List::<u16, 1>::default();
+        assert_eq!(value.hash_tree_root().unwrap(), list.hash_tree_root().unwrap());
+
+        let inner = List::<u8, 4>::try_from(vec![3u8, 4]).unwrap();
+        let value = Optional::from(Some(inner.clone()));
+        let list = List::<List<u8, 4>, 1>::try_from(vec![inner]).unwrap();
+        assert_eq!(value.hash_tree_root().unwrap(), list.hash_tree_root().unwrap());
+
+        for path in [vec![PathElement::Length], vec![0.into()], vec![0.into(), 1.into()]] {
+            assert_eq!(
+                Optional::<List<u8, 4>>::generalized_index(&path).unwrap(),
+                List::<List<u8, 4>, 1>::generalized_index(&path).unwrap()
+            );
+        }
+        assert!(Optional::<u16>::generalized_index(&[1.into()]).is_err());
+    }
+
+    #[test]
+    fn test_prove() {
+        let value = Foo {
+            a: Some(1),
+            b: Some(2).into(),
+            c: Some(List::try_from(vec![3u8, 4]).unwrap()).into(),
+        };
+        compute_and_verify_proof_for_path(&value, &["b".into(), 0.into()]);
+        compute_and_verify_proof_for_path(&value, &["b".into(), PathElement::Length]);
+        compute_and_verify_proof_for_path(&value, &["c".into(), 0.into(), 1.into()]);
+
+        let value = Foo::default();
+        compute_and_verify_proof_for_path(&value, &["b".into(), PathElement::Length]);
+        compute_and_verify_proof_for_path(&value, &["b".into(), 0.into()]);
+        assert!(value.prove(&["c".into(), 0.into(), 1.into()]).is_err());
+    }
+}
@@ -0,0 +1,412 @@
+//! A persistent Merkle tree over the elements of a homogeneous sequence, backing `TreeList` and
+//! `TreeVector`.
+use crate::{
+    lib::*,
+    merkleization::{
+        diff::{diff_packed_chunks, Diff, Differ},
+        hash_node_pair, is_default_hasher, merkleize_with, pack, GeneralizedIndexable,
+        MerkleHasher, MerkleizationError, Node, PathElement, Sha256Hasher, BYTES_PER_CHUNK,
+    },
+    SimpleSerialize, BITS_PER_BYTE,
+};
+
+// A chunk of the tree along with the elements it commits to: a single element of a composite
+// type or the elements of a basic type packed into the chunk.
+struct Leaf<T> {
+    root: Node,
+    elements: Vec<T>,
+}
+
+struct Branch<T> {
+    root: Node,
+    left: Subtree<T>,
+    right: Subtree<T>,
+}
+
+// Subtrees are shared between clones of a tree and copied on write. Roots are computed with the
+// default `Sha256Hasher`.
+// Invariant: a subtree is `Zero` if and only if it has no elements.
+enum Subtree<T> {
+    Zero,
+    Leaf(Arc<Leaf<T>>),
+    Branch(Arc<Branch<T>>),
+}
+
+impl<T> Clone for Subtree<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Zero => Self::Zero,
+            Self::Leaf(leaf) => Self::Leaf(leaf.clone()),
+            Self::Branch(branch) => Self::Branch(branch.clone()),
+        }
+    }
+}
+
+impl<T> Subtree<T> {
+    // Returns the root of `self`, with `depth` layers below the root.
+    fn root(&self, depth: usize) -> Node {
+        match self {
+            Self::Zero => Sha256Hasher::zero_hash(depth),
+            Self::Leaf(leaf) => leaf.root,
+            Self::Branch(branch) => branch.root,
+        }
+    }
+
+    // Returns the parent of `left` and `right`, with `depth` layers below their roots.
+    fn branch(hasher: &mut Sha256Hasher, left: Self, right: Self, depth: usize) -> Self {
+        if matches!((&left, &right), (Self::Zero, Self::Zero)) {
+            return Self::Zero
+        }
+        let root = hash_node_pair(hasher, &left.root(depth), &right.root(depth));
+        Self::Branch(Arc::new(Branch { root, left, right }))
+    }
+
+    // Replace the leaf at `chunk` of `self`, with `depth` layers below the root, with `leaf`.
+    fn replace(&mut self, chunk: usize, depth: usize, leaf: Self, hasher: &mut Sha256Hasher) {
+        if depth == 0 {
+            *self = leaf;
+            return
+        }
+        let (mut left, mut right) = match mem::replace(self, Self::Zero) {
+            Self::Zero => (Self::Zero, Self::Zero),
+            // NOTE: reuse the branch in place unless it is shared
+            Self::Branch(branch) => match Arc::try_unwrap(branch) {
+                Ok(branch) => (branch.left, branch.right),
+                Err(branch) => (branch.left.clone(), branch.right.clone()),
+            },
+            Self::Leaf(..) => unreachable!("leaves are at the bottom of the tree"),
+        };
+        if (chunk >> (depth - 1)) & 1 == 0 {
+            left.replace(chunk, depth - 1, leaf, hasher);
+        } else {
+            right.replace(chunk, depth - 1, leaf, hasher);
+        }
+        *self = Self::branch(hasher, left, right, depth - 1);
+    }
+
+    fn extend_leaf_roots(&self, chunks: &mut Vec<u8>) {
+        match self {
+            Self::Zero => {}
+            Self::Leaf(leaf) => chunks.extend_from_slice(leaf.root.as_ref()),
+            Self::Branch(branch) => {
+                branch.left.extend_leaf_roots(chunks);
+                branch.right.extend_leaf_roots(chunks);
+            }
+        }
+    }
+}
+
+// Returns the number of elements committed to by each leaf of a tree of elements of type `T`.
+fn elements_per_leaf<T: SimpleSerialize>() -> usize {
+    if T::is_composite_type() {
+        1
+    } else {
+        BYTES_PER_CHUNK / T::size_hint()
+    }
+}
+
+fn leaf_root<T: SimpleSerialize>(elements: &[T]) -> Result<Node, MerkleizationError> {
+    if T::is_composite_type() {
+        elements[0].hash_tree_root()
+    } else {
+        Ok(Node::from_slice(&pack(elements)?))
+    }
+}
+
+fn leaf<T: SimpleSerialize>(elements: Vec<T>) -> Result<Subtree<T>, MerkleizationError> {
+    if elements.is_empty() {
+        return Ok(Subtree::Zero)
+    }
+    let root = leaf_root(&elements)?;
+    Ok(Subtree::Leaf(Arc::new(Leaf { root, elements })))
+}
+
+// A Merkle tree committing to a sequence of elements in its leaves, where each node stores its
+// root so that updates only rehash the path from the updated leaf to the root.
+pub(crate) struct PersistentTree<T> {
+    root: Subtree<T>,
+    depth: usize,
+    len: usize,
+}
+
+impl<T> Clone for PersistentTree<T> {
+    fn clone(&self) -> Self {
+        Self { root: self.root.clone(), depth: self.depth, len: self.len }
+    }
+}
+
+impl<T: SimpleSerialize> PersistentTree<T> {
+    // Build a tree from `elements` with enough leaves for `chunk_count` chunks.
+    // Invariant: `elements` fit in `chunk_count` chunks
+    pub(crate) fn new(
+        elements: Vec<T>,
+        chunk_count: usize,
+    ) -> Result<Self, (Vec<T>, MerkleizationError)> {
+        let per_leaf = elements_per_leaf::<T>();
+        let depth = chunk_count.next_power_of_two().trailing_zeros() as usize;
+        let len = elements.len();
+        let roots = match elements.chunks(per_leaf).map(leaf_root).collect::<Result<Vec<_>, _>>() {
+            Ok(roots) => roots,
+            Err(err) => return Err((elements, err)),
+        };
+        let mut elements = elements.into_iter();
+        let mut layer = roots
+            .into_iter()
+            .map(|root| {
+                let elements = elements.by_ref().take(per_leaf).collect();
+                Subtree::Leaf(Arc::new(Leaf { root, elements }))
+            })
+            .collect::<Vec<_>>();
+        let mut hasher = Sha256Hasher::default();
+        for depth in 0..depth {
+            let mut nodes = layer.into_iter();
+            let mut parents = Vec::with_capacity(nodes.len().div_ceil(2));
+            while let Some(left) = nodes.next() {
+                let right = nodes.next().unwrap_or(Subtree::Zero);
+                parents.push(Subtree::branch(&mut hasher, left, right, depth));
+            }
+            layer = parents;
+        }
+        let root = layer.pop().unwrap_or(Subtree::Zero);
+        Ok(Self { root, depth, len })
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn root(&self) -> Node {
+        self.root.root(self.depth)
+    }
+
+    // Returns the root of the tree with the hasher `H`, which is recomputed from the elements
+    // unless `H` is the default hasher.
+    pub(crate) fn root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        if is_default_hasher::<H>() {
+            return Ok(self.root())
+        }
+        merkleize_with::<H>(&self.chunks::<H>()?, Some(1 << self.depth))
+    }
+
+    pub(crate) fn iter(&self) -> TreeIter<'_, T> {
+        TreeIter { stack: vec![&self.root], elements: [].iter(), remaining: self.len }
+    }
+
+    fn leaf(&self, chunk: usize) -> Option<&Leaf<T>> {
+        let mut node = &self.root;
+        for depth in (0..self.depth).rev() {
+            match node {
+                Subtree::Branch(branch) if (chunk >> depth) & 1 == 0 => node = &branch.left,
+                Subtree::Branch(branch) => node = &branch.right,
+                _ => return None,
+            }
+        }
+        match node {
+            Subtree::Leaf(leaf) => Some(leaf),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.len {
+            return None
+        }
+        let per_leaf = elements_per_leaf::<T>();
+        self.leaf(index / per_leaf)?.elements.get(index % per_leaf)
+    }
+
+    // Replace the elements of the leaf at `chunk` with `elements`.
+    fn replace_leaf(&mut self, chunk: usize, elements: Vec<T>) -> Result<(), MerkleizationError> {
+        let leaf = leaf(elements)?;
+        self.root.replace(chunk, self.depth, leaf, &mut Sha256Hasher::default());
+        Ok(())
+    }
+
+    // Returns the serialized roots of the leaves holding elements, with the hasher `H`.
+    pub(crate) fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
+        if !is_default_hasher::<H>() && T::is_composite_type() {
+            let mut chunks = Vec::with_capacity(self.len * BYTES_PER_CHUNK);
+            for element in self.iter() {
+                chunks.extend_from_slice(element.hash_tree_root_with::<H>()?.as_ref());
+            }
+            return Ok(chunks)
+        }
+        let mut chunks = Vec::with_capacity(self.len.div_ceil(elements_per_leaf::<T>()));
+        self.root.extend_leaf_roots(&mut chunks);
+        Ok(chunks)
+    }
+
+    // Compare the elements of `self` and `other`, trees of the values of type `P`, skipping the
+    // subtrees with matching roots, and their lengths if `P` has a length.
+    pub(crate) fn diff_elements<P: GeneralizedIndexable + ?Sized>(
+        &self,
+        other: &Self,
+        has_length: bool,
+        differ: &mut Differ,
+    ) -> Result<(), MerkleizationError>
+    where
+        T: Diff,
+    {
+        diff_subtrees::<P, T>(&self.root, &other.root, self.depth, 0, differ)?;
+        if has_length && self.len != other.len {
+            differ.compare_element::<P, usize>(PathElement::Length, &self.len, &other.len)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: SimpleSerialize + Clone> PersistentTree<T> {
+    // Invariant: `index < self.len()`
+    pub(crate) fn set(&mut self, index: usize, value: T) -> Result<(), MerkleizationError> {
+        let per_leaf = elements_per_leaf::<T>();
+        let chunk = index / per_leaf;
+        let elements = if per_leaf == 1 {
+            vec![value]
+        } else {
+            let mut elements =
+                self.leaf(chunk).map(|leaf| leaf.elements.clone()).unwrap_or_default();
+            elements[index % per_leaf] = value;
+            elements
+        };
+        self.replace_leaf(chunk, elements)
+    }
+
+    pub(crate) fn push(&mut self, value: T) -> Result<(), MerkleizationError> {
+        let per_leaf = elements_per_leaf::<T>();
+        let chunk = self.len / per_leaf;
+        let mut elements = self.leaf(chunk).map(|leaf| leaf.elements.clone()).unwrap_or_default();
+        elements.push(value);
+        self.replace_leaf(chunk, elements)?;
+        self.len += 1;
+        Ok(())
+    }
+
+    pub(crate) fn pop(&mut self) -> Result<Option<T>, MerkleizationError> {
+        let Some(index) = self.len.checked_sub(1) else { return Ok(None) };
+        let chunk = index / elements_per_leaf::<T>();
+        let mut elements = self.leaf(chunk).map(|leaf| leaf.elements.clone()).unwrap_or_default();
+        let value = elements.pop();
+        self.replace_leaf(chunk, elements)?;
+        self.len = index;
+        Ok(value)
+    }
+}
+
+fn diff_subtrees<P, T>(
+    left: &Subtree<T>,
+    right: &Subtree<T>,
+    depth: usize,
+    first_chunk: usize,
+    differ: &mut Differ,
+) -> Result<(), MerkleizationError>
+where
+    P: GeneralizedIndexable + ?Sized,
+    T: SimpleSerialize + Diff,
+{
+    if left.root(depth) == right.root(depth) {
+        return Ok(())
+    }
+    match (left, right) {
+        (Subtree::Branch(left), Subtree::Branch(right)) => {
+            diff_subtrees::<P, T>(&left.left, &right.left, depth - 1, first_chunk, differ)?;
+            let first_chunk = first_chunk + (1 << (depth - 1));
+            diff_subtrees::<P, T>(&left.right, &right.right, depth - 1, first_chunk, differ)
+        }
+        (Subtree::Leaf(left), Subtree::Leaf(right)) => {
+            if !T::is_composite_type() {
+                let element_bits = T::item_length() * BITS_PER_BYTE as usize;
+                let (left, right) = (pack(&left.elements)?, pack(&right.elements)?);
+                return diff_packed_chunks::<P>(&left, &right, first_chunk, element_bits, differ)
+            }
+            for (i, (left, right)) in left.elements.iter().zip(&right.elements).enumerate() {
+                differ.compare_element::<P, T>(PathElement::Index(first_chunk + i), left, right)?;
+            }
+            Ok(())
+        }
+        // NOTE: there are no elements in common with an empty subtree
+        _ => Ok(()),
+    }
+}
+
+/// An iterator over the elements of a `TreeList` or a `TreeVector`.
+pub struct TreeIter<'a, T> {
+    stack: Vec<&'a Subtree<T>>,
+    elements: core::slice::Iter<'a, T>,
+    remaining: usize,
+}
+
+impl<'a, T> Clone for TreeIter<'a, T> {
+    fn clone(&self) -> Self {
+        Self {
+            stack: self.stack.clone(),
+            elements: self.elements.clone(),
+            remaining: self.remaining,
+        }
+    }
+}
+
+impl<'a, T> Iterator for TreeIter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(element) = self.elements.next() {
+                self.remaining -= 1;
+                return Some(element)
+            }
+            match self.stack.pop()? {
+                Subtree::Zero => {}
+                Subtree::Leaf(leaf) => self.elements = leaf.elements.iter(),
+                Subtree::Branch(branch) => {
+                    self.stack.push(&branch.right);
+                    self.stack.push(&branch.left);
+                }
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, T> ExactSizeIterator for TreeIter<'a, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_persistent_tree() {
+        let elements = (0..100u16).collect::<Vec<_>>();
+        let mut tree = PersistentTree::new(elements.clone(), 16).unwrap();
+        assert_eq!(tree.iter().copied().collect::<Vec<_>>(), elements);
+        assert_eq!(tree.get(99), Some(&99));
+        assert_eq!(tree.get(100), None);
+
+        let snapshot = tree.clone();
+        tree.set(17, 1).unwrap();
+        assert_eq!(tree.get(17), Some(&1));
+        assert_eq!(snapshot.get(17), Some(&17));
+        // NOTE: the unchanged subtrees are shared with the snapshot
+        let (Subtree::Branch(left), Subtree::Branch(right)) = (&tree.root, &snapshot.root) else {
+            unreachable!()
+        };
+        let (Subtree::Branch(left), Subtree::Branch(right)) = (&left.left, &right.left) else {
+            unreachable!()
+        };
+        assert!(!Arc::ptr_eq(left, right));
+        let (Subtree::Branch(left), Subtree::Branch(right)) = (&left.right, &right.right) else {
+            unreachable!()
+        };
+        assert!(Arc::ptr_eq(left, right));
+
+        for _ in 0..100 {
+            tree.pop().unwrap();
+        }
+        assert_eq!(tree.len(), 0);
+        assert!(matches!(tree.root, Subtree::Zero));
+        assert_eq!(tree.root(), Sha256Hasher::zero_hash(4));
+        assert_eq!(tree.pop().unwrap(), None);
+    }
+}
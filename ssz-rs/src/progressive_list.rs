@@ -0,0 +1,515 @@
+#[cfg(feature = "rayon")]
+use crate::merkleization::elements_to_chunks_in_parallel;
+use crate::{
+    de::{
+        deserialize_homogeneous_composite, validate_variable_offsets_prefix, DecodeContext,
+        Deserialize, DeserializeError,
+    },
+    lib::*,
+    merkleization::{
+        diff::{diff_sequences, Diff, Differ},
+        merkleize_progressive_with, mix_in_length, pack,
+        proofs::{Prove, Prover},
+        tree::Subtree,
+        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleHasher, MerkleizationError,
+        Node, Path, PathElement, Sha256Hasher, BYTES_PER_CHUNK,
+    },
+    ser::{
+        homogeneous_composite_encoded_len, serialize_homogeneous_composite, Serialize,
+        SerializeError, Sink,
+    },
+    view::{SequenceView, Viewable},
+    Serializable, SimpleSerialize,
+};
+
+/// A homogenous collection of a variable number of values without any fixed capacity, following
+/// EIP-7916.
+///
+/// A `ProgressiveList` has the same encoding as a `List` but is merkleized progressively (see
+/// `merkleize_progressive`), so that its Merkle tree grows with the number of elements.
+#[derive(PartialOrd, Ord, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+pub struct ProgressiveList<T: Serializable> {
+    data: Vec<T>,
+}
+
+impl<T: Serializable> AsRef<[T]> for ProgressiveList<T> {
+    fn as_ref(&self) -> &[T] {
+        &self.data
+    }
+}
+
+impl<T> fmt::Debug for ProgressiveList<T>
+where
+    T: Serializable + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        if f.alternate() {
+            write!(
+                f,
+                "ProgressiveList<{}>(len={}){:#?}",
+                any::type_name::<T>(),
+                self.len(),
+                self.data
+            )
+        } else {
+            write!(
+                f,
+                "ProgressiveList<{}>(len={}){:?}",
+                any::type_name::<T>(),
+                self.len(),
+                self.data
+            )
+        }
+    }
+}
+
+impl<T> Default for ProgressiveList<T>
+where
+    T: Serializable,
+{
+    fn default() -> Self {
+        Self { data: vec![] }
+    }
+}
+
+impl<T> PartialEq for ProgressiveList<T>
+where
+    T: Serializable + PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.data == other.data
+    }
+}
+
+impl<T> Eq for ProgressiveList<T> where T: Serializable + Eq {}
+
+impl<T> From<Vec<T>> for ProgressiveList<T>
+where
+    T: Serializable,
+{
+    fn from(data: Vec<T>) -> Self {
+        Self { data }
+    }
+}
+
+impl<T> From<&[T]> for ProgressiveList<T>
+where
+    T: Serializable + Clone,
+{
+    fn from(data: &[T]) -> Self {
+        Self { data: data.to_vec() }
+    }
+}
+
+impl<T> Deref for ProgressiveList<T>
+where
+    T: Serializable,
+{
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl<T> DerefMut for ProgressiveList<T>
+where
+    T: Serializable,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+impl<T, Idx: SliceIndex<[T]>> Index<Idx> for ProgressiveList<T>
+where
+    T: Serializable,
+{
+    type Output = <Idx as SliceIndex<[T]>>::Output;
+
+    fn index(&self, index: Idx) -> &Self::Output {
+        &self.data[index]
+    }
+}
+
+impl<T, Idx: SliceIndex<[T]>> IndexMut<Idx> for ProgressiveList<T>
+where
+    T: Serializable,
+{
+    fn index_mut(&mut self, index: Idx) -> &mut Self::Output {
+        &mut self.data[index]
+    }
+}
+
+impl<T> Serializable for ProgressiveList<T>
+where
+    T: Serializable,
+{
+    fn is_variable_size() -> bool {
+        true
+    }
+
+    fn size_hint() -> usize {
+        0
+    }
+
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        homogeneous_composite_encoded_len(&self.data)
+    }
+
+    fn bytes_at_path<'a>(encoding: &'a [u8], path: Path) -> Result<&'a [u8], DeserializeError> {
+        if path.is_empty() {
+            return Ok(encoding)
+        }
+        SequenceView::<T>::new(encoding)?.bytes_at_path(path)
+    }
+
+    fn validate_prefix(prefix: &[u8]) -> Result<bool, DeserializeError> {
+        if !T::is_variable_size() {
+            return Ok(true)
+        }
+        validate_variable_offsets_prefix(prefix, |_| Ok(()))
+    }
+}
+
+impl<T> Serialize for ProgressiveList<T>
+where
+    T: Serializable,
+{
+    fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
+        self.serialize_to(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
+        serialize_homogeneous_composite(&self.data, sink)
+    }
+}
+
+impl<T> Deserialize for ProgressiveList<T>
+where
+    T: Serializable,
+{
+    fn deserialize(encoding: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_with(encoding, &mut DecodeContext::default())
+    }
+
+    fn deserialize_with(
+        encoding: &[u8],
+        context: &mut DecodeContext,
+    ) -> Result<Self, DeserializeError> {
+        if !T::is_variable_size() {
+            let remainder = encoding.len() % T::size_hint();
+            if remainder != 0 {
+                return Err(DeserializeError::AdditionalInput {
+                    provided: encoding.len(),
+                    // SAFETY: checked subtraction is unnecessary, as encoding.len() > remainder;
+                    // qed
+                    expected: encoding.len() - remainder,
+                })
+            }
+        }
+
+        let data = deserialize_homogeneous_composite(encoding, context, |_| Ok(()))?;
+        Ok(Self { data })
+    }
+}
+
+impl<T> ProgressiveList<T>
+where
+    T: SimpleSerialize,
+{
+    fn assemble_chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
+        if T::is_composite_type() {
+            T::elements_to_chunks::<H>(&self.data)
+        } else {
+            pack(self)
+        }
+    }
+
+    fn root_of_chunks<H: MerkleHasher>(&self, chunks: &[u8]) -> Result<Node, MerkleizationError> {
+        let data_root = merkleize_progressive_with::<H>(chunks, 1)?;
+        Ok(mix_in_length::<H>(data_root, self.len()))
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T> ProgressiveList<T>
+where
+    T: SimpleSerialize + Sync,
+{
+    /// Compute the "hash tree root" of `self` like `HashTreeRoot::hash_tree_root`, computing the
+    /// roots of the elements in parallel on the `rayon` thread pool when there are enough of them.
+    pub fn hash_tree_root_in_parallel(&self) -> Result<Node, MerkleizationError> {
+        self.hash_tree_root_in_parallel_with::<Sha256Hasher>()
+    }
+
+    /// Compute the "hash tree root" of `self` like `hash_tree_root_in_parallel`, with the
+    /// hasher `H`.
+    pub fn hash_tree_root_in_parallel_with<H: MerkleHasher>(
+        &self,
+    ) -> Result<Node, MerkleizationError> {
+        let chunks = if T::is_composite_type() {
+            elements_to_chunks_in_parallel::<H, _>(&self.data)?
+        } else {
+            pack(&self.data)?
+        };
+        self.root_of_chunks::<H>(&chunks)
+    }
+}
+
+impl<T> HashTreeRoot for ProgressiveList<T>
+where
+    T: SimpleSerialize,
+{
+    fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        let chunks = self.assemble_chunks::<H>()?;
+        self.root_of_chunks::<H>(&chunks)
+    }
+}
+
+impl<T> GeneralizedIndexable for ProgressiveList<T>
+where
+    T: SimpleSerialize,
+{
+    // NOTE: the number of chunks is unbounded so the default `chunk_count` is left in place;
+    // generalized indices and proofs follow the progressive shape of the Merkle tree instead.
+    fn compute_generalized_index(
+        parent: GeneralizedIndex,
+        path: Path,
+    ) -> Result<GeneralizedIndex, MerkleizationError> {
+        if let Some((next, rest)) = path.split_first() {
+            match next {
+                PathElement::Index(i) => {
+                    let child =
+                        progressive_chunk_generalized_index(parent, *i, T::item_length())
+                            .ok_or_else(|| MerkleizationError::InvalidPathElement(next.clone()))?;
+                    T::compute_generalized_index(child, rest)
+                }
+                PathElement::Length => {
+                    if rest.is_empty() {
+                        parent
+                            .checked_mul(2)
+                            .and_then(|index| index.checked_add(1))
+                            .ok_or_else(|| MerkleizationError::InvalidPathElement(next.clone()))
+                    } else {
+                        Err(MerkleizationError::InvalidPath(rest.to_vec()))
+                    }
+                }
+                elem => Err(MerkleizationError::InvalidPathElement(elem.clone())),
+            }
+        } else {
+            Ok(parent)
+        }
+    }
+}
+
+// Returns the generalized index of the chunk containing the element at `index` of a progressive
+// list with elements of `item_length` bytes, or `None` if it does not fit in a `GeneralizedIndex`.
+pub(crate) fn progressive_chunk_generalized_index(
+    parent: GeneralizedIndex,
+    index: usize,
+    item_length: usize,
+) -> Option<GeneralizedIndex> {
+    let chunk_position = index.checked_mul(item_length)? / BYTES_PER_CHUNK;
+    // walk down the progressive "spine" to the subtree containing the chunk
+    let mut spine = parent.checked_mul(2)?;
+    let mut offset = 0usize;
+    let mut subtree_leaf_count = 1usize;
+    while chunk_position >= offset.checked_add(subtree_leaf_count)? {
+        spine = spine.checked_mul(2)?;
+        offset += subtree_leaf_count;
+        subtree_leaf_count = subtree_leaf_count.checked_mul(4)?;
+    }
+    spine
+        .checked_mul(2)?
+        .checked_add(1)?
+        .checked_mul(subtree_leaf_count)?
+        .checked_add(chunk_position - offset)
+}
+
+impl<T> Prove for ProgressiveList<T>
+where
+    T: SimpleSerialize,
+{
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
+        self.assemble_chunks::<H>()
+    }
+
+    fn prove_element<H: MerkleHasher>(
+        &self,
+        index: usize,
+        prover: &mut Prover<H>,
+    ) -> Result<(), MerkleizationError> {
+        let child = self.get(index).ok_or(MerkleizationError::InvalidInnerIndex)?;
+        prover.compute_proof(child)
+    }
+
+    fn subtree<H: MerkleHasher>(&self) -> Result<Subtree<H>, MerkleizationError> {
+        Subtree::from_progressive_sequence(self, self.iter())
+    }
+
+    fn decoration(&self) -> Option<usize> {
+        Some(self.len())
+    }
+
+    fn extend_proof<H: MerkleHasher>(
+        &self,
+        prover: &mut Prover<H>,
+    ) -> Result<(), MerkleizationError> {
+        prover.compute_progressive_proof(self, self.len())
+    }
+}
+
+impl<T> Viewable for ProgressiveList<T>
+where
+    T: Viewable,
+{
+    type View<'a> = SequenceView<'a, T>;
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        SequenceView::new(encoding)
+    }
+}
+
+impl<T> Diff for ProgressiveList<T>
+where
+    T: SimpleSerialize + Diff,
+{
+    fn diff_elements(&self, other: &Self, differ: &mut Differ) -> Result<(), MerkleizationError> {
+        diff_sequences::<Self, T>(self, other, true, differ)
+    }
+}
+
+impl<T> SimpleSerialize for ProgressiveList<T> where T: SimpleSerialize {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        merkleization::{merkleize, proofs::tests::compute_and_verify_proof_for_path},
+        prelude::*,
+    };
+
+    #[derive(PartialEq, Eq, Debug, Default, Clone, SimpleSerialize)]
+    struct Foo {
+        a: u8,
+        b: ProgressiveList<u16>,
+    }
+
+    // Compute the root following the definition in EIP-7916.
+    fn progressive_root(chunks: &[Node], leaf_count: usize) -> Node {
+        if chunks.is_empty() {
+            return Node::default()
+        }
+        let split = leaf_count.min(chunks.len());
+        let subtree = chunks[..split].iter().flat_map(|chunk| chunk.0).collect::<Vec<_>>();
+        let mut data = progressive_root(&chunks[split..], leaf_count * 4).to_vec();
+        data.extend_from_slice(merkleize(&subtree, Some(leaf_count)).unwrap().as_ref());
+        merkleize(&data, None).unwrap()
+    }
+
+    #[test]
+    fn test_serialization_matches_list() {
+        let data = vec![1u16, 2, 3, 4, 5];
+        let value = ProgressiveList::from(data.clone());
+        let list = List::<u16, 8>::try_from(data).unwrap();
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(encoding, serialize(&list).unwrap());
+        assert_eq!(ProgressiveList::<u16>::deserialize(&encoding).unwrap(), value);
+
+        let value = ProgressiveList::from(vec![
+            ProgressiveList::from(vec![1u8, 2]),
+            ProgressiveList::default(),
+            ProgressiveList::from(vec![3u8]),
+        ]);
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(ProgressiveList::<ProgressiveList<u8>>::deserialize(&encoding).unwrap(), value);
+        let path = &[2.into(), 0.into()];
+        let element = ProgressiveList::<ProgressiveList<u8>>::decode_at_path::<u8>(&encoding, path);
+        assert_eq!(element.unwrap(), 3);
+    }
+
+    #[test]
+    fn test_hash_tree_root() {
+        let value = ProgressiveList::<U256>::default();
+        assert_eq!(
+            value.hash_tree_root().unwrap(),
+            mix_in_length::<Sha256Hasher>(Node::default(), 0)
+        );
+
+        for count in [1, 2, 4, 5, 6, 21, 22, 100] {
+            let value = ProgressiveList::from((0..count).map(U256::from).collect::<Vec<_>>());
+            let chunks = value.iter().map(|x| x.hash_tree_root().unwrap()).collect::<Vec<_>>();
+            let expected = mix_in_length::<Sha256Hasher>(progressive_root(&chunks, 1), count);
+            assert_eq!(value.hash_tree_root().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_generalized_index() {
+        type L = ProgressiveList<U256>;
+        assert_eq!(L::generalized_index(&[PathElement::Length]).unwrap(), 3);
+        // the first chunk is the right child of the data root
+        assert_eq!(L::generalized_index(&[0.into()]).unwrap(), 5);
+        // the next 4 chunks are in the right subtree of the left child of the data root
+        assert_eq!(L::generalized_index(&[1.into()]).unwrap(), 9 * 4);
+        assert_eq!(L::generalized_index(&[4.into()]).unwrap(), 9 * 4 + 3);
+        // followed by 16 chunks
+        assert_eq!(L::generalized_index(&[5.into()]).unwrap(), 17 * 16);
+        assert_eq!(L::generalized_index(&[20.into()]).unwrap(), 17 * 16 + 15);
+        assert_eq!(L::generalized_index(&[21.into()]).unwrap(), 33 * 64);
+
+        // basic types are packed into chunks
+        type M = ProgressiveList<u64>;
+        assert_eq!(M::generalized_index(&[3.into()]).unwrap(), 5);
+        assert_eq!(M::generalized_index(&[4.into()]).unwrap(), 9 * 4);
+
+        // indices whose generalized index does not fit in a `usize` are rejected
+        for i in [usize::MAX / 2, usize::MAX] {
+            let result = L::generalized_index(&[i.into()]);
+            assert!(matches!(result, Err(MerkleizationError::InvalidPathElement(_))));
+        }
+        let result = M::generalized_index(&[(usize::MAX / 8).into()]);
+        assert!(matches!(result, Err(MerkleizationError::InvalidPathElement(_))));
+    }
+
+    #[test]
+    fn test_prove() {
+        let value = ProgressiveList::from((0..30).map(U256::from).collect::<Vec<_>>());
+        for i in [0, 1, 3, 4, 5, 17, 20, 21, 29, 84] {
+            compute_and_verify_proof_for_path(&value, &[i.into()]);
+        }
+        compute_and_verify_proof_for_path(&value, &[PathElement::Length]);
+        compute_and_verify_proof_for_path(&value, &[]);
+
+        let value = ProgressiveList::from((0..30u16).collect::<Vec<_>>());
+        for i in [0, 15, 16, 29, 79] {
+            compute_and_verify_proof_for_path(&value, &[i.into()]);
+        }
+
+        // elements beyond the populated part of the tree have no proof
+        let value = ProgressiveList::from(vec![U256::from(1)]);
+        assert!(value.prove(&[1.into()]).is_err());
+        let value = ProgressiveList::<U256>::default();
+        compute_and_verify_proof_for_path(&value, &[PathElement::Length]);
+        assert!(value.prove(&[0.into()]).is_err());
+    }
+
+    #[test]
+    fn test_in_container() {
+        let value = Foo { a: 3, b: ProgressiveList::from(vec![5u16; 40]) };
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(Foo::deserialize(&encoding).unwrap(), value);
+        compute_and_verify_proof_for_path(&value, &["b".into(), 39.into()]);
+        compute_and_verify_proof_for_path(&value, &["b".into(), PathElement::Length]);
+
+        let value = ProgressiveList::from(vec![value.clone(), Foo::default(), value]);
+        compute_and_verify_proof_for_path(&value, &[2.into(), "b".into(), 17.into()]);
+        compute_and_verify_proof_for_path(&value, &[1.into(), "a".into()]);
+    }
+}
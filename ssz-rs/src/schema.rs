@@ -0,0 +1,654 @@
+//! Runtime descriptions of the structure of SSZ types.
+use crate::{
+    error::TypeError,
+    lib::*,
+    merkleization::{
+        generalized_index::default_generalized_index, GeneralizedIndex, MerkleizationError, Path,
+        PathElement, BYTES_PER_CHUNK,
+    },
+    progressive_list::progressive_chunk_generalized_index,
+    ser::BYTES_PER_LENGTH_OFFSET,
+    Bitlist, Bitvector, ByteList, ByteVector, Cached, List, Optional, ProgressiveList,
+    Serializable, SimpleSerialize, TreeList, TreeVector, Vector, BITS_PER_BYTE, U256,
+};
+
+/// A tree describing the structure of an SSZ type.
+///
+/// Types whose values share an encoding and Merkle tree have the same schema, e.g. a `ByteList<N>`
+/// is described as a `List` of `uint8` like a `List<u8, N>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Schema {
+    /// An unsigned integer with `bits` bits.
+    Uint {
+        bits: usize,
+    },
+    Boolean,
+    /// A `Vector` of `len` elements.
+    Vector {
+        elem: Box<Schema>,
+        len: usize,
+    },
+    /// A `List` of at most `limit` elements.
+    List {
+        elem: Box<Schema>,
+        limit: usize,
+    },
+    /// A `ProgressiveList` from EIP-7916.
+    ProgressiveList {
+        elem: Box<Schema>,
+    },
+    /// A `Bitvector` of `len` bits.
+    Bitvector {
+        len: usize,
+    },
+    /// A `Bitlist` of at most `limit` bits.
+    Bitlist {
+        limit: usize,
+    },
+    /// An `Optional` from EIP-6475.
+    Optional {
+        elem: Box<Schema>,
+    },
+    /// A container with its fields in order.
+    Container {
+        name: String,
+        fields: Vec<(String, Schema)>,
+    },
+    /// A `StableContainer[max_fields]` from EIP-7495 with the type of each of its fields when
+    /// present.
+    StableContainer {
+        name: String,
+        max_fields: usize,
+        fields: Vec<(String, Schema)>,
+    },
+    /// A `Profile[base]` from EIP-7495 with the type of each of its fields when present and
+    /// whether the field is optional.
+    Profile {
+        name: String,
+        base: Box<Schema>,
+        fields: Vec<(String, Schema, bool)>,
+    },
+    /// A union with the selector of each variant and its type, or `None` for a variant without
+    /// data.
+    Union {
+        name: String,
+        variants: Vec<(u8, Option<Schema>)>,
+    },
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Uint { bits } => write!(f, "uint{bits}"),
+            Self::Boolean => write!(f, "boolean"),
+            Self::Vector { elem, len } => write!(f, "Vector[{elem}, {len}]"),
+            Self::List { elem, limit } => write!(f, "List[{elem}, {limit}]"),
+            Self::ProgressiveList { elem } => write!(f, "ProgressiveList[{elem}]"),
+            Self::Bitvector { len } => write!(f, "Bitvector[{len}]"),
+            Self::Bitlist { limit } => write!(f, "Bitlist[{limit}]"),
+            Self::Optional { elem } => write!(f, "Optional[{elem}]"),
+            Self::Container { name, .. } |
+            Self::StableContainer { name, .. } |
+            Self::Profile { name, .. } |
+            Self::Union { name, .. } => write!(f, "{name}"),
+        }
+    }
+}
+
+// A field of a stable container or profile.
+pub(crate) struct StableField<'a> {
+    pub(crate) name: &'a str,
+    // the type of the field when present
+    pub(crate) schema: &'a Schema,
+    // the index of the field in the (base) stable container
+    pub(crate) index: usize,
+    pub(crate) is_optional: bool,
+}
+
+// The layout of a stable container or profile.
+pub(crate) struct StableFields<'a> {
+    pub(crate) max_fields: usize,
+    pub(crate) fields: Vec<StableField<'a>>,
+    // the number of bits in the serialized active fields, which are `Bitvector[N]` for a
+    // `StableContainer[N]` and have one bit per optional field for a profile
+    pub(crate) bit_count: usize,
+}
+
+impl Schema {
+    pub(crate) fn is_basic(&self) -> bool {
+        matches!(self, Self::Uint { .. } | Self::Boolean)
+    }
+
+    /// Returns `true` if the encoding of values of this type has a variable size.
+    pub fn is_variable_size(&self) -> bool {
+        match self {
+            Self::Uint { .. } | Self::Boolean | Self::Bitvector { .. } => false,
+            Self::Vector { elem, .. } => elem.is_variable_size(),
+            Self::Container { fields, .. } => {
+                fields.iter().any(|(_, schema)| schema.is_variable_size())
+            }
+            Self::Profile { fields, .. } => fields
+                .iter()
+                .any(|(_, schema, is_optional)| *is_optional || schema.is_variable_size()),
+            _ => true,
+        }
+    }
+
+    // Returns the error for a type whose encoding or Merkle tree is too large to describe.
+    fn too_large(&self) -> TypeError {
+        TypeError::InvalidSchema(format!("{self} is too large"))
+    }
+
+    /// Returns the number of bytes in the encoding of values of this type, or `0` if the encoding
+    /// has a variable size.
+    ///
+    /// Returns an error if the number of bytes does not fit in a `usize`.
+    pub fn size_hint(&self) -> Result<usize, TypeError> {
+        if self.is_variable_size() {
+            return Ok(0)
+        }
+        match self {
+            Self::Uint { bits } => Ok(bits / BITS_PER_BYTE as usize),
+            Self::Boolean => Ok(1),
+            Self::Vector { elem, len } => {
+                len.checked_mul(elem.size_hint()?).ok_or_else(|| self.too_large())
+            }
+            Self::Bitvector { len } => Ok(len.div_ceil(BITS_PER_BYTE as usize)),
+            Self::Container { fields, .. } => {
+                self.total_size(fields.iter().map(|(_, schema)| schema))
+            }
+            Self::Profile { fields, .. } => {
+                self.total_size(fields.iter().map(|(_, schema, _)| schema))
+            }
+            _ => Ok(0),
+        }
+    }
+
+    fn total_size<'a>(
+        &self,
+        mut schemas: impl Iterator<Item = &'a Schema>,
+    ) -> Result<usize, TypeError> {
+        schemas.try_fold(0usize, |total, schema| {
+            total.checked_add(schema.size_hint()?).ok_or_else(|| self.too_large())
+        })
+    }
+
+    // Returns the number of bytes a value of this type occupies in the fixed-size section of the
+    // encoding of a composite type containing it.
+    pub(crate) fn fixed_portion_length(&self) -> Result<usize, TypeError> {
+        if self.is_variable_size() {
+            Ok(BYTES_PER_LENGTH_OFFSET)
+        } else {
+            self.size_hint()
+        }
+    }
+
+    fn item_length(&self) -> Result<usize, TypeError> {
+        if self.is_basic() {
+            self.size_hint()
+        } else {
+            Ok(BYTES_PER_CHUNK)
+        }
+    }
+
+    // Returns the number of chunks when merkleizing values of this type.
+    pub(crate) fn chunk_count(&self) -> Result<usize, TypeError> {
+        match self {
+            Self::Vector { elem, len: count } | Self::List { elem, limit: count } => {
+                if elem.is_basic() {
+                    let size =
+                        count.checked_mul(elem.size_hint()?).ok_or_else(|| self.too_large())?;
+                    Ok(size.div_ceil(BYTES_PER_CHUNK))
+                } else {
+                    Ok(*count)
+                }
+            }
+            Self::Bitvector { len: count } | Self::Bitlist { limit: count } => {
+                Ok(count.div_ceil(BYTES_PER_CHUNK * BITS_PER_BYTE as usize))
+            }
+            Self::Container { fields, .. } => Ok(fields.len()),
+            Self::StableContainer { max_fields, .. } => Ok(*max_fields),
+            Self::Profile { base, .. } => base.chunk_count(),
+            _ => Ok(1),
+        }
+    }
+
+    // Returns the layout of a stable container or profile.
+    pub(crate) fn stable_fields(&self) -> Result<StableFields<'_>, TypeError> {
+        match self {
+            Self::StableContainer { max_fields, fields, .. } => {
+                if fields.len() > *max_fields {
+                    return Err(TypeError::InvalidSchema(format!(
+                        "{self} has more than {max_fields} fields"
+                    )))
+                }
+                let fields = fields
+                    .iter()
+                    .enumerate()
+                    .map(|(index, (name, schema))| StableField {
+                        name,
+                        schema,
+                        index,
+                        is_optional: true,
+                    })
+                    .collect();
+                Ok(StableFields { max_fields: *max_fields, fields, bit_count: *max_fields })
+            }
+            Self::Profile { base, fields, .. } => {
+                let StableFields { max_fields, fields: base_fields, .. } = base.stable_fields()?;
+                let mut bit_count = 0;
+                let mut previous_index = None;
+                let fields = fields
+                    .iter()
+                    .map(|(name, schema, is_optional)| {
+                        // NOTE: the fields must follow the order of the base to share its
+                        // serialization
+                        let index = base_fields
+                            .iter()
+                            .find(|field| field.name == name && field.schema == schema)
+                            .map(|field| field.index)
+                            .filter(|index| {
+                                previous_index.map_or(true, |previous| previous < *index)
+                            })
+                            .ok_or_else(|| {
+                                TypeError::InvalidSchema(format!(
+                                    "field `{name}` of {self} does not match a field of {base}"
+                                ))
+                            })?;
+                        previous_index = Some(index);
+                        bit_count += *is_optional as usize;
+                        Ok(StableField { name, schema, index, is_optional: *is_optional })
+                    })
+                    .collect::<Result<_, TypeError>>()?;
+                Ok(StableFields { max_fields, fields, bit_count })
+            }
+            _ => Err(TypeError::InvalidSchema(format!("{self} is not a stable container"))),
+        }
+    }
+
+    // Returns the generalized index of the chunk at `chunk_position` under the node at `parent`
+    // with the chunks of this type, or `None` if it does not fit in a `GeneralizedIndex`.
+    fn child_generalized_index(
+        &self,
+        parent: GeneralizedIndex,
+        chunk_position: usize,
+    ) -> Option<GeneralizedIndex> {
+        let chunk_count = self.chunk_count().ok()?;
+        parent.checked_mul(chunk_count.checked_next_power_of_two()?)?.checked_add(chunk_position)
+    }
+
+    /// Returns the generalized index of the node at `path` in the Merkle tree of values of this
+    /// type.
+    pub fn generalized_index(&self, path: Path) -> Result<GeneralizedIndex, MerkleizationError> {
+        self.compute_generalized_index(default_generalized_index(), path)
+    }
+
+    /// Computes the generalized index starting from `parent` and following `path` through this
+    /// type, like `GeneralizedIndexable::compute_generalized_index` for the type itself.
+    pub fn compute_generalized_index(
+        &self,
+        parent: GeneralizedIndex,
+        path: Path,
+    ) -> Result<GeneralizedIndex, MerkleizationError> {
+        let Some((next, rest)) = path.split_first() else { return Ok(parent) };
+        let invalid_element = || MerkleizationError::InvalidPathElement(next.clone());
+        match (self, next) {
+            (Self::Vector { elem, len: count }, PathElement::Index(i)) |
+            (Self::List { elem, limit: count }, PathElement::Index(i)) => {
+                if i >= count {
+                    return Err(invalid_element())
+                }
+                let item_length = elem.item_length().map_err(|_| invalid_element())?;
+                let chunk_position =
+                    i.checked_mul(item_length).ok_or_else(invalid_element)? / BYTES_PER_CHUNK;
+                let subtree_root = if matches!(self, Self::List { .. }) {
+                    parent.checked_mul(2).ok_or_else(invalid_element)?
+                } else {
+                    parent
+                };
+                let child = self
+                    .child_generalized_index(subtree_root, chunk_position)
+                    .ok_or_else(invalid_element)?;
+                elem.compute_generalized_index(child, rest)
+            }
+            (Self::Bitvector { len: count }, PathElement::Index(i)) |
+            (Self::Bitlist { limit: count }, PathElement::Index(i)) => {
+                if i >= count {
+                    return Err(invalid_element())
+                }
+                let chunk_position = i / (BYTES_PER_CHUNK * BITS_PER_BYTE as usize);
+                let subtree_root = if matches!(self, Self::Bitlist { .. }) {
+                    parent.checked_mul(2).ok_or_else(invalid_element)?
+                } else {
+                    parent
+                };
+                let child = self
+                    .child_generalized_index(subtree_root, chunk_position)
+                    .ok_or_else(invalid_element)?;
+                // NOTE: use `bool` as effective type of element
+                Self::Boolean.compute_generalized_index(child, rest)
+            }
+            (Self::ProgressiveList { elem }, PathElement::Index(i)) => {
+                let item_length = elem.item_length().map_err(|_| invalid_element())?;
+                let child = progressive_chunk_generalized_index(parent, *i, item_length)
+                    .ok_or_else(invalid_element)?;
+                elem.compute_generalized_index(child, rest)
+            }
+            (Self::Optional { elem }, PathElement::Index(0)) => {
+                let child = parent.checked_mul(2).ok_or_else(invalid_element)?;
+                elem.compute_generalized_index(child, rest)
+            }
+            (Self::List { .. }, PathElement::Length) |
+            (Self::Bitlist { .. }, PathElement::Length) |
+            (Self::ProgressiveList { .. }, PathElement::Length) |
+            (Self::Optional { .. }, PathElement::Length) |
+            (Self::Union { .. }, PathElement::Selector) => {
+                if rest.is_empty() {
+                    parent
+                        .checked_mul(2)
+                        .and_then(|index| index.checked_add(1))
+                        .ok_or_else(invalid_element)
+                } else {
+                    Err(MerkleizationError::InvalidPath(rest.to_vec()))
+                }
+            }
+            (Self::Container { fields, .. }, PathElement::Field(field)) => {
+                let chunk_position = fields
+                    .iter()
+                    .position(|(name, _)| name == field)
+                    .ok_or_else(invalid_element)?;
+                let child = self
+                    .child_generalized_index(parent, chunk_position)
+                    .ok_or_else(invalid_element)?;
+                fields[chunk_position].1.compute_generalized_index(child, rest)
+            }
+            (Self::StableContainer { .. } | Self::Profile { .. }, PathElement::Field(field)) => {
+                let layout = self.stable_fields().map_err(|_| invalid_element())?;
+                let field =
+                    layout.fields.iter().find(|f| f.name == field).ok_or_else(invalid_element)?;
+                // NOTE: the fields are under the left child of the root, next to the active fields
+                let child = parent
+                    .checked_mul(2)
+                    .and_then(|index| {
+                        index.checked_mul(layout.max_fields.checked_next_power_of_two()?)
+                    })
+                    .and_then(|index| index.checked_add(field.index))
+                    .ok_or_else(invalid_element)?;
+                field.schema.compute_generalized_index(child, rest)
+            }
+            (Self::Union { variants, .. }, PathElement::Index(i)) => {
+                let child = parent.checked_mul(2).ok_or_else(invalid_element)?;
+                match variants.iter().find(|(selector, _)| *selector as usize == *i) {
+                    Some((_, Some(schema))) => schema.compute_generalized_index(child, rest),
+                    Some((_, None)) if rest.is_empty() => Ok(child),
+                    Some((_, None)) => Err(MerkleizationError::InvalidPath(rest.to_vec())),
+                    None => Err(invalid_element()),
+                }
+            }
+            _ => Err(invalid_element()),
+        }
+    }
+}
+
+/// A type that can describe its structure at runtime.
+///
+/// Implemented for the types in this crate and derived for containers and unions with
+/// `#[derive(SszSchema)]`.
+pub trait SszSchema {
+    fn schema() -> Schema;
+}
+
+macro_rules! define_uint_schema {
+    ($uint:ty) => {
+        impl SszSchema for $uint {
+            fn schema() -> Schema {
+                Schema::Uint { bits: <$uint>::BITS as usize }
+            }
+        }
+    };
+}
+
+define_uint_schema!(u8);
+define_uint_schema!(u16);
+define_uint_schema!(u32);
+define_uint_schema!(u64);
+define_uint_schema!(u128);
+define_uint_schema!(usize);
+define_uint_schema!(U256);
+
+impl SszSchema for bool {
+    fn schema() -> Schema {
+        Schema::Boolean
+    }
+}
+
+impl<T: SszSchema, const N: usize> SszSchema for [T; N] {
+    fn schema() -> Schema {
+        Schema::Vector { elem: Box::new(T::schema()), len: N }
+    }
+}
+
+impl<T: Serializable + SszSchema, const N: usize> SszSchema for Vector<T, N> {
+    fn schema() -> Schema {
+        Schema::Vector { elem: Box::new(T::schema()), len: N }
+    }
+}
+
+impl<T: Serializable + SszSchema, const N: usize> SszSchema for List<T, N> {
+    fn schema() -> Schema {
+        Schema::List { elem: Box::new(T::schema()), limit: N }
+    }
+}
+
+impl<T: SimpleSerialize + SszSchema, const N: usize> SszSchema for TreeVector<T, N> {
+    fn schema() -> Schema {
+        Vector::<T, N>::schema()
+    }
+}
+
+impl<T: SimpleSerialize + SszSchema, const N: usize> SszSchema for TreeList<T, N> {
+    fn schema() -> Schema {
+        List::<T, N>::schema()
+    }
+}
+
+impl<const N: usize> SszSchema for ByteVector<N> {
+    fn schema() -> Schema {
+        Vector::<u8, N>::schema()
+    }
+}
+
+impl<const N: usize> SszSchema for ByteList<N> {
+    fn schema() -> Schema {
+        List::<u8, N>::schema()
+    }
+}
+
+impl<T: Serializable + SszSchema> SszSchema for ProgressiveList<T> {
+    fn schema() -> Schema {
+        Schema::ProgressiveList { elem: Box::new(T::schema()) }
+    }
+}
+
+impl<const N: usize> SszSchema for Bitvector<N> {
+    fn schema() -> Schema {
+        Schema::Bitvector { len: N }
+    }
+}
+
+impl<const N: usize> SszSchema for Bitlist<N> {
+    fn schema() -> Schema {
+        Schema::Bitlist { limit: N }
+    }
+}
+
+impl<T: Serializable + SszSchema> SszSchema for Optional<T> {
+    fn schema() -> Schema {
+        Schema::Optional { elem: Box::new(T::schema()) }
+    }
+}
+
+impl<T: SszSchema> SszSchema for Cached<T> {
+    fn schema() -> Schema {
+        T::schema()
+    }
+}
+
+impl<T: SszSchema> SszSchema for Option<T> {
+    fn schema() -> Schema {
+        Schema::Union {
+            name: "Option".to_string(),
+            variants: vec![(0, None), (1, Some(T::schema()))],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[derive(Debug, Default, SimpleSerialize, SszSchema)]
+    struct Foo<const N: usize> {
+        a: u64,
+        b: List<ByteVector<4>, N>,
+        c: Option<Bitlist<8>>,
+    }
+
+    #[derive(Debug, SimpleSerialize, SszSchema)]
+    enum Bar {
+        None,
+        Foo(Foo<2>),
+        Flag(bool),
+    }
+
+    #[derive(Debug, Default, SimpleSerialize, SszSchema)]
+    #[ssz(stable_container = 4)]
+    struct Shape {
+        side: Option<u16>,
+        color: Option<u8>,
+        radius: Option<u16>,
+    }
+
+    #[derive(Debug, Default, SimpleSerialize, SszSchema)]
+    #[ssz(profile(Shape))]
+    struct Circle {
+        color: u8,
+        radius: Option<u16>,
+    }
+
+    #[derive(Debug, SimpleSerialize, SszSchema)]
+    #[ssz(compatible_union)]
+    enum AnyShape {
+        #[ssz(selector = 1)]
+        Circle(Circle),
+        #[ssz(selector = 3)]
+        Shape(Shape),
+    }
+
+    #[derive(Debug, Default, SimpleSerialize, SszSchema)]
+    struct Wrapper(Optional<u32>);
+
+    fn uint(bits: usize) -> Schema {
+        Schema::Uint { bits }
+    }
+
+    #[test]
+    fn test_builtin_schemas() {
+        assert_eq!(u64::schema(), uint(64));
+        assert_eq!(U256::schema(), uint(256));
+        assert_eq!(ByteList::<32>::schema(), List::<u8, 32>::schema());
+        assert_eq!(ByteVector::<4>::schema(), <[u8; 4]>::schema());
+        assert_eq!(
+            ProgressiveList::<bool>::schema(),
+            Schema::ProgressiveList { elem: Box::new(Schema::Boolean) }
+        );
+        assert_eq!(Bitvector::<3>::schema().to_string(), "Bitvector[3]");
+        assert_eq!(List::<Vector<u16, 2>, 8>::schema().to_string(), "List[Vector[uint16, 2], 8]");
+        assert_eq!(Optional::<u8>::schema().to_string(), "Optional[uint8]");
+    }
+
+    #[test]
+    fn test_derived_schemas() {
+        let foo = Schema::Container {
+            name: "Foo".to_string(),
+            fields: vec![
+                ("a".to_string(), uint(64)),
+                (
+                    "b".to_string(),
+                    Schema::List {
+                        elem: Box::new(Schema::Vector { elem: Box::new(uint(8)), len: 4 }),
+                        limit: 2,
+                    },
+                ),
+                (
+                    "c".to_string(),
+                    Schema::Union {
+                        name: "Option".to_string(),
+                        variants: vec![(0, None), (1, Some(Schema::Bitlist { limit: 8 }))],
+                    },
+                ),
+            ],
+        };
+        assert_eq!(Foo::<2>::schema(), foo);
+        assert_eq!(
+            Bar::schema(),
+            Schema::Union {
+                name: "Bar".to_string(),
+                variants: vec![(0, None), (1, Some(foo)), (2, Some(Schema::Boolean))],
+            }
+        );
+        assert_eq!(Wrapper::schema(), Optional::<u32>::schema());
+
+        let shape = Schema::StableContainer {
+            name: "Shape".to_string(),
+            max_fields: 4,
+            fields: vec![
+                ("side".to_string(), uint(16)),
+                ("color".to_string(), uint(8)),
+                ("radius".to_string(), uint(16)),
+            ],
+        };
+        assert_eq!(Shape::schema(), shape);
+        let circle = Schema::Profile {
+            name: "Circle".to_string(),
+            base: Box::new(shape.clone()),
+            fields: vec![
+                ("color".to_string(), uint(8), false),
+                ("radius".to_string(), uint(16), true),
+            ],
+        };
+        assert_eq!(Circle::schema(), circle);
+        assert_eq!(
+            AnyShape::schema(),
+            Schema::Union {
+                name: "AnyShape".to_string(),
+                variants: vec![(1, Some(circle)), (3, Some(shape))],
+            }
+        );
+    }
+
+    #[test]
+    fn test_large_schemas() {
+        let vector = Schema::Vector { elem: Box::new(uint(64)), len: usize::MAX / 4 };
+        assert!(matches!(vector.size_hint(), Err(TypeError::InvalidSchema(_))));
+        let container =
+            Schema::Container { name: "Foo".to_string(), fields: vec![("a".to_string(), vector)] };
+        assert!(matches!(container.size_hint(), Err(TypeError::InvalidSchema(_))));
+
+        let list = Schema::List { elem: Box::new(uint(64)), limit: usize::MAX / 4 };
+        let result = list.generalized_index(&[0.into()]);
+        assert!(matches!(result, Err(MerkleizationError::InvalidPathElement(_))));
+
+        let list = Schema::ProgressiveList { elem: Box::new(uint(64)) };
+        for i in [usize::MAX / 8, usize::MAX / 2] {
+            let result = list.generalized_index(&[i.into()]);
+            assert!(matches!(result, Err(MerkleizationError::InvalidPathElement(_))));
+        }
+    }
+}
@@ -1,12 +1,13 @@
 use crate::{
     error::{InstanceError, TypeError},
     lib::*,
+    merkleization::{generalized_index::DisplayPath, Path, PathElement},
     Serializable,
 };
 
 // NOTE: if this is changed, go change in `ssz_derive` as well!
 pub(crate) const BYTES_PER_LENGTH_OFFSET: usize = 4;
-const MAXIMUM_LENGTH: u64 = 2u64.pow((8 * BYTES_PER_LENGTH_OFFSET) as u32);
+pub(crate) const MAXIMUM_LENGTH: u64 = 2u64.pow((8 * BYTES_PER_LENGTH_OFFSET) as u32);
 
 /// Serialization errors.
 #[derive(Debug)]
@@ -17,6 +18,40 @@ pub enum SerializeError {
     InvalidInstance(InstanceError),
     /// An invalid type was encountered.
     InvalidType(TypeError),
+    /// An error was encountered when writing to the target `Sink`.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// An error was encountered when serializing the element at `path`.
+    AtPath { path: Vec<PathElement>, source: Box<SerializeError> },
+}
+
+impl SerializeError {
+    /// Record that this error was encountered when serializing `element` of some composite type.
+    pub fn in_element(self, element: PathElement) -> Self {
+        match self {
+            Self::AtPath { mut path, source } => {
+                path.insert(0, element);
+                Self::AtPath { path, source }
+            }
+            err => Self::AtPath { path: vec![element], source: Box::new(err) },
+        }
+    }
+
+    /// Returns the path to the element where this error was encountered, if known.
+    pub fn path(&self) -> Path {
+        match self {
+            Self::AtPath { path, .. } => path,
+            _ => &[],
+        }
+    }
+
+    /// Returns the underlying error without any location context.
+    pub fn innermost(&self) -> &Self {
+        match self {
+            Self::AtPath { source, .. } => source,
+            err => err,
+        }
+    }
 }
 
 impl From<InstanceError> for SerializeError {
@@ -40,12 +75,48 @@ impl Display for SerializeError {
             ),
             SerializeError::InvalidInstance(err) => write!(f, "invalid instance: {err}"),
             SerializeError::InvalidType(err) => write!(f, "invalid type: {err}"),
+            #[cfg(feature = "std")]
+            SerializeError::Io(err) => write!(f, "could not write encoding: {err}"),
+            SerializeError::AtPath { path, source } => write!(f, "{}: {source}", DisplayPath(path)),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SerializeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::AtPath { source, .. } => Some(source.as_ref()),
+            Self::Io(err) => Some(err),
+            _ => None,
         }
     }
 }
 
+/// A destination for SSZ-encoded bytes.
+///
+/// With the `std` feature enabled, this trait is implemented for any type implementing
+/// `std::io::Write`. Otherwise, it is implemented for `Vec<u8>` and can be implemented by
+/// callers for other kinds of buffers.
+pub trait Sink {
+    /// Write all of `bytes` to this sink.
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), SerializeError>;
+}
+
 #[cfg(feature = "std")]
-impl std::error::Error for SerializeError {}
+impl<W: std::io::Write + ?Sized> Sink for W {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), SerializeError> {
+        self.write_all(bytes).map_err(SerializeError::Io)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Sink for Vec<u8> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), SerializeError> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
 
 /// A data structure that can be serialized using SSZ.
 pub trait Serialize {
@@ -53,66 +124,260 @@ pub trait Serialize {
     ///
     /// Returns the number of bytes written.
     fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError>;
+
+    /// Write an encoding of `self` to the `sink`.
+    ///
+    /// Returns the number of bytes written.
+    ///
+    /// The default implementation encodes `self` into an intermediate buffer before writing it to
+    /// the `sink`; implementing types should override it to write directly to the `sink`.
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
+        let mut buffer = Vec::new();
+        let bytes_written = self.serialize(&mut buffer)?;
+        sink.write_bytes(&buffer)?;
+        Ok(bytes_written)
+    }
 }
 
-// Part represents either a fixed sized part of the serialization
-// or an offset pointing to a variably sized part of the serialization
-pub enum Part {
-    Fixed(Vec<u8>),
-    Offset(usize),
+// Return the number of bytes a value of type `T` occupies in the fixed-size section of the
+// encoding of a composite type containing it.
+pub fn fixed_portion_length<T: Serializable>() -> usize {
+    if T::is_variable_size() {
+        BYTES_PER_LENGTH_OFFSET
+    } else {
+        T::size_hint()
+    }
 }
 
-#[derive(Default)]
-pub struct Serializer {
-    parts: Vec<Part>,
-    variable: Vec<u8>,
-    fixed_lengths_sum: usize,
-    variable_lengths_sum: usize,
+// Return the number of bytes `element` occupies in the encoding of a composite type containing
+// it, including the offset for variable-size elements.
+pub fn element_encoded_len<T: Serializable>(element: &T) -> Result<usize, SerializeError> {
+    if T::is_variable_size() {
+        Ok(BYTES_PER_LENGTH_OFFSET + element.encoded_len()?)
+    } else {
+        Ok(T::size_hint())
+    }
 }
 
-impl Serializer {
-    pub fn serialize(mut self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
-        let total_size = self.fixed_lengths_sum + self.variable_lengths_sum;
-        if total_size as u64 >= MAXIMUM_LENGTH {
-            return Err(SerializeError::MaximumEncodedLengthReached(total_size))
+// Return the maximum number of bytes an element of type `T` occupies in the encoding of a
+// composite type containing it, including the offset for variable-size elements.
+pub fn element_max_encoded_len<T: Serializable>() -> usize {
+    if T::is_variable_size() {
+        BYTES_PER_LENGTH_OFFSET.saturating_add(T::max_encoded_len())
+    } else {
+        T::size_hint()
+    }
+}
+
+// `Serializer` facilitates the serialization of possibly variable heterogenous composite types.
+// The encoding is written directly to the target `Sink` in two passes over the elements without
+// any intermediate buffering: the first pass writes the fixed-size elements and the offsets of
+// the variable-size elements, computed from their `Serializable::encoded_len`, and the second
+// pass writes the variable-size elements.
+// Intended use:
+// - construct with the total length of the fixed-size section of the encoding
+// - call `with_fixed_part` for each element in order
+// - call `with_variable_part` for each element in order
+// - call `finalize` to get the total number of bytes written
+// NOTE: mainly intended for private use in the proc derive macro.
+pub struct Serializer<'a, S: ?Sized> {
+    sink: &'a mut S,
+    fixed_length: usize,
+    variable_length: usize,
+}
+
+impl<'a, S: Sink + ?Sized> Serializer<'a, S> {
+    pub fn new(sink: &'a mut S, fixed_length: usize) -> Self {
+        Self { sink, fixed_length, variable_length: 0 }
+    }
+
+    pub fn with_fixed_part<T: Serializable>(&mut self, element: &T) -> Result<(), SerializeError> {
+        if T::is_variable_size() {
+            self.with_offset(element.encoded_len()?)?;
+        } else {
+            element.serialize_to(self.sink)?;
         }
+        Ok(())
+    }
 
-        // SAFETY: `fixed_lengths_sum` fits in `u32` if the total size check holds
-        let mut running_length = self.fixed_lengths_sum as u32;
-        for part in self.parts {
-            match part {
-                Part::Fixed(mut data) => {
-                    buffer.append(&mut data);
-                }
-                Part::Offset(offset) => {
-                    let bytes_written = running_length.serialize(buffer)?;
-                    debug_assert_eq!(bytes_written, BYTES_PER_LENGTH_OFFSET);
-
-                    // SAFETY: `offset` fits in `u32` if the total size check holds
-                    running_length += offset as u32;
-                }
-            }
+    // Write the offset of a variable-size element with an encoding of `encoded_len` bytes.
+    pub(crate) fn with_offset(&mut self, encoded_len: usize) -> Result<(), SerializeError> {
+        let offset = self.fixed_length + self.variable_length;
+        if offset as u64 >= MAXIMUM_LENGTH {
+            return Err(SerializeError::MaximumEncodedLengthReached(offset))
         }
+        // SAFETY: `offset` fits in `u32` if the maximum length check holds
+        let bytes_written = (offset as u32).serialize_to(self.sink)?;
+        debug_assert_eq!(bytes_written, BYTES_PER_LENGTH_OFFSET);
+        self.variable_length += encoded_len;
+        Ok(())
+    }
+
+    // The target of the serialization, for elements written without a static type.
+    pub(crate) fn sink(&mut self) -> &mut S {
+        self.sink
+    }
 
-        buffer.append(&mut self.variable);
+    pub fn with_variable_part<T: Serializable>(
+        &mut self,
+        element: &T,
+    ) -> Result<(), SerializeError> {
+        if T::is_variable_size() {
+            let bytes_written = element.serialize_to(self.sink)?;
+            debug_assert_eq!(Some(bytes_written), element.encoded_len().ok());
+        }
+        Ok(())
+    }
 
+    pub fn finalize(self) -> Result<usize, SerializeError> {
+        let total_size = self.fixed_length + self.variable_length;
+        if total_size as u64 >= MAXIMUM_LENGTH {
+            return Err(SerializeError::MaximumEncodedLengthReached(total_size))
+        }
         Ok(total_size)
     }
+}
 
-    pub fn with_element<T: Serializable>(&mut self, element: &T) -> Result<(), SerializeError> {
-        let mut element_buffer = Vec::with_capacity(T::size_hint());
-        element.serialize(&mut element_buffer)?;
+// Serialize `elements` as a homogeneous composite type (i.e. a `List` or `Vector`) to `sink`.
+pub fn serialize_homogeneous_composite<'a, T, S, I>(
+    elements: I,
+    sink: &mut S,
+) -> Result<usize, SerializeError>
+where
+    T: Serializable + 'a,
+    S: Sink + ?Sized,
+    I: IntoIterator<Item = &'a T>,
+    I::IntoIter: ExactSizeIterator + Clone,
+{
+    let elements = elements.into_iter();
+    let mut serializer = Serializer::new(sink, elements.len() * fixed_portion_length::<T>());
+    for (i, element) in elements.clone().enumerate() {
+        serializer.with_fixed_part(element).map_err(|err| err.in_element(PathElement::Index(i)))?;
+    }
+    if T::is_variable_size() {
+        for (i, element) in elements.enumerate() {
+            serializer
+                .with_variable_part(element)
+                .map_err(|err| err.in_element(PathElement::Index(i)))?;
+        }
+    }
+    serializer.finalize()
+}
 
-        let element_buffer_len = element_buffer.len();
-        if T::is_variable_size() {
-            self.parts.push(Part::Offset(element_buffer_len));
-            self.variable.append(&mut element_buffer);
-            self.fixed_lengths_sum += BYTES_PER_LENGTH_OFFSET;
-            self.variable_lengths_sum += element_buffer_len;
-        } else {
-            self.parts.push(Part::Fixed(element_buffer));
-            self.fixed_lengths_sum += element_buffer_len;
+// Return the exact length of the encoding of `elements` as a homogeneous composite type.
+pub fn homogeneous_composite_encoded_len<'a, T, I>(elements: I) -> Result<usize, SerializeError>
+where
+    T: Serializable + 'a,
+    I: IntoIterator<Item = &'a T>,
+    I::IntoIter: ExactSizeIterator,
+{
+    let elements = elements.into_iter();
+    if T::is_variable_size() {
+        elements
+            .enumerate()
+            .map(|(i, element)| {
+                element_encoded_len(element).map_err(|err| err.in_element(PathElement::Index(i)))
+            })
+            .sum()
+    } else {
+        Ok(elements.len() * T::size_hint())
+    }
+}
+
+// Return the maximum length of the encoding of at most `count` elements of type `T` as a
+// homogeneous composite type.
+pub fn homogeneous_composite_max_encoded_len<T: Serializable>(count: usize) -> usize {
+    count.saturating_mul(element_max_encoded_len::<T>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    struct FailingWriter;
+
+    impl std::io::Write for FailingWriter {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
         }
-        Ok(())
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_serialize_to_writer() {
+        let value = List::<List<u16, 8>, 4>::try_from(vec![
+            List::try_from(vec![1u16, 2u16]).unwrap(),
+            List::default(),
+            List::try_from(vec![3u16]).unwrap(),
+        ])
+        .unwrap();
+        let expected = serialize(&value).expect("can serialize");
+
+        let mut writer = std::io::BufWriter::new(vec![]);
+        let bytes_written = value.serialize_to(&mut writer).expect("can serialize");
+        assert_eq!(bytes_written, expected.len());
+        assert_eq!(writer.into_inner().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_encoded_len() {
+        let value = List::<List<u16, 8>, 4>::try_from(vec![
+            List::try_from(vec![1u16, 2u16]).unwrap(),
+            List::default(),
+            List::try_from(vec![3u16]).unwrap(),
+        ])
+        .unwrap();
+        assert_eq!(value.encoded_len().unwrap(), serialize(&value).unwrap().len());
+
+        let value = Vector::<Bitlist<20>, 3>::try_from(vec![
+            Bitlist::default(),
+            Bitlist::try_from([true; 8].as_ref()).unwrap(),
+            Bitlist::try_from([false; 13].as_ref()).unwrap(),
+        ])
+        .unwrap();
+        assert_eq!(value.encoded_len().unwrap(), serialize(&value).unwrap().len());
+
+        let value = [List::<u8, 4>::default(), List::try_from(vec![1u8]).unwrap()];
+        assert_eq!(value.encoded_len().unwrap(), serialize(&value).unwrap().len());
+
+        let value = Vector::<u64, 5>::default();
+        assert_eq!(value.encoded_len().unwrap(), 40);
+    }
+
+    #[test]
+    fn test_max_encoded_len() {
+        let value =
+            List::<List<u16, 8>, 4>::try_from(vec![List::try_from(vec![1u16; 8]).unwrap(); 4])
+                .unwrap();
+        assert_eq!(List::<List<u16, 8>, 4>::max_encoded_len(), serialize(&value).unwrap().len());
+
+        let value = Bitlist::<20>::try_from([true; 20].as_ref()).unwrap();
+        assert_eq!(Bitlist::<20>::max_encoded_len(), serialize(&value).unwrap().len());
+
+        assert_eq!(Option::<ByteList<5>>::max_encoded_len(), 6);
+        assert_eq!(Vector::<u64, 5>::max_encoded_len(), 40);
+        assert_eq!(ProgressiveList::<u8>::max_encoded_len(), usize::MAX);
+        assert_eq!(List::<ProgressiveList<u8>, 2>::max_encoded_len(), usize::MAX);
+    }
+
+    #[test]
+    fn test_encoded_len_reports_serialize_errors() {
+        let value: [[List<u8, 1>; 0]; 2] = [[], []];
+        let err = value.encoded_len().unwrap_err();
+        assert!(matches!(err.innermost(), SerializeError::InvalidType(TypeError::InvalidBound(0))));
+        assert_eq!(err.path(), &[PathElement::Index(0)]);
+        assert_eq!(err.path(), serialize(&value).unwrap_err().path());
+    }
+
+    #[test]
+    fn test_serialize_to_failing_writer() {
+        let value = Vector::<u32, 3>::try_from(vec![1u32, 2u32, 3u32]).unwrap();
+        let err = value.serialize_to(&mut FailingWriter).unwrap_err();
+        assert!(matches!(err.innermost(), SerializeError::Io(..)));
+        assert_eq!(err.path(), &[PathElement::Index(0)]);
     }
 }
@@ -1,3 +1,486 @@
+//! Support for the JSON representation of SSZ values used by the consensus APIs.
+//!
+//! The default `serde` implementations of the types in this crate follow Rust's conventions, e.g.
+//! a `u64` is a JSON number and a `List<u8, N>` is an array of numbers. The consensus APIs instead
+//! use the following mapping:
+//!
+//! | SSZ                              | JSON                                      |
+//! |----------------------------------|-------------------------------------------|
+//! | `uintN`                          | decimal string, e.g. `"42"`               |
+//! | `boolean`                        | `true` or `false`                         |
+//! | `List[byte, N]`, `Vector[byte, N]` | `0x`-prefixed hex string                |
+//! | `Bitlist[N]`, `Bitvector[N]`     | `0x`-prefixed hex string of the encoding  |
+//! | `List[T, N]`, `Vector[T, N]`     | array                                     |
+//! | `Container`                      | object with a key for each field          |
+//! | `Union`                          | `{ "selector": number, "data": value }`   |
+//! | `Optional[T]`                    | `null` or the value                       |
+//!
+//! This representation is opt-in: types implement `ConsensusSerialize` and `ConsensusDeserialize`,
+//! which are derived for containers and unions with the macros of the same name, and the
+//! `Consensus` wrapper or the `consensus` module for `#[serde(with = "...")]` bridge them to
+//! `serde`.
+use crate::{
+    lib::*, Bitlist, Bitvector, ByteList, ByteVector, Cached, List, Optional, ProgressiveList,
+    Serializable, SimpleSerialize, TreeList, TreeVector, Vector, U256,
+};
+use serde::{
+    de::{Error as _, MapAccess, Unexpected, Visitor},
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+/// A type that can be serialized with the JSON representation of the consensus APIs.
+pub trait ConsensusSerialize {
+    fn serialize_consensus<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>;
+
+    // Serialize a sequence of `values`, which allows bytes to be represented as a hex string.
+    #[doc(hidden)]
+    fn serialize_consensus_seq<S: Serializer>(
+        values: &[Self],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        Self: Sized,
+    {
+        serializer.collect_seq(values.iter().map(Consensus))
+    }
+}
+
+/// A type that can be deserialized from the JSON representation of the consensus APIs.
+pub trait ConsensusDeserialize: Sized {
+    fn deserialize_consensus<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>;
+
+    // Deserialize a sequence of values, which allows bytes to be represented as a hex string.
+    #[doc(hidden)]
+    fn deserialize_consensus_seq<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Self>, D::Error> {
+        let values = Vec::<Consensus<Self>>::deserialize(deserializer)?;
+        Ok(values.into_iter().map(|value| value.0).collect())
+    }
+}
+
+impl<T: ConsensusSerialize + ?Sized> ConsensusSerialize for &T {
+    fn serialize_consensus<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize_consensus(serializer)
+    }
+}
+
+/// Wraps a value so that `serde` uses the JSON representation of the consensus APIs.
+///
+/// For example, `serde_json::to_string(&Consensus(&value))` produces the consensus JSON of `value`
+/// and `serde_json::from_str::<Consensus<T>>(json)` parses it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Consensus<T>(pub T);
+
+impl<T: ConsensusSerialize> Serialize for Consensus<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize_consensus(serializer)
+    }
+}
+
+impl<'de, T: ConsensusDeserialize> Deserialize<'de> for Consensus<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize_consensus(deserializer).map(Consensus)
+    }
+}
+
+/// Use the JSON representation of the consensus APIs for a field with
+/// `#[serde(with = "ssz_rs::consensus")]`.
+pub mod consensus {
+    use super::{ConsensusDeserialize, ConsensusSerialize};
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: ConsensusSerialize + ?Sized,
+        S: Serializer,
+    {
+        value.serialize_consensus(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: ConsensusDeserialize,
+        D: Deserializer<'de>,
+    {
+        T::deserialize_consensus(deserializer)
+    }
+}
+
+struct DecimalVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for DecimalVisitor<T>
+where
+    T: core::str::FromStr,
+    T::Err: fmt::Display,
+{
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string of decimal digits")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(E::invalid_value(Unexpected::Str(value), &self))
+        }
+        value.parse().map_err(E::custom)
+    }
+}
+
+macro_rules! define_decimal {
+    ($type: ty) => {
+        impl ConsensusSerialize for $type {
+            fn serialize_consensus<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl ConsensusDeserialize for $type {
+            fn deserialize_consensus<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                deserializer.deserialize_str(DecimalVisitor(PhantomData))
+            }
+        }
+    };
+}
+
+define_decimal!(u16);
+define_decimal!(u32);
+define_decimal!(u64);
+define_decimal!(u128);
+define_decimal!(usize);
+define_decimal!(U256);
+
+impl ConsensusSerialize for u8 {
+    fn serialize_consensus<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+
+    fn serialize_consensus_seq<S: Serializer>(
+        values: &[Self],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        alloy_primitives::serde_hex::serialize(values, serializer)
+    }
+}
+
+impl ConsensusDeserialize for u8 {
+    fn deserialize_consensus<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(DecimalVisitor(PhantomData))
+    }
+
+    fn deserialize_consensus_seq<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Self>, D::Error> {
+        alloy_primitives::serde_hex::deserialize(deserializer)
+    }
+}
+
+impl ConsensusSerialize for bool {
+    fn serialize_consensus<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bool(*self)
+    }
+}
+
+impl ConsensusDeserialize for bool {
+    fn deserialize_consensus<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        bool::deserialize(deserializer)
+    }
+}
+
+// The default `serde` implementations of these types already match the consensus representation.
+macro_rules! define_hex {
+    ($type: ident) => {
+        impl<const N: usize> ConsensusSerialize for $type<N> {
+            fn serialize_consensus<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                Serialize::serialize(self, serializer)
+            }
+        }
+
+        impl<const N: usize> ConsensusDeserialize for $type<N> {
+            fn deserialize_consensus<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<Self, D::Error> {
+                Deserialize::deserialize(deserializer)
+            }
+        }
+    };
+}
+
+define_hex!(Bitlist);
+define_hex!(Bitvector);
+define_hex!(ByteList);
+define_hex!(ByteVector);
+
+impl<T, const N: usize> ConsensusSerialize for List<T, N>
+where
+    T: Serializable + ConsensusSerialize,
+{
+    fn serialize_consensus<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        T::serialize_consensus_seq(self, serializer)
+    }
+}
+
+impl<T, const N: usize> ConsensusDeserialize for List<T, N>
+where
+    T: Serializable + ConsensusDeserialize,
+{
+    fn deserialize_consensus<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = T::deserialize_consensus_seq(deserializer)?;
+        Self::try_from(data).map_err(|(_, err)| D::Error::custom(err))
+    }
+}
+
+impl<T, const N: usize> ConsensusSerialize for Vector<T, N>
+where
+    T: Serializable + ConsensusSerialize,
+{
+    fn serialize_consensus<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        T::serialize_consensus_seq(self, serializer)
+    }
+}
+
+impl<T, const N: usize> ConsensusDeserialize for Vector<T, N>
+where
+    T: Serializable + ConsensusDeserialize,
+{
+    fn deserialize_consensus<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = T::deserialize_consensus_seq(deserializer)?;
+        Self::try_from(data).map_err(|(_, err)| D::Error::custom(err))
+    }
+}
+
+impl<T, const N: usize> ConsensusSerialize for TreeList<T, N>
+where
+    T: SimpleSerialize + Clone + ConsensusSerialize,
+{
+    fn serialize_consensus<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        T::serialize_consensus_seq(&self.iter().cloned().collect::<Vec<_>>(), serializer)
+    }
+}
+
+impl<T, const N: usize> ConsensusDeserialize for TreeList<T, N>
+where
+    T: SimpleSerialize + ConsensusDeserialize,
+{
+    fn deserialize_consensus<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = T::deserialize_consensus_seq(deserializer)?;
+        Self::try_from(data).map_err(|(_, err)| D::Error::custom(err))
+    }
+}
+
+impl<T, const N: usize> ConsensusSerialize for TreeVector<T, N>
+where
+    T: SimpleSerialize + Clone + ConsensusSerialize,
+{
+    fn serialize_consensus<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        T::serialize_consensus_seq(&self.iter().cloned().collect::<Vec<_>>(), serializer)
+    }
+}
+
+impl<T, const N: usize> ConsensusDeserialize for TreeVector<T, N>
+where
+    T: SimpleSerialize + ConsensusDeserialize,
+{
+    fn deserialize_consensus<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = T::deserialize_consensus_seq(deserializer)?;
+        Self::try_from(data).map_err(|(_, err)| D::Error::custom(err))
+    }
+}
+
+impl<T, const N: usize> ConsensusSerialize for [T; N]
+where
+    T: ConsensusSerialize,
+{
+    fn serialize_consensus<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        T::serialize_consensus_seq(self, serializer)
+    }
+}
+
+impl<T, const N: usize> ConsensusDeserialize for [T; N]
+where
+    T: ConsensusDeserialize,
+{
+    fn deserialize_consensus<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = T::deserialize_consensus_seq(deserializer)?;
+        data.try_into().map_err(|data: Vec<T>| D::Error::invalid_length(data.len(), &"N elements"))
+    }
+}
+
+impl<T> ConsensusSerialize for ProgressiveList<T>
+where
+    T: Serializable + ConsensusSerialize,
+{
+    fn serialize_consensus<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        T::serialize_consensus_seq(self, serializer)
+    }
+}
+
+impl<T> ConsensusDeserialize for ProgressiveList<T>
+where
+    T: Serializable + ConsensusDeserialize,
+{
+    fn deserialize_consensus<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize_consensus_seq(deserializer).map(Self::from)
+    }
+}
+
+impl<T> ConsensusSerialize for Optional<T>
+where
+    T: Serializable + ConsensusSerialize,
+{
+    fn serialize_consensus<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_ref().map(Consensus).serialize(serializer)
+    }
+}
+
+impl<T> ConsensusDeserialize for Optional<T>
+where
+    T: Serializable + ConsensusDeserialize,
+{
+    fn deserialize_consensus<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = Option::<Consensus<T>>::deserialize(deserializer)?;
+        Ok(value.map(|value| value.0).into())
+    }
+}
+
+impl<T: ConsensusSerialize> ConsensusSerialize for Cached<T> {
+    fn serialize_consensus<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize_consensus(serializer)
+    }
+}
+
+impl<T: ConsensusDeserialize> ConsensusDeserialize for Cached<T> {
+    fn deserialize_consensus<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize_consensus(deserializer).map(Self::from)
+    }
+}
+
+impl<T> ConsensusSerialize for Option<T>
+where
+    T: ConsensusSerialize,
+{
+    fn serialize_consensus<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Some(value) => serialize_union(serializer, 1, &Consensus(value)),
+            None => serialize_union(serializer, 0, &()),
+        }
+    }
+}
+
+impl<T> ConsensusDeserialize for Option<T>
+where
+    T: ConsensusDeserialize,
+{
+    fn deserialize_consensus<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_union(deserializer)
+    }
+}
+
+impl<T> ConsensusUnion for Option<T>
+where
+    T: ConsensusDeserialize,
+{
+    fn deserialize_variant<'de, D: Deserializer<'de>>(
+        selector: u8,
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        match selector {
+            0 => <()>::deserialize(deserializer).map(|_| None),
+            1 => T::deserialize_consensus(deserializer).map(Some),
+            selector => Err(invalid_selector(selector)),
+        }
+    }
+}
+
+// Serialize the variant of a union with `selector` and the already wrapped `data`.
+pub fn serialize_union<S: Serializer, T: Serialize + ?Sized>(
+    serializer: S,
+    selector: u8,
+    data: &T,
+) -> Result<S::Ok, S::Error> {
+    let mut state = serializer.serialize_struct("Union", 2)?;
+    state.serialize_field("selector", &selector)?;
+    state.serialize_field("data", data)?;
+    state.end()
+}
+
+// A union that can deserialize the data of the variant with a given selector.
+#[doc(hidden)]
+pub trait ConsensusUnion: Sized {
+    fn deserialize_variant<'de, D: Deserializer<'de>>(
+        selector: u8,
+        deserializer: D,
+    ) -> Result<Self, D::Error>;
+}
+
+pub fn invalid_selector<E: serde::de::Error>(selector: u8) -> E {
+    E::invalid_value(Unexpected::Unsigned(selector as u64), &"the selector of a variant")
+}
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum UnionField {
+    Selector,
+    Data,
+}
+
+struct VariantSeed<T> {
+    selector: u8,
+    _p: PhantomData<T>,
+}
+
+impl<'de, T: ConsensusUnion> serde::de::DeserializeSeed<'de> for VariantSeed<T> {
+    type Value = T;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        T::deserialize_variant(self.selector, deserializer)
+    }
+}
+
+struct UnionVisitor<T>(PhantomData<T>);
+
+impl<'de, T: ConsensusUnion> Visitor<'de> for UnionVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object with a `selector` followed by `data`")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        // NOTE: the type of `data` depends on `selector` so it must come first
+        match map.next_key()? {
+            Some(UnionField::Selector) => {}
+            Some(UnionField::Data) => {
+                return Err(A::Error::custom("`selector` must precede `data` in a union"))
+            }
+            None => return Err(A::Error::missing_field("selector")),
+        }
+        let selector = map.next_value()?;
+        match map.next_key()? {
+            Some(UnionField::Data) => {}
+            Some(UnionField::Selector) => return Err(A::Error::duplicate_field("selector")),
+            None => return Err(A::Error::missing_field("data")),
+        }
+        let value = map.next_value_seed(VariantSeed { selector, _p: PhantomData })?;
+        match map.next_key()? {
+            Some(UnionField::Selector) => Err(A::Error::duplicate_field("selector")),
+            Some(UnionField::Data) => Err(A::Error::duplicate_field("data")),
+            None => Ok(value),
+        }
+    }
+}
+
+// Deserialize a union from an object with a `selector` followed by its `data`.
+pub fn deserialize_union<'de, D: Deserializer<'de>, T: ConsensusUnion>(
+    deserializer: D,
+) -> Result<T, D::Error> {
+    deserializer.deserialize_struct("Union", &["selector", "data"], UnionVisitor(PhantomData))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::prelude::*;
@@ -79,4 +562,136 @@ mod tests {
         let roundtrip_value: ComplexTestStruct = serde_json::from_value(json_repr).unwrap();
         assert_eq!(value, roundtrip_value);
     }
+
+    #[derive(
+        PartialEq,
+        Eq,
+        Debug,
+        Default,
+        Clone,
+        SimpleSerialize,
+        ConsensusSerialize,
+        ConsensusDeserialize,
+    )]
+    struct Checkpoint {
+        epoch: u64,
+        root: Vector<u8, 4>,
+    }
+
+    #[derive(PartialEq, Eq, Debug, SimpleSerialize, ConsensusSerialize, ConsensusDeserialize)]
+    enum Payload {
+        None,
+        Bytes(ByteList<8>),
+        Checkpoint(Checkpoint),
+    }
+
+    #[derive(PartialEq, Eq, Debug, SimpleSerialize, ConsensusSerialize, ConsensusDeserialize)]
+    struct State {
+        slot: u64,
+        balance: U256,
+        flag: bool,
+        small: u8,
+        graffiti: List<u8, 32>,
+        roots: ByteVector<2>,
+        epochs: Vector<u16, 2>,
+        checkpoints: List<Checkpoint, 4>,
+        bits: Bitlist<8>,
+        mask: Bitvector<4>,
+        payload: Payload,
+        parent: Option<u32>,
+        extra: Optional<u32>,
+    }
+
+    #[derive(
+        PartialEq, Eq, Debug, Default, SimpleSerialize, ConsensusSerialize, ConsensusDeserialize,
+    )]
+    #[ssz(stable_container = 4)]
+    struct Shape {
+        side: Option<u16>,
+        color: Option<u8>,
+    }
+
+    #[test]
+    fn test_consensus_json() {
+        let value = State {
+            slot: 12345678901234,
+            balance: U256::from(1u64) << 100,
+            flag: true,
+            small: 7,
+            graffiti: List::try_from(vec![0xde, 0xad]).unwrap(),
+            roots: ByteVector::from([0xbe, 0xef]),
+            epochs: Vector::try_from(vec![1, 2]).unwrap(),
+            checkpoints: List::try_from(vec![Checkpoint {
+                epoch: 3,
+                root: Vector::try_from(vec![1, 2, 3, 4]).unwrap(),
+            }])
+            .unwrap(),
+            bits: Bitlist::try_from([true, false, true].as_ref()).unwrap(),
+            mask: Bitvector::try_from([false, true, false, false].as_ref()).unwrap(),
+            payload: Payload::Bytes(ByteList::try_from([0xff].as_ref()).unwrap()),
+            parent: Some(9),
+            extra: None.into(),
+        };
+        let json = serde_json::to_string(&Consensus(&value)).unwrap();
+        let expected = concat!(
+            r#"{"slot":"12345678901234","balance":"1267650600228229401496703205376","flag":true,"#,
+            r#""small":"7","graffiti":"0xdead","roots":"0xbeef","epochs":["1","2"],"#,
+            r#""checkpoints":[{"epoch":"3","root":"0x01020304"}],"bits":"0x0d","mask":"0x02","#,
+            r#""payload":{"selector":1,"data":"0xff"},"parent":{"selector":1,"data":"9"},"#,
+            r#""extra":null}"#,
+        );
+        assert_eq!(json, expected);
+        let Consensus(recovered) = serde_json::from_str::<Consensus<State>>(&json).unwrap();
+        assert_eq!(recovered, value);
+
+        for (payload, json) in [
+            (Payload::None, r#"{"selector":0,"data":null}"#),
+            (
+                Payload::Checkpoint(Checkpoint::default()),
+                r#"{"selector":2,"data":{"epoch":"0","root":"0x00000000"}}"#,
+            ),
+        ] {
+            assert_eq!(serde_json::to_string(&Consensus(&payload)).unwrap(), json);
+            assert_eq!(serde_json::from_str::<Consensus<Payload>>(json).unwrap().0, payload);
+        }
+
+        let value = Shape { side: Some(3), color: None };
+        let json = serde_json::to_string(&Consensus(&value)).unwrap();
+        assert_eq!(json, r#"{"side":"3","color":null}"#);
+        assert_eq!(serde_json::from_str::<Consensus<Shape>>(&json).unwrap().0, value);
+        assert_eq!(serde_json::from_str::<Consensus<Shape>>(r#"{"side":"3"}"#).unwrap().0, value);
+    }
+
+    #[test]
+    fn test_consensus_json_is_strict() {
+        assert_eq!(serde_json::from_str::<Consensus<u64>>(r#""42""#).unwrap().0, 42);
+        assert!(serde_json::from_str::<Consensus<u64>>("42").is_err());
+        assert!(serde_json::from_str::<Consensus<u64>>(r#""0x2a""#).is_err());
+        assert!(serde_json::from_str::<Consensus<u64>>(r#""-1""#).is_err());
+        assert!(serde_json::from_str::<Consensus<u8>>(r#""256""#).is_err());
+        assert!(serde_json::from_str::<Consensus<List<u8, 1>>>(r#""0x0102""#).is_err());
+        assert!(serde_json::from_str::<Consensus<Vector<u16, 2>>>(r#"["1"]"#).is_err());
+        assert!(
+            serde_json::from_str::<Consensus<Payload>>(r#"{"selector":3,"data":null}"#).is_err()
+        );
+        assert!(
+            serde_json::from_str::<Consensus<Payload>>(r#"{"data":null,"selector":0}"#).is_err()
+        );
+        assert!(serde_json::from_str::<Consensus<Payload>>(r#"{"selector":0}"#).is_err());
+    }
+
+    #[test]
+    fn test_consensus_with_adapter() {
+        #[derive(PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
+        struct Response {
+            #[serde(with = "crate::consensus")]
+            data: Checkpoint,
+            version: String,
+        }
+
+        let value = Response { data: Checkpoint::default(), version: "phase0".into() };
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(json, r#"{"data":{"epoch":"0","root":"0x00000000"},"version":"phase0"}"#);
+        assert_eq!(serde_json::from_str::<Response>(&json).unwrap(), value);
+    }
 }
@@ -0,0 +1,381 @@
+//! Snappy-compressed SSZ encodings (`ssz_snappy`), as used by the Ethereum networking layer.
+//!
+//! Gossip messages carry the SSZ encoding of a value compressed with the "raw" snappy format,
+//! while req/resp messages carry an unsigned varint of the length of the SSZ encoding followed
+//! by the encoding compressed with the snappy "frame" format.
+//!
+//! When decoding, the declared length of the SSZ encoding is checked before decompressing:
+//! fixed-size types must declare exactly their `size_hint` and the length of any type is limited
+//! by its `Serializable::max_encoded_len` and by `DecodeOptions::max_bytes`.
+use crate::{
+    de::{DecodeOptions, DeserializeError},
+    lib::*,
+    ser::{SerializeError, MAXIMUM_LENGTH},
+    Serializable,
+};
+use std::io::{Read, Write};
+
+// The maximum number of bytes in the unsigned varint encoding of a `u64`.
+const MAX_VARINT_LENGTH: usize = 10;
+
+/// Errors when encoding or decoding snappy-compressed SSZ.
+#[derive(Debug)]
+pub enum SnappyError {
+    /// An error was encountered when serializing the value.
+    Serialize(SerializeError),
+    /// An error was encountered when deserializing the value, or its declared length was invalid.
+    Deserialize(DeserializeError),
+    /// The compressed data was invalid.
+    Snappy(snap::Error),
+    /// An error was encountered when reading or writing the snappy frame format.
+    Io(std::io::Error),
+    /// The length prefix of a req/resp message was not a valid unsigned varint.
+    InvalidLengthPrefix,
+    /// The given number of bytes followed the end of a req/resp message.
+    TrailingBytes(usize),
+}
+
+impl From<SerializeError> for SnappyError {
+    fn from(err: SerializeError) -> Self {
+        Self::Serialize(err)
+    }
+}
+
+impl From<DeserializeError> for SnappyError {
+    fn from(err: DeserializeError) -> Self {
+        Self::Deserialize(err)
+    }
+}
+
+impl From<snap::Error> for SnappyError {
+    fn from(err: snap::Error) -> Self {
+        Self::Snappy(err)
+    }
+}
+
+impl From<std::io::Error> for SnappyError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl Display for SnappyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Serialize(err) => write!(f, "{err}"),
+            Self::Deserialize(err) => write!(f, "{err}"),
+            Self::Snappy(err) => write!(f, "invalid snappy data: {err}"),
+            Self::Io(err) => write!(f, "invalid snappy frame data: {err}"),
+            Self::InvalidLengthPrefix => write!(f, "invalid varint length prefix"),
+            Self::TrailingBytes(count) => write!(f, "{count} bytes after the end of the message"),
+        }
+    }
+}
+
+impl std::error::Error for SnappyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Serialize(err) => Some(err),
+            Self::Deserialize(err) => Some(err),
+            Self::Snappy(err) => Some(err),
+            Self::Io(err) => Some(err),
+            Self::InvalidLengthPrefix | Self::TrailingBytes(..) => None,
+        }
+    }
+}
+
+// Check the `length` declared for an encoding of `T` before allocating or decompressing anything.
+fn validate_declared_length<T: Serializable>(
+    length: u64,
+    options: &DecodeOptions,
+) -> Result<usize, DeserializeError> {
+    // NOTE: valid encodings are always shorter than `MAXIMUM_LENGTH`
+    let mut limit = (MAXIMUM_LENGTH - 1).min(options.max_bytes as u64);
+    // NOTE: fixed-size types are checked against their exact size below
+    if T::is_variable_size() {
+        limit = limit.min(T::max_encoded_len() as u64);
+    }
+    if length > limit {
+        return Err(DeserializeError::InputTooLarge {
+            provided: usize::try_from(length).unwrap_or(usize::MAX),
+            // SAFETY: cast is safe because `limit` is at most `options.max_bytes`; qed
+            limit: limit as usize,
+        })
+    }
+    // SAFETY: cast is safe because `length` is at most `limit`; qed
+    let length = length as usize;
+    if !T::is_variable_size() {
+        let expected = T::size_hint();
+        if length < expected {
+            return Err(DeserializeError::ExpectedFurtherInput { provided: length, expected })
+        }
+        if length > expected {
+            return Err(DeserializeError::AdditionalInput { provided: length, expected })
+        }
+    }
+    Ok(length)
+}
+
+/// Encode `value` for gossip, as the SSZ encoding compressed with the raw snappy format.
+pub fn encode_gossip<T: Serializable>(value: &T) -> Result<Vec<u8>, SnappyError> {
+    let encoding = crate::serialize(value)?;
+    Ok(snap::raw::Encoder::new().compress_vec(&encoding)?)
+}
+
+/// Decode a value from a gossip message produced by `encode_gossip`.
+pub fn decode_gossip<T: Serializable>(data: &[u8]) -> Result<T, SnappyError> {
+    decode_gossip_with_options(data, &DecodeOptions::default())
+}
+
+/// `decode_gossip_with_options` is like `decode_gossip` but enforces the limits in `options`.
+pub fn decode_gossip_with_options<T: Serializable>(
+    data: &[u8],
+    options: &DecodeOptions,
+) -> Result<T, SnappyError> {
+    let length = snap::raw::decompress_len(data)?;
+    validate_declared_length::<T>(length as u64, options)?;
+    let encoding = snap::raw::Decoder::new().decompress_vec(data)?;
+    Ok(crate::deserialize_with_options(&encoding, options)?)
+}
+
+/// Encode `value` for req/resp, as the unsigned varint length of the SSZ encoding followed by
+/// the encoding compressed with the snappy frame format.
+pub fn encode_frame<T: Serializable>(value: &T) -> Result<Vec<u8>, SnappyError> {
+    let encoding = crate::serialize(value)?;
+    let mut result = Vec::with_capacity(MAX_VARINT_LENGTH + encoding.len());
+    write_varint(encoding.len() as u64, &mut result);
+    let mut encoder = snap::write::FrameEncoder::new(result);
+    encoder.write_all(&encoding)?;
+    encoder.into_inner().map_err(|err| SnappyError::Io(err.into_error()))
+}
+
+/// Decode a value from a req/resp message produced by `encode_frame`.
+///
+/// `data` must contain exactly one message: no bytes may follow the chunk that completes it.
+pub fn decode_frame<T: Serializable>(data: &[u8]) -> Result<T, SnappyError> {
+    decode_frame_with_options(data, &DecodeOptions::default())
+}
+
+/// `decode_frame_with_options` is like `decode_frame` but enforces the limits in `options`.
+pub fn decode_frame_with_options<T: Serializable>(
+    data: &[u8],
+    options: &DecodeOptions,
+) -> Result<T, SnappyError> {
+    let (length, prefix_length) = read_varint(data)?;
+    let length = validate_declared_length::<T>(length, options)?;
+
+    let mut compressed = &data[prefix_length..];
+    let mut decoder = snap::read::FrameDecoder::new(&mut compressed);
+    let mut encoding = Vec::new();
+    (&mut decoder).take(length as u64).read_to_end(&mut encoding)?;
+    if encoding.len() < length {
+        return Err(DeserializeError::ExpectedFurtherInput {
+            provided: encoding.len(),
+            expected: length,
+        }
+        .into())
+    }
+    // NOTE: the decoder only consumes whole chunks, so any input left now follows the chunk that
+    // completes the message; an empty message has no such chunk, and the input must only
+    // contain chunks without data
+    let trailing = decoder.get_ref().len();
+    if trailing > 0 && length > 0 {
+        return Err(SnappyError::TrailingBytes(trailing))
+    }
+    // detect any data decompressed past the declared length, e.g. in the last chunk
+    if decoder.read(&mut [0u8])? > 0 {
+        return Err(
+            DeserializeError::AdditionalInput { provided: length + 1, expected: length }.into()
+        )
+    }
+    Ok(crate::deserialize_with_options(&encoding, options)?)
+}
+
+fn write_varint(mut value: u64, buffer: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buffer.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buffer.push(value as u8);
+}
+
+// Returns the value of the unsigned varint at the start of `data` and the number of bytes it
+// occupies.
+fn read_varint(data: &[u8]) -> Result<(u64, usize), SnappyError> {
+    let mut value = 0u64;
+    for (i, byte) in data.iter().take(MAX_VARINT_LENGTH).enumerate() {
+        let bits = (byte & 0x7f) as u64;
+        // the last byte of a `u64` varint may only carry a single bit
+        if i == MAX_VARINT_LENGTH - 1 && bits > 1 {
+            return Err(SnappyError::InvalidLengthPrefix)
+        }
+        value |= bits << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1))
+        }
+    }
+    Err(SnappyError::InvalidLengthPrefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[derive(Debug, Default, PartialEq, Eq, SimpleSerialize)]
+    struct Foo {
+        a: u32,
+        b: List<u8, 64>,
+    }
+
+    fn sample() -> Foo {
+        Foo { a: 7, b: List::try_from(vec![3u8; 40]).unwrap() }
+    }
+
+    #[test]
+    fn test_varint() {
+        for value in [0, 1, 127, 128, 300, u32::MAX as u64, u64::MAX] {
+            let mut buffer = vec![];
+            write_varint(value, &mut buffer);
+            assert_eq!(read_varint(&buffer).unwrap(), (value, buffer.len()));
+        }
+        assert!(read_varint(&[]).is_err());
+        assert!(read_varint(&[0x80]).is_err());
+        assert!(read_varint(&[0xff; 10]).is_err());
+    }
+
+    #[test]
+    fn test_gossip_round_trip() {
+        let value = sample();
+        let data = encode_gossip(&value).unwrap();
+        assert_eq!(
+            snap::raw::Decoder::new().decompress_vec(&data).unwrap(),
+            serialize(&value).unwrap()
+        );
+        assert_eq!(decode_gossip::<Foo>(&data).unwrap(), value);
+    }
+
+    #[test]
+    fn test_frame_round_trip() {
+        let value = sample();
+        let data = encode_frame(&value).unwrap();
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(data[0] as usize, encoding.len());
+        let mut decompressed = vec![];
+        snap::read::FrameDecoder::new(&data[1..]).read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, encoding);
+        assert_eq!(decode_frame::<Foo>(&data).unwrap(), value);
+    }
+
+    #[test]
+    fn test_declared_length_is_checked() {
+        // fixed-size types must declare their exact size
+        let data = encode_gossip(&12u64).unwrap();
+        assert!(matches!(
+            decode_gossip::<u32>(&data),
+            Err(SnappyError::Deserialize(DeserializeError::AdditionalInput {
+                provided: 8,
+                expected: 4
+            }))
+        ));
+        let data = encode_frame(&12u16).unwrap();
+        assert!(matches!(
+            decode_frame::<u32>(&data),
+            Err(SnappyError::Deserialize(DeserializeError::ExpectedFurtherInput {
+                provided: 2,
+                expected: 4
+            }))
+        ));
+
+        // variable-size types are limited by the options
+        let options = DecodeOptions { max_bytes: 16, ..Default::default() };
+        let data = encode_gossip(&sample()).unwrap();
+        assert!(matches!(
+            decode_gossip_with_options::<Foo>(&data, &options),
+            Err(SnappyError::Deserialize(DeserializeError::InputTooLarge {
+                provided: 48,
+                limit: 16
+            }))
+        ));
+        let data = encode_frame(&sample()).unwrap();
+        assert!(matches!(
+            decode_frame_with_options::<Foo>(&data, &options),
+            Err(SnappyError::Deserialize(DeserializeError::InputTooLarge {
+                provided: 48,
+                limit: 16
+            }))
+        ));
+
+        // variable-size types are limited by their maximum encoded length
+        assert_eq!(Foo::max_encoded_len(), 72);
+        let data = snap::raw::Encoder::new().compress_vec(&[0u8; 73]).unwrap();
+        assert!(matches!(
+            decode_gossip::<Foo>(&data),
+            Err(SnappyError::Deserialize(DeserializeError::InputTooLarge {
+                provided: 73,
+                limit: 72
+            }))
+        ));
+        let mut data = vec![];
+        write_varint(73, &mut data);
+        assert!(matches!(
+            decode_frame::<Foo>(&data),
+            Err(SnappyError::Deserialize(DeserializeError::InputTooLarge {
+                provided: 73,
+                limit: 72
+            }))
+        ));
+
+        // a huge declared length is rejected before decompressing anything
+        let mut data = vec![];
+        write_varint(u64::MAX, &mut data);
+        assert!(matches!(
+            decode_frame::<Foo>(&data),
+            Err(SnappyError::Deserialize(DeserializeError::InputTooLarge { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_frame_length_mismatch() {
+        let value = sample();
+        let mut data = encode_frame(&value).unwrap();
+        data[0] -= 1;
+        assert!(matches!(
+            decode_frame::<Foo>(&data),
+            Err(SnappyError::Deserialize(DeserializeError::AdditionalInput { .. }))
+        ));
+        data[0] += 2;
+        assert!(matches!(
+            decode_frame::<Foo>(&data),
+            Err(SnappyError::Deserialize(DeserializeError::ExpectedFurtherInput { .. }))
+        ));
+    }
+
+    #[test]
+    fn test_frame_trailing_bytes() {
+        let value = sample();
+        let data = encode_frame(&value).unwrap();
+
+        // a padding chunk, which the frame format would otherwise skip
+        let mut padded = data.clone();
+        padded.extend_from_slice(&[0xfe, 0, 0, 0]);
+        assert!(matches!(decode_frame::<Foo>(&padded), Err(SnappyError::TrailingBytes(4))));
+
+        // a second message
+        let mut doubled = data.clone();
+        doubled.extend_from_slice(&data);
+        assert!(matches!(
+            decode_frame::<Foo>(&doubled),
+            Err(SnappyError::TrailingBytes(count)) if count == data.len()
+        ));
+
+        // an empty message may consist of the stream identifier alone
+        let value = List::<u8, 4>::default();
+        let data = encode_frame(&value).unwrap();
+        assert_eq!(data, [0]);
+        assert_eq!(decode_frame::<List<u8, 4>>(&data).unwrap(), value);
+        let mut data = vec![0, 0xff, 6, 0, 0];
+        data.extend_from_slice(b"sNaPpY");
+        assert_eq!(decode_frame::<List<u8, 4>>(&data).unwrap(), value);
+    }
+}
@@ -0,0 +1,298 @@
+//! Support for the forward-compatible `StableContainer` and `Profile` types of EIP-7495.
+//!
+//! Both are implemented for structs with the derive macros, using `#[ssz(stable_container = N)]`
+//! for a `StableContainer[N]` and `#[ssz(profile(B))]` for a `Profile[B]`.
+use crate::{
+    de::DeserializeError,
+    lib::*,
+    merkleization::{
+        merkleize_with, mix_in_aux, pack_bytes, MerkleHasher, MerkleizationError, Node,
+        BYTES_PER_CHUNK,
+    },
+    ser::{SerializeError, Sink},
+    BITS_PER_BYTE,
+};
+
+/// A container whose Merkle tree keeps the same shape as fields are added, up to a capacity of
+/// `MAX_FIELDS` fields.
+///
+/// Implemented with `#[derive(SimpleSerialize)]` and `#[ssz(stable_container = MAX_FIELDS)]` on a
+/// struct whose fields all have type `Option<T>`, where `None` marks an inactive field.
+pub trait StableContainer {
+    const MAX_FIELDS: usize;
+}
+
+/// Types with the Merkle tree layout of the stable container `Base`, i.e. the stable container
+/// itself and each of its profiles.
+///
+/// Implemented with the derive macros for both stable containers and profiles. Types with the same
+/// `Base` can be the variants of a compatible union.
+pub trait StableLayout {
+    type Base: StableContainer;
+}
+
+// The variants `A` and `B` of a compatible union, which share a Merkle tree layout if they are the
+// same type or have the same `StableLayout::Base`.
+//
+// `(&&VariantLayouts::<A, B>::default()).assert_compatible()` only compiles if they do, with both
+// `IdenticalLayouts` and `StableLayouts` in scope: method resolution tries `StableLayouts` on
+// `&VariantLayouts` before `IdenticalLayouts` on `VariantLayouts`, so no pair of types is
+// ambiguous.
+pub struct VariantLayouts<A, B>(PhantomData<(A, B)>);
+
+impl<A, B> Default for VariantLayouts<A, B> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+pub trait IdenticalLayouts {
+    fn assert_compatible(&self) {}
+}
+
+impl<T> IdenticalLayouts for VariantLayouts<T, T> {}
+
+pub trait StableLayouts {
+    fn assert_compatible(&self) {}
+}
+
+impl<A: StableLayout, B: StableLayout<Base = A::Base>> StableLayouts for &VariantLayouts<A, B> {}
+
+fn active_fields_byte_length(bit_count: usize) -> usize {
+    bit_count.div_ceil(BITS_PER_BYTE as usize)
+}
+
+fn active_fields_to_bytes(active_fields: &[bool], bit_count: usize) -> Vec<u8> {
+    let mut bytes = vec![0u8; active_fields_byte_length(bit_count)];
+    for (i, _) in active_fields.iter().enumerate().filter(|(_, &is_active)| is_active) {
+        bytes[i / BITS_PER_BYTE as usize] |= 1 << (i % BITS_PER_BYTE as usize);
+    }
+    bytes
+}
+
+// Serialize `active_fields` as a `Bitvector` of `bit_count` bits.
+pub fn serialize_active_fields<S: Sink + ?Sized>(
+    active_fields: &[bool],
+    bit_count: usize,
+    sink: &mut S,
+) -> Result<usize, SerializeError> {
+    let bytes = active_fields_to_bytes(active_fields, bit_count);
+    sink.write_bytes(&bytes)?;
+    Ok(bytes.len())
+}
+
+// Deserialize a `Bitvector` of `bit_count` bits from the start of `encoding` where only the first
+// `field_count` bits may be set. Returns the bits along with the length of their encoding.
+pub fn deserialize_active_fields(
+    encoding: &[u8],
+    bit_count: usize,
+    field_count: usize,
+) -> Result<(Vec<bool>, usize), DeserializeError> {
+    let byte_length = active_fields_byte_length(bit_count);
+    let bytes = encoding.get(..byte_length).ok_or(DeserializeError::ExpectedFurtherInput {
+        provided: encoding.len(),
+        expected: byte_length,
+    })?;
+    let mut active_fields = vec![false; field_count];
+    for (i, byte) in bytes.iter().enumerate() {
+        for bit in 0..BITS_PER_BYTE as usize {
+            if byte & (1 << bit) == 0 {
+                continue
+            }
+            let index = i * BITS_PER_BYTE as usize + bit;
+            if index >= field_count {
+                return Err(DeserializeError::InvalidByte(*byte))
+            }
+            active_fields[index] = true;
+        }
+    }
+    Ok((active_fields, byte_length))
+}
+
+// Compute the root of a `Bitvector[max_fields]` with the bits in `active_fields` set, with the
+// hasher `H`.
+pub fn active_fields_root<H: MerkleHasher>(
+    active_fields: &[bool],
+    max_fields: usize,
+) -> Result<Node, MerkleizationError> {
+    let mut chunks = active_fields_to_bytes(active_fields, max_fields);
+    pack_bytes(&mut chunks);
+    merkleize_with::<H>(
+        &chunks,
+        Some(max_fields.div_ceil(BYTES_PER_CHUNK * BITS_PER_BYTE as usize)),
+    )
+}
+
+// Compute the root of a stable container with `max_fields` from the roots of its fields in
+// `chunks`, where the chunks of inactive fields are zero, with the hasher `H`.
+pub fn merkleize_stable_container<H: MerkleHasher>(
+    chunks: &[u8],
+    active_fields: &[bool],
+    max_fields: usize,
+) -> Result<Node, MerkleizationError> {
+    let data_root = merkleize_with::<H>(chunks, Some(max_fields))?;
+    Ok(mix_in_aux::<H>(data_root, active_fields_root::<H>(active_fields, max_fields)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, SimpleSerialize)]
+    #[ssz(stable_container = 4)]
+    struct Shape {
+        side: Option<u16>,
+        color: Option<u8>,
+        radius: Option<u16>,
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, SimpleSerialize)]
+    #[ssz(profile(Shape))]
+    struct Square {
+        side: u16,
+        color: u8,
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, SimpleSerialize)]
+    #[ssz(profile(Shape))]
+    struct Circle {
+        color: u8,
+        radius: Option<u16>,
+    }
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, SimpleSerialize)]
+    #[ssz(stable_container = 8)]
+    struct ShapeContainer {
+        shape: Option<Shape>,
+        squares: Option<List<Square, 4>>,
+        circle: Option<Circle>,
+    }
+
+    fn compute_root(chunks: &[Node], max_fields: usize, active_fields: &[u8]) -> Node {
+        let chunks = chunks.iter().flat_map(|chunk| chunk.0).collect::<Vec<_>>();
+        let data_root = ssz_rs::__internal::merkleize(&chunks, Some(max_fields)).unwrap();
+        let mut active_fields = active_fields.to_vec();
+        active_fields.resize(32, 0);
+        let mut buffer = data_root.to_vec();
+        buffer.extend_from_slice(&active_fields);
+        ssz_rs::__internal::merkleize(&buffer, None).unwrap()
+    }
+
+    #[test]
+    fn test_stable_container() {
+        let value = Shape { side: Some(0x42), color: Some(1), radius: None };
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(encoding, [0b011, 0x42, 0x00, 0x01]);
+        assert_eq!(value.encoded_len().unwrap(), encoding.len());
+        assert_eq!(Shape::deserialize(&encoding).unwrap(), value);
+        let expected = compute_root(
+            &[0x42u16.hash_tree_root().unwrap(), 1u8.hash_tree_root().unwrap()],
+            4,
+            &[0b011],
+        );
+        assert_eq!(value.hash_tree_root().unwrap(), expected);
+
+        let value = Shape { side: None, color: Some(1), radius: Some(0x42) };
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(encoding, [0b110, 0x01, 0x42, 0x00]);
+        assert_eq!(Shape::deserialize(&encoding).unwrap(), value);
+
+        let value = Shape::default();
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(encoding, [0]);
+        assert_eq!(Shape::deserialize(&encoding).unwrap(), value);
+        assert_eq!(value.hash_tree_root().unwrap(), compute_root(&[], 4, &[0]));
+
+        // bits beyond the defined fields must not be set
+        assert!(matches!(Shape::deserialize(&[0b1000]), Err(DeserializeError::InvalidByte(..))));
+        assert!(Shape::deserialize(&[0b011, 0x42]).is_err());
+        assert!(Shape::deserialize(&[0b000, 0x42]).is_err());
+    }
+
+    #[test]
+    fn test_profile() {
+        let square = Square { side: 0x42, color: 1 };
+        let encoding = serialize(&square).unwrap();
+        assert_eq!(encoding, [0x42, 0x00, 0x01]);
+        assert!(!Square::is_variable_size());
+        assert_eq!(Square::size_hint(), 3);
+        assert_eq!(Square::deserialize(&encoding).unwrap(), square);
+
+        // a profile shares the Merkle tree of its base stable container
+        let shape = Shape { side: Some(0x42), color: Some(1), radius: None };
+        assert_eq!(square.hash_tree_root().unwrap(), shape.hash_tree_root().unwrap());
+
+        let circle = Circle { color: 1, radius: Some(0x42) };
+        let encoding = serialize(&circle).unwrap();
+        assert_eq!(encoding, [0b1, 0x01, 0x42, 0x00]);
+        assert!(Circle::is_variable_size());
+        assert_eq!(Circle::deserialize(&encoding).unwrap(), circle);
+        let shape = Shape { side: None, color: Some(1), radius: Some(0x42) };
+        assert_eq!(circle.hash_tree_root().unwrap(), shape.hash_tree_root().unwrap());
+
+        let circle = Circle { color: 1, radius: None };
+        let encoding = serialize(&circle).unwrap();
+        assert_eq!(encoding, [0b0, 0x01]);
+        assert_eq!(Circle::deserialize(&encoding).unwrap(), circle);
+        let shape = Shape { side: None, color: Some(1), radius: None };
+        assert_eq!(circle.hash_tree_root().unwrap(), shape.hash_tree_root().unwrap());
+        assert!(matches!(
+            Circle::deserialize(&[0b10, 0x01]),
+            Err(DeserializeError::InvalidByte(..))
+        ));
+    }
+
+    #[test]
+    fn test_generalized_indices_are_stable() {
+        for field in ["side", "color", "radius"] {
+            let index = Shape::generalized_index(&[field.into()]).unwrap();
+            if field != "side" {
+                assert_eq!(Circle::generalized_index(&[field.into()]).unwrap(), index);
+            }
+            if field != "radius" {
+                assert_eq!(Square::generalized_index(&[field.into()]).unwrap(), index);
+            }
+        }
+        assert_eq!(Shape::generalized_index(&["side".into()]).unwrap(), 8);
+        assert_eq!(Shape::generalized_index(&["radius".into()]).unwrap(), 10);
+        assert!(Square::generalized_index(&["radius".into()]).is_err());
+
+        let path = &["circle".into(), "radius".into()];
+        assert_eq!(ShapeContainer::generalized_index(path).unwrap(), (16 + 2) * 8 + 2);
+    }
+
+    #[test]
+    fn test_nested() {
+        let value = ShapeContainer {
+            shape: Some(Shape { side: Some(3), color: None, radius: None }),
+            squares: Some(
+                List::try_from(vec![Square { side: 1, color: 2 }, Square { side: 3, color: 4 }])
+                    .unwrap(),
+            ),
+            circle: None,
+        };
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(value.encoded_len().unwrap(), encoding.len());
+        assert_eq!(ShapeContainer::deserialize(&encoding).unwrap(), value);
+
+        let path = &["squares".into(), 1.into(), "color".into()];
+        assert_eq!(ShapeContainer::decode_at_path::<u8>(&encoding, path).unwrap(), 4);
+        let path = &["circle".into(), "color".into()];
+        assert!(ShapeContainer::bytes_at_path(&encoding, path).is_err());
+
+        for path in [
+            vec!["shape".into(), "side".into()],
+            vec!["shape".into(), "radius".into()],
+            vec!["squares".into(), 1.into(), "color".into()],
+            vec!["circle".into()],
+        ] {
+            let (proof, witness) = value.prove(&path).unwrap();
+            assert_eq!(witness, value.hash_tree_root().unwrap());
+            assert!(proof.verify(witness).is_ok());
+        }
+        assert!(value.prove(&["circle".into(), "color".into()]).is_err());
+
+        let err = ShapeContainer::deserialize(&encoding[..encoding.len() - 1]).unwrap_err();
+        assert_eq!(err.path(), &["squares".into()]);
+    }
+}
@@ -0,0 +1,473 @@
+use crate::{
+    de::{deserialize_homogeneous_composite, DecodeContext, Deserialize, DeserializeError},
+    error::{Error, InstanceError},
+    lib::*,
+    list::ListView,
+    merkleization::{
+        diff::{Diff, Differ},
+        mix_in_length,
+        proofs::{Prove, Prover},
+        tree::Subtree,
+        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleHasher, MerkleizationError,
+        Node, Path, Sha256Hasher,
+    },
+    persistent_tree::{PersistentTree, TreeIter},
+    ser::{
+        homogeneous_composite_encoded_len, homogeneous_composite_max_encoded_len,
+        serialize_homogeneous_composite, Serialize, SerializeError, Sink,
+    },
+    view::Viewable,
+    List, Serializable, SimpleSerialize,
+};
+
+/// A homogenous collection of a variable number of values, stored in a persistent Merkle tree.
+///
+/// A `TreeList` has the same encoding and Merkle tree as a `List`, but its elements are held in the
+/// leaves of a tree that stores the root of each node and shares unchanged subtrees between
+/// clones. Clones take constant time, updates take time logarithmic in `N` and the
+/// `hash_tree_root` is read from the tree, which suits large lists that are cloned and modified a
+/// little at a time, e.g. in a beacon state.
+pub struct TreeList<T: Serializable, const N: usize> {
+    tree: PersistentTree<T>,
+}
+
+impl<T: Serializable, const N: usize> Clone for TreeList<T, N> {
+    fn clone(&self) -> Self {
+        Self { tree: self.tree.clone() }
+    }
+}
+
+impl<T, const N: usize> TreeList<T, N>
+where
+    T: SimpleSerialize,
+{
+    // Number of chunks for this type, rounded up to a complete number of chunks
+    fn chunk_count() -> usize {
+        <List<T, N> as GeneralizedIndexable>::chunk_count()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the element at `index`, if any.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.tree.get(index)
+    }
+
+    pub fn iter(&self) -> TreeIter<'_, T> {
+        self.tree.iter()
+    }
+}
+
+impl<T, const N: usize> TreeList<T, N>
+where
+    T: SimpleSerialize + Clone,
+{
+    /// Replace the element at `index` with `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: T) -> Result<(), Error> {
+        assert!(index < self.len(), "index {index} is out of bounds for length {}", self.len());
+        Ok(self.tree.set(index, value)?)
+    }
+
+    /// Modify the element at `index` with `f`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn update<F: FnOnce(&mut T)>(&mut self, index: usize, f: F) -> Result<(), Error> {
+        let mut value = self[index].clone();
+        f(&mut value);
+        self.set(index, value)
+    }
+
+    /// Append `value` to the list, if it is not full.
+    pub fn push(&mut self, value: T) -> Result<(), Error> {
+        if self.len() == N {
+            return Err(Error::Instance(InstanceError::Bounded { bound: N, provided: N + 1 }))
+        }
+        Ok(self.tree.push(value)?)
+    }
+
+    /// Remove the last element of the list and return it, if any.
+    pub fn pop(&mut self) -> Result<Option<T>, Error> {
+        Ok(self.tree.pop()?)
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for TreeList<T, N>
+where
+    T: SimpleSerialize + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "TreeList<{}, {}>", any::type_name::<T>(), N)?;
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, const N: usize> Default for TreeList<T, N>
+where
+    T: SimpleSerialize,
+{
+    fn default() -> Self {
+        vec![].try_into().map_err(|(_, err)| err).expect("can construct an empty list")
+    }
+}
+
+impl<T, const N: usize> PartialEq for TreeList<T, N>
+where
+    T: SimpleSerialize + PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<T, const N: usize> Eq for TreeList<T, N> where T: SimpleSerialize + Eq {}
+
+impl<T, const N: usize> TryFrom<Vec<T>> for TreeList<T, N>
+where
+    T: SimpleSerialize,
+{
+    type Error = (Vec<T>, Error);
+
+    fn try_from(data: Vec<T>) -> Result<Self, Self::Error> {
+        if data.len() > N {
+            let len = data.len();
+            return Err((data, Error::Instance(InstanceError::Bounded { bound: N, provided: len })))
+        }
+        let tree = PersistentTree::new(data, Self::chunk_count())
+            .map_err(|(data, err)| (data, Error::Merkleization(err)))?;
+        Ok(Self { tree })
+    }
+}
+
+impl<T, const N: usize> TryFrom<&[T]> for TreeList<T, N>
+where
+    T: SimpleSerialize + Clone,
+{
+    type Error = Error;
+
+    fn try_from(data: &[T]) -> Result<Self, Self::Error> {
+        Self::try_from(data.to_vec()).map_err(|(_, err)| err)
+    }
+}
+
+impl<T, const N: usize> Index<usize> for TreeList<T, N>
+where
+    T: SimpleSerialize,
+{
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        self.get(index)
+            .unwrap_or_else(|| panic!("index {index} is out of bounds for length {}", self.len()))
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a TreeList<T, N>
+where
+    T: SimpleSerialize,
+{
+    type Item = &'a T;
+    type IntoIter = TreeIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, const N: usize> Serializable for TreeList<T, N>
+where
+    T: SimpleSerialize,
+{
+    fn is_variable_size() -> bool {
+        true
+    }
+
+    fn size_hint() -> usize {
+        0
+    }
+
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        homogeneous_composite_encoded_len(self)
+    }
+
+    fn max_encoded_len() -> usize {
+        homogeneous_composite_max_encoded_len::<T>(N)
+    }
+
+    fn bytes_at_path<'a>(encoding: &'a [u8], path: Path) -> Result<&'a [u8], DeserializeError> {
+        List::<T, N>::bytes_at_path(encoding, path)
+    }
+
+    fn validate_prefix(prefix: &[u8]) -> Result<bool, DeserializeError> {
+        List::<T, N>::validate_prefix(prefix)
+    }
+}
+
+impl<T, const N: usize> Serialize for TreeList<T, N>
+where
+    T: SimpleSerialize,
+{
+    fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
+        self.serialize_to(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
+        serialize_homogeneous_composite(self, sink)
+    }
+}
+
+impl<T, const N: usize> Deserialize for TreeList<T, N>
+where
+    T: SimpleSerialize,
+{
+    fn deserialize(encoding: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_with(encoding, &mut DecodeContext::default())
+    }
+
+    fn deserialize_with(
+        encoding: &[u8],
+        context: &mut DecodeContext,
+    ) -> Result<Self, DeserializeError> {
+        if !T::is_variable_size() {
+            let remainder = encoding.len() % T::size_hint();
+            if remainder != 0 {
+                return Err(DeserializeError::AdditionalInput {
+                    provided: encoding.len(),
+                    // SAFETY: checked subtraction is unnecessary, as encoding.len() > remainder;
+                    // qed
+                    expected: encoding.len() - remainder,
+                })
+            }
+        }
+
+        let elements = deserialize_homogeneous_composite(encoding, context, |len| {
+            if len > N {
+                return Err(InstanceError::Bounded { bound: N, provided: len }.into())
+            }
+            Ok(())
+        })?;
+        elements.try_into().map_err(|(_, err)| match err {
+            Error::Instance(err) => DeserializeError::InvalidInstance(err),
+            _ => unreachable!("decoded elements can be merkleized"),
+        })
+    }
+}
+
+impl<T, const N: usize> HashTreeRoot for TreeList<T, N>
+where
+    T: SimpleSerialize,
+{
+    fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        Ok(mix_in_length::<H>(self.tree.root_with::<H>()?, self.len()))
+    }
+}
+
+impl<T, const N: usize> GeneralizedIndexable for TreeList<T, N>
+where
+    T: SimpleSerialize,
+{
+    fn chunk_count() -> usize {
+        Self::chunk_count()
+    }
+
+    fn compute_generalized_index(
+        parent: GeneralizedIndex,
+        path: Path,
+    ) -> Result<GeneralizedIndex, MerkleizationError> {
+        List::<T, N>::compute_generalized_index(parent, path)
+    }
+}
+
+impl<T, const N: usize> Prove for TreeList<T, N>
+where
+    T: SimpleSerialize,
+{
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
+        self.tree.chunks::<H>()
+    }
+
+    fn prove_element<H: MerkleHasher>(
+        &self,
+        index: usize,
+        prover: &mut Prover<H>,
+    ) -> Result<(), MerkleizationError> {
+        match self.get(index) {
+            Some(child) => prover.compute_proof(child),
+            None => Err(MerkleizationError::InvalidInnerIndex),
+        }
+    }
+
+    fn subtree<H: MerkleHasher>(&self) -> Result<Subtree<H>, MerkleizationError> {
+        Subtree::from_sequence(self, self.iter())
+    }
+
+    fn decoration(&self) -> Option<usize> {
+        Some(self.len())
+    }
+}
+
+impl<T, const N: usize> Viewable for TreeList<T, N>
+where
+    T: SimpleSerialize + Viewable,
+{
+    type View<'a> = ListView<'a, T, N>;
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        List::<T, N>::view(encoding)
+    }
+}
+
+impl<T, const N: usize> Diff for TreeList<T, N>
+where
+    T: SimpleSerialize + Diff,
+{
+    fn diff_elements(&self, other: &Self, differ: &mut Differ) -> Result<(), MerkleizationError> {
+        self.tree.diff_elements::<Self>(&other.tree, true, differ)
+    }
+}
+
+impl<T, const N: usize> SimpleSerialize for TreeList<T, N> where T: SimpleSerialize {}
+
+#[cfg(feature = "serde")]
+impl<T, const N: usize> serde::Serialize for TreeList<T, N>
+where
+    T: SimpleSerialize + serde::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T, const N: usize> serde::Deserialize<'de> for TreeList<T, N>
+where
+    T: SimpleSerialize + serde::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let data = Vec::<T>::deserialize(deserializer)?;
+        Self::try_from(data).map_err(|(_, err)| serde::de::Error::custom(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{merkleization::proofs::tests::compute_and_verify_proof_for_path, prelude::*};
+
+    #[derive(Debug, Default, Clone, PartialEq, Eq, SimpleSerialize, Diff)]
+    struct Validator {
+        pubkey: ByteVector<48>,
+        effective_balance: u64,
+        slashed: bool,
+    }
+
+    fn validator(i: usize) -> Validator {
+        Validator {
+            pubkey: ByteVector::try_from([i as u8; 48].as_ref()).unwrap(),
+            effective_balance: 32 + i as u64,
+            slashed: false,
+        }
+    }
+
+    // Assert that `tree` matches `list` in encoding and merkleization.
+    fn assert_matches<T: SimpleSerialize + Clone + PartialEq + fmt::Debug, const N: usize>(
+        tree: &TreeList<T, N>,
+        list: &List<T, N>,
+    ) {
+        assert_eq!(tree.iter().cloned().collect::<Vec<_>>(), list.to_vec());
+        assert_eq!(tree.hash_tree_root().unwrap(), list.hash_tree_root().unwrap());
+        assert_eq!(tree.chunks::<Sha256Hasher>().unwrap(), list.chunks::<Sha256Hasher>().unwrap());
+        let encoding = serialize(list).unwrap();
+        assert_eq!(serialize(tree).unwrap(), encoding);
+        assert_eq!(tree.encoded_len().unwrap(), encoding.len());
+        assert_eq!(&TreeList::<T, N>::deserialize(&encoding).unwrap(), tree);
+    }
+
+    #[test]
+    fn test_tree_list() {
+        let mut list = List::<Validator, 1099511627776>::default();
+        let mut tree = TreeList::<Validator, 1099511627776>::default();
+        assert_matches(&tree, &list);
+
+        for i in 0..10 {
+            list.push(validator(i));
+            tree.push(validator(i)).unwrap();
+            assert_matches(&tree, &list);
+        }
+
+        let snapshot = tree.clone();
+        list[3].slashed = true;
+        tree.update(3, |validator| validator.slashed = true).unwrap();
+        tree.set(7, validator(70)).unwrap();
+        list[7] = validator(70);
+        assert_matches(&tree, &list);
+        assert!(!snapshot[3].slashed);
+        assert_eq!(snapshot[7], validator(7));
+
+        for _ in 0..4 {
+            assert_eq!(tree.pop().unwrap(), list.pop());
+            assert_matches(&tree, &list);
+        }
+
+        let differences = diff(&snapshot, &tree).unwrap();
+        assert_eq!(
+            differences.iter().map(|d| d.path.clone()).collect::<Vec<_>>(),
+            vec![vec![3.into(), "slashed".into()], vec![PathElement::Length]]
+        );
+    }
+
+    #[test]
+    fn test_tree_list_of_basic_type() {
+        let mut list = List::<u16, 100>::try_from((0..33).collect::<Vec<_>>()).unwrap();
+        let mut tree = TreeList::<u16, 100>::try_from(list.as_ref()).unwrap();
+        assert_matches(&tree, &list);
+
+        for i in [0, 15, 16, 32] {
+            list[i] = 1000;
+            tree.set(i, 1000).unwrap();
+            assert_matches(&tree, &list);
+        }
+        while let Some(value) = list.pop() {
+            assert_eq!(tree.pop().unwrap(), Some(value));
+            assert_matches(&tree, &list);
+        }
+        assert_eq!(tree.pop().unwrap(), None);
+
+        let full = TreeList::<u16, 2>::try_from(vec![1, 2]).unwrap();
+        assert!(matches!(full.clone().push(3), Err(Error::Instance(..))));
+        assert!(TreeList::<u16, 1>::try_from(vec![1, 2]).is_err());
+        assert!(TreeList::<u16, 1>::deserialize(&serialize(&full).unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_prove_tree_list() {
+        let tree =
+            TreeList::<Validator, 32>::try_from((0..5).map(validator).collect::<Vec<_>>()).unwrap();
+        compute_and_verify_proof_for_path(&tree, &[2.into(), "effective_balance".into()]);
+        compute_and_verify_proof_for_path(&tree, &[PathElement::Length]);
+
+        let tree = TreeList::<u8, 100>::try_from(vec![7; 70]).unwrap();
+        compute_and_verify_proof_for_path(&tree, &[65.into()]);
+    }
+}
@@ -0,0 +1,405 @@
+use crate::{
+    de::{deserialize_homogeneous_composite, DecodeContext, Deserialize, DeserializeError},
+    error::{Error, InstanceError, TypeError},
+    lib::*,
+    merkleization::{
+        diff::{Diff, Differ},
+        proofs::{Prove, Prover},
+        tree::Subtree,
+        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleHasher, MerkleizationError,
+        Node, Path, Sha256Hasher,
+    },
+    persistent_tree::{PersistentTree, TreeIter},
+    ser::{
+        homogeneous_composite_encoded_len, homogeneous_composite_max_encoded_len,
+        serialize_homogeneous_composite, Serialize, SerializeError, Sink,
+    },
+    vector::VectorView,
+    view::Viewable,
+    Serializable, SimpleSerialize, Vector,
+};
+
+/// A homogenous collection of a fixed number of values, stored in a persistent Merkle tree.
+///
+/// A `TreeVector` has the same encoding and Merkle tree as a `Vector`, with the constant time
+/// clones and logarithmic time updates of a `TreeList`.
+///
+/// NOTE: a `TreeVector` with `N == 0` is illegal.
+pub struct TreeVector<T: Serializable, const N: usize> {
+    tree: PersistentTree<T>,
+}
+
+impl<T: Serializable, const N: usize> Clone for TreeVector<T, N> {
+    fn clone(&self) -> Self {
+        Self { tree: self.tree.clone() }
+    }
+}
+
+impl<T, const N: usize> TreeVector<T, N>
+where
+    T: SimpleSerialize,
+{
+    pub fn len(&self) -> usize {
+        N
+    }
+
+    pub fn is_empty(&self) -> bool {
+        N == 0
+    }
+
+    /// Returns the element at `index`, if any.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.tree.get(index)
+    }
+
+    pub fn iter(&self) -> TreeIter<'_, T> {
+        self.tree.iter()
+    }
+}
+
+impl<T, const N: usize> TreeVector<T, N>
+where
+    T: SimpleSerialize + Clone,
+{
+    /// Replace the element at `index` with `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn set(&mut self, index: usize, value: T) -> Result<(), Error> {
+        assert!(index < N, "index {index} is out of bounds for length {N}");
+        Ok(self.tree.set(index, value)?)
+    }
+
+    /// Modify the element at `index` with `f`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn update<F: FnOnce(&mut T)>(&mut self, index: usize, f: F) -> Result<(), Error> {
+        let mut value = self[index].clone();
+        f(&mut value);
+        self.set(index, value)
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for TreeVector<T, N>
+where
+    T: SimpleSerialize + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "TreeVector<{}, {}>", any::type_name::<T>(), N)?;
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T, const N: usize> Default for TreeVector<T, N>
+where
+    T: SimpleSerialize + Default,
+{
+    fn default() -> Self {
+        // SAFETY: there is currently no way to enforce statically
+        // that `N` is non-zero with const generics so panics are possible.
+        assert!(N > 0);
+
+        let data = (0..N).map(|_| T::default()).collect::<Vec<_>>();
+
+        // SAFETY: panic can't happen because data.len() == N != 0; qed
+        data.try_into()
+            // need to drop data so we do not require it as Debug as required by `expect`
+            .map_err(|(_, err)| err)
+            .expect("any TreeVector can be constructed with nonzero default data")
+    }
+}
+
+impl<T, const N: usize> PartialEq for TreeVector<T, N>
+where
+    T: SimpleSerialize + PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.iter().eq(other.iter())
+    }
+}
+
+impl<T, const N: usize> Eq for TreeVector<T, N> where T: SimpleSerialize + Eq {}
+
+impl<T, const N: usize> TryFrom<Vec<T>> for TreeVector<T, N>
+where
+    T: SimpleSerialize,
+{
+    type Error = (Vec<T>, Error);
+
+    fn try_from(data: Vec<T>) -> Result<Self, Self::Error> {
+        if N == 0 {
+            return Err((data, Error::Type(TypeError::InvalidBound(N))))
+        }
+        if data.len() != N {
+            let len = data.len();
+            return Err((data, Error::Instance(InstanceError::Exact { required: N, provided: len })))
+        }
+        let chunk_count = <Vector<T, N> as GeneralizedIndexable>::chunk_count();
+        let tree = PersistentTree::new(data, chunk_count)
+            .map_err(|(data, err)| (data, Error::Merkleization(err)))?;
+        Ok(Self { tree })
+    }
+}
+
+impl<T, const N: usize> TryFrom<&[T]> for TreeVector<T, N>
+where
+    T: SimpleSerialize + Clone,
+{
+    type Error = Error;
+
+    fn try_from(data: &[T]) -> Result<Self, Self::Error> {
+        Self::try_from(data.to_vec()).map_err(|(_, err)| err)
+    }
+}
+
+impl<T, const N: usize> Index<usize> for TreeVector<T, N>
+where
+    T: SimpleSerialize,
+{
+    type Output = T;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        self.get(index).unwrap_or_else(|| panic!("index {index} is out of bounds for length {N}"))
+    }
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a TreeVector<T, N>
+where
+    T: SimpleSerialize,
+{
+    type Item = &'a T;
+    type IntoIter = TreeIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<T, const N: usize> Serializable for TreeVector<T, N>
+where
+    T: SimpleSerialize,
+{
+    fn is_variable_size() -> bool {
+        T::is_variable_size()
+    }
+
+    fn size_hint() -> usize {
+        Vector::<T, N>::size_hint()
+    }
+
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        homogeneous_composite_encoded_len(self)
+    }
+
+    fn max_encoded_len() -> usize {
+        homogeneous_composite_max_encoded_len::<T>(N)
+    }
+
+    fn bytes_at_path<'a>(encoding: &'a [u8], path: Path) -> Result<&'a [u8], DeserializeError> {
+        Vector::<T, N>::bytes_at_path(encoding, path)
+    }
+
+    fn validate_prefix(prefix: &[u8]) -> Result<bool, DeserializeError> {
+        Vector::<T, N>::validate_prefix(prefix)
+    }
+}
+
+impl<T, const N: usize> Serialize for TreeVector<T, N>
+where
+    T: SimpleSerialize,
+{
+    fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
+        self.serialize_to(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
+        serialize_homogeneous_composite(self, sink)
+    }
+}
+
+impl<T, const N: usize> Deserialize for TreeVector<T, N>
+where
+    T: SimpleSerialize,
+{
+    fn deserialize(encoding: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_with(encoding, &mut DecodeContext::default())
+    }
+
+    fn deserialize_with(
+        encoding: &[u8],
+        context: &mut DecodeContext,
+    ) -> Result<Self, DeserializeError> {
+        if N == 0 {
+            return Err(TypeError::InvalidBound(N).into())
+        }
+        if !T::is_variable_size() {
+            let expected_length = N * T::size_hint();
+            if encoding.len() < expected_length {
+                return Err(DeserializeError::ExpectedFurtherInput {
+                    provided: encoding.len(),
+                    expected: expected_length,
+                })
+            }
+            if encoding.len() > expected_length {
+                return Err(DeserializeError::AdditionalInput {
+                    provided: encoding.len(),
+                    expected: expected_length,
+                })
+            }
+        }
+        let elements = deserialize_homogeneous_composite(encoding, context, |len| {
+            if len != N {
+                return Err(InstanceError::Exact { required: N, provided: len }.into())
+            }
+            Ok(())
+        })?;
+        elements.try_into().map_err(|(_, err)| match err {
+            Error::Instance(err) => DeserializeError::InvalidInstance(err),
+            Error::Type(err) => DeserializeError::InvalidType(err),
+            _ => unreachable!("decoded elements can be merkleized"),
+        })
+    }
+}
+
+impl<T, const N: usize> HashTreeRoot for TreeVector<T, N>
+where
+    T: SimpleSerialize,
+{
+    fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        self.tree.root_with::<H>()
+    }
+}
+
+impl<T, const N: usize> GeneralizedIndexable for TreeVector<T, N>
+where
+    T: SimpleSerialize,
+{
+    fn chunk_count() -> usize {
+        Vector::<T, N>::chunk_count()
+    }
+
+    fn compute_generalized_index(
+        parent: GeneralizedIndex,
+        path: Path,
+    ) -> Result<GeneralizedIndex, MerkleizationError> {
+        Vector::<T, N>::compute_generalized_index(parent, path)
+    }
+}
+
+impl<T, const N: usize> Prove for TreeVector<T, N>
+where
+    T: SimpleSerialize,
+{
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
+        self.tree.chunks::<H>()
+    }
+
+    fn prove_element<H: MerkleHasher>(
+        &self,
+        index: usize,
+        prover: &mut Prover<H>,
+    ) -> Result<(), MerkleizationError> {
+        match self.get(index) {
+            Some(child) => prover.compute_proof(child),
+            None => Err(MerkleizationError::InvalidInnerIndex),
+        }
+    }
+
+    fn subtree<H: MerkleHasher>(&self) -> Result<Subtree<H>, MerkleizationError> {
+        Subtree::from_sequence(self, self.iter())
+    }
+}
+
+impl<T, const N: usize> Viewable for TreeVector<T, N>
+where
+    T: SimpleSerialize + Viewable,
+{
+    type View<'a> = VectorView<'a, T, N>;
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        Vector::<T, N>::view(encoding)
+    }
+}
+
+impl<T, const N: usize> Diff for TreeVector<T, N>
+where
+    T: SimpleSerialize + Diff,
+{
+    fn diff_elements(&self, other: &Self, differ: &mut Differ) -> Result<(), MerkleizationError> {
+        self.tree.diff_elements::<Self>(&other.tree, false, differ)
+    }
+}
+
+impl<T, const N: usize> SimpleSerialize for TreeVector<T, N> where T: SimpleSerialize {}
+
+#[cfg(feature = "serde")]
+impl<T, const N: usize> serde::Serialize for TreeVector<T, N>
+where
+    T: SimpleSerialize + serde::Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T, const N: usize> serde::Deserialize<'de> for TreeVector<T, N>
+where
+    T: SimpleSerialize + serde::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let data = Vec::<T>::deserialize(deserializer)?;
+        Self::try_from(data).map_err(|(_, err)| serde::de::Error::custom(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{merkleization::proofs::tests::compute_and_verify_proof_for_path, prelude::*};
+
+    #[test]
+    fn test_tree_vector() {
+        let mut vector = Vector::<u64, 20>::try_from((0..20).collect::<Vec<_>>()).unwrap();
+        let mut tree = TreeVector::<u64, 20>::try_from(vector.as_ref()).unwrap();
+        let snapshot = tree.clone();
+        for i in [0, 3, 4, 19] {
+            vector[i] = 100;
+            tree.update(i, |value| *value = 100).unwrap();
+            assert_eq!(tree.hash_tree_root().unwrap(), vector.hash_tree_root().unwrap());
+            assert_eq!(serialize(&tree).unwrap(), serialize(&vector).unwrap());
+        }
+        assert_eq!(snapshot.iter().copied().collect::<Vec<_>>(), (0..20).collect::<Vec<_>>());
+        assert_eq!(TreeVector::<u64, 20>::deserialize(&serialize(&vector).unwrap()).unwrap(), tree);
+        // NOTE: elements 0 and 3 are packed into the same chunk
+        let differences = diff(&snapshot, &tree).unwrap();
+        assert_eq!(
+            differences.iter().map(|d| d.path.clone()).collect::<Vec<_>>(),
+            vec![vec![0.into()], vec![4.into()], vec![19.into()]]
+        );
+        let snapshot = Vector::<u64, 20>::try_from(snapshot.iter().copied().collect::<Vec<_>>());
+        assert_eq!(diff(&snapshot.unwrap(), &vector).unwrap(), differences);
+        compute_and_verify_proof_for_path(&tree, &[17.into()]);
+
+        let tree = TreeVector::<List<u8, 4>, 3>::default();
+        assert_eq!(
+            tree.hash_tree_root().unwrap(),
+            Vector::<List<u8, 4>, 3>::default().hash_tree_root().unwrap()
+        );
+        assert!(TreeVector::<u8, 3>::try_from(vec![1, 2]).is_err());
+        assert!(TreeVector::<u8, 3>::deserialize(&[1, 2]).is_err());
+    }
+}
@@ -2,10 +2,11 @@ use crate::{
     de::{Deserialize, DeserializeError},
     lib::*,
     merkleization::{
-        pack_bytes, proofs::Prove, GeneralizedIndexable, HashTreeRoot, MerkleizationError, Node,
-        BYTES_PER_CHUNK,
+        diff::Diff, pack_bytes, proofs::Prove, GeneralizedIndexable, HashTreeRoot, MerkleHasher,
+        MerkleizationError, Node, Sha256Hasher, BYTES_PER_CHUNK,
     },
-    ser::{Serialize, SerializeError},
+    ser::{Serialize, SerializeError, Sink},
+    view::Viewable,
     Serializable, SimpleSerialize, BITS_PER_BYTE,
 };
 
@@ -24,11 +25,22 @@ macro_rules! define_uint {
             fn size_hint() -> usize {
                 bits_to_bytes(<$uint>::BITS)
             }
+
+            fn encoded_len(&self) -> Result<usize, SerializeError> {
+                Ok(Self::size_hint())
+            }
         }
 
         impl Serialize for $uint {
             fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
-                buffer.extend_from_slice(&self.to_le_bytes());
+                self.serialize_to(buffer)
+            }
+
+            fn serialize_to<S: Sink + ?Sized>(
+                &self,
+                sink: &mut S,
+            ) -> Result<usize, SerializeError> {
+                sink.write_bytes(&self.to_le_bytes())?;
                 Ok(bits_to_bytes(<$uint>::BITS))
             }
         }
@@ -57,7 +69,11 @@ macro_rules! define_uint {
 
         impl HashTreeRoot for $uint {
             fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
-                let root = self.chunks()?;
+                self.hash_tree_root_with::<Sha256Hasher>()
+            }
+
+            fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+                let root = self.chunks::<H>()?;
                 Ok(root.as_slice().try_into().expect("is valid root"))
             }
 
@@ -73,7 +89,7 @@ macro_rules! define_uint {
         }
 
         impl Prove for $uint {
-            fn chunks(&self) -> Result<Vec<u8>, MerkleizationError> {
+            fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
                 let mut root = Vec::with_capacity(BYTES_PER_CHUNK);
                 let _ = self.serialize(&mut root)?;
                 pack_bytes(&mut root);
@@ -81,6 +97,16 @@ macro_rules! define_uint {
             }
         }
 
+        impl Viewable for $uint {
+            type View<'a> = Self;
+
+            fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+                Self::deserialize(encoding)
+            }
+        }
+
+        impl Diff for $uint {}
+
         impl SimpleSerialize for $uint {}
     };
 }
@@ -105,11 +131,19 @@ impl Serializable for U256 {
     fn size_hint() -> usize {
         U256_BYTE_COUNT
     }
+
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        Ok(Self::size_hint())
+    }
 }
 
 impl Serialize for U256 {
     fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
-        buffer.extend_from_slice(self.as_le_slice());
+        self.serialize_to(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
+        sink.write_bytes(self.as_le_slice())?;
         Ok(Self::size_hint())
     }
 }
@@ -138,7 +172,11 @@ impl Deserialize for U256 {
 
 impl HashTreeRoot for U256 {
     fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
-        let chunks = self.chunks()?;
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        let chunks = self.chunks::<H>()?;
         Ok(Node::try_from(chunks.as_slice()).expect("is right size"))
     }
 
@@ -154,11 +192,21 @@ impl GeneralizedIndexable for U256 {
 }
 
 impl Prove for U256 {
-    fn chunks(&self) -> Result<Vec<u8>, MerkleizationError> {
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
         Ok(self.as_le_bytes().to_vec())
     }
 }
 
+impl Viewable for U256 {
+    type View<'a> = Self;
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        Self::deserialize(encoding)
+    }
+}
+
+impl Diff for U256 {}
+
 impl SimpleSerialize for U256 {}
 
 #[cfg(test)]
@@ -1,13 +1,16 @@
 use crate::{
-    de::{Deserialize, DeserializeError},
+    de::{DecodeContext, Deserialize, DeserializeError},
     lib::*,
     merkleization::{
-        mix_in_selector,
+        diff::{Diff, Differ},
+        mix_in_selector_with,
         proofs::{Prove, Prover},
-        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleizationError, Node, Path,
-        PathElement, BYTES_PER_CHUNK,
+        tree::Subtree,
+        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleHasher, MerkleizationError,
+        Node, Path, PathElement, Sha256Hasher, BYTES_PER_CHUNK,
     },
-    ser::{Serialize, SerializeError},
+    ser::{Serialize, SerializeError, Sink},
+    view::Viewable,
     Serializable, SimpleSerialize,
 };
 
@@ -26,6 +29,48 @@ impl<T: Serializable> Serializable for Option<T> {
     fn size_hint() -> usize {
         0
     }
+
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        match self {
+            Some(data) => {
+                Ok(1 + data.encoded_len().map_err(|err| err.in_element(PathElement::Index(1)))?)
+            }
+            None => Ok(1),
+        }
+    }
+
+    fn max_encoded_len() -> usize {
+        T::max_encoded_len().saturating_add(1)
+    }
+
+    fn bytes_at_path<'a>(encoding: &'a [u8], path: Path) -> Result<&'a [u8], DeserializeError> {
+        let Some((next, rest)) = path.split_first() else { return Ok(encoding) };
+        if encoding.is_empty() {
+            return Err(DeserializeError::ExpectedFurtherInput { provided: 0, expected: 1 })
+        }
+        match next {
+            // SAFETY: index is safe because encoding is not empty; qed
+            PathElement::Selector if rest.is_empty() => Ok(&encoding[..1]),
+            PathElement::Index(1) => match encoding[0] {
+                0 => Err(DeserializeError::InvalidPath(path.to_vec())),
+                1 => T::bytes_at_path(&encoding[1..], rest)
+                    .map_err(|err| err.in_element(PathElement::Index(1), 1)),
+                b => Err(DeserializeError::InvalidByte(b)),
+            },
+            _ => Err(DeserializeError::InvalidPath(path.to_vec())),
+        }
+    }
+
+    fn validate_prefix(prefix: &[u8]) -> Result<bool, DeserializeError> {
+        match prefix.first() {
+            None => Ok(false),
+            Some(0) => Ok(true),
+            // SAFETY: index is safe because prefix is not empty; qed
+            Some(1) => T::validate_prefix(&prefix[1..])
+                .map_err(|err| err.in_element(PathElement::Index(1), 1)),
+            Some(b) => Err(DeserializeError::InvalidByte(*b)),
+        }
+    }
 }
 
 impl<T> Serialize for Option<T>
@@ -33,13 +78,18 @@ where
     T: Serializable,
 {
     fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
+        self.serialize_to(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
         match self {
             Some(data) => {
-                let selector_bytes = 1u8.serialize(buffer)?;
-                let value_bytes = data.serialize(buffer)?;
+                let selector_bytes = 1u8.serialize_to(sink)?;
+                let value_bytes =
+                    data.serialize_to(sink).map_err(|err| err.in_element(PathElement::Index(1)))?;
                 Ok(selector_bytes + value_bytes)
             }
-            None => 0u8.serialize(buffer),
+            None => 0u8.serialize_to(sink),
         }
     }
 }
@@ -49,6 +99,13 @@ where
     T: Serializable,
 {
     fn deserialize(encoding: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_with(encoding, &mut DecodeContext::default())
+    }
+
+    fn deserialize_with(
+        encoding: &[u8],
+        context: &mut DecodeContext,
+    ) -> Result<Self, DeserializeError> {
         if encoding.is_empty() {
             return Err(DeserializeError::ExpectedFurtherInput { provided: 0, expected: 1 })
         }
@@ -66,7 +123,9 @@ where
             }
             1 => {
                 // SAFETY: index is safe because encoding is not empty; qed
-                let inner = T::deserialize(&encoding[1..])?;
+                let inner = context
+                    .nested(|context| T::deserialize_with(&encoding[1..], context))
+                    .map_err(|err| err.in_element(PathElement::Index(1), 1))?;
                 Ok(Some(inner))
             }
             b => Err(DeserializeError::InvalidByte(b)),
@@ -79,10 +138,14 @@ where
     T: SimpleSerialize,
 {
     fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
-        let chunks = Node::try_from(self.chunks()?.as_slice()).expect("is correct size");
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        let chunks = Node::try_from(self.chunks::<H>()?.as_slice()).expect("is correct size");
         match self {
-            Some(_) => Ok(mix_in_selector(chunks, 1)),
-            None => Ok(mix_in_selector(chunks, 0)),
+            Some(_) => Ok(mix_in_selector_with::<H>(chunks, 1)),
+            None => Ok(mix_in_selector_with::<H>(chunks, 0)),
         }
     }
 }
@@ -133,17 +196,21 @@ impl<T> Prove for Option<T>
 where
     T: SimpleSerialize,
 {
-    fn chunks(&self) -> Result<Vec<u8>, MerkleizationError> {
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
         match self {
             Some(value) => {
-                let root = value.hash_tree_root()?;
+                let root = value.hash_tree_root_with::<H>()?;
                 Ok(root.to_vec())
             }
             None => Ok(vec![0u8; BYTES_PER_CHUNK]),
         }
     }
 
-    fn prove_element(&self, index: usize, prover: &mut Prover) -> Result<(), MerkleizationError> {
+    fn prove_element<H: MerkleHasher>(
+        &self,
+        index: usize,
+        prover: &mut Prover<H>,
+    ) -> Result<(), MerkleizationError> {
         if index >= 2 {
             Err(MerkleizationError::InvalidInnerIndex)
         } else {
@@ -157,6 +224,14 @@ where
         }
     }
 
+    fn subtree<H: MerkleHasher>(&self) -> Result<Subtree<H>, MerkleizationError> {
+        let element = match self {
+            Some(value) => value.subtree::<H>()?,
+            None => Subtree::default(),
+        };
+        Ok(Subtree::from_elements(vec![element], Self::chunk_count(), self.decoration_root::<H>()?))
+    }
+
     fn decoration(&self) -> Option<usize> {
         match self {
             Some(_) => Some(1),
@@ -165,6 +240,49 @@ where
     }
 }
 
+impl<T> Viewable for Option<T>
+where
+    T: Viewable,
+{
+    type View<'a> = Option<T::View<'a>>;
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        if encoding.is_empty() {
+            return Err(DeserializeError::ExpectedFurtherInput { provided: 0, expected: 1 })
+        }
+
+        // SAFETY: index is safe because encoding is not empty; qed
+        match encoding[0] {
+            0 => {
+                if encoding.len() != 1 {
+                    return Err(DeserializeError::AdditionalInput {
+                        provided: encoding.len(),
+                        expected: 1,
+                    })
+                }
+                Ok(None)
+            }
+            // SAFETY: index is safe because encoding is not empty; qed
+            1 => T::view(&encoding[1..]).map(Some),
+            b => Err(DeserializeError::InvalidByte(b)),
+        }
+    }
+}
+
+impl<T> Diff for Option<T>
+where
+    T: SimpleSerialize + Diff,
+{
+    fn diff_elements(&self, other: &Self, differ: &mut Differ) -> Result<(), MerkleizationError> {
+        match (self, other) {
+            (Some(left), Some(right)) => {
+                differ.compare_element::<Self, T>(PathElement::Index(1), left, right)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 impl<T> SimpleSerialize for Option<T> where T: SimpleSerialize {}
 
 #[cfg(test)]
@@ -240,6 +358,96 @@ mod tests {
         }
     }
 
+    type Bytes12 = List<u8, 12>;
+
+    // variants of the same type can be spelled differently
+    #[derive(Debug, PartialEq, Eq, SimpleSerialize)]
+    #[ssz(compatible_union)]
+    enum Sparse {
+        #[ssz(selector = 1)]
+        A(List<u8, 12>),
+        #[ssz(selector = 5)]
+        B(Bytes12),
+    }
+
+    #[derive(Debug, PartialEq, Eq, SimpleSerialize)]
+    #[ssz(compatible_union)]
+    enum Either<T: SimpleSerialize> {
+        #[ssz(selector = 1)]
+        Left(T),
+        #[ssz(selector = 2)]
+        Right(T),
+    }
+
+    #[derive(Debug, Default, PartialEq, Eq, SimpleSerialize)]
+    #[ssz(stable_container = 4)]
+    struct Shape {
+        side: Option<u16>,
+        color: Option<u8>,
+        radius: Option<u16>,
+    }
+
+    #[derive(Debug, Default, PartialEq, Eq, SimpleSerialize)]
+    #[ssz(profile(Shape))]
+    struct Square {
+        side: u16,
+        color: u8,
+    }
+
+    #[derive(Debug, Default, PartialEq, Eq, SimpleSerialize)]
+    #[ssz(profile(Shape))]
+    struct Circle {
+        color: u8,
+        radius: u16,
+    }
+
+    #[derive(Debug, PartialEq, Eq, SimpleSerialize)]
+    #[ssz(compatible_union)]
+    enum AnyShape {
+        #[ssz(selector = 1)]
+        Square(Square),
+        #[ssz(selector = 2)]
+        Circle(Circle),
+    }
+
+    #[test]
+    fn test_union_encoded_len() {
+        let values = [
+            Boo::A(12),
+            Boo::B(Inner { data: List::try_from(vec![1u8, 2, 3]).unwrap() }),
+            Boo::C(List::default()),
+            Boo::D(Vector::default()),
+        ];
+        for value in values {
+            assert_eq!(value.encoded_len().unwrap(), serialize(&value).unwrap().len());
+        }
+        for value in [AnotherOption::None, AnotherOption::B(3)] {
+            assert_eq!(value.encoded_len().unwrap(), serialize(&value).unwrap().len());
+        }
+        for value in [None, Some(List::<u16, 4>::try_from(vec![1u16, 2]).unwrap())] {
+            assert_eq!(value.encoded_len().unwrap(), serialize(&value).unwrap().len());
+        }
+    }
+
+    #[test]
+    fn test_union_decode_at_path() {
+        let value = Boo::B(Inner { data: List::try_from(vec![1u8, 2, 3]).unwrap() });
+        let encoding = serialize(&value).unwrap();
+        let path = &[1.into(), "data".into(), 2.into()];
+        assert_eq!(Boo::decode_at_path::<u8>(&encoding, path).unwrap(), 3);
+        assert_eq!(Boo::decode_at_path::<u8>(&encoding, &[PathElement::Selector]).unwrap(), 1);
+        let err = Boo::bytes_at_path(&encoding, &[2.into()]).unwrap_err();
+        assert!(matches!(err, DeserializeError::InvalidPath(..)));
+
+        let value = Some(List::<u16, 4>::try_from(vec![1u16, 2]).unwrap());
+        let encoding = serialize(&value).unwrap();
+        let decoded =
+            Option::<List<u16, 4>>::decode_at_path::<u16>(&encoding, &[1.into(), 1.into()]);
+        assert_eq!(decoded.unwrap(), 2);
+        let err = Option::<List<u16, 4>>::bytes_at_path(&[0], &[1.into()]).unwrap_err();
+        assert!(matches!(err, DeserializeError::InvalidPath(..)));
+    }
+
     #[test]
     fn test_option() {
         let mut x = Some(12u8);
@@ -507,4 +715,64 @@ mod tests {
         let path = &[3.into(), 1.into()];
         crate::merkleization::proofs::tests::compute_and_verify_proof_for_path(&data, path);
     }
+
+    #[test]
+    fn test_compatible_union() {
+        let value = Sparse::B(List::try_from(vec![3, 4]).unwrap());
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(encoding, [5, 3, 4]);
+        assert_eq!(value.encoded_len().unwrap(), encoding.len());
+        assert_eq!(Sparse::deserialize(&encoding).unwrap(), value);
+        assert_eq!(Sparse::deserialize(&[1, 3, 4]).unwrap(), Sparse::A(value_list(&[3, 4])));
+        for selector in [0, 2, 128] {
+            let result = Sparse::deserialize(&[selector, 3, 4]);
+            assert!(matches!(result, Err(DeserializeError::InvalidByte(b)) if b == selector));
+        }
+        assert_eq!(Sparse::decode_at_path::<u8>(&encoding, &[5.into(), 1.into()]).unwrap(), 4);
+        assert!(Sparse::bytes_at_path(&encoding, &[1.into(), 1.into()]).is_err());
+
+        let list_root = value_list(&[3, 4]).hash_tree_root().unwrap();
+        let expected = crate::merkleization::mix_in_selector(list_root, 5);
+        assert_eq!(value.hash_tree_root().unwrap(), expected);
+
+        assert_eq!(Sparse::generalized_index(&[PathElement::Selector]).unwrap(), 3);
+        let index = Sparse::generalized_index(&[5.into(), 1.into()]).unwrap();
+        assert_eq!(Sparse::generalized_index(&[1.into(), 1.into()]).unwrap(), index);
+        assert!(Sparse::generalized_index(&[2.into()]).is_err());
+        let path = &[5.into(), 1.into()];
+        crate::merkleization::proofs::tests::compute_and_verify_proof_for_path(&value, path);
+        let path = &[PathElement::Selector];
+        crate::merkleization::proofs::tests::compute_and_verify_proof_for_path(&value, path);
+
+        let value = Either::Right(value_list(&[3, 4]));
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(encoding, [2, 3, 4]);
+        assert_eq!(Either::deserialize(&encoding).unwrap(), value);
+        assert_eq!(
+            value.hash_tree_root().unwrap(),
+            crate::merkleization::mix_in_selector(list_root, 2)
+        );
+    }
+
+    fn value_list(values: &[u8]) -> List<u8, 12> {
+        List::try_from(values.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_compatible_union_of_profiles() {
+        let value = AnyShape::Circle(Circle { color: 1, radius: 0x42 });
+        let encoding = serialize(&value).unwrap();
+        assert_eq!(encoding, [2, 0x01, 0x42, 0x00]);
+        assert_eq!(AnyShape::deserialize(&encoding).unwrap(), value);
+
+        let shape = Shape { side: None, color: Some(1), radius: Some(0x42) };
+        let expected = crate::merkleization::mix_in_selector(shape.hash_tree_root().unwrap(), 2);
+        assert_eq!(value.hash_tree_root().unwrap(), expected);
+
+        // the variants share the Merkle tree layout of their base stable container
+        let index = AnyShape::generalized_index(&[1.into(), "color".into()]).unwrap();
+        assert_eq!(AnyShape::generalized_index(&[2.into(), "color".into()]).unwrap(), index);
+        let path = &[2.into(), "radius".into()];
+        crate::merkleization::proofs::tests::compute_and_verify_proof_for_path(&value, path);
+    }
 }
@@ -1,14 +1,25 @@
+#[cfg(feature = "rayon")]
+use crate::merkleization::elements_to_chunks_in_parallel;
 use crate::{
-    de::{deserialize_homogeneous_composite, Deserialize, DeserializeError},
+    de::{
+        deserialize_homogeneous_composite, validate_variable_offsets_prefix, DecodeContext,
+        Deserialize, DeserializeError,
+    },
     error::{Error, InstanceError, TypeError},
     lib::*,
     merkleization::{
-        elements_to_chunks, get_power_of_two_ceil, merkleize, pack,
+        diff::{diff_sequences, Diff, Differ},
+        get_power_of_two_ceil, merkleize_with, pack,
         proofs::{Prove, Prover},
-        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleizationError, Node, Path,
-        PathElement,
+        tree::Subtree,
+        GeneralizedIndex, GeneralizedIndexable, HashTreeRoot, MerkleHasher, MerkleizationError,
+        Node, Path, PathElement, Sha256Hasher,
+    },
+    ser::{
+        homogeneous_composite_encoded_len, homogeneous_composite_max_encoded_len,
+        serialize_homogeneous_composite, Serialize, SerializeError, Sink,
     },
-    ser::{Serialize, SerializeError, Serializer},
+    view::{SequenceIter, SequenceView, Viewable},
     Serializable, SimpleSerialize,
 };
 
@@ -156,6 +167,38 @@ where
     fn size_hint() -> usize {
         T::size_hint() * N
     }
+    fn encoded_len(&self) -> Result<usize, SerializeError> {
+        if N == 0 {
+            return Err(TypeError::InvalidBound(N).into())
+        }
+        homogeneous_composite_encoded_len(&self.data)
+    }
+
+    fn max_encoded_len() -> usize {
+        homogeneous_composite_max_encoded_len::<T>(N)
+    }
+
+    fn bytes_at_path<'a>(encoding: &'a [u8], path: Path) -> Result<&'a [u8], DeserializeError> {
+        if path.is_empty() {
+            return Ok(encoding)
+        }
+        SequenceView::<T>::new_exact(encoding, N)?.bytes_at_path(path)
+    }
+
+    fn validate_prefix(prefix: &[u8]) -> Result<bool, DeserializeError> {
+        if N == 0 {
+            return Err(TypeError::InvalidBound(N).into())
+        }
+        if !T::is_variable_size() {
+            return Ok(true)
+        }
+        validate_variable_offsets_prefix(prefix, |len| {
+            if len != N {
+                return Err(InstanceError::Exact { required: N, provided: len }.into())
+            }
+            Ok(())
+        })
+    }
 }
 
 impl<T, const N: usize> Serialize for Vector<T, N>
@@ -163,14 +206,14 @@ where
     T: Serializable,
 {
     fn serialize(&self, buffer: &mut Vec<u8>) -> Result<usize, SerializeError> {
+        self.serialize_to(buffer)
+    }
+
+    fn serialize_to<S: Sink + ?Sized>(&self, sink: &mut S) -> Result<usize, SerializeError> {
         if N == 0 {
             return Err(TypeError::InvalidBound(N).into())
         }
-        let mut serializer = Serializer::default();
-        for element in &self.data {
-            serializer.with_element(element)?;
-        }
-        serializer.serialize(buffer)
+        serialize_homogeneous_composite(&self.data, sink)
     }
 }
 
@@ -179,6 +222,13 @@ where
     T: Serializable,
 {
     fn deserialize(encoding: &[u8]) -> Result<Self, DeserializeError> {
+        Self::deserialize_with(encoding, &mut DecodeContext::default())
+    }
+
+    fn deserialize_with(
+        encoding: &[u8],
+        context: &mut DecodeContext,
+    ) -> Result<Self, DeserializeError> {
         if N == 0 {
             return Err(TypeError::InvalidBound(N).into())
         }
@@ -197,7 +247,12 @@ where
                 })
             }
         }
-        let inner = deserialize_homogeneous_composite(encoding)?;
+        let inner = deserialize_homogeneous_composite(encoding, context, |len| {
+            if len != N {
+                return Err(InstanceError::Exact { required: N, provided: len }.into())
+            }
+            Ok(())
+        })?;
         inner.try_into().map_err(|(_, err)| match err {
             Error::Deserialize(err) => err,
             Error::Instance(err) => DeserializeError::InvalidInstance(err),
@@ -211,18 +266,46 @@ impl<T, const N: usize> Vector<T, N>
 where
     T: SimpleSerialize,
 {
-    fn assemble_chunks(&self) -> Result<Vec<u8>, MerkleizationError> {
+    fn assemble_chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
         if T::is_composite_type() {
-            let count = self.len();
-            elements_to_chunks(self.data.iter().enumerate(), count)
+            T::elements_to_chunks::<H>(&self.data)
         } else {
             pack(&self.data)
         }
     }
 
-    fn compute_hash_tree_root(&self) -> Result<Node, MerkleizationError> {
-        let chunks = self.assemble_chunks()?;
-        merkleize(&chunks, None)
+    fn compute_hash_tree_root<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        let chunks = self.assemble_chunks::<H>()?;
+        self.root_of_chunks::<H>(&chunks)
+    }
+
+    fn root_of_chunks<H: MerkleHasher>(&self, chunks: &[u8]) -> Result<Node, MerkleizationError> {
+        merkleize_with::<H>(chunks, None)
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<T, const N: usize> Vector<T, N>
+where
+    T: SimpleSerialize + Sync,
+{
+    /// Compute the "hash tree root" of `self` like `HashTreeRoot::hash_tree_root`, computing the
+    /// roots of the elements in parallel on the `rayon` thread pool when there are enough of them.
+    pub fn hash_tree_root_in_parallel(&self) -> Result<Node, MerkleizationError> {
+        self.hash_tree_root_in_parallel_with::<Sha256Hasher>()
+    }
+
+    /// Compute the "hash tree root" of `self` like `hash_tree_root_in_parallel`, with the
+    /// hasher `H`.
+    pub fn hash_tree_root_in_parallel_with<H: MerkleHasher>(
+        &self,
+    ) -> Result<Node, MerkleizationError> {
+        let chunks = if T::is_composite_type() {
+            elements_to_chunks_in_parallel::<H, _>(&self.data)?
+        } else {
+            pack(&self.data)?
+        };
+        self.root_of_chunks::<H>(&chunks)
     }
 }
 
@@ -231,7 +314,11 @@ where
     T: SimpleSerialize,
 {
     fn hash_tree_root(&self) -> Result<Node, MerkleizationError> {
-        self.compute_hash_tree_root()
+        self.hash_tree_root_with::<Sha256Hasher>()
+    }
+
+    fn hash_tree_root_with<H: MerkleHasher>(&self) -> Result<Node, MerkleizationError> {
+        self.compute_hash_tree_root::<H>()
     }
 }
 
@@ -270,11 +357,15 @@ impl<T, const N: usize> Prove for Vector<T, N>
 where
     T: SimpleSerialize,
 {
-    fn chunks(&self) -> Result<Vec<u8>, MerkleizationError> {
-        self.assemble_chunks()
+    fn chunks<H: MerkleHasher>(&self) -> Result<Vec<u8>, MerkleizationError> {
+        self.assemble_chunks::<H>()
     }
 
-    fn prove_element(&self, index: usize, prover: &mut Prover) -> Result<(), MerkleizationError> {
+    fn prove_element<H: MerkleHasher>(
+        &self,
+        index: usize,
+        prover: &mut Prover<H>,
+    ) -> Result<(), MerkleizationError> {
         if index >= N {
             Err(MerkleizationError::InvalidInnerIndex)
         } else {
@@ -282,10 +373,80 @@ where
             prover.compute_proof(child)
         }
     }
+
+    fn subtree<H: MerkleHasher>(&self) -> Result<Subtree<H>, MerkleizationError> {
+        Subtree::from_sequence(self, self.iter())
+    }
+}
+
+impl<T, const N: usize> Viewable for Vector<T, N>
+where
+    T: Viewable,
+{
+    type View<'a> = VectorView<'a, T, N>;
+
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError> {
+        let elements = SequenceView::new_exact(encoding, N)?;
+        Ok(VectorView { elements })
+    }
+}
+
+impl<T, const N: usize> Diff for Vector<T, N>
+where
+    T: SimpleSerialize + Diff,
+{
+    fn diff_elements(&self, other: &Self, differ: &mut Differ) -> Result<(), MerkleizationError> {
+        diff_sequences::<Self, T>(self, other, false, differ)
+    }
 }
 
 impl<T, const N: usize> SimpleSerialize for Vector<T, N> where T: SimpleSerialize {}
 
+/// A borrowed view over the encoding of a `Vector<T, N>`.
+///
+/// Dereferences to a `SequenceView` for access to the elements of the vector.
+pub struct VectorView<'a, T, const N: usize> {
+    elements: SequenceView<'a, T>,
+}
+
+impl<'a, T, const N: usize> Clone for VectorView<'a, T, N> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T, const N: usize> Copy for VectorView<'a, T, N> {}
+
+impl<'a, T, const N: usize> fmt::Debug for VectorView<'a, T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "VectorView<{}, {}>({:?})", any::type_name::<T>(), N, self.elements)
+    }
+}
+
+impl<'a, T, const N: usize> Deref for VectorView<'a, T, N> {
+    type Target = SequenceView<'a, T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.elements
+    }
+}
+
+impl<'a, T: Viewable, const N: usize> VectorView<'a, T, N> {
+    /// Decode the entire `Vector` this view refers to.
+    pub fn decode(&self) -> Result<Vector<T, N>, DeserializeError> {
+        Vector::deserialize(self.elements.as_bytes())
+    }
+}
+
+impl<'a, T: Viewable, const N: usize> IntoIterator for VectorView<'a, T, N> {
+    type Item = Result<T::View<'a>, DeserializeError>;
+    type IntoIter = SequenceIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.elements.iter()
+    }
+}
+
 #[cfg(feature = "serde")]
 struct VectorVisitor<T: Serializable>(PhantomData<Vec<T>>);
 
@@ -0,0 +1,210 @@
+//! Borrowed, lazily-decoded views over SSZ encodings.
+use crate::{
+    de::{validate_variable_offsets, variable_element_span, DeserializeError},
+    error::{InstanceError, TypeError},
+    lib::*,
+    merkleization::{Path, PathElement},
+    Serializable,
+};
+
+/// A type whose SSZ encoding can be accessed through a borrowed view without decoding the
+/// entire value.
+///
+/// Basic types are cheap to decode and use `Self` as their view. Composite types use a view
+/// over the underlying bytes that validates offsets once on construction and then decodes
+/// fields and elements as they are accessed.
+pub trait Viewable: Serializable {
+    /// The type providing access to an encoding of `Self`.
+    type View<'a>;
+
+    /// Construct a view over `encoding`.
+    fn view(encoding: &[u8]) -> Result<Self::View<'_>, DeserializeError>;
+}
+
+/// A view over the encoding of a homogeneous sequence of values of type `T`.
+pub struct SequenceView<'a, T> {
+    encoding: &'a [u8],
+    len: usize,
+    _p: PhantomData<T>,
+}
+
+impl<'a, T> Clone for SequenceView<'a, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Copy for SequenceView<'a, T> {}
+
+impl<'a, T: Serializable> SequenceView<'a, T> {
+    pub(crate) fn new(encoding: &'a [u8]) -> Result<Self, DeserializeError> {
+        let len = if T::is_variable_size() {
+            validate_variable_offsets(encoding)?
+        } else {
+            let remainder = encoding.len() % T::size_hint();
+            if remainder != 0 {
+                return Err(DeserializeError::AdditionalInput {
+                    provided: encoding.len(),
+                    // SAFETY: checked subtraction is unnecessary, as encoding.len() > remainder;
+                    // qed
+                    expected: encoding.len() - remainder,
+                })
+            }
+            encoding.len() / T::size_hint()
+        };
+        Ok(Self { encoding, len, _p: PhantomData })
+    }
+
+    // Construct a view over `encoding` that must contain exactly `len` elements.
+    pub(crate) fn new_exact(encoding: &'a [u8], len: usize) -> Result<Self, DeserializeError> {
+        if len == 0 {
+            return Err(TypeError::InvalidBound(len).into())
+        }
+        if !T::is_variable_size() {
+            let expected_length = len * T::size_hint();
+            if encoding.len() < expected_length {
+                return Err(DeserializeError::ExpectedFurtherInput {
+                    provided: encoding.len(),
+                    expected: expected_length,
+                })
+            }
+            if encoding.len() > expected_length {
+                return Err(DeserializeError::AdditionalInput {
+                    provided: encoding.len(),
+                    expected: expected_length,
+                })
+            }
+        }
+        let view = Self::new(encoding)?;
+        if view.len() != len {
+            return Err(InstanceError::Exact { required: len, provided: view.len() }.into())
+        }
+        Ok(view)
+    }
+
+    /// Returns the number of elements in the sequence.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the underlying encoding of the sequence.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.encoding
+    }
+
+    // Returns the `(start, end)` span of the encoding of the element at `index`, if it exists.
+    fn element_span(&self, index: usize) -> Option<(usize, usize)> {
+        if index >= self.len {
+            return None
+        }
+        let span = if T::is_variable_size() {
+            variable_element_span(self.encoding, index, self.len)
+        } else {
+            let size = T::size_hint();
+            (index * size, (index + 1) * size)
+        };
+        Some(span)
+    }
+
+    /// Returns the encoding of the element at `index`, if it exists.
+    pub fn element_bytes(&self, index: usize) -> Option<&'a [u8]> {
+        // SAFETY: index is safe because spans have been validated; qed
+        self.element_span(index).map(|(start, end)| &self.encoding[start..end])
+    }
+
+    // Returns the encoding of the value at `path` relative to the elements of this sequence.
+    pub(crate) fn bytes_at_path(&self, path: Path) -> Result<&'a [u8], DeserializeError> {
+        let Some((next, rest)) = path.split_first() else { return Ok(self.encoding) };
+        match next {
+            PathElement::Index(index) => {
+                let (start, end) = self
+                    .element_span(*index)
+                    .ok_or_else(|| DeserializeError::InvalidPath(path.to_vec()))?;
+                // SAFETY: index is safe because spans have been validated; qed
+                T::bytes_at_path(&self.encoding[start..end], rest)
+                    .map_err(|err| err.in_element(next.clone(), start))
+            }
+            _ => Err(DeserializeError::InvalidPath(path.to_vec())),
+        }
+    }
+}
+
+impl<'a, T: Viewable> SequenceView<'a, T> {
+    /// Returns a view of the element at `index`, if it exists.
+    pub fn get(&self, index: usize) -> Option<Result<T::View<'a>, DeserializeError>> {
+        self.element_bytes(index).map(T::view)
+    }
+
+    pub fn iter(&self) -> SequenceIter<'a, T> {
+        SequenceIter { sequence: *self, index: 0 }
+    }
+}
+
+impl<'a, T: Viewable> IntoIterator for SequenceView<'a, T> {
+    type Item = Result<T::View<'a>, DeserializeError>;
+    type IntoIter = SequenceIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> Debug for SequenceView<'a, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SequenceView").field("len", &self.len).finish()
+    }
+}
+
+/// An iterator over views of the elements of a `SequenceView`.
+pub struct SequenceIter<'a, T> {
+    sequence: SequenceView<'a, T>,
+    index: usize,
+}
+
+impl<'a, T: Viewable> Iterator for SequenceIter<'a, T> {
+    type Item = Result<T::View<'a>, DeserializeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let element = self.sequence.get(self.index)?;
+        self.index += 1;
+        Some(element)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.sequence.len() - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a, T: Viewable> ExactSizeIterator for SequenceIter<'a, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_sequence_view() {
+        let value = List::<List<u16, 4>, 8>::try_from(vec![
+            List::try_from(vec![1u16, 2]).unwrap(),
+            List::default(),
+            List::try_from(vec![3u16, 4, 5]).unwrap(),
+        ])
+        .unwrap();
+        let encoding = serialize(&value).unwrap();
+        let view = SequenceView::<List<u16, 4>>::new(&encoding).unwrap();
+        assert_eq!(view.len(), 3);
+        for (element, expected) in view.iter().zip(value.iter()) {
+            let element = element.unwrap();
+            assert_eq!(element.len(), expected.len());
+            for (x, y) in element.iter().zip(expected.iter()) {
+                assert_eq!(x.unwrap(), *y);
+            }
+        }
+        assert!(view.get(3).is_none());
+    }
+}